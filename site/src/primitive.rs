@@ -380,6 +380,8 @@ fn all_unders() -> impl IntoView {
             { inverse_row([Sys(RunStream)], Optional, view!("Calls "<Prim prim=Sys(Close)/>" on handle"), None) }
             { inverse_row([Sys(TcpConnect)], Optional, view!("Calls "<Prim prim=Sys(Close)/>" on handle"), None) }
             { inverse_row([Sys(TcpAccept)], Optional, view!("Calls "<Prim prim=Sys(Close)/>" on handle"), None) }
+            { inverse_row([Sys(UdpBind)], Optional, view!("Calls "<Prim prim=Sys(Close)/>" on handle"), None) }
+            { inverse_row([Sys(UdpConnect)], Optional, view!("Calls "<Prim prim=Sys(Close)/>" on handle"), None) }
             { inverse_row([Sys(FReadAllStr)], Optional, view!("Calls "<Prim prim=Sys(FWriteAll)/>), None) }
             { inverse_row([Sys(FReadAllBytes)], Optional, view!("Calls "<Prim prim=Sys(FWriteAll)/>), None) }
             { inverse_row([Sys(RawMode)], Optional, "Resets raw state", None) }
@@ -0,0 +1,377 @@
+//! En/decode Uiua values to/from a lossless binary format
+//!
+//! # Layout
+//!
+//! Encoded data begins with the bytes `UAV` followed by a format version byte, currently `1`.
+//! This is followed by a single encoded value.
+//!
+//! A value is encoded as:
+//! - A type byte: `0` for numbers, `1` for bytes, `2` for complex numbers, `3` for characters, and `4` for boxes
+//! - A byte of [`ArrayFlags`]
+//! - A metadata byte: bit `0` is set if the value has a label, and bit `1` is set if it is a map
+//! - The rank, followed by each dimension of the shape
+//! - The elements:
+//!   - Numbers are little-endian `f64`s
+//!   - Bytes are single bytes
+//!   - Complex numbers are the real part, then the imaginary part, as little-endian `f64`s
+//!   - Characters are their code points
+//!   - Boxes are encoded values, without the header
+//! - If the value has a label, its length in bytes followed by its UTF-8 bytes
+//! - If the value is a map, its keys as an encoded value, without the header
+//!
+//! All ranks, dimensions, code points, and lengths are unsigned LEB128 varints.
+
+use ecow::{EcoString, EcoVec};
+
+use crate::{Array, ArrayFlags, Boxed, Complex, Shape, Uiua, UiuaResult, Value};
+
+const MAGIC: &[u8] = b"UAV";
+const FORMAT_VERSION: u8 = 1;
+
+/// Boxes and map keys nested deeper than this are rejected
+///
+/// The limit is the same when encoding and decoding, so anything that encodes can be decoded.
+const MAX_DEPTH: usize = 256;
+
+const NUM_TAG: u8 = 0;
+const BYTE_TAG: u8 = 1;
+const COMPLEX_TAG: u8 = 2;
+const CHAR_TAG: u8 = 3;
+const BOX_TAG: u8 = 4;
+
+const LABEL_BIT: u8 = 1;
+const MAP_BIT: u8 = 2;
+
+impl Value {
+    /// Encode the value in the lossless binary format
+    pub fn to_binary(&self, env: &Uiua) -> UiuaResult<Vec<u8>> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        write_value(self, 0, &mut bytes).map_err(|e| env.error(e))?;
+        Ok(bytes)
+    }
+    /// Decode a value from the lossless binary format
+    pub fn from_binary(bytes: &[u8], env: &Uiua) -> UiuaResult<Self> {
+        let Some(body) = bytes.strip_prefix(MAGIC) else {
+            return Err(env.error("Bytes are not an encoded Uiua value"));
+        };
+        match body.first() {
+            Some(&FORMAT_VERSION) => {}
+            Some(version) => {
+                return Err(env.error(format!(
+                    "Encoded value uses format version {version}, \
+                    but only version {FORMAT_VERSION} is supported"
+                )))
+            }
+            None => return Err(env.error("Encoded value is truncated")),
+        }
+        let mut reader = Reader {
+            bytes: &body[1..],
+            env,
+        };
+        let val = reader.value()?;
+        if !reader.bytes.is_empty() {
+            return Err(env.error(format!(
+                "Encoded value has {} unexpected trailing bytes",
+                reader.bytes.len()
+            )));
+        }
+        Ok(val)
+    }
+}
+
+fn write_varint(mut n: u64, bytes: &mut Vec<u8>) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(byte);
+            break;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+fn write_value(val: &Value, depth: usize, bytes: &mut Vec<u8>) -> Result<(), &'static str> {
+    let too_deep = "Value is nested too deeply to encode";
+    let tag = match val {
+        Value::Num(_) => NUM_TAG,
+        Value::Byte(_) => BYTE_TAG,
+        Value::Complex(_) => COMPLEX_TAG,
+        Value::Char(_) => CHAR_TAG,
+        Value::Box(_) => BOX_TAG,
+    };
+    bytes.push(tag);
+    let meta = val.meta();
+    bytes.push(meta.flags.bits());
+    let mut meta_bits = 0;
+    if meta.label.is_some() {
+        meta_bits |= LABEL_BIT;
+    }
+    if meta.map_keys.is_some() {
+        meta_bits |= MAP_BIT;
+    }
+    bytes.push(meta_bits);
+    write_varint(val.rank() as u64, bytes);
+    for &dim in val.shape().iter() {
+        write_varint(dim as u64, bytes);
+    }
+    match val {
+        Value::Num(arr) => bytes.extend(arr.data.iter().flat_map(|n| n.to_le_bytes())),
+        Value::Byte(arr) => bytes.extend(arr.data.iter()),
+        Value::Complex(arr) => {
+            for c in &arr.data {
+                bytes.extend(c.re.to_le_bytes());
+                bytes.extend(c.im.to_le_bytes());
+            }
+        }
+        Value::Char(arr) => {
+            for &c in &arr.data {
+                write_varint(c as u64, bytes);
+            }
+        }
+        Value::Box(arr) => {
+            if depth >= MAX_DEPTH && !arr.data.is_empty() {
+                return Err(too_deep);
+            }
+            for Boxed(val) in &arr.data {
+                write_value(val, depth + 1, bytes)?;
+            }
+        }
+    }
+    if let Some(label) = &meta.label {
+        write_varint(label.len() as u64, bytes);
+        bytes.extend(label.as_bytes());
+    }
+    if let Some(keys) = &meta.map_keys {
+        if depth >= MAX_DEPTH {
+            return Err(too_deep);
+        }
+        write_value(&keys.clone().normalized(), depth + 1, bytes)?;
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    env: &'a Uiua,
+}
+
+impl<'a> Reader<'a> {
+    fn truncated(&self) -> crate::UiuaError {
+        self.env.error("Encoded value is truncated")
+    }
+    fn take(&mut self, n: usize) -> UiuaResult<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(self.truncated());
+        }
+        let (taken, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(taken)
+    }
+    fn byte(&mut self) -> UiuaResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+    fn f64(&mut self) -> UiuaResult<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    fn varint(&mut self) -> UiuaResult<u64> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        Err(self.env.error("Encoded value has an invalid varint"))
+    }
+    fn usize(&mut self) -> UiuaResult<usize> {
+        let n = self.varint()?;
+        usize::try_from(n).map_err(|_| self.env.error(format!("{n} is too large")))
+    }
+    /// Read `count` elements, each at least `min_size` bytes long
+    fn elems<T: Clone>(
+        &mut self,
+        count: usize,
+        min_size: usize,
+        mut read: impl FnMut(&mut Self) -> UiuaResult<T>,
+    ) -> UiuaResult<EcoVec<T>> {
+        // Check the length before allocating so bad shapes cannot exhaust memory
+        if count.saturating_mul(min_size) > self.bytes.len() {
+            return Err(self.truncated());
+        }
+        let mut data = EcoVec::with_capacity(count);
+        for _ in 0..count {
+            data.push(read(self)?);
+        }
+        Ok(data)
+    }
+    /// Decode a value
+    ///
+    /// Boxes and map keys are decoded with an explicit stack rather than by recursion,
+    /// so that nesting cannot overflow the native stack.
+    fn value(&mut self) -> UiuaResult<Value> {
+        let env = self.env;
+        let too_deep = || env.error("Encoded value is nested too deeply");
+        let mut pending = Vec::new();
+        'values: loop {
+            let tag = self.byte()?;
+            let flags = ArrayFlags::from_bits_truncate(self.byte()?);
+            let meta_bits = self.byte()?;
+            let rank = self.usize()?;
+            if rank > self.bytes.len() {
+                return Err(self.truncated());
+            }
+            let shape: Shape = (0..rank).map(|_| self.usize()).collect::<UiuaResult<_>>()?;
+            let count = (shape.iter())
+                .try_fold(1usize, |acc, &dim| acc.checked_mul(dim))
+                .ok_or_else(|| self.truncated())?;
+            let val: Value = match tag {
+                NUM_TAG => Array::new(shape, self.elems(count, 8, Self::f64)?).into(),
+                BYTE_TAG => Array::new(shape, self.elems(count, 1, Self::byte)?).into(),
+                COMPLEX_TAG => {
+                    let data = self.elems(count, 16, |r| Ok(Complex::new(r.f64()?, r.f64()?)))?;
+                    Array::new(shape, data).into()
+                }
+                CHAR_TAG => {
+                    let data = self.elems(count, 1, |r| {
+                        let n = r.varint()?;
+                        (u32::try_from(n).ok().and_then(char::from_u32)).ok_or_else(|| {
+                            r.env
+                                .error(format!("Encoded value has invalid character {n:#x}"))
+                        })
+                    })?;
+                    Array::new(shape, data).into()
+                }
+                BOX_TAG if count == 0 => Array::<Boxed>::new(shape, EcoVec::new()).into(),
+                BOX_TAG => {
+                    if pending.len() >= MAX_DEPTH {
+                        return Err(too_deep());
+                    }
+                    // Check the length before allocating so bad shapes cannot exhaust memory
+                    if count.saturating_mul(4) > self.bytes.len() {
+                        return Err(self.truncated());
+                    }
+                    pending.push(Pending::Box {
+                        shape,
+                        flags,
+                        meta_bits,
+                        count,
+                        data: EcoVec::with_capacity(count),
+                    });
+                    continue;
+                }
+                tag => return Err(env.error(format!("Encoded value has invalid type {tag}"))),
+            };
+            let mut parts = (val, flags, meta_bits);
+            // Finish the value, along with any boxes it completes
+            loop {
+                let (mut val, flags, meta_bits) = parts;
+                if !flags.is_empty() {
+                    val.meta_mut().flags = flags;
+                }
+                if meta_bits & LABEL_BIT != 0 {
+                    let len = self.usize()?;
+                    let label = std::str::from_utf8(self.take(len)?).map_err(|e| {
+                        env.error(format!("Encoded value has an invalid label: {e}"))
+                    })?;
+                    val.meta_mut().label = Some(EcoString::from(label));
+                }
+                if meta_bits & MAP_BIT != 0 {
+                    if pending.len() >= MAX_DEPTH {
+                        return Err(too_deep());
+                    }
+                    pending.push(Pending::Keys(val));
+                    continue 'values;
+                }
+                loop {
+                    match pending.pop() {
+                        None => return Ok(val),
+                        Some(Pending::Keys(mut map)) => {
+                            map.map(val, env)?;
+                            val = map;
+                        }
+                        Some(Pending::Box {
+                            shape,
+                            flags,
+                            meta_bits,
+                            count,
+                            mut data,
+                        }) => {
+                            data.push(Boxed(val));
+                            if data.len() < count {
+                                pending.push(Pending::Box {
+                                    shape,
+                                    flags,
+                                    meta_bits,
+                                    count,
+                                    data,
+                                });
+                                continue 'values;
+                            }
+                            parts = (Array::new(shape, data).into(), flags, meta_bits);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A value whose nested values are still being decoded
+enum Pending {
+    /// A box array and the elements decoded so far
+    Box {
+        shape: Shape,
+        flags: ArrayFlags,
+        meta_bits: u8,
+        count: usize,
+        data: EcoVec<Boxed>,
+    },
+    /// A map waiting for its keys
+    Keys(Value),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint_round_trip() {
+        let env = Uiua::with_safe_sys();
+        for n in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut bytes = Vec::new();
+            write_varint(n, &mut bytes);
+            let mut reader = Reader {
+                bytes: &bytes,
+                env: &env,
+            };
+            assert_eq!(reader.varint().unwrap(), n);
+            assert!(reader.bytes.is_empty());
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let env = Uiua::with_safe_sys();
+        let bytes = Value::from([1u8, 2]).to_binary(&env).unwrap();
+        assert_eq!(bytes, b"UAV\x01\x01\x00\x00\x01\x02\x01\x02");
+    }
+
+    #[test]
+    fn nesting() {
+        let env = Uiua::with_safe_sys();
+        let mut val = Value::from(1.0);
+        for _ in 0..MAX_DEPTH {
+            val = Value::from(Boxed(val));
+        }
+        let bytes = val.to_binary(&env).unwrap();
+        assert_eq!(Value::from_binary(&bytes, &env).unwrap(), val);
+
+        // Values that could not be decoded are not encoded
+        let err = Value::from(Boxed(val)).to_binary(&env).unwrap_err();
+        assert!(err.to_string().contains("nested too deeply"), "{err}");
+    }
+}
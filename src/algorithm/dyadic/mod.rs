@@ -212,6 +212,7 @@ impl<T: Clone> Array<T> {
         self.take_map_keys();
         match count {
             Ok(count) => {
+                validate_size::<T>([count.unsigned_abs(), self.data.len()], env)?;
                 if count < 0 {
                     self.reverse();
                 }
@@ -11,21 +11,38 @@ use crate::{Array, Uiua, UiuaResult, Value};
 pub(crate) fn image_encode(env: &mut Uiua) -> UiuaResult {
     #[cfg(feature = "image")]
     {
-        let format = env
-            .pop(1)?
-            .as_string(env, "Image format must be a string")?;
+        use image::codecs::pnm::{PnmSubtype, SampleEncoding};
+
+        let (format, options) = split_options(env.pop(1)?);
+        let format = format.as_string(env, "Image format must be a string")?;
         let value = env.pop(2)?;
+        let image = value_to_image(&value).map_err(|e| env.error(e))?;
+        if !options.is_empty() && !["jpg", "jpeg", "png"].contains(&format.as_str()) {
+            return Err(env.error(format!("{format} encoding does not take options")));
+        }
         let output_format = match format.as_str() {
-            "jpg" | "jpeg" => ImageOutputFormat::Jpeg(100),
-            "png" => ImageOutputFormat::Png,
+            "jpg" | "jpeg" => ImageOutputFormat::Jpeg(jpeg_quality(&options, env)?),
+            "png" => {
+                let compression = png_compression(&options, env)?;
+                let bytes = png_bytes(&image, compression).map_err(|e| env.error(e))?;
+                env.push(Array::<u8>::from(bytes.as_slice()));
+                return Ok(());
+            }
             "bmp" => ImageOutputFormat::Bmp,
             "gif" => ImageOutputFormat::Gif,
             "ico" => ImageOutputFormat::Ico,
             "qoi" => ImageOutputFormat::Qoi,
+            "tiff" | "tif" => ImageOutputFormat::Tiff,
+            "webp" => ImageOutputFormat::WebP,
+            // Use the most specific subtype that can represent the image
+            "pnm" => ImageOutputFormat::Pnm(match image.color() {
+                image::ColorType::L8 => PnmSubtype::Graymap(SampleEncoding::Binary),
+                image::ColorType::Rgb8 => PnmSubtype::Pixmap(SampleEncoding::Binary),
+                _ => PnmSubtype::ArbitraryMap,
+            }),
             format => return Err(env.error(format!("Invalid image format: {}", format))),
         };
-        let bytes =
-            crate::encode::value_to_image_bytes(&value, output_format).map_err(|e| env.error(e))?;
+        let bytes = image_to_bytes(&image, output_format).map_err(|e| env.error(e))?;
         env.push(Array::<u8>::from(bytes.as_slice()));
         Ok(())
     }
@@ -33,6 +50,84 @@ pub(crate) fn image_encode(env: &mut Uiua) -> UiuaResult {
     Err(env.error("Image encoding is not supported in this environment"))
 }
 
+/// Split an argument into its main value and its options
+///
+/// The argument is either just the main value or a list whose first item is the main value.
+#[cfg(feature = "image")]
+fn split_options(val: Value) -> (Value, Vec<Value>) {
+    match val {
+        Value::Box(arr) if arr.rank() == 1 && arr.row_count() > 0 => {
+            let mut rows = Value::from(arr).into_rows().map(Value::unboxed);
+            let main = rows.next().unwrap();
+            (main, rows.collect())
+        }
+        val => (val, Vec::new()),
+    }
+}
+
+#[cfg(feature = "image")]
+fn jpeg_quality(options: &[Value], env: &Uiua) -> UiuaResult<u8> {
+    match options {
+        [] => Ok(100),
+        [quality] => {
+            let quality = quality.as_nat(env, "JPEG quality must be a natural number")?;
+            if !(1..=100).contains(&quality) {
+                return Err(env.error(format!(
+                    "JPEG quality must be between 1 and 100, but it is {quality}"
+                )));
+            }
+            Ok(quality as u8)
+        }
+        _ => Err(env.error("JPEG encoding takes at most 1 option")),
+    }
+}
+
+#[cfg(feature = "image")]
+fn png_compression(
+    options: &[Value],
+    env: &Uiua,
+) -> UiuaResult<image::codecs::png::CompressionType> {
+    use image::codecs::png::CompressionType;
+    match options {
+        [] => Ok(CompressionType::Fast),
+        [compression] => {
+            let compression = compression.as_string(env, "PNG compression must be a string")?;
+            match compression.as_str() {
+                "fast" => Ok(CompressionType::Fast),
+                "default" => Ok(CompressionType::Default),
+                "best" => Ok(CompressionType::Best),
+                _ => Err(env.error(format!(
+                    "Invalid PNG compression {compression:?}. \
+                    Valid compressions are \"fast\", \"default\", and \"best\""
+                ))),
+            }
+        }
+        _ => Err(env.error("PNG encoding takes at most 1 option")),
+    }
+}
+
+#[doc(hidden)]
+#[cfg(feature = "image")]
+pub fn png_bytes(
+    image: &DynamicImage,
+    compression: image::codecs::png::CompressionType,
+) -> Result<Vec<u8>, String> {
+    use image::{
+        codecs::png::{FilterType, PngEncoder},
+        ImageEncoder,
+    };
+    let mut bytes = Vec::new();
+    PngEncoder::new_with_quality(&mut bytes, compression, FilterType::Adaptive)
+        .write_image(
+            image.as_bytes(),
+            image.width(),
+            image.height(),
+            image.color(),
+        )
+        .map_err(|e| format!("Failed to write image: {e}"))?;
+    Ok(bytes)
+}
+
 pub(crate) fn image_decode(env: &mut Uiua) -> UiuaResult {
     #[cfg(feature = "image")]
     {
@@ -74,9 +169,35 @@ pub(crate) fn image_decode(env: &mut Uiua) -> UiuaResult {
 pub(crate) fn gif_encode(env: &mut Uiua) -> UiuaResult {
     #[cfg(feature = "gif")]
     {
-        let delay = env.pop(1)?.as_num(env, "Delay must be a number")?;
+        let (timing, options) = split_options(env.pop(1)?);
         let value = env.pop(2)?;
-        let bytes = crate::encode::value_to_gif_bytes(&value, delay).map_err(|e| env.error(e))?;
+        let mut gif_options = match timing.rank() {
+            0 => GifOptions::from_frame_rate(
+                timing.as_num(env, "Frame rate must be a number or list of delays")?,
+            ),
+            _ => {
+                let delays = timing.as_nums(env, "Frame delays must be a list of numbers")?;
+                if delays.len() != value.row_count() {
+                    return Err(env.error(format!(
+                        "There are {} frame delays, but there are {} frames",
+                        delays.len(),
+                        value.row_count()
+                    )));
+                }
+                if let Some(delay) = delays.iter().find(|d| **d < 0.0 || !d.is_finite()) {
+                    return Err(env.error(format!(
+                        "Frame delays must be non-negative and finite, but one is {delay}"
+                    )));
+                }
+                GifOptions {
+                    delays,
+                    ..GifOptions::default()
+                }
+            }
+        };
+        gif_options.parse_options(options, env)?;
+        let bytes = crate::encode::value_to_gif_bytes_with_options(&value, &gif_options)
+            .map_err(|e| env.error(e))?;
         env.push(Array::<u8>::from(bytes.as_slice()));
         Ok(())
     }
@@ -90,10 +211,23 @@ pub(crate) fn gif_decode(env: &mut Uiua) -> UiuaResult {
         let bytes = env
             .pop(1)?
             .as_bytes(env, "Gif bytes must be a byte array")?;
-        let (frame_rate, value) =
+        let (options, value) =
             crate::encode::gif_bytes_to_value(&bytes).map_err(|e| env.error(e))?;
         env.push(value);
-        env.push(frame_rate);
+        // These are the options that encode the gif again
+        let delays: Value = options.delays.into_iter().collect();
+        let plays = options.plays.map_or(f64::INFINITY, f64::from);
+        let palette = if options.local_palettes {
+            "local"
+        } else {
+            "global"
+        };
+        env.push(Array::<crate::Boxed>::from_iter([
+            crate::Boxed(delays),
+            crate::Boxed(plays.into()),
+            crate::Boxed(options.alpha_threshold.into()),
+            crate::Boxed(palette.into()),
+        ]));
         Ok(())
     }
     #[cfg(not(feature = "gif"))]
@@ -468,14 +602,123 @@ fn array_from_wav_bytes_impl<T: hound::Sample>(
     }
 }
 
+/// Options for encoding a GIF
+#[doc(hidden)]
+#[cfg(feature = "gif")]
+#[derive(Debug, Clone)]
+pub struct GifOptions {
+    /// The delay after each frame in seconds
+    ///
+    /// A single delay is used for all frames.
+    pub delays: Vec<f64>,
+    /// The number of times the animation plays, or `None` to loop forever
+    pub plays: Option<u16>,
+    /// Pixels with a lower alpha than this are transparent
+    ///
+    /// Fully transparent pixels are always transparent.
+    pub alpha_threshold: f64,
+    /// Whether each frame has its own palette instead of sharing one
+    pub local_palettes: bool,
+    /// Whether to dither colors that are not in the palette
+    pub dither: bool,
+}
+
+#[cfg(feature = "gif")]
+impl Default for GifOptions {
+    fn default() -> Self {
+        Self::from_frame_rate(10.0)
+    }
+}
+
+#[cfg(feature = "gif")]
+impl GifOptions {
+    /// Get the default options with a frame rate
+    pub fn from_frame_rate(frame_rate: f64) -> Self {
+        const MIN_FRAME_RATE: f64 = 1.0 / 60.0;
+        GifOptions {
+            delays: vec![(1.0 / frame_rate.max(MIN_FRAME_RATE)).abs()],
+            plays: None,
+            alpha_threshold: 0.0,
+            local_palettes: false,
+            dither: false,
+        }
+    }
+    fn parse_options(&mut self, options: Vec<Value>, env: &Uiua) -> UiuaResult {
+        let mut scalar_index = 0;
+        for (i, option) in options.into_iter().enumerate() {
+            match option {
+                Value::Char(_) => {
+                    let option = option.as_string(env, "GIF option must be a string")?;
+                    match option.as_str() {
+                        "global" => self.local_palettes = false,
+                        "local" => self.local_palettes = true,
+                        "dither" => self.dither = true,
+                        _ => {
+                            return Err(env.error(format!(
+                                "Invalid GIF option {option:?}. \
+                                Valid options are \"global\", \"local\", and \"dither\""
+                            )))
+                        }
+                    }
+                }
+                option if option.rank() == 0 => {
+                    let n = option.as_num(env, "GIF options must be numbers or strings")?;
+                    match scalar_index {
+                        0 => {
+                            self.plays = if n == f64::INFINITY {
+                                None
+                            } else if n >= 1.0 && n.fract() == 0.0 && n <= u16::MAX as f64 {
+                                Some(n as u16)
+                            } else {
+                                return Err(env.error(format!(
+                                    "GIF play count must be a positive integer \
+                                    or infinity, but it is {n}"
+                                )));
+                            }
+                        }
+                        1 => {
+                            if !(0.0..=1.0).contains(&n) {
+                                return Err(env.error(format!(
+                                    "GIF alpha threshold must be between 0 and 1, but it is {n}"
+                                )));
+                            }
+                            self.alpha_threshold = n;
+                        }
+                        count => {
+                            return Err(env.error(format!(
+                                "{} is too many scalar options to encode a GIF",
+                                count + 1
+                            )))
+                        }
+                    }
+                    scalar_index += 1;
+                }
+                option => {
+                    return Err(env.error(format!(
+                        "GIF options must be strings or scalars, \
+                        but option {i} has shape {}",
+                        option.shape()
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[doc(hidden)]
 #[cfg(feature = "gif")]
 pub fn value_to_gif_bytes(value: &Value, frame_rate: f64) -> Result<Vec<u8>, String> {
-    use std::collections::{HashMap, HashSet};
+    value_to_gif_bytes_with_options(value, &GifOptions::from_frame_rate(frame_rate))
+}
 
-    use color_quant::NeuQuant;
+#[doc(hidden)]
+#[cfg(feature = "gif")]
+pub fn value_to_gif_bytes_with_options(
+    value: &Value,
+    options: &GifOptions,
+) -> Result<Vec<u8>, String> {
     use gif::{DisposalMethod, Frame};
-    use image::Rgba;
 
     if value.row_count() == 0 {
         return Err("Cannot convert empty array into GIF".into());
@@ -498,67 +741,49 @@ pub fn value_to_gif_bytes(value: &Value, frame_rate: f64) -> Result<Vec<u8>, Str
             height
         ));
     }
-    let mut bytes = std::io::Cursor::new(Vec::new());
-    let mut opaque_colors = HashSet::new();
-    for frame in &frames {
-        for &Rgba([r, g, b, a]) in frame.pixels() {
-            if a > 0 {
-                opaque_colors.insert([r, g, b]);
-            }
-        }
-    }
-    let mut reduced_colors = HashSet::new();
-    let mut color_reduction = HashMap::new();
-    if opaque_colors.len() <= 255 {
-        for color in opaque_colors {
-            reduced_colors.insert(color);
-            color_reduction.insert(color, color);
-        }
+    let alpha_threshold = (options.alpha_threshold * 255.0).round() as u8;
+    let is_opaque = |a: u8| a > 0 && a >= alpha_threshold;
+    let global_palette = if options.local_palettes {
+        None
     } else {
-        let opaque_data: Vec<u8> = opaque_colors
-            .iter()
-            .flat_map(|c| c.iter().copied().chain([128]))
-            .collect();
-        let nq = NeuQuant::new(10, 255, &opaque_data);
-        let map = nq.color_map_rgb();
-        for color in opaque_colors {
-            let index = nq.index_of(&[color[0], color[1], color[2], 128]);
-            let start = index * 3;
-            let reduced = [map[start], map[start + 1], map[start + 2]];
-            reduced_colors.insert(reduced);
-            color_reduction.insert(color, reduced);
-        }
+        Some(GifPalette::new(frames.iter().flat_map(|frame| {
+            (frame.pixels())
+                .filter(|p| is_opaque(p.0[3]))
+                .map(|p| [p.0[0], p.0[1], p.0[2]])
+        })))
+    };
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    let global_bytes = global_palette.as_ref().map(GifPalette::bytes);
+    let mut encoder = gif::Encoder::new(
+        &mut bytes,
+        width as u16,
+        height as u16,
+        global_bytes.as_deref().unwrap_or(&[]),
+    )
+    .map_err(|e| e.to_string())?;
+    let repeat = match options.plays {
+        None => Some(gif::Repeat::Infinite),
+        // A GIF without a repeat extension plays once
+        Some(1) => None,
+        Some(n) => Some(gif::Repeat::Finite(n - 1)),
+    };
+    if let Some(repeat) = repeat {
+        encoder.set_repeat(repeat).map_err(|e| e.to_string())?;
     }
-    let mut palette = Vec::with_capacity(reduced_colors.len() * 3);
-    let mut color_map: HashMap<[u8; 3], u8> = HashMap::new();
-    for color in reduced_colors {
-        color_map.insert(color, (palette.len() / 3) as u8);
-        palette.extend(color);
-    }
-    let transparent_index = color_map.len() as u8;
-    palette.extend([0; 3]);
-    let mut encoder = gif::Encoder::new(&mut bytes, width as u16, height as u16, &palette)
-        .map_err(|e| e.to_string())?;
-    const MIN_FRAME_RATE: f64 = 1.0 / 60.0;
-    let delay = ((1.0 / frame_rate.max(MIN_FRAME_RATE)).abs() * 100.0) as u16;
-    encoder
-        .set_repeat(gif::Repeat::Infinite)
-        .map_err(|e| e.to_string())?;
-    for image in frames {
-        let mut has_transparent = false;
-        let indices: Vec<u8> = image
-            .as_raw()
-            .chunks_exact(4)
-            .map(|chunk| {
-                if chunk[3] == 0 {
-                    has_transparent = true;
-                    return transparent_index;
-                }
-                let color = [chunk[0], chunk[1], chunk[2]];
-                let reduced = color_reduction[&color];
-                color_map[&reduced]
-            })
-            .collect();
+    for (i, image) in frames.into_iter().enumerate() {
+        let local_palette;
+        let palette = match &global_palette {
+            Some(palette) => palette,
+            None => {
+                local_palette = GifPalette::new(
+                    (image.pixels())
+                        .filter(|p| is_opaque(p.0[3]))
+                        .map(|p| [p.0[0], p.0[1], p.0[2]]),
+                );
+                &local_palette
+            }
+        };
+        let (indices, has_transparent) = palette.frame_indices(&image, is_opaque, options.dither);
         let dispose = if has_transparent {
             DisposalMethod::Previous
         } else {
@@ -570,19 +795,131 @@ pub fn value_to_gif_bytes(value: &Value, frame_rate: f64) -> Result<Vec<u8>, Str
                 width as u16,
                 height as u16,
                 indices,
-                Some(transparent_index),
+                Some(palette.transparent_index()),
             )
         };
-        frame.delay = delay;
+        if global_palette.is_none() {
+            frame.palette = Some(palette.bytes());
+        }
+        let delay = options
+            .delays
+            .get(i)
+            .or(options.delays.last())
+            .unwrap_or(&0.0);
+        frame.delay = (delay * 100.0).round().min(u16::MAX as f64) as u16;
         encoder.write_frame(&frame).map_err(|e| e.to_string())?;
     }
     drop(encoder);
     Ok(bytes.into_inner())
 }
 
+/// The colors that a GIF or one of its frames can use
+///
+/// The last index is reserved for transparent pixels.
+#[cfg(feature = "gif")]
+struct GifPalette {
+    colors: Vec<[u8; 3]>,
+    /// The exact index of each color, if there are few enough colors
+    exact: std::collections::HashMap<[u8; 3], u8>,
+    /// The quantizer, if there are too many colors
+    quantizer: Option<color_quant::NeuQuant>,
+}
+
+#[cfg(feature = "gif")]
+impl GifPalette {
+    fn new(colors: impl Iterator<Item = [u8; 3]>) -> Self {
+        use std::collections::{HashMap, HashSet};
+
+        use color_quant::NeuQuant;
+
+        let unique: HashSet<[u8; 3]> = colors.collect();
+        if unique.len() <= 255 {
+            let colors: Vec<[u8; 3]> = unique.into_iter().collect();
+            let exact: HashMap<[u8; 3], u8> = (colors.iter().enumerate())
+                .map(|(i, &c)| (c, i as u8))
+                .collect();
+            GifPalette {
+                colors,
+                exact,
+                quantizer: None,
+            }
+        } else {
+            let data: Vec<u8> = (unique.iter())
+                .flat_map(|c| c.iter().copied().chain([128]))
+                .collect();
+            let quantizer = NeuQuant::new(10, 255, &data);
+            let colors = (quantizer.color_map_rgb().chunks_exact(3))
+                .map(|c| [c[0], c[1], c[2]])
+                .collect();
+            GifPalette {
+                colors,
+                exact: HashMap::new(),
+                quantizer: Some(quantizer),
+            }
+        }
+    }
+    fn transparent_index(&self) -> u8 {
+        self.colors.len() as u8
+    }
+    /// Get the palette in the format used by GIF
+    fn bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self.colors.iter().flatten().copied().collect();
+        bytes.extend([0; 3]);
+        bytes
+    }
+    fn index_of(&self, [r, g, b]: [u8; 3]) -> u8 {
+        match &self.quantizer {
+            Some(quantizer) => quantizer.index_of(&[r, g, b, 128]) as u8,
+            None => self.exact[&[r, g, b]],
+        }
+    }
+    /// Get the palette index of each pixel of a frame, and whether any are transparent
+    fn frame_indices(
+        &self,
+        image: &image::RgbaImage,
+        is_opaque: impl Fn(u8) -> bool,
+        dither: bool,
+    ) -> (Vec<u8>, bool) {
+        let width = image.width() as usize;
+        let mut has_transparent = false;
+        // Only approximated colors need dithering
+        let dither = dither && self.quantizer.is_some();
+        let mut errors = vec![[0.0f32; 3]; if dither { image.as_raw().len() / 4 } else { 0 }];
+        let indices = (image.as_raw().chunks_exact(4).enumerate())
+            .map(|(i, pixel)| {
+                if !is_opaque(pixel[3]) {
+                    has_transparent = true;
+                    return self.transparent_index();
+                }
+                if !dither {
+                    return self.index_of([pixel[0], pixel[1], pixel[2]]);
+                }
+                // Floyd-Steinberg dithering
+                let wanted: [f32; 3] =
+                    std::array::from_fn(|c| (pixel[c] as f32 + errors[i][c]).clamp(0.0, 255.0));
+                let index = self.index_of(wanted.map(|c| c.round() as u8));
+                let got = self.colors[index as usize];
+                let (x, y) = (i % width, i / width);
+                for (dx, dy, weight) in [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)] {
+                    let (nx, ny) = (x as isize + dx, y + dy);
+                    if nx < 0 || nx as usize >= width || ny * width >= errors.len() {
+                        continue;
+                    }
+                    let error = &mut errors[ny * width + nx as usize];
+                    for ((e, w), g) in error.iter_mut().zip(wanted).zip(got) {
+                        *e += (w - g as f32) * weight / 16.0;
+                    }
+                }
+                index
+            })
+            .collect();
+        (indices, has_transparent)
+    }
+}
+
 #[doc(hidden)]
 #[cfg(feature = "gif")]
-pub fn gif_bytes_to_value(bytes: &[u8]) -> Result<(f64, Value), gif::DecodingError> {
+pub fn gif_bytes_to_value(bytes: &[u8]) -> Result<(GifOptions, Value), gif::DecodingError> {
     let mut decoder = gif::DecodeOptions::new();
     decoder.set_color_output(gif::ColorOutput::RGBA);
     let mut decoder = decoder.read_info(bytes)?;
@@ -590,8 +927,9 @@ pub fn gif_bytes_to_value(bytes: &[u8]) -> Result<(f64, Value), gif::DecodingErr
     let gif_width = first_frame.width as usize;
     let gif_height = first_frame.height as usize;
     let mut data: crate::cowslice::CowSlice<f64> = Default::default();
-    let mut frame_count = 1;
-    let mut delay_sum = first_frame.delay as f64 / 100.0;
+    let mut delays = vec![first_frame.delay as f64 / 100.0];
+    let mut transparent = first_frame.transparent.is_some();
+    let mut local_palettes = first_frame.palette.is_some();
     // Init frame data with the first frame
     let mut frame_data = first_frame.buffer.to_vec();
     data.extend(frame_data.iter().map(|b| *b as f64 / 255.0));
@@ -617,15 +955,28 @@ pub fn gif_bytes_to_value(bytes: &[u8]) -> Result<(f64, Value), gif::DecodingErr
             }
         }
         data.extend(frame_data.iter().map(|b| *b as f64 / 255.0));
-        frame_count += 1;
-        delay_sum += frame.delay as f64 / 100.0;
+        delays.push(frame.delay as f64 / 100.0);
+        transparent |= frame.transparent.is_some();
+        local_palettes |= frame.palette.is_some();
     }
-    let avg_delay = delay_sum / frame_count as f64;
-    let frame_rate = 1.0 / avg_delay;
-    let shape = crate::Shape::from_iter([frame_count, gif_height, gif_width, 4]);
+    let shape = crate::Shape::from_iter([delays.len(), gif_height, gif_width, 4]);
     let mut num = Value::Num(Array::new(shape, data));
     num.compress();
-    Ok((frame_rate, num))
+    let plays = match decoder.repeat() {
+        gif::Repeat::Infinite => None,
+        gif::Repeat::Finite(n) => Some(n.saturating_add(1)),
+    };
+    // Decoded pixels are either fully transparent or fully opaque,
+    // so any pixel that is not opaque should be transparent when encoding them again
+    let alpha_threshold = if transparent { 1.0 } else { 0.0 };
+    let options = GifOptions {
+        delays,
+        plays,
+        alpha_threshold,
+        local_palettes,
+        dither: false,
+    };
+    Ok((options, num))
 }
 
 pub(crate) fn layout_text(options: Value, text: Value, env: &Uiua) -> UiuaResult<Value> {
@@ -637,18 +988,17 @@ pub(crate) fn layout_text(options: Value, text: Value, env: &Uiua) -> UiuaResult
     Err(env.error("Text layout is not supported in this environment"))
 }
 
+/// Call a function with the font system used to render text
+///
+/// The font system is created the first time it is used on each thread.
 #[cfg(feature = "font_shaping")]
-fn layout_text_impl(options: Value, text: Value, env: &Uiua) -> UiuaResult<Value> {
-    use std::{cell::RefCell, iter::repeat};
+pub(crate) fn with_font_system<T>(
+    f: impl FnOnce(&mut cosmic_text::FontSystem, &mut cosmic_text::SwashCache) -> T,
+) -> T {
+    use std::cell::RefCell;
 
     use cosmic_text::*;
-    use ecow::eco_vec;
 
-    use crate::{
-        algorithm::{validate_size, FillContext},
-        grid_fmt::GridFmt,
-        Boxed, Shape,
-    };
     struct FontStuff {
         system: FontSystem,
         swash_cache: SwashCache,
@@ -657,6 +1007,41 @@ fn layout_text_impl(options: Value, text: Value, env: &Uiua) -> UiuaResult<Value
         static FONT_STUFF: RefCell<Option<FontStuff>> = const { RefCell::new(None) };
     }
 
+    FONT_STUFF.with(|stuff| {
+        let mut stuff = stuff.borrow_mut();
+        let FontStuff {
+            system,
+            swash_cache,
+        } = stuff.get_or_insert_with(|| {
+            let mut db = fontdb::Database::new();
+            db.set_monospace_family("Uiua386");
+            db.set_sans_serif_family("Uiua386");
+            db.load_system_fonts();
+            db.load_font_data(include_bytes!("Uiua386.ttf").to_vec());
+            let locale = sys_locale::get_locale().unwrap_or_else(|| "en-US".into());
+            let system = FontSystem::new_with_locale_and_db(locale, db);
+            FontStuff {
+                system,
+                swash_cache: SwashCache::new(),
+            }
+        });
+        f(system, swash_cache)
+    })
+}
+
+#[cfg(feature = "font_shaping")]
+fn layout_text_impl(options: Value, text: Value, env: &Uiua) -> UiuaResult<Value> {
+    use std::iter::repeat;
+
+    use cosmic_text::*;
+    use ecow::eco_vec;
+
+    use crate::{
+        algorithm::{validate_size, FillContext},
+        grid_fmt::GridFmt,
+        Boxed, Shape,
+    };
+
     let mut string = String::new();
     match text {
         Value::Char(arr) if arr.rank() <= 1 => string = arr.data.iter().copied().collect(),
@@ -809,25 +1194,7 @@ fn layout_text_impl(options: Value, text: Value, env: &Uiua) -> UiuaResult<Value
     line_height *= size;
     let metrics = Metrics::new(size, line_height);
 
-    FONT_STUFF.with(|stuff| -> UiuaResult<Value> {
-        let mut stuff = stuff.borrow_mut();
-        if stuff.is_none() {
-            let mut db = fontdb::Database::new();
-            db.set_monospace_family("Uiua386");
-            db.set_sans_serif_family("Uiua386");
-            db.load_system_fonts();
-            db.load_font_data(include_bytes!("Uiua386.ttf").to_vec());
-            let locale = sys_locale::get_locale().unwrap_or_else(|| "en-US".into());
-            let system = FontSystem::new_with_locale_and_db(locale, db);
-            *stuff = Some(FontStuff {
-                system,
-                swash_cache: SwashCache::new(),
-            });
-        }
-        let FontStuff {
-            system,
-            swash_cache,
-        } = stuff.as_mut().unwrap();
+    with_font_system(|system, swash_cache| -> UiuaResult<Value> {
         // Init buffer
         let mut buffer = Buffer::new(system, metrics);
         let mut buffer = buffer.borrow_with(system);
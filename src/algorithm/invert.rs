@@ -5,7 +5,7 @@ use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
     error::Error,
-    fmt,
+    fmt, slice,
 };
 
 use ecow::{eco_vec, EcoString, EcoVec};
@@ -13,11 +13,11 @@ use regex::Regex;
 use serde::*;
 
 use crate::{
-    check::{instrs_clean_signature, instrs_signature, naive_under_sig, SigCheckError},
-    instrs_are_pure,
+    check::{naive_under_sig, nodes_clean_signature, nodes_signature, SigCheckError},
+    nodes_are_pure,
     primitive::{ImplPrimitive, Primitive},
-    Array, Assembly, BindingKind, Compiler, Complex, FmtInstrs, Function, FunctionFlags,
-    FunctionId, Instr, NewFunction, Purity, Signature, Span, SysOp, TempStack, Uiua, UiuaResult,
+    Array, Assembly, BindingKind, Compiler, Complex, FuncNode, Function, FunctionFlags, FunctionId,
+    Instr, NewFunction, Node, Purity, Signature, Span, SysOp, TempStack, Uiua, UiuaResult,
     UnderedFunctions, Value,
 };
 
@@ -89,7 +89,7 @@ impl fmt::Display for InversionError {
 }
 
 impl InversionError {
-    fn func(self, f: &Function) -> Self {
+    fn func(self, f: &FuncNode) -> Self {
         match self {
             InversionError::InnerFunc(mut ids, inner) => {
                 ids.push(f.id.clone());
@@ -221,6 +221,8 @@ fn prim_inverse(prim: Primitive, span: usize) -> Option<Instr> {
         Json => Instr::ImplPrim(UnJson, span),
         Csv => Instr::ImplPrim(UnCsv, span),
         Xlsx => Instr::ImplPrim(UnXlsx, span),
+        Npy => Instr::ImplPrim(UnNpy, span),
+        Binary => Instr::ImplPrim(UnBinary, span),
         Fft => Instr::ImplPrim(UnFft, span),
         DateTime => Instr::ImplPrim(UnDatetime, span),
         _ => return None,
@@ -251,6 +253,8 @@ fn impl_prim_inverse(prim: ImplPrimitive, span: usize) -> Option<Instr> {
         UnJson => Instr::Prim(Json, span),
         UnCsv => Instr::Prim(Csv, span),
         UnXlsx => Instr::Prim(Xlsx, span),
+        UnNpy => Instr::Prim(Npy, span),
+        UnBinary => Instr::Prim(Binary, span),
         UnFft => Instr::Prim(Fft, span),
         ImageDecode => Instr::Prim(ImageEncode, span),
         GifDecode => Instr::Prim(GifEncode, span),
@@ -416,40 +420,60 @@ static BY_INVERT_PATTERNS: &[&dyn InvertPattern] = {
     ]
 };
 
-/// Invert a sequence of instructions
-pub(crate) fn invert_instrs(
-    instrs: &[Instr],
-    comp: &mut Compiler,
-) -> InversionResult<EcoVec<Instr>> {
-    if instrs.is_empty() {
+/// Lift the functions that instructions push into nodes
+///
+/// This lets patterns see into functions that were compiled before the current one.
+/// Modifiers whose operands become lifted are gathered into [`Node::Mod`]s.
+pub(crate) fn lift_funcs(nodes: &[Node], asm: &Assembly) -> EcoVec<Node> {
+    let mut lifted = EcoVec::with_capacity(nodes.len());
+    lift_funcs_into(nodes, asm, &mut lifted);
+    lifted
+}
+fn lift_funcs_into(nodes: &[Node], asm: &Assembly, lifted: &mut EcoVec<Node>) {
+    for node in nodes {
+        match node {
+            Node::Run(nodes) => lift_funcs_into(nodes, asm, lifted),
+            Node::Instr(instr @ (Instr::PushFunc(_) | Instr::CustomInverse(..))) => {
+                lifted.extend(Node::from_instrs(slice::from_ref(instr), asm).into_vec())
+            }
+            Node::Instr(instr) => match Node::fold_modifier(lifted, instr.clone()) {
+                Ok(node) => lifted.push(node),
+                Err(instr) => lifted.push(Node::Instr(instr)),
+            },
+            node => lifted.push(node.clone()),
+        }
+    }
+}
+
+/// Invert a sequence of nodes
+pub(crate) fn invert_nodes(nodes: &[Node], comp: &mut Compiler) -> InversionResult<EcoVec<Node>> {
+    if nodes.is_empty() {
         return Ok(EcoVec::new());
     }
-    dbgln!("inverting {:?}", FmtInstrs(instrs, &comp.asm));
+    let lifted = lift_funcs(nodes, &comp.asm);
+    let nodes = lifted.as_slice();
+    dbgln!("inverting {:?}", nodes);
 
     let mut inverted = EcoVec::new();
-    let mut curr_instrs = instrs;
+    let mut curr_nodes = nodes;
     let mut error = Generic;
     'find_pattern: loop {
         for pattern in INVERT_PATTERNS {
-            match pattern.invert_extract(curr_instrs, comp) {
+            match pattern.invert_extract(curr_nodes, comp) {
                 Ok((input, mut inv)) => {
                     dbgln!(
                         "matched pattern {:?} on {:?} to {:?}",
                         pattern,
-                        FmtInstrs(&curr_instrs[..curr_instrs.len() - input.len()], &comp.asm),
-                        FmtInstrs(&inv, &comp.asm)
+                        &curr_nodes[..curr_nodes.len() - input.len()],
+                        &inv
                     );
                     inv.extend(inverted);
                     inverted = inv;
                     if input.is_empty() {
-                        dbgln!(
-                            "inverted {:?} to {:?}",
-                            FmtInstrs(instrs, &comp.asm),
-                            FmtInstrs(&inverted, &comp.asm)
-                        );
+                        dbgln!("inverted {:?} to {:?}", nodes, &inverted);
                         return resolve_uns(inverted, comp);
                     }
-                    curr_instrs = input;
+                    curr_nodes = input;
                     continue 'find_pattern;
                 }
                 Err(e) => error = error.max(e),
@@ -460,44 +484,42 @@ pub(crate) fn invert_instrs(
 
     dbgln!(
         "inverting {:?} failed with remaining {:?}",
-        FmtInstrs(instrs, &comp.asm),
-        FmtInstrs(curr_instrs, &comp.asm)
+        nodes,
+        curr_nodes
     );
 
     Err(error)
 }
 
-/// Invert a sequence of instructions with anti
-pub(crate) fn anti_instrs(instrs: &[Instr], comp: &mut Compiler) -> InversionResult<EcoVec<Instr>> {
-    if instrs.is_empty() {
+/// Invert a sequence of nodes with anti
+pub(crate) fn anti_nodes(nodes: &[Node], comp: &mut Compiler) -> InversionResult<EcoVec<Node>> {
+    if nodes.is_empty() {
         return Ok(EcoVec::new());
     }
-    dbgln!("anti-inverting {:?}", FmtInstrs(instrs, &comp.asm));
+    let lifted = lift_funcs(nodes, &comp.asm);
+    let nodes = lifted.as_slice();
+    dbgln!("anti-inverting {:?}", nodes);
 
     let mut inverted = EcoVec::new();
-    let mut curr_instrs = instrs;
+    let mut curr_nodes = nodes;
     let mut error = Generic;
     'find_pattern: loop {
         for pattern in ON_INVERT_PATTERNS {
-            match pattern.invert_extract(curr_instrs, comp) {
+            match pattern.invert_extract(curr_nodes, comp) {
                 Ok((input, mut inv)) => {
                     dbgln!(
                         "matched pattern {:?} on {:?} to {:?}",
                         pattern,
-                        FmtInstrs(&curr_instrs[..curr_instrs.len() - input.len()], &comp.asm),
-                        FmtInstrs(&inv, &comp.asm)
+                        &curr_nodes[..curr_nodes.len() - input.len()],
+                        &inv
                     );
                     inv.extend(inverted);
                     inverted = inv;
                     if input.is_empty() {
-                        dbgln!(
-                            "inverted {:?} to {:?}",
-                            FmtInstrs(instrs, &comp.asm),
-                            FmtInstrs(&inverted, &comp.asm)
-                        );
+                        dbgln!("inverted {:?} to {:?}", nodes, &inverted);
                         return resolve_uns(inverted, comp);
                     }
-                    curr_instrs = input;
+                    curr_nodes = input;
                     continue 'find_pattern;
                 }
                 Err(e) => error = error.max(e),
@@ -508,43 +530,47 @@ pub(crate) fn anti_instrs(instrs: &[Instr], comp: &mut Compiler) -> InversionRes
 
     dbgln!(
         "anti-inverting {:?} failed with remaining {:?}",
-        FmtInstrs(instrs, &comp.asm),
-        FmtInstrs(curr_instrs, &comp.asm)
+        nodes,
+        curr_nodes
     );
 
-    let sig = instrs_signature(instrs)?;
+    let sig = nodes_signature(nodes)?;
     if sig.args >= 2 {
-        let mut instrs = instrs.to_vec();
-        let span = instrs.iter().find_map(|instr| instr.span()).unwrap_or(0);
-        instrs.insert(0, Instr::copy_inline(span));
-        instrs.push(Instr::pop_inline(1, span));
-        let mut inverse = invert_instrs(&instrs, comp)?;
-        inverse.push(Instr::Prim(Primitive::Pop, span));
+        let mut nodes = nodes.to_vec();
+        let span = (nodes.iter())
+            .find_map(|node| node.as_instr().and_then(Instr::span))
+            .unwrap_or(0);
+        nodes.insert(0, Node::Instr(Instr::copy_inline(span)));
+        nodes.push(Node::Instr(Instr::pop_inline(1, span)));
+        let mut inverse = invert_nodes(&nodes, comp)?;
+        inverse.push(Node::Instr(Instr::Prim(Primitive::Pop, span)));
         Ok(inverse)
     } else {
-        invert_instrs(instrs, comp).map_err(|e| e.max(error))
+        invert_nodes(nodes, comp).map_err(|e| e.max(error))
     }
 }
 
-type Under = (EcoVec<Instr>, EcoVec<Instr>);
+type Under = (EcoVec<Node>, EcoVec<Node>);
 
-/// Calculate the "before" and "after" instructions for `under`ing a sequence of instructions.
+/// Calculate the "before" and "after" nodes for `under`ing a sequence of nodes.
 ///
 /// `g_sig` is the signature of `under`'s second function
-pub(crate) fn under_instrs(
-    instrs: &[Instr],
+pub(crate) fn under_nodes(
+    nodes: &[Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(EcoVec<Instr>, EcoVec<Instr>)> {
+) -> InversionResult<(EcoVec<Node>, EcoVec<Node>)> {
     use ImplPrimitive::*;
     use Primitive::*;
 
-    if instrs.is_empty() {
+    if nodes.is_empty() {
         return Ok((EcoVec::new(), EcoVec::new()));
     }
-    if instrs.len() > 30 {
+    if nodes.len() > 30 {
         return Err(InversionError::TooManyInstructions);
     }
+    let lifted = lift_funcs(nodes, &comp.asm);
+    let nodes = lifted.as_slice();
 
     /// Copy 1 value to the temp stack before the "before", and pop it before the "after"
     macro_rules! stash1 {
@@ -823,6 +849,8 @@ pub(crate) fn under_instrs(
         &maybe_val!(store1copy!(Sys(SysOp::TcpAccept), Sys(SysOp::Close))),
         &maybe_val!(store1copy!(Sys(SysOp::TcpListen), Sys(SysOp::Close))),
         &maybe_val!(store1copy!(Sys(SysOp::TlsListen), Sys(SysOp::Close))),
+        &maybe_val!(store1copy!(Sys(SysOp::UdpBind), Sys(SysOp::Close))),
+        &maybe_val!(store1copy!(Sys(SysOp::UdpConnect), Sys(SysOp::Close))),
         &maybe_val!(stash1!(Sys(SysOp::FReadAllStr), Sys(SysOp::FWriteAll))),
         &maybe_val!(stash1!(Sys(SysOp::FReadAllBytes), Sys(SysOp::FWriteAll))),
         &maybe_val!(pat!(
@@ -844,22 +872,20 @@ pub(crate) fn under_instrs(
         &UnderPatternFn(under_from_inverse_pattern, "from inverse"), // These must come last!
     ];
 
-    dbgln!("undering {:?}", instrs);
-
-    let comp_instrs_backup = comp.asm.instrs.clone();
+    dbgln!("undering {:?}", nodes);
 
     let mut befores = EcoVec::new();
     let mut afters = EcoVec::new();
-    let mut curr_instrs = instrs;
+    let mut curr_nodes = nodes;
     let mut error = Generic;
     'find_pattern: loop {
         for pattern in patterns {
-            match pattern.under_extract(curr_instrs, g_sig, comp) {
+            match pattern.under_extract(curr_nodes, g_sig, comp) {
                 Ok((input, (bef, aft))) => {
                     dbgln!(
                         "matched pattern {:?} on {:?} to {bef:?} {aft:?}",
                         pattern,
-                        &curr_instrs[..curr_instrs.len() - input.len()],
+                        &curr_nodes[..curr_nodes.len() - input.len()],
                     );
                     befores.extend(bef);
                     afters = aft.into_iter().chain(afters).collect();
@@ -867,11 +893,11 @@ pub(crate) fn under_instrs(
                         let befores = resolve_uns(befores, comp)?;
                         let afters = resolve_uns(afters, comp)?;
                         if DEBUG {
-                            dbgln!("undered {:?} to {:?} {:?}", instrs, befores, afters);
+                            dbgln!("undered {:?} to {:?} {:?}", nodes, befores, afters);
                         }
                         return Ok((befores, afters));
                     }
-                    curr_instrs = input;
+                    curr_nodes = input;
                     continue 'find_pattern;
                 }
                 Err(e) => error = error.max(e),
@@ -880,94 +906,108 @@ pub(crate) fn under_instrs(
         break;
     }
 
-    comp.asm.instrs = comp_instrs_backup;
-    // dbgln!("under {:?} failed with remaining {:?}", instrs, curr_instrs);
+    // dbgln!("under {:?} failed with remaining {:?}", nodes, curr_nodes);
 
     Err(error)
 }
 
-fn resolve_uns(instrs: EcoVec<Instr>, comp: &mut Compiler) -> InversionResult<EcoVec<Instr>> {
-    fn contains_un(instrs: &[Instr], asm: &Assembly) -> bool {
-        instrs.iter().any(|instr| match instr {
-            Instr::PushFunc(f) => contains_un(f.instrs(asm), asm),
-            Instr::Prim(Primitive::Un | Primitive::Anti, _) => true,
+fn resolve_uns(nodes: EcoVec<Node>, comp: &mut Compiler) -> InversionResult<EcoVec<Node>> {
+    fn contains_un(nodes: &[Node]) -> bool {
+        nodes.iter().any(|node| match node {
+            Node::Func(f) => contains_un(f.nodes()),
+            Node::Array { inner, .. } => contains_un(inner.as_slice()),
+            Node::Mod(Primitive::Un | Primitive::Anti, ..) => true,
+            Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => {
+                args.iter().any(|f| contains_un(f.nodes()))
+            }
             _ => false,
         })
     }
-    if !contains_un(&instrs, &comp.asm) {
-        return Ok(instrs);
+    fn resolve_func(f: FuncNode, comp: &mut Compiler) -> InversionResult<FuncNode> {
+        if !contains_un(f.nodes()) {
+            return Ok(f);
+        }
+        let resolved_f = resolve_uns(f.nodes().into(), comp)?;
+        let sig = nodes_signature(&resolved_f)?;
+        let new_func = NewFunction {
+            nodes: resolved_f,
+            flags: f.flags,
+        };
+        Ok(comp.make_function(f.id.clone(), sig, new_func))
+    }
+    fn resolve_args(
+        args: EcoVec<FuncNode>,
+        comp: &mut Compiler,
+    ) -> InversionResult<EcoVec<FuncNode>> {
+        (args.into_iter()).map(|f| resolve_func(f, comp)).collect()
+    }
+    if !contains_un(&nodes) {
+        return Ok(nodes);
     }
-    fn resolve_uns(instrs: EcoVec<Instr>, comp: &mut Compiler) -> InversionResult<EcoVec<Instr>> {
+    fn resolve_uns(nodes: EcoVec<Node>, comp: &mut Compiler) -> InversionResult<EcoVec<Node>> {
         let mut resolved = EcoVec::new();
-        let mut instrs = instrs.into_iter().peekable();
-        while let Some(instr) = instrs.next() {
-            match instr {
-                Instr::PushFunc(f)
-                    if (instrs.peek())
-                        .is_some_and(|instr| matches!(instr, Instr::Prim(Primitive::Un, _))) =>
-                {
-                    instrs.next();
-                    let instrs = f.instrs(&comp.asm).to_vec();
-                    let inverse = invert_instrs(&instrs, comp).map_err(|e| e.func(&f))?;
+        for node in nodes {
+            match node {
+                Node::Mod(Primitive::Un, args, _) => {
+                    let [f] = args.as_slice() else {
+                        return generic();
+                    };
+                    let inverse = invert_nodes(f.nodes(), comp).map_err(|e| e.func(f))?;
                     resolved.extend(inverse);
                 }
-                Instr::PushFunc(f)
-                    if (instrs.peek())
-                        .is_some_and(|instr| matches!(instr, Instr::Prim(Primitive::Anti, _))) =>
-                {
-                    instrs.next();
-                    let instrs = f.instrs(&comp.asm).to_vec();
-                    let inverse = anti_instrs(&instrs, comp).map_err(|e| e.func(&f))?;
+                Node::Mod(Primitive::Anti, args, _) => {
+                    let [f] = args.as_slice() else {
+                        return generic();
+                    };
+                    let inverse = anti_nodes(f.nodes(), comp).map_err(|e| e.func(f))?;
                     resolved.extend(inverse);
                 }
-                Instr::PushFunc(f) => {
-                    let instrs = f.instrs(&comp.asm);
-                    if !contains_un(instrs, &comp.asm) {
-                        resolved.push(Instr::PushFunc(f));
-                        continue;
-                    }
-                    let instrs = EcoVec::from(instrs);
-                    let id = f.id.clone();
-                    let resolved_f = resolve_uns(instrs, comp)?;
-                    let sig = instrs_signature(&resolved_f)?;
-                    let new_func = NewFunction {
-                        instrs: resolved_f,
-                        flags: f.flags,
-                    };
-                    let func = comp.make_function(id, sig, new_func);
-                    resolved.push(Instr::PushFunc(func));
+                Node::Func(f) => resolved.push(Node::Func(resolve_func(f, comp)?)),
+                Node::Mod(prim, args, span) => {
+                    resolved.push(Node::Mod(prim, resolve_args(args, comp)?, span))
                 }
-                instr => resolved.push(instr.clone()),
+                Node::ImplMod(prim, args, span) => {
+                    resolved.push(Node::ImplMod(prim, resolve_args(args, comp)?, span))
+                }
+                Node::Array { inner, boxed, span } if contains_un(inner.as_slice()) => {
+                    let inner = resolve_uns(inner.into_vec(), comp)?;
+                    resolved.push(Node::Array {
+                        inner: Box::new(Node::Run(inner)),
+                        boxed,
+                        span,
+                    });
+                }
+                node => resolved.push(node),
             }
         }
         Ok(resolved)
     }
-    resolve_uns(instrs, comp)
+    resolve_uns(nodes, comp)
 }
 
 trait InvertPattern: fmt::Debug + Sync {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)>;
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)>;
 }
 
 trait UnderPattern: fmt::Debug {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         g_sig: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)>;
+    ) -> InversionResult<(&'a [Node], Under)>;
 }
 
 macro_rules! invert_pat {
     ($name:ident, ($($pat:pat),*), |$input:ident, $comp:ident| $body:expr) => {
         fn $name<'a>(
-            $input: &'a [Instr],
+            $input: &'a [Node],
             $comp: &mut Compiler,
-        ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+        ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
             let [$($pat,)* $input @ ..] = $input else {
                 return generic();
             };
@@ -981,36 +1021,41 @@ macro_rules! invert_pat {
 
 invert_pat!(
     invert_call_pattern,
-    (Instr::PushFunc(f), Instr::Call(_)),
+    (Node::Func(f), Node::Instr(Instr::Call(_))),
     |input, comp| {
-        let instrs = f.instrs(&comp.asm).to_vec();
-        let inverse = invert_instrs(&instrs, comp)?;
+        let nodes = f.nodes();
+        let inverse = invert_nodes(nodes, comp)?;
         (input, inverse)
     }
 );
 
-invert_pat!(
-    invert_un_pattern,
-    (
-        Instr::PushFunc(f),
-        Instr::Prim(Primitive::Un | Primitive::Anti, _)
-    ),
-    |input, comp| (input, EcoVec::from(f.instrs(&comp.asm)))
-);
+fn invert_un_pattern<'a>(
+    input: &'a [Node],
+    _: &mut Compiler,
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Mod(Primitive::Un | Primitive::Anti, args, _), input @ ..] = input else {
+        return generic();
+    };
+    let [f] = args.as_slice() else {
+        return generic();
+    };
+    Ok((input, EcoVec::from(f.nodes())))
+}
 
 fn get_undered<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(UnderedFunctions, &'a [Instr])> {
-    let res = if let [Instr::PushFunc(g), Instr::PushFunc(f), Instr::Prim(Primitive::Under, span), input @ ..] =
-        input
-    {
+) -> InversionResult<(UnderedFunctions, &'a [Node])> {
+    let res = if let [Node::Mod(Primitive::Under, args, span), input @ ..] = input {
+        let [f, g] = args.as_slice() else {
+            return generic();
+        };
         Ok((
             UnderedFunctions {
-                f: EcoVec::from(f.instrs(&comp.asm)),
-                f_sig: f.signature(),
-                g: EcoVec::from(g.instrs(&comp.asm)),
-                g_sig: g.signature(),
+                f: EcoVec::from(f.nodes()),
+                f_sig: f.sig,
+                g: EcoVec::from(g.nodes()),
+                g_sig: g.sig,
                 span: *span,
             },
             input,
@@ -1034,9 +1079,9 @@ fn get_undered<'a>(
 }
 
 fn invert_under_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     let (u, input) = get_undered(input, comp)?;
     let UnderedFunctions {
         f, f_sig, g, g_sig, ..
@@ -1044,22 +1089,22 @@ fn invert_under_pattern<'a>(
     if g_sig.args != g_sig.outputs {
         return Err(InversionError::AsymmetricUnderSig(g_sig));
     }
-    let (f_before, f_after) = under_instrs(&f, g_sig, comp)?;
-    let g_inv = invert_instrs(&g, comp)?;
+    let (f_before, f_after) = under_nodes(&f, g_sig, comp)?;
+    let g_inv = invert_nodes(&g, comp)?;
     let mut inverse = f_before;
     inverse.extend(g_inv);
     inverse.extend(f_after);
-    if instrs_signature(&inverse)? != naive_under_sig(f_sig, g_sig) {
+    if nodes_signature(&inverse)? != naive_under_sig(f_sig, g_sig) {
         return Err(InversionError::ComplexInvertedUnder);
     }
     Ok((input, inverse))
 }
 
 fn under_under_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     outer_g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     let (u, input) = get_undered(input, comp)?;
     let UnderedFunctions {
         f,
@@ -1071,32 +1116,32 @@ fn under_under_pattern<'a>(
     if g_sig.args != g_sig.outputs {
         return Err(InversionError::AsymmetricUnderSig(g_sig));
     }
-    let (f_before, f_after) = under_instrs(&f, g_sig, comp)?;
-    let (g_before, g_after) = under_instrs(&g, outer_g_sig, comp)?;
+    let (f_before, f_after) = under_nodes(&f, g_sig, comp)?;
+    let (g_before, g_after) = under_nodes(&g, outer_g_sig, comp)?;
     let context_size = f_sig.args.saturating_sub(f_sig.outputs);
 
     let mut before = EcoVec::new();
     if context_size > 0 {
-        before.push(Instr::CopyToTemp {
+        before.push(Node::Instr(Instr::CopyToTemp {
             stack: TempStack::Under,
             count: context_size,
             span,
-        });
+        }));
     }
     before.extend(f_before.iter().cloned());
     before.extend(g_before);
     before.extend(f_after.iter().cloned());
-    if instrs_signature(&before)? != naive_under_sig(f_sig, g_sig) {
+    if nodes_signature(&before)? != naive_under_sig(f_sig, g_sig) {
         return Err(InversionError::ComplexInvertedUnder);
     }
 
     let mut after = EcoVec::new();
     if context_size > 0 {
-        after.push(Instr::PopTemp {
+        after.push(Node::Instr(Instr::PopTemp {
             stack: TempStack::Under,
             count: context_size,
             span,
-        });
+        }));
     }
     after.extend(f_before);
     after.extend(g_after);
@@ -1105,73 +1150,75 @@ fn under_under_pattern<'a>(
 }
 
 fn under_un_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(f), Instr::Prim(Primitive::Un, _), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::Un, args, _), input @ ..] = input else {
         return generic();
     };
-    let instrs = EcoVec::from(f.instrs(&comp.asm));
-    let befores = invert_instrs(&instrs, comp).map_err(|e| e.func(f))?;
-    if let [Instr::PushFunc(_), Instr::PushFunc(_), Instr::Prim(Primitive::SetInverse, _)] =
-        befores.as_slice()
-    {
-        if let Ok(under) = under_instrs(&befores, g_sig, comp) {
+    let [f] = args.as_slice() else {
+        return generic();
+    };
+    let nodes = EcoVec::from(f.nodes());
+    let befores = invert_nodes(&nodes, comp).map_err(|e| e.func(f))?;
+    if let [Node::Mod(Primitive::SetInverse, ..)] = befores.as_slice() {
+        if let Ok(under) = under_nodes(&befores, g_sig, comp) {
             return Ok((input, under));
         }
     }
     Ok((
         input,
-        if let Ok((befores, afters)) = under_instrs(&befores, g_sig, comp) {
+        if let Ok((befores, afters)) = under_nodes(&befores, g_sig, comp) {
             (befores, afters)
         } else {
-            (befores, instrs)
+            (befores, nodes)
         },
     ))
 }
 
 fn under_anti_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(f), Instr::Prim(Primitive::Anti, _), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::Anti, args, _), input @ ..] = input else {
         return generic();
     };
-    let instrs = EcoVec::from(f.instrs(&comp.asm));
-    let befores = anti_instrs(&instrs, comp).map_err(|e| e.func(f))?;
-    if let [Instr::PushFunc(_), Instr::PushFunc(_), Instr::Prim(Primitive::SetInverse, _)] =
-        befores.as_slice()
-    {
-        if let Ok(under) = under_instrs(&befores, g_sig, comp) {
+    let [f] = args.as_slice() else {
+        return generic();
+    };
+    let nodes = EcoVec::from(f.nodes());
+    let befores = anti_nodes(&nodes, comp).map_err(|e| e.func(f))?;
+    if let [Node::Mod(Primitive::SetInverse, ..)] = befores.as_slice() {
+        if let Ok(under) = under_nodes(&befores, g_sig, comp) {
             return Ok((input, under));
         }
     }
     Ok((
         input,
-        if let Ok((befores, afters)) = under_instrs(&befores, g_sig, comp) {
+        if let Ok((befores, afters)) = under_nodes(&befores, g_sig, comp) {
             (befores, afters)
         } else {
-            (befores, instrs)
+            (befores, nodes)
         },
     ))
 }
 
 fn under_call_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(f), Instr::Call(span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Func(f), Node::Instr(Instr::Call(span)), input @ ..] = input else {
         return generic();
     };
     let span = *span;
-    let instrs = f.instrs(&comp.asm).to_vec();
-    let (befores, afters) = under_instrs(&instrs, g_sig, comp)?;
+    let nodes = f.nodes();
+    let (befores, afters) = under_nodes(nodes, g_sig, comp)?;
     Ok((
         input,
-        if comp.inlinable(&instrs, f.flags) {
+        if comp.inlinable(nodes, f.flags) {
             (befores, afters)
         } else {
             let mut before = make_fn(befores, f.flags, span, comp)?;
@@ -1179,46 +1226,48 @@ fn under_call_pattern<'a>(
             before.flags |= FunctionFlags::NO_PRE_EVAL;
             after.flags |= FunctionFlags::NO_PRE_EVAL;
             (
-                eco_vec![Instr::PushFunc(before), Instr::Call(span)],
-                eco_vec![Instr::PushFunc(after), Instr::Call(span)],
+                eco_vec![Node::Func(before), Node::Instr(Instr::Call(span))],
+                eco_vec![Node::Func(after), Node::Instr(Instr::Call(span))],
             )
         },
     ))
 }
 
 fn invert_trivial_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     use Instr::*;
-    match input {
-        [Prim(prim, span), input @ ..] => {
+    let [Node::Instr(instr), input @ ..] = input else {
+        return generic();
+    };
+    match instr {
+        Prim(prim, span) => {
             if let Some(inv) = prim_inverse(*prim, *span) {
-                return Ok((input, eco_vec![inv]));
+                return Ok((input, eco_vec![Node::Instr(inv)]));
             }
         }
-        [instr @ (SetOutputComment { .. } | TouchStack { .. } | ValidateType { .. }), input @ ..] =>
-        {
-            return Ok((input, eco_vec![instr.clone()]));
+        SetOutputComment { .. } | TouchStack { .. } | ValidateType { .. } => {
+            return Ok((input, eco_vec![Node::Instr(instr.clone())]));
         }
-        [ImplPrim(prim, span), input @ ..] => {
-            if let Some(inv) = impl_prim_inverse(*prim, *span).map(|instr| eco_vec![instr]) {
-                return Ok((input, inv));
+        ImplPrim(prim, span) => {
+            if let Some(inv) = impl_prim_inverse(*prim, *span) {
+                return Ok((input, eco_vec![Node::Instr(inv)]));
             }
         }
-        [Comment(_) | PushSig(_) | PopSig, input @ ..] => return Ok((input, EcoVec::new())),
-        [Label {
+        Comment(_) | PushSig(_) | PopSig => return Ok((input, EcoVec::new())),
+        Label {
             label,
             span,
             remove,
-        }, input @ ..] => {
+        } => {
             return Ok((
                 input,
-                eco_vec![Label {
+                eco_vec![Node::Instr(Label {
                     label: label.clone(),
                     span: *span,
                     remove: !*remove
-                }],
+                })],
             ))
         }
         _ => {}
@@ -1227,97 +1276,102 @@ fn invert_trivial_pattern<'a>(
 }
 
 fn invert_on_inv_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    if let Some((input, Instr::PushTemp { span, .. }, inner, _, 1)) = try_push_temp_wrap(input) {
-        let (inp_after_val, mut instrs) = Val.invert_extract(inner, comp)?;
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    if let Some((input, Node::Instr(Instr::PushTemp { span, .. }), inner, _, 1)) =
+        try_push_temp_wrap(input)
+    {
+        let (inp_after_val, mut nodes) = Val.invert_extract(inner, comp)?;
         if !inp_after_val.is_empty() {
             return generic();
         }
         let mut temp_input = input.to_vec();
-        temp_input.insert(0, Instr::Prim(Primitive::Flip, *span));
+        temp_input.insert(0, Node::Instr(Instr::Prim(Primitive::Flip, *span)));
         for pattern in ON_INVERT_PATTERNS {
             if let Ok((temp_inp, inv)) = pattern.invert_extract(&temp_input, comp) {
-                instrs.extend(inv);
-                return Ok((&input[temp_input.len() - 1 - temp_inp.len()..], instrs));
+                nodes.extend(inv);
+                return Ok((&input[temp_input.len() - 1 - temp_inp.len()..], nodes));
             }
         }
     }
 
-    let (input, mut instrs) = Val.invert_extract(input, comp)?;
+    let (input, mut nodes) = Val.invert_extract(input, comp)?;
     for pattern in ON_INVERT_PATTERNS {
         if let Ok((input, inv)) = pattern.invert_extract(input, comp) {
-            instrs.extend(inv);
-            return Ok((input, instrs));
+            nodes.extend(inv);
+            return Ok((input, nodes));
         }
     }
     generic()
 }
 
 fn under_trivial_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     use Instr::*;
     match input {
-        [instr @ SetOutputComment { .. }, input @ ..] => {
-            Ok((input, (eco_vec![instr.clone()], eco_vec![])))
+        [node @ Node::Instr(SetOutputComment { .. }), input @ ..] => {
+            Ok((input, (eco_vec![node.clone()], eco_vec![])))
+        }
+        [Node::Instr(Comment(_) | PushSig(_) | PopSig), input @ ..] => {
+            Ok((input, (eco_vec![], eco_vec![])))
         }
-        [Comment(_) | PushSig(_) | PopSig, input @ ..] => Ok((input, (eco_vec![], eco_vec![]))),
         _ => generic(),
     }
 }
 
 fn invert_dup_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::Prim(Primitive::Dup, dup_span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Instr(Instr::Prim(Primitive::Dup, dup_span)), input @ ..] = input else {
         return generic();
     };
 
     // As under's undo step
     for end in (1..=input.len()).rev() {
-        let mut instrs = &input[..end];
-        let Some(Signature { args: 1, outputs }) = instrs_clean_signature(instrs) else {
+        let mut nodes = &input[..end];
+        let Some(Signature { args: 1, outputs }) = nodes_clean_signature(nodes) else {
             continue;
         };
-        if !instrs_are_pure(instrs, &comp.asm, Purity::Impure) {
+        if !nodes_are_pure(nodes, &comp.asm, Purity::Impure) {
             continue;
         }
-        let Ok((before, mut after)) = under_instrs(instrs, Signature::new(1, 1), comp) else {
+        let Ok((before, mut after)) = under_nodes(nodes, Signature::new(1, 1), comp) else {
             continue;
         };
-        while let [Instr::PushFunc(f), Instr::Call(_)] = after.as_slice() {
-            after = EcoVec::from(f.instrs(&comp.asm));
+        while let [Node::Func(f), Node::Instr(Instr::Call(_))] = after.as_slice() {
+            after = EcoVec::from(f.nodes());
         }
-        while let [Instr::PushFunc(f), Instr::Call(_)] = instrs {
-            instrs = f.instrs(&comp.asm);
+        while let [Node::Func(f), Node::Instr(Instr::Call(_))] = nodes {
+            nodes = f.nodes();
         }
-        let mut new_instrs = EcoVec::new();
+        let mut new_nodes = EcoVec::new();
         // Stash all inputs but the last one
-        new_instrs.push(Instr::push_inline(outputs, *dup_span));
+        new_nodes.push(Node::Instr(Instr::push_inline(outputs, *dup_span)));
         // Invoke the "do" step of the original function on the remaining argument
-        new_instrs.extend(before);
+        new_nodes.extend(before);
         // Discard the outputs of the function and replace them with the stashed inputs
-        (0..outputs).for_each(|_| new_instrs.push(Instr::Prim(Primitive::Pop, *dup_span)));
-        new_instrs.push(Instr::pop_inline(outputs, *dup_span));
+        (0..outputs)
+            .for_each(|_| new_nodes.push(Node::Instr(Instr::Prim(Primitive::Pop, *dup_span))));
+        new_nodes.push(Node::Instr(Instr::pop_inline(outputs, *dup_span)));
         // Invoke the "undo" step with the replaced arguments
-        new_instrs.extend(after);
-        return Ok((&input[end..], new_instrs));
+        new_nodes.extend(after);
+        return Ok((&input[end..], new_nodes));
     }
 
     let Some(dyadic_i) = (0..=input.len())
-        .find(|&i| instrs_clean_signature(&input[..i]).is_some_and(|sig| sig == (2, 1)))
+        .find(|&i| nodes_clean_signature(&input[..i]).is_some_and(|sig| sig == (2, 1)))
     else {
         // Pattern matching
-        let sig = instrs_signature(input)?;
+        let sig = nodes_signature(input)?;
         return if sig.args == sig.outputs {
             let inv = eco_vec![
-                Instr::Prim(Primitive::Over, *dup_span),
-                Instr::ImplPrim(ImplPrimitive::MatchPattern, *dup_span)
+                Node::Instr(Instr::Prim(Primitive::Over, *dup_span)),
+                Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, *dup_span))
             ];
             Ok((input, inv))
         } else {
@@ -1331,7 +1385,7 @@ fn invert_dup_pattern<'a>(
     let monadic_i = (0..=dyadic_whole.len())
         .rev()
         .find(|&i| {
-            instrs_clean_signature(&dyadic_whole[..i])
+            nodes_clean_signature(&dyadic_whole[..i])
                 .is_some_and(|sig| sig.args == 0 && sig.outputs == 0)
         })
         .ok_or(Generic)?;
@@ -1339,20 +1393,20 @@ fn invert_dup_pattern<'a>(
     let dyadic_part = &dyadic_whole[monadic_i..];
     dbgln!("inverse monadic part: {monadic_part:?}");
     dbgln!("inverse dyadic part: {dyadic_part:?}");
-    let monadic_inv = invert_instrs(monadic_part, comp)?;
+    let monadic_inv = invert_nodes(monadic_part, comp)?;
     let inverse = match *dyadic_part {
-        [Instr::Prim(Primitive::Add, span)] => {
+        [Node::Instr(Instr::Prim(Primitive::Add, span))] => {
             let mut inv = monadic_inv;
-            inv.push(Instr::push(2));
-            inv.push(Instr::Prim(Primitive::Div, span));
+            inv.push(Node::Instr(Instr::push(2)));
+            inv.push(Node::Instr(Instr::Prim(Primitive::Div, span)));
             inv
         }
-        [Instr::Prim(Primitive::Mul, span)] => {
-            let mut inv = eco_vec![Instr::Prim(Primitive::Sqrt, span)];
+        [Node::Instr(Instr::Prim(Primitive::Mul, span))] => {
+            let mut inv = eco_vec![Node::Instr(Instr::Prim(Primitive::Sqrt, span))];
             if !monadic_inv.is_empty() {
-                inv.push(Instr::Prim(Primitive::Dup, *dup_span));
+                inv.push(Node::Instr(Instr::Prim(Primitive::Dup, *dup_span)));
                 inv.extend(monadic_inv);
-                inv.push(Instr::Prim(Primitive::Pop, span));
+                inv.push(Node::Instr(Instr::Prim(Primitive::Pop, span)));
             }
             inv
         }
@@ -1362,77 +1416,77 @@ fn invert_dup_pattern<'a>(
 }
 
 fn under_dup_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::Prim(Primitive::Dup, dup_span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Instr(Instr::Prim(Primitive::Dup, dup_span)), input @ ..] = input else {
         return generic();
     };
     let dyadic_i = (0..=input.len())
-        .find(|&i| instrs_clean_signature(&input[..i]).is_some_and(|sig| sig == (2, 1)))
+        .find(|&i| nodes_clean_signature(&input[..i]).is_some_and(|sig| sig == (2, 1)))
         .ok_or(Generic)?;
     let dyadic_whole = &input[..dyadic_i];
     let input = &input[dyadic_i..];
     let (monadic_i, monadic_sig) = (0..=dyadic_whole.len())
         .rev()
-        .filter_map(|i| instrs_clean_signature(&dyadic_whole[..i]).map(|sig| (i, sig)))
+        .filter_map(|i| nodes_clean_signature(&dyadic_whole[..i]).map(|sig| (i, sig)))
         .find(|(_, sig)| sig.args == sig.outputs)
         .ok_or(Generic)?;
     let monadic_part = &dyadic_whole[..monadic_i];
     let dyadic_part = &dyadic_whole[monadic_i..];
     dbgln!("under monadic part: {monadic_part:?}");
     dbgln!("under dyadic part: {dyadic_part:?}");
-    let (monadic_befores, monadic_afters) = under_instrs(monadic_part, g_sig, comp)?;
+    let (monadic_befores, monadic_afters) = under_nodes(monadic_part, g_sig, comp)?;
 
-    let mut befores = eco_vec![Instr::Prim(Primitive::Dup, *dup_span),];
+    let mut befores = eco_vec![Node::Instr(Instr::Prim(Primitive::Dup, *dup_span)),];
     let mut afters = EcoVec::new();
 
     let temp = |temp: bool| {
         if temp {
-            befores.push(Instr::CopyToTemp {
+            befores.push(Node::Instr(Instr::CopyToTemp {
                 stack: TempStack::Under,
                 count: 1,
                 span: *dup_span,
-            });
+            }));
         }
         befores.extend(monadic_befores);
         befores.extend(dyadic_part.iter().cloned());
 
         if temp {
-            afters.push(Instr::PopTemp {
+            afters.push(Node::Instr(Instr::PopTemp {
                 stack: TempStack::Under,
                 count: 1,
                 span: *dup_span,
-            });
+            }));
         }
     };
 
     match dyadic_part {
-        [Instr::Prim(Primitive::Add, span)] if monadic_sig == (0, 0) => {
+        [Node::Instr(Instr::Prim(Primitive::Add, span))] if monadic_sig == (0, 0) => {
             temp(false);
-            afters.push(Instr::push(2));
-            afters.push(Instr::Prim(Primitive::Div, *span));
+            afters.push(Node::Instr(Instr::push(2)));
+            afters.push(Node::Instr(Instr::Prim(Primitive::Div, *span)));
         }
-        [Instr::Prim(Primitive::Add, span)] => {
+        [Node::Instr(Instr::Prim(Primitive::Add, span))] => {
             temp(true);
-            afters.push(Instr::Prim(Primitive::Sub, *span));
+            afters.push(Node::Instr(Instr::Prim(Primitive::Sub, *span)));
         }
-        [Instr::Prim(Primitive::Sub, span)] => {
+        [Node::Instr(Instr::Prim(Primitive::Sub, span))] => {
             temp(true);
-            afters.push(Instr::Prim(Primitive::Add, *span));
+            afters.push(Node::Instr(Instr::Prim(Primitive::Add, *span)));
         }
-        [Instr::Prim(Primitive::Mul, span)] if monadic_sig == (0, 0) => {
+        [Node::Instr(Instr::Prim(Primitive::Mul, span))] if monadic_sig == (0, 0) => {
             temp(false);
-            afters.push(Instr::Prim(Primitive::Sqrt, *span));
+            afters.push(Node::Instr(Instr::Prim(Primitive::Sqrt, *span)));
         }
-        [Instr::Prim(Primitive::Mul, span)] => {
+        [Node::Instr(Instr::Prim(Primitive::Mul, span))] => {
             temp(true);
-            afters.push(Instr::Prim(Primitive::Div, *span));
+            afters.push(Node::Instr(Instr::Prim(Primitive::Div, *span)));
         }
-        [Instr::Prim(Primitive::Div, span)] => {
+        [Node::Instr(Instr::Prim(Primitive::Div, span))] => {
             temp(true);
-            afters.push(Instr::Prim(Primitive::Mul, *span));
+            afters.push(Node::Instr(Instr::Prim(Primitive::Mul, *span)));
         }
         _ => return generic(),
     }
@@ -1441,11 +1495,12 @@ fn under_dup_pattern<'a>(
 }
 
 fn invert_select_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     Ok(match input {
-        [Instr::Push(val), sel @ Instr::Prim(Primitive::Select, _), input @ ..] => {
+        [Node::Instr(Instr::Push(val)), sel @ Node::Instr(Instr::Prim(Primitive::Select, _)), input @ ..] =>
+        {
             let indices = val.as_nats(&IgnoreError, "")?;
             let unique_indices: HashSet<usize> = indices.iter().copied().collect();
             if unique_indices.len() != indices.len() {
@@ -1455,89 +1510,89 @@ fn invert_select_pattern<'a>(
             for (i, &j) in indices.iter().enumerate() {
                 *inverse_indices.get_mut(j).ok_or(Generic)? = i;
             }
-            let instrs = eco_vec![
-                Instr::Push(inverse_indices.into_iter().collect()),
+            let nodes = eco_vec![
+                Node::Instr(Instr::Push(inverse_indices.into_iter().collect())),
                 sel.clone()
             ];
-            (input, instrs)
+            (input, nodes)
         }
-        [Instr::Prim(Primitive::Select, span), input @ ..] => {
-            let instrs = eco_vec![
-                Instr::Prim(Primitive::Dup, *span),
-                Instr::Prim(Primitive::Len, *span),
-                Instr::Prim(Primitive::Range, *span),
+        [Node::Instr(Instr::Prim(Primitive::Select, span)), input @ ..] => {
+            let nodes = eco_vec![
+                Node::Instr(Instr::Prim(Primitive::Dup, *span)),
+                Node::Instr(Instr::Prim(Primitive::Len, *span)),
+                Node::Instr(Instr::Prim(Primitive::Range, *span)),
             ];
-            (input, instrs)
+            (input, nodes)
         }
         _ => return generic(),
     })
 }
 
 fn invert_push_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     let (instr, input) = match input {
-        [instr @ Instr::Push(_), input @ ..] => (instr, input),
-        [instr @ Instr::CallGlobal { index, .. }, input @ ..]
+        [instr @ Node::Instr(Instr::Push(_)), input @ ..] => (instr, input),
+        [instr @ Node::Instr(Instr::CallGlobal { index, .. }), input @ ..]
             if matches!(comp.asm.bindings[*index].kind, BindingKind::Const(_)) =>
         {
             (instr, input)
         }
         _ => return generic(),
     };
-    if let [Instr::ImplPrim(ImplPrimitive::MatchPattern, _), input @ ..] = input {
+    if let [Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, _)), input @ ..] = input {
         return Ok((input, eco_vec![instr.clone()]));
     }
-    let mut instrs = eco_vec![instr.clone()];
-    instrs.push(Instr::ImplPrim(
+    let mut nodes = eco_vec![instr.clone()];
+    nodes.push(Node::Instr(Instr::ImplPrim(
         ImplPrimitive::MatchPattern,
         comp.asm.spans.len() - 1,
-    ));
-    Ok((input, instrs))
+    )));
+    Ok((input, nodes))
 }
 
 fn invert_format_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     Ok(match input {
-        [Instr::Format { parts, span }, input @ ..] => (
+        [Node::Instr(Instr::Format { parts, span }), input @ ..] => (
             input,
-            eco_vec![Instr::MatchFormatPattern {
+            eco_vec![Node::Instr(Instr::MatchFormatPattern {
                 parts: parts.clone(),
                 span: *span
-            }],
+            })],
         ),
-        [Instr::MatchFormatPattern { parts, span }, input @ ..] => (
+        [Node::Instr(Instr::MatchFormatPattern { parts, span }), input @ ..] => (
             input,
-            eco_vec![Instr::Format {
+            eco_vec![Node::Instr(Instr::Format {
                 parts: parts.clone(),
                 span: *span
-            }],
+            })],
         ),
         _ => return generic(),
     })
 }
 
 fn invert_join_pattern<'a>(
-    mut input: &'a [Instr],
+    mut input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     use ImplPrimitive::*;
     use Instr::*;
     use Primitive::*;
     let Some((join_index, join_span)) = (input.iter().enumerate().rev())
-        .filter_map(|(i, instr)| match instr {
-            Prim(Join, span) => Some((i, *span)),
+        .filter_map(|(i, node)| match node {
+            Node::Instr(Prim(Join, span)) => Some((i, *span)),
             _ => None,
         })
-        .find(|(i, _)| instrs_clean_signature(&input[..*i]).is_some())
+        .find(|(i, _)| nodes_clean_signature(&input[..*i]).is_some())
     else {
         return generic();
     };
     let mut dipped = false;
-    if let Some((inp, mut instrs)) = Val.invert_extract(input, comp).ok().or_else(|| {
+    if let Some((inp, mut nodes)) = Val.invert_extract(input, comp).ok().or_else(|| {
         try_push_temp_wrap(input).and_then(|(input, _, inner, _, depth)| {
             if depth != 1 {
                 return None;
@@ -1552,78 +1607,78 @@ fn invert_join_pattern<'a>(
         input = inp;
         if let Some(i) = (1..=input.len())
             .rev()
-            .find(|&i| instrs_clean_signature(&input[..i]).is_some_and(|sig| sig == (0, 0)))
+            .find(|&i| nodes_clean_signature(&input[..i]).is_some_and(|sig| sig == (0, 0)))
         {
-            instrs.extend(invert_instrs(&input[..i], comp)?);
+            nodes.extend(invert_nodes(&input[..i], comp)?);
             input = &input[i..];
         }
         let (prim, span) = match *input {
-            [Prim(Join, span), ref inp @ ..] if dipped => {
+            [Node::Instr(Prim(Join, span)), ref inp @ ..] if dipped => {
                 input = inp;
                 (UnJoinShapeEnd, span)
             }
-            [Prim(Join, span), ref inp @ ..] => {
+            [Node::Instr(Prim(Join, span)), ref inp @ ..] => {
                 input = inp;
                 (UnJoinShape, span)
             }
-            [Prim(Flip, _), Prim(Join, span), ref inp @ ..] if !dipped => {
+            [Node::Instr(Prim(Flip, _)), Node::Instr(Prim(Join, span)), ref inp @ ..]
+                if !dipped =>
+            {
                 input = inp;
                 (UnJoinShapeEnd, span)
             }
             _ => return generic(),
         };
-        instrs.extend([
-            CopyToTemp {
+        nodes.extend([
+            Node::Instr(CopyToTemp {
                 stack: TempStack::Inline,
                 count: 1,
                 span,
-            },
-            Prim(Primitive::Shape, span),
-            ImplPrim(prim, span),
-            Instr::pop_inline(1, span),
-            Instr::ImplPrim(ImplPrimitive::MatchPattern, span),
+            }),
+            Node::Instr(Prim(Primitive::Shape, span)),
+            Node::Instr(ImplPrim(prim, span)),
+            Node::Instr(Instr::pop_inline(1, span)),
+            Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, span)),
         ]);
-        Ok((input, instrs))
+        Ok((input, nodes))
     } else {
-        fn invert_inner(
-            mut input: &[Instr],
-            comp: &mut Compiler,
-        ) -> InversionResult<EcoVec<Instr>> {
-            let mut instrs = EcoVec::new();
+        fn invert_inner(mut input: &[Node], comp: &mut Compiler) -> InversionResult<EcoVec<Node>> {
+            let mut nodes = EcoVec::new();
             while !input.is_empty() {
                 if let Some((inp, _, inner, ..)) = try_push_temp_wrap(input) {
-                    instrs.extend(invert_inner(inner, comp)?);
+                    nodes.extend(invert_inner(inner, comp)?);
                     input = inp;
                     continue;
                 }
-                if let Some((i, _)) = input.iter().enumerate().skip(1).find(|(i, instr)| {
-                    instrs_clean_signature(&input[..*i]).is_some()
+                if let Some((i, _)) = input.iter().enumerate().skip(1).find(|(i, node)| {
+                    nodes_clean_signature(&input[..*i]).is_some()
                         && matches!(
-                            instr,
-                            PushTemp {
+                            node,
+                            Node::Instr(PushTemp {
                                 stack: TempStack::Inline,
                                 ..
-                            }
+                            })
                         )
                 }) {
-                    instrs.extend(invert_instrs(&input[..i], comp)?);
+                    nodes.extend(invert_nodes(&input[..i], comp)?);
                     input = &input[i..];
                     continue;
                 }
-                instrs.extend(invert_instrs(input, comp)?);
+                nodes.extend(invert_nodes(input, comp)?);
                 break;
             }
-            Ok(instrs)
+            Ok(nodes)
         }
 
-        let flip_after = join_index > 0 && matches!(input[join_index - 1], Prim(Flip, _));
-        let flip_before = join_index > 1 && matches!(input[0], Prim(Flip, _));
+        let flip_after =
+            join_index > 0 && matches!(input[join_index - 1], Node::Instr(Prim(Flip, _)));
+        let flip_before = join_index > 1 && matches!(input[0], Node::Instr(Prim(Flip, _)));
         let flip = flip_before ^ flip_after;
         let before = &input[flip_before as usize..join_index - flip_after as usize];
         input = &input[join_index + 1..];
         let before_inv = invert_inner(before, comp)?;
-        let before_sig = instrs_clean_signature(&before_inv).ok_or(Generic)?;
-        let mut instrs = EcoVec::new();
+        let before_sig = nodes_clean_signature(&before_inv).ok_or(Generic)?;
+        let mut nodes = EcoVec::new();
         let count = before_sig.outputs.saturating_sub(before_sig.args) + 1;
         let prim = if count <= 1 {
             if flip {
@@ -1632,26 +1687,26 @@ fn invert_join_pattern<'a>(
                 UnJoin
             }
         } else {
-            instrs.push(Push(count.into()));
+            nodes.push(Node::Instr(Push(count.into())));
             if flip {
                 UnJoinShapeEnd
             } else {
                 UnJoinShape
             }
         };
-        instrs.push(ImplPrim(prim, join_span));
-        instrs.extend(before_inv);
-        Ok((input, instrs))
+        nodes.push(Node::Instr(ImplPrim(prim, join_span)));
+        nodes.extend(before_inv);
+        Ok((input, nodes))
     }
 }
 
 fn invert_insert_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     let (input, first) = Val.invert_extract(input, comp)?;
     let second = Val.invert_extract(input, comp);
-    let &[Instr::Prim(Primitive::Insert, span), ref input @ ..] = input else {
+    let &[Node::Instr(Instr::Prim(Primitive::Insert, span)), ref input @ ..] = input else {
         return generic();
     };
     let (input, key, value) = if let Ok((input, key)) = second {
@@ -1659,171 +1714,196 @@ fn invert_insert_pattern<'a>(
     } else {
         (input, first, None)
     };
-    let mut instrs = key;
-    instrs.extend([
-        Instr::Prim(Primitive::Over, span),
-        Instr::Prim(Primitive::Over, span),
-        Instr::Prim(Primitive::Has, span),
-        Instr::push(1),
-        Instr::ImplPrim(ImplPrimitive::MatchPattern, span),
-        Instr::Prim(Primitive::Over, span),
-        Instr::Prim(Primitive::Over, span),
-        Instr::Prim(Primitive::Get, span),
-        Instr::PushTemp {
+    let mut nodes = key;
+    nodes.extend([
+        Node::Instr(Instr::Prim(Primitive::Over, span)),
+        Node::Instr(Instr::Prim(Primitive::Over, span)),
+        Node::Instr(Instr::Prim(Primitive::Has, span)),
+        Node::Instr(Instr::push(1)),
+        Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, span)),
+        Node::Instr(Instr::Prim(Primitive::Over, span)),
+        Node::Instr(Instr::Prim(Primitive::Over, span)),
+        Node::Instr(Instr::Prim(Primitive::Get, span)),
+        Node::Instr(Instr::PushTemp {
             stack: TempStack::Inline,
             count: 1,
             span,
-        },
-        Instr::Prim(Primitive::Remove, span),
-        Instr::pop_inline(1, span),
+        }),
+        Node::Instr(Instr::Prim(Primitive::Remove, span)),
+        Node::Instr(Instr::pop_inline(1, span)),
     ]);
     if let Some(value) = value {
-        instrs.extend(value);
-        instrs.push(Instr::ImplPrim(ImplPrimitive::MatchPattern, span));
+        nodes.extend(value);
+        nodes.push(Node::Instr(Instr::ImplPrim(
+            ImplPrimitive::MatchPattern,
+            span,
+        )));
     }
-    Ok((input, instrs))
+    Ok((input, nodes))
 }
 
 fn invert_custom_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(f), Instr::CustomInverse(cust, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Func(f), Node::CustomInverse {
+        un,
+        under,
+        anti,
+        span,
+    }, input @ ..] = input
+    else {
         return generic();
     };
-    let mut cust = cust.clone();
-    let inv = cust.un.ok_or(Generic)?;
-    cust.un = Some(f.clone());
-    Ok((
-        input,
-        eco_vec![Instr::PushFunc(inv), Instr::CustomInverse(cust, *span)],
-    ))
+    let inv = un.clone().ok_or(Generic)?;
+    let cust = Node::CustomInverse {
+        un: Some(f.clone()),
+        under: under.clone(),
+        anti: anti.clone(),
+        span: *span,
+    };
+    Ok((input, eco_vec![Node::Func(inv), cust]))
 }
 
 fn anti_custom_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(f), Instr::CustomInverse(cust, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Func(f), Node::CustomInverse {
+        un,
+        under,
+        anti,
+        span,
+    }, input @ ..] = input
+    else {
         return generic();
     };
-    let mut cust = cust.clone();
-    let anti = cust.anti.ok_or(Generic)?;
-    cust.anti = Some(f.clone());
-    Ok((
-        input,
-        eco_vec![Instr::PushFunc(anti), Instr::CustomInverse(cust, *span)],
-    ))
+    let anti_fn = anti.clone().ok_or(Generic)?;
+    let cust = Node::CustomInverse {
+        un: un.clone(),
+        under: under.clone(),
+        anti: Some(f.clone()),
+        span: *span,
+    };
+    Ok((input, eco_vec![Node::Func(anti_fn), cust]))
 }
 
 fn under_custom_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
-    comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(f), Instr::CustomInverse(cust, span), input @ ..] = input else {
+    _: &mut Compiler,
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Func(f), Node::CustomInverse {
+        under, anti, span, ..
+    }, input @ ..] = input
+    else {
         return generic();
     };
-    let (mut befores, mut afters, to_save) = if let Some((before, after)) = cust.under.clone() {
-        if before.signature().outputs < f.signature().outputs {
+    let (mut befores, mut afters, to_save) = if let Some((before, after)) = under {
+        if before.sig.outputs < f.sig.outputs {
             return generic();
         }
-        let befores = EcoVec::from(before.instrs(&comp.asm));
-        let afters = EcoVec::from(after.instrs(&comp.asm));
-        let to_save = before.signature().outputs - f.signature().outputs;
+        let befores = EcoVec::from(before.nodes());
+        let afters = EcoVec::from(after.nodes());
+        let to_save = before.sig.outputs - f.sig.outputs;
         (befores, afters, to_save)
-    } else if let Some(anti) = cust.anti.clone() {
-        let to_save = anti.signature().args - f.signature().outputs;
-        let mut befores = EcoVec::from(f.instrs(&comp.asm));
-        befores.insert(0, Instr::copy_inline(*span));
-        befores.push(Instr::pop_inline(1, *span));
-        let afters = EcoVec::from(anti.instrs(&comp.asm));
+    } else if let Some(anti) = anti {
+        let to_save = anti.sig.args - f.sig.outputs;
+        let mut befores = EcoVec::from(f.nodes());
+        befores.insert(0, Node::Instr(Instr::copy_inline(*span)));
+        befores.push(Node::Instr(Instr::pop_inline(1, *span)));
+        let afters = EcoVec::from(anti.nodes());
         (befores, afters, to_save)
     } else {
         return generic();
     };
     if to_save > 0 {
-        befores.push(Instr::PushTemp {
+        befores.push(Node::Instr(Instr::PushTemp {
             stack: TempStack::Under,
             count: to_save,
             span: *span,
-        });
+        }));
         afters.insert(
             0,
-            Instr::PopTemp {
+            Node::Instr(Instr::PopTemp {
                 stack: TempStack::Under,
                 count: to_save,
                 span: *span,
-            },
+            }),
         );
     }
     Ok((input, (befores, afters)))
 }
 
 fn invert_setinv_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [inv @ Instr::PushFunc(_), normal @ Instr::PushFunc(_), set @ Instr::Prim(Primitive::SetInverse, _), input @ ..] =
-        input
-    else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Mod(Primitive::SetInverse, args, span), input @ ..] = input else {
+        return generic();
+    };
+    let [normal, inv] = args.as_slice() else {
         return generic();
     };
-    Ok((input, eco_vec![normal.clone(), inv.clone(), set.clone(),]))
+    let args = eco_vec![inv.clone(), normal.clone()];
+    Ok((
+        input,
+        eco_vec![Node::Mod(Primitive::SetInverse, args, *span)],
+    ))
 }
 
 fn invert_setund_setinv_pattern<'a>(
-    input: &'a [Instr],
-    comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(_), Instr::PushFunc(_), Instr::PushFunc(normal), Instr::Prim(Primitive::SetUnder, _), input @ ..] =
-        input
-    else {
+    input: &'a [Node],
+    _: &mut Compiler,
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Mod(Primitive::SetUnder, args, _), input @ ..] = input else {
         return generic();
     };
-    let [Instr::PushFunc(inv), Instr::PushFunc(_), Instr::Prim(Primitive::SetInverse, _)] =
-        normal.instrs(&comp.asm)
+    let Some([Node::Mod(Primitive::SetInverse, set_args, _)]) = args.first().map(FuncNode::nodes)
     else {
         return generic();
     };
-    Ok((input, inv.instrs(&comp.asm).into()))
+    let [_, inv] = set_args.as_slice() else {
+        return generic();
+    };
+    Ok((input, inv.nodes().into()))
 }
 
 fn invert_dump_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     match input {
-        [f @ Instr::PushFunc(_), Instr::Prim(Primitive::Dump, span), input @ ..] => Ok((
+        [Node::Mod(Primitive::Dump, args, span), input @ ..] => Ok((
             input,
-            eco_vec![f.clone(), Instr::ImplPrim(ImplPrimitive::UnDump, *span)],
+            eco_vec![Node::ImplMod(ImplPrimitive::UnDump, args.clone(), *span)],
         )),
-        [f @ Instr::PushFunc(_), Instr::ImplPrim(ImplPrimitive::UnDump, span), input @ ..] => Ok((
+        [Node::ImplMod(ImplPrimitive::UnDump, args, span), input @ ..] => Ok((
             input,
-            eco_vec![f.clone(), Instr::Prim(Primitive::Dump, *span)],
+            eco_vec![Node::Mod(Primitive::Dump, args.clone(), *span)],
         )),
         _ => generic(),
     }
 }
 
 fn under_dump_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     match input {
-        [f @ Instr::PushFunc(_), Instr::Prim(Primitive::Dump, span), input @ ..] => Ok((
+        [node @ Node::Mod(Primitive::Dump, args, span), input @ ..] => Ok((
             input,
             (
-                eco_vec![f.clone(), Instr::Prim(Primitive::Dump, *span)],
-                eco_vec![f.clone(), Instr::ImplPrim(ImplPrimitive::UnDump, *span)],
+                eco_vec![node.clone()],
+                eco_vec![Node::ImplMod(ImplPrimitive::UnDump, args.clone(), *span)],
             ),
         )),
-        [f @ Instr::PushFunc(_), Instr::ImplPrim(ImplPrimitive::UnDump, span), input @ ..] => Ok((
+        [node @ Node::ImplMod(ImplPrimitive::UnDump, args, span), input @ ..] => Ok((
             input,
             (
-                eco_vec![f.clone(), Instr::ImplPrim(ImplPrimitive::UnDump, *span)],
-                eco_vec![f.clone(), Instr::Prim(Primitive::Dump, *span)],
+                eco_vec![node.clone()],
+                eco_vec![Node::Mod(Primitive::Dump, args.clone(), *span)],
             ),
         )),
         _ => generic(),
@@ -1831,10 +1911,10 @@ fn under_dump_pattern<'a>(
 }
 
 fn under_from_inverse_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     if input.is_empty() {
         return generic();
     }
@@ -1845,14 +1925,20 @@ fn under_from_inverse_pattern<'a>(
                 let input_pattern_match_count = input[..end]
                     .iter()
                     .filter(|instr| {
-                        matches!(instr, Instr::ImplPrim(ImplPrimitive::MatchPattern, _))
+                        matches!(
+                            instr,
+                            Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, _))
+                        )
                     })
                     .count();
                 let before = EcoVec::from(&input[..input.len() - inp.len()]);
                 let after_pattern_match_count = after
                     .iter()
                     .filter(|instr| {
-                        matches!(instr, Instr::ImplPrim(ImplPrimitive::MatchPattern, _))
+                        matches!(
+                            instr,
+                            Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, _))
+                        )
                     })
                     .count();
                 if after_pattern_match_count > input_pattern_match_count {
@@ -1870,105 +1956,98 @@ fn under_from_inverse_pattern<'a>(
 }
 
 fn under_setinv_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
-    comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(inv), Instr::PushFunc(normal), Instr::Prim(Primitive::SetInverse, _), input @ ..] =
-        input
-    else {
+    _: &mut Compiler,
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::SetInverse, args, _), input @ ..] = input else {
         return generic();
     };
-    let under = (
-        normal.instrs(&comp.asm).into(),
-        inv.instrs(&comp.asm).into(),
-    );
+    let [normal, inv] = args.as_slice() else {
+        return generic();
+    };
+    let under = (normal.nodes().into(), inv.nodes().into());
     Ok((input, under))
 }
 
 fn under_setund_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
-    comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(after), Instr::PushFunc(before), Instr::PushFunc(normal), Instr::Prim(Primitive::SetUnder, span), input @ ..] =
-        input
-    else {
+    _: &mut Compiler,
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::SetUnder, args, span), input @ ..] = input else {
+        return generic();
+    };
+    let [normal, before, after] = args.as_slice() else {
         return generic();
     };
-    if before.signature().outputs < normal.signature().outputs {
+    if before.sig.outputs < normal.sig.outputs {
         return generic();
     }
-    let to_save = before.signature().outputs - normal.signature().outputs;
-    let mut befores = EcoVec::from(before.instrs(&comp.asm));
-    let mut afters = EcoVec::from(after.instrs(&comp.asm));
+    let to_save = before.sig.outputs - normal.sig.outputs;
+    let mut befores = EcoVec::from(before.nodes());
+    let mut afters = EcoVec::from(after.nodes());
     if to_save > 0 {
-        befores.push(Instr::PushTemp {
+        befores.push(Node::Instr(Instr::PushTemp {
             stack: TempStack::Under,
             count: to_save,
             span: *span,
-        });
+        }));
         afters.insert(
             0,
-            Instr::PopTemp {
+            Node::Instr(Instr::PopTemp {
                 stack: TempStack::Under,
                 count: to_save,
                 span: *span,
-            },
+            }),
         );
     }
     Ok((input, (befores, afters)))
 }
 
 fn under_setinv_setund_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: Signature,
-    comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(_), Instr::PushFunc(normal), Instr::Prim(Primitive::SetInverse, _), input @ ..] =
-        input
-    else {
+    _: &mut Compiler,
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::SetInverse, args, _), input @ ..] = input else {
         return generic();
     };
-    let [Instr::PushFunc(after), Instr::PushFunc(before), Instr::PushFunc(_), Instr::Prim(Primitive::SetUnder, _)] =
-        normal.instrs(&comp.asm)
+    let Some([Node::Mod(Primitive::SetUnder, set_args, _)]) = args.first().map(FuncNode::nodes)
     else {
         return generic();
     };
-    Ok((
-        input,
-        (
-            before.instrs(&comp.asm).into(),
-            after.instrs(&comp.asm).into(),
-        ),
-    ))
+    let [_, before, after] = set_args.as_slice() else {
+        return generic();
+    };
+    Ok((input, (before.nodes().into(), after.nodes().into())))
 }
 
-type TempWrap<'a> = (&'a [Instr], &'a Instr, &'a [Instr], &'a Instr, usize);
+type TempWrap<'a> = (&'a [Node], &'a Node, &'a [Node], &'a Node, usize);
 
-fn try_push_temp_wrap(input: &[Instr]) -> Option<TempWrap> {
+fn try_push_temp_wrap(input: &[Node]) -> Option<TempWrap> {
     temp_wrap_impl(input, |instr| match instr {
-        Instr::PushTemp {
+        Node::Instr(Instr::PushTemp {
             stack: TempStack::Inline,
             count,
             ..
-        } => Some(*count),
+        }) => Some(*count),
         _ => None,
     })
 }
 
-fn try_copy_temp_wrap(input: &[Instr]) -> Option<TempWrap> {
+fn try_copy_temp_wrap(input: &[Node]) -> Option<TempWrap> {
     temp_wrap_impl(input, |instr| match instr {
-        Instr::CopyToTemp {
+        Node::Instr(Instr::CopyToTemp {
             stack: TempStack::Inline,
             count,
             ..
-        } => Some(*count),
+        }) => Some(*count),
         _ => None,
     })
 }
 
-fn temp_wrap_impl(input: &[Instr], f: impl Fn(&Instr) -> Option<usize>) -> Option<TempWrap> {
+fn temp_wrap_impl(input: &[Node], f: impl Fn(&Node) -> Option<usize>) -> Option<TempWrap> {
     let (first_instr, input) = input.split_first()?;
     let count = f(first_instr)?;
     // Find end
@@ -1976,24 +2055,24 @@ fn temp_wrap_impl(input: &[Instr], f: impl Fn(&Instr) -> Option<usize>) -> Optio
     let mut max_depth = depth;
     for (i, instr) in input.iter().enumerate() {
         match instr {
-            Instr::PushTemp {
+            Node::Instr(Instr::PushTemp {
                 count,
                 stack: TempStack::Inline,
                 ..
-            }
-            | Instr::CopyToTemp {
+            })
+            | Node::Instr(Instr::CopyToTemp {
                 count,
                 stack: TempStack::Inline,
                 ..
-            } => {
+            }) => {
                 depth += *count;
                 max_depth = max_depth.max(depth);
             }
-            Instr::PopTemp {
+            Node::Instr(Instr::PopTemp {
                 count,
                 stack: TempStack::Inline,
                 ..
-            } => {
+            }) => {
                 depth = depth.saturating_sub(*count);
                 if depth == 0 {
                     let (inner, input) = input.split_at(i);
@@ -2009,66 +2088,66 @@ fn temp_wrap_impl(input: &[Instr], f: impl Fn(&Instr) -> Option<usize>) -> Optio
 }
 
 fn invert_push_temp_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     let (input, instr, inner, end_instr, depth) = try_push_temp_wrap(input).ok_or(Generic)?;
     // By-inverse
-    if let [Instr::Prim(Primitive::Dup, dup_span)] = inner {
+    if let [Node::Instr(Instr::Prim(Primitive::Dup, dup_span))] = inner {
         // By
         for end in 1..=input.len() {
             for mid in 0..end {
                 let before = &input[..mid];
-                if instrs_clean_signature(before).map_or(true, |sig| sig != (0, 0)) {
+                if nodes_clean_signature(before).map_or(true, |sig| sig != (0, 0)) {
                     continue;
                 };
-                let instrs = &input[mid..end];
-                let Some(sig) = instrs_clean_signature(instrs) else {
+                let nodes = &input[mid..end];
+                let Some(sig) = nodes_clean_signature(nodes) else {
                     continue;
                 };
                 if sig.args != depth + 1 {
                     continue;
                 }
                 for pat in BY_INVERT_PATTERNS {
-                    if let Ok((after, by_inv)) = pat.invert_extract(instrs, comp) {
+                    if let Ok((after, by_inv)) = pat.invert_extract(nodes, comp) {
                         if after.is_empty() {
-                            let mut instrs = eco_vec![
+                            let mut nodes = eco_vec![
                                 instr.clone(),
-                                Instr::Prim(Primitive::Dup, *dup_span),
+                                Node::Instr(Instr::Prim(Primitive::Dup, *dup_span)),
                                 end_instr.clone(),
                             ];
-                            instrs.extend(by_inv);
-                            instrs.extend_from_slice(before);
-                            return Ok((&input[end..], instrs));
+                            nodes.extend(by_inv);
+                            nodes.extend_from_slice(before);
+                            return Ok((&input[end..], nodes));
                         }
                     }
                 }
             }
         }
-    } else if instrs_signature(inner).is_ok_and(|sig| sig == (0, 1)) {
+    } else if nodes_signature(inner).is_ok_and(|sig| sig == (0, 1)) {
         // Dip constant
         for pat in BY_INVERT_PATTERNS {
             if let Ok((input, by_inv)) = pat.invert_extract(input, comp) {
-                let mut instrs = eco_vec![instr.clone()];
-                instrs.extend_from_slice(inner);
-                instrs.push(end_instr.clone());
-                instrs.extend(by_inv);
-                return Ok((input, instrs));
+                let mut nodes = eco_vec![instr.clone()];
+                nodes.extend_from_slice(inner);
+                nodes.push(end_instr.clone());
+                nodes.extend(by_inv);
+                return Ok((input, nodes));
             }
         }
     }
     // Normal inverse
-    let mut instrs = invert_instrs(inner, comp)?;
-    instrs.insert(0, instr.clone());
-    instrs.push(end_instr.clone());
-    Ok((input, instrs))
+    let mut nodes = invert_nodes(inner, comp)?;
+    nodes.insert(0, instr.clone());
+    nodes.push(end_instr.clone());
+    Ok((input, nodes))
 }
 
 fn invert_copy_temp_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let (input, start_instr @ Instr::CopyToTemp { span, .. }, inner, end_instr, count) =
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let (input, start_instr @ Node::Instr(Instr::CopyToTemp { span, .. }), inner, end_instr, count) =
         try_copy_temp_wrap(input).ok_or(Generic)?
     else {
         return generic();
@@ -2076,7 +2155,7 @@ fn invert_copy_temp_pattern<'a>(
     // On-inverse
     for mid in 0..inner.len() {
         let (before, after) = inner.split_at(mid);
-        let Ok(before_sig) = instrs_signature(before) else {
+        let Ok(before_sig) = nodes_signature(before) else {
             continue;
         };
         if before_sig.args == 0 && before_sig.outputs != 0 {
@@ -2084,115 +2163,115 @@ fn invert_copy_temp_pattern<'a>(
         }
         for pat in ON_INVERT_PATTERNS {
             if let Ok((after, on_inv)) = pat.invert_extract(after, comp) {
-                if let Ok(after_inv) = invert_instrs(after, comp) {
-                    let mut instrs = eco_vec![start_instr.clone()];
+                if let Ok(after_inv) = invert_nodes(after, comp) {
+                    let mut nodes = eco_vec![start_instr.clone()];
 
                     if !after_inv.is_empty() {
-                        instrs.push(Instr::PushTemp {
+                        nodes.push(Node::Instr(Instr::PushTemp {
                             stack: TempStack::Inline,
                             count,
                             span: *span,
-                        });
-                        instrs.extend(after_inv);
-                        instrs.push(end_instr.clone());
+                        }));
+                        nodes.extend(after_inv);
+                        nodes.push(end_instr.clone());
                     }
 
-                    instrs.extend_from_slice(before);
+                    nodes.extend_from_slice(before);
 
-                    instrs.extend(on_inv);
+                    nodes.extend(on_inv);
 
-                    instrs.push(end_instr.clone());
-                    return Ok((input, instrs));
+                    nodes.push(end_instr.clone());
+                    return Ok((input, nodes));
                 }
             }
         }
     }
     // Pattern matching
-    if let Ok(inverse) = invert_instrs(inner, comp) {
-        let mut instrs = eco_vec![Instr::PushTemp {
+    if let Ok(inverse) = invert_nodes(inner, comp) {
+        let mut nodes = eco_vec![Node::Instr(Instr::PushTemp {
             stack: TempStack::Inline,
             count,
             span: *span
-        }];
-        instrs.extend(inverse);
-        instrs.push(end_instr.clone());
-        instrs.extend([
-            Instr::Prim(Primitive::Over, *span),
-            Instr::ImplPrim(ImplPrimitive::MatchPattern, *span),
+        })];
+        nodes.extend(inverse);
+        nodes.push(end_instr.clone());
+        nodes.extend([
+            Node::Instr(Instr::Prim(Primitive::Over, *span)),
+            Node::Instr(Instr::ImplPrim(ImplPrimitive::MatchPattern, *span)),
         ]);
-        return Ok((input, instrs));
+        return Ok((input, nodes));
     }
 
     generic()
 }
 
 fn invert_flip_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let Ok((input, mut instrs)) = Val.invert_extract(input, comp) else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let Ok((input, mut nodes)) = Val.invert_extract(input, comp) else {
         return generic();
     };
-    let [Instr::Prim(Primitive::Flip, span), input @ ..] = input else {
+    let [Node::Instr(Instr::Prim(Primitive::Flip, span)), input @ ..] = input else {
         return generic();
     };
     for pat in BY_INVERT_PATTERNS {
         if let Ok((input, mut by_inv)) = pat.invert_extract(input, comp) {
-            if instrs
+            if nodes
                 .first()
-                .is_some_and(|instr| matches!(instr, Instr::Prim(Primitive::Flip, _)))
+                .is_some_and(|instr| matches!(instr, Node::Instr(Instr::Prim(Primitive::Flip, _))))
             {
                 by_inv.remove(0);
             } else {
-                by_inv.insert(0, Instr::Prim(Primitive::Flip, *span));
+                by_inv.insert(0, Node::Instr(Instr::Prim(Primitive::Flip, *span)));
             }
-            instrs.extend(by_inv);
-            return Ok((input, instrs));
+            nodes.extend(by_inv);
+            return Ok((input, nodes));
         }
     }
     generic()
 }
 
 fn under_flip_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::Prim(Primitive::Flip, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Instr(Instr::Prim(Primitive::Flip, span)), input @ ..] = input else {
         return generic();
     };
-    let befores = eco_vec![Instr::Prim(Primitive::Flip, *span)];
-    let (rest_befores, rest_afters) = under_instrs(input, g_sig, &mut comp.clone())?;
-    let rest_befores_sig = instrs_signature(&rest_befores)?;
-    let rest_afters_sig = instrs_signature(&rest_afters)?;
+    let befores = eco_vec![Node::Instr(Instr::Prim(Primitive::Flip, *span))];
+    let (rest_befores, rest_afters) = under_nodes(input, g_sig, &mut comp.clone())?;
+    let rest_befores_sig = nodes_signature(&rest_befores)?;
+    let rest_afters_sig = nodes_signature(&rest_afters)?;
     let total_args = g_sig.args + rest_befores_sig.args + rest_afters_sig.args;
     let total_outputs = g_sig.outputs + rest_befores_sig.outputs + rest_afters_sig.outputs;
     let afters = if total_outputs < total_args {
         EcoVec::new()
     } else {
-        eco_vec![Instr::Prim(Primitive::Flip, *span)]
+        eco_vec![Node::Instr(Instr::Prim(Primitive::Flip, *span))]
     };
     Ok((input, (befores, afters)))
 }
 
 fn under_copy_temp_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     let (input, instr, inner, end_instr, _) = try_copy_temp_wrap(input).ok_or(Generic)?;
-    let (mut inner_befores, mut inner_afters) = under_instrs(inner, g_sig, comp)?;
+    let (mut inner_befores, mut inner_afters) = under_nodes(inner, g_sig, comp)?;
     let Some((
-        Instr::CopyToTemp {
+        Node::Instr(Instr::CopyToTemp {
             stack: TempStack::Under,
             count: before_count,
             ..
-        },
-        Instr::PopTemp {
+        }),
+        Node::Instr(Instr::PopTemp {
             stack: TempStack::Under,
             count: after_count,
             ..
-        },
+        }),
     )) = inner_befores.first().zip(inner_afters.first())
     else {
         return generic();
@@ -2216,34 +2295,35 @@ fn under_copy_temp_pattern<'a>(
 }
 
 fn under_push_temp_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     let (input, start_instr, inner, end_instr, _) = try_push_temp_wrap(input).ok_or(Generic)?;
 
     // Calcular inner functions and signatures
-    let (inner_befores, inner_afters) = under_instrs(inner, g_sig, comp)?;
-    let inner_befores_sig = instrs_signature(&inner_befores)?;
-    let inner_afters_sig = instrs_signature(&inner_afters)?;
+    let (inner_befores, inner_afters) = under_nodes(inner, g_sig, comp)?;
+    let inner_befores_sig = nodes_signature(&inner_befores)?;
+    let inner_afters_sig = nodes_signature(&inner_afters)?;
 
     if g_sig.args < g_sig.outputs {
         return generic();
     }
 
-    let temp_depth = if let Instr::PopTemp {
+    let temp_depth = if let Node::Instr(Instr::PopTemp {
         count,
         stack: TempStack::Inline,
         ..
-    } = end_instr
+    }) = end_instr
     {
         *count
     } else {
         return generic();
     };
 
-    let input_iter = input.iter().filter(|instr| !instr.is_compile_only());
-    let inner_iter = inner.iter().filter(|instr| !instr.is_compile_only());
+    let compile_only = |node: &&Node| matches!(node, Node::Instr(instr) if instr.is_compile_only());
+    let input_iter = input.iter().filter(|node| !compile_only(node));
+    let inner_iter = inner.iter().filter(|node| !compile_only(node));
     let both = inner_befores_sig.args == temp_depth
         && input_iter.clone().count() >= inner_iter.clone().count()
         && input_iter.zip(inner_iter).all(|(a, b)| a == b);
@@ -2253,44 +2333,46 @@ fn under_push_temp_pattern<'a>(
     befores.push(end_instr.clone());
 
     let afters = if both && g_sig.args > g_sig.outputs {
-        fn count_under_temps(instrs: &[Instr], asm: &Assembly) -> usize {
+        fn count_under_temps(nodes: &[Node]) -> usize {
             let mut temp_count = 0;
-            for i in 0..instrs.len() {
-                match &instrs[i] {
-                    Instr::PushTemp {
+            for i in 0..nodes.len() {
+                match &nodes[i] {
+                    Node::Instr(Instr::PushTemp {
                         count,
                         stack: TempStack::Under,
                         ..
-                    }
-                    | Instr::CopyToTemp {
+                    })
+                    | Node::Instr(Instr::CopyToTemp {
                         count,
                         stack: TempStack::Under,
                         ..
-                    } => temp_count += *count,
-                    Instr::PopTemp {
+                    }) => temp_count += *count,
+                    Node::Instr(Instr::PopTemp {
                         count,
                         stack: TempStack::Under,
                         ..
-                    } => temp_count = temp_count.saturating_sub(*count),
-                    Instr::PushFunc(f) if matches!(instrs.get(i + 1), Some(Instr::Call(_))) => {
-                        temp_count += count_under_temps(f.instrs(asm), asm)
+                    }) => temp_count = temp_count.saturating_sub(*count),
+                    Node::Func(f)
+                        if matches!(nodes.get(i + 1), Some(Node::Instr(Instr::Call(_)))) =>
+                    {
+                        temp_count += count_under_temps(f.nodes())
                     }
                     _ => {}
                 }
             }
             temp_count
         }
-        let before_temp_count = count_under_temps(&befores, &comp.asm);
+        let before_temp_count = count_under_temps(&befores);
         if before_temp_count > 0 {
-            let mut instrs = eco_vec![Instr::PopTemp {
+            let mut nodes = eco_vec![Node::Instr(Instr::PopTemp {
                 count: before_temp_count,
                 stack: TempStack::Under,
                 span: 0,
-            }];
+            })];
             for _ in 0..before_temp_count {
-                instrs.push(Instr::Prim(Primitive::Pop, before_temp_count))
+                nodes.push(Node::Instr(Instr::Prim(Primitive::Pop, before_temp_count)))
             }
-            instrs
+            nodes
         } else {
             EcoVec::new()
         }
@@ -2306,7 +2388,7 @@ fn under_push_temp_pattern<'a>(
                     *start_count -= inner_afters_sig.outputs;
                     *end_count = (*end_count).min(inner_befores_sig.outputs);
                 }
-                let outers_sig = instrs_signature(input)?;
+                let outers_sig = nodes_signature(input)?;
                 if (both && inner_befores_sig.args > 1 || outers_sig.args <= outers_sig.outputs)
                     && inner_afters_sig.outputs > 0
                 {
@@ -2338,130 +2420,134 @@ fn under_push_temp_pattern<'a>(
 }
 
 fn temp_pair_counts<'a, 'b>(
-    start_instr: &'a mut Instr,
-    end_instr: &'b mut Instr,
+    start_instr: &'a mut Node,
+    end_instr: &'b mut Node,
 ) -> Option<(&'a mut usize, &'b mut usize)> {
     match (start_instr, end_instr) {
         (
-            Instr::PushTemp {
+            Node::Instr(Instr::PushTemp {
                 count: start_count, ..
-            },
-            Instr::PopTemp {
+            }),
+            Node::Instr(Instr::PopTemp {
                 count: end_count, ..
-            },
+            }),
         ) => Some((start_count, end_count)),
         (
-            Instr::CopyToTemp {
+            Node::Instr(Instr::CopyToTemp {
                 count: start_count, ..
-            },
-            Instr::PopTemp {
+            }),
+            Node::Instr(Instr::PopTemp {
                 count: end_count, ..
-            },
+            }),
         ) => Some((start_count, end_count)),
         _ => None,
     }
 }
 
 fn make_fn(
-    instrs: EcoVec<Instr>,
+    nodes: EcoVec<Node>,
     flags: FunctionFlags,
     span: usize,
     comp: &mut Compiler,
-) -> InversionResult<Function> {
-    let sig = instrs_signature(&instrs)?;
+) -> InversionResult<FuncNode> {
+    let sig = nodes_signature(&nodes)?;
     let Span::Code(span) = comp.get_span(span) else {
         return generic();
     };
     let id = FunctionId::Anonymous(span);
-    Ok(comp.make_function(id, sig, NewFunction { instrs, flags }))
+    Ok(comp.make_function(id, sig, NewFunction { nodes, flags }))
 }
 
 fn under_each_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let &[Instr::PushFunc(ref f), Instr::Prim(Primitive::Each, span), ref input @ ..] = input
-    else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::Each, args, span), input @ ..] = input else {
         return generic();
     };
-    let instrs = f.instrs(&comp.asm).to_vec();
-    let (f_before, f_after) = under_instrs(&instrs, g_sig, comp).map_err(|e| e.func(f))?;
-    let befores = eco_vec![
-        Instr::PushFunc(make_fn(f_before, f.flags, span, comp)?),
-        Instr::Prim(Primitive::Each, span),
-    ];
-    let afters = eco_vec![
-        Instr::PushFunc(make_fn(f_after, f.flags, span, comp)?),
-        Instr::Prim(Primitive::Each, span),
-    ];
+    let ([f], span) = (args.as_slice(), *span) else {
+        return generic();
+    };
+    let nodes = f.nodes();
+    let (f_before, f_after) = under_nodes(nodes, g_sig, comp).map_err(|e| e.func(f))?;
+    let before = make_fn(f_before, f.flags, span, comp)?;
+    let after = make_fn(f_after, f.flags, span, comp)?;
+    let befores = eco_vec![Node::Mod(Primitive::Each, eco_vec![before], span)];
+    let afters = eco_vec![Node::Mod(Primitive::Each, eco_vec![after], span)];
     Ok((input, (befores, afters)))
 }
 
 fn invert_rows_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(f), instr @ Instr::Prim(Primitive::Rows, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Mod(Primitive::Rows, args, span), input @ ..] = input else {
         return generic();
     };
-    let instrs = f.instrs(&comp.asm).to_vec();
-    let inverse = invert_instrs(&instrs, comp).map_err(|e| e.func(f))?;
+    let [f] = args.as_slice() else {
+        return generic();
+    };
+    let nodes = f.nodes();
+    let inverse = invert_nodes(nodes, comp).map_err(|e| e.func(f))?;
     let f = make_fn(inverse, f.flags, *span, comp)?;
-    Ok((input, eco_vec![Instr::PushFunc(f), instr.clone()]))
+    Ok((
+        input,
+        eco_vec![Node::Mod(Primitive::Rows, eco_vec![f], *span)],
+    ))
 }
 
 fn under_rows_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let &[Instr::PushFunc(ref f), Instr::Prim(prim @ (Primitive::Rows | Primitive::Inventory), span), ref input @ ..] =
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(prim @ (Primitive::Rows | Primitive::Inventory), args, span), input @ ..] =
         input
     else {
         return generic();
     };
-    let instrs = f.instrs(&comp.asm).to_vec();
-    let (f_before, f_after) = under_instrs(&instrs, g_sig, comp).map_err(|e| e.func(f))?;
-    let befores = eco_vec![
-        Instr::PushFunc(make_fn(f_before, f.flags, span, comp)?),
-        Instr::Prim(prim, span),
-    ];
+    let ([f], prim, span) = (args.as_slice(), *prim, *span) else {
+        return generic();
+    };
+    let nodes = f.nodes();
+    let (f_before, f_after) = under_nodes(nodes, g_sig, comp).map_err(|e| e.func(f))?;
+    let before_fn = make_fn(f_before, f.flags, span, comp)?;
+    let befores = eco_vec![Node::Mod(prim, eco_vec![before_fn], span)];
     let after_fn = make_fn(f_after, f.flags, span, comp)?;
-    let after_sig = after_fn.signature();
+    let after_sig = after_fn.sig;
     let mut afters = eco_vec![
-        Instr::PushFunc(after_fn),
-        Instr::Prim(Primitive::Reverse, span),
-        Instr::Prim(prim, span),
+        Node::Instr(Instr::Prim(Primitive::Reverse, span)),
+        Node::Mod(prim, eco_vec![after_fn], span),
     ];
     if after_sig.outputs > 0 {
-        afters.push(Instr::Prim(Primitive::Reverse, span));
+        afters.push(Node::Instr(Instr::Prim(Primitive::Reverse, span)));
     }
     for count in 1..after_sig.outputs {
         afters.extend([
-            Instr::PushTemp {
+            Node::Instr(Instr::PushTemp {
                 stack: TempStack::Inline,
                 count,
                 span,
-            },
-            Instr::Prim(Primitive::Reverse, span),
+            }),
+            Node::Instr(Instr::Prim(Primitive::Reverse, span)),
         ]);
     }
     if after_sig.outputs > 0 {
-        afters.push(Instr::pop_inline(after_sig.outputs - 1, span));
+        afters.push(Node::Instr(Instr::pop_inline(after_sig.outputs - 1, span)));
     }
     Ok((input, (befores, afters)))
 }
 
 fn under_reverse_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     if g_sig.outputs == 1 {
         return generic();
     }
-    let [Instr::Prim(Primitive::Reverse, span), input @ ..] = input else {
+    let [Node::Instr(Instr::Prim(Primitive::Reverse, span)), input @ ..] = input else {
         return generic();
     };
     let span = *span;
@@ -2470,24 +2556,32 @@ fn under_reverse_pattern<'a>(
     } else {
         1
     };
-    let after = eco_vec![Instr::ImplPrim(ImplPrimitive::UndoReverse(count), span)];
+    let after = eco_vec![Node::Instr(Instr::ImplPrim(
+        ImplPrimitive::UndoReverse(count),
+        span
+    ))];
     Ok((
         input,
-        (eco_vec![Instr::Prim(Primitive::Reverse, span)], after),
+        (
+            eco_vec![Node::Instr(Instr::Prim(Primitive::Reverse, span))],
+            after,
+        ),
     ))
 }
 
 fn under_transpose_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     if g_sig.outputs == 1 {
         return generic();
     }
     let (instr, span, amnt, input) = match input {
-        [instr @ Instr::Prim(Primitive::Transpose, span), input @ ..] => (instr, *span, 1, input),
-        [instr @ Instr::ImplPrim(ImplPrimitive::TransposeN(amnt), span), input @ ..] => {
+        [instr @ Node::Instr(Instr::Prim(Primitive::Transpose, span)), input @ ..] => {
+            (instr, *span, 1, input)
+        }
+        [instr @ Node::Instr(Instr::ImplPrim(ImplPrimitive::TransposeN(amnt), span)), input @ ..] => {
             (instr, *span, *amnt, input)
         }
         _ => return generic(),
@@ -2497,19 +2591,19 @@ fn under_transpose_pattern<'a>(
     } else {
         1
     };
-    let after = eco_vec![Instr::ImplPrim(
+    let after = eco_vec![Node::Instr(Instr::ImplPrim(
         ImplPrimitive::UndoTransposeN(count, amnt),
         span
-    )];
+    ))];
     Ok((input, (eco_vec![instr.clone()], after)))
 }
 
 fn under_rotate_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::Prim(Primitive::Rotate, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Instr(Instr::Prim(Primitive::Rotate, span)), input @ ..] = input else {
         return generic();
     };
     let span = *span;
@@ -2519,97 +2613,97 @@ fn under_rotate_pattern<'a>(
         1
     };
     let before = eco_vec![
-        Instr::CopyToTemp {
+        Node::Instr(Instr::CopyToTemp {
             stack: TempStack::Under,
             count: 1,
             span
-        },
-        Instr::Prim(Primitive::Rotate, span)
+        }),
+        Node::Instr(Instr::Prim(Primitive::Rotate, span))
     ];
     let after = eco_vec![
-        Instr::PopTemp {
+        Node::Instr(Instr::PopTemp {
             stack: TempStack::Under,
             count: 1,
             span
-        },
-        Instr::ImplPrim(ImplPrimitive::UndoRotate(count), span)
+        }),
+        Node::Instr(Instr::ImplPrim(ImplPrimitive::UndoRotate(count), span))
     ];
     Ok((input, (before, after)))
 }
 
 fn invert_fill_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(f), Instr::PushFunc(get_fill), Instr::Prim(Primitive::Fill, span), input @ ..] =
-        input
-    else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Mod(Primitive::Fill, args, span), input @ ..] = input else {
         return generic();
     };
-    if get_fill.signature() != (0, 1) {
+    let [get_fill, f] = args.as_slice() else {
+        return generic();
+    };
+    if get_fill.sig != (0, 1) {
         return generic();
     }
-    let f_instrs = f.instrs(&comp.asm).to_vec();
-    let f_inv = invert_instrs(&f_instrs, comp).map_err(|e| e.func(f))?;
+    let f_nodes = f.nodes();
+    let f_inv = invert_nodes(f_nodes, comp).map_err(|e| e.func(f))?;
     let inverse = make_fn(f_inv, f.flags, *span, comp)?;
-    let instrs = eco_vec![
-        Instr::PushFunc(inverse),
-        Instr::PushFunc(get_fill.clone()),
-        Instr::ImplPrim(ImplPrimitive::UnFill, *span),
-    ];
-    Ok((input, instrs))
+    let args = eco_vec![get_fill.clone(), inverse];
+    Ok((
+        input,
+        eco_vec![Node::ImplMod(ImplPrimitive::UnFill, args, *span)],
+    ))
 }
 
 fn under_fill_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(f), Instr::PushFunc(get_fill), Instr::Prim(Primitive::Fill, span), input @ ..] =
-        input
-    else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::Fill, args, span), input @ ..] = input else {
         return generic();
     };
-    let span = *span;
-    if get_fill.signature() != (0, 1) {
+    let ([get_fill, f], span) = (args.as_slice(), *span) else {
+        return generic();
+    };
+    if get_fill.sig != (0, 1) {
         return generic();
     }
-    let f_instrs = f.instrs(&comp.asm).to_vec();
-    let (f_before, f_after) = under_instrs(&f_instrs, g_sig, comp).map_err(|e| e.func(f))?;
+    let f_nodes = f.nodes();
+    let (f_before, f_after) = under_nodes(f_nodes, g_sig, comp).map_err(|e| e.func(f))?;
     let f_before = make_fn(f_before, f.flags, span, comp)?;
     let f_after = make_fn(f_after, f.flags, span, comp)?;
-    let befores = eco_vec![
-        Instr::PushFunc(f_before),
-        Instr::PushFunc(get_fill.clone()),
-        Instr::Prim(Primitive::Fill, span),
-    ];
-    let afters = eco_vec![
-        Instr::PushFunc(f_after),
-        Instr::PushFunc(get_fill.clone()),
-        Instr::ImplPrim(ImplPrimitive::UnFill, span),
-    ];
+    let befores = eco_vec![Node::Mod(
+        Primitive::Fill,
+        eco_vec![get_fill.clone(), f_before],
+        span
+    )];
+    let afters = eco_vec![Node::ImplMod(
+        ImplPrimitive::UnFill,
+        eco_vec![get_fill.clone(), f_after],
+        span
+    )];
     Ok((input, (befores, afters)))
 }
 
 fn under_switch_pattern<'a>(
-    mut input: &'a [Instr],
+    mut input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     let mut funcs = Vec::new();
-    while let (Instr::PushFunc(f), rest) = input.split_first().ok_or(Generic)? {
+    while let (Node::Func(f), rest) = input.split_first().ok_or(Generic)? {
         funcs.push(f);
         input = rest;
     }
     if funcs.is_empty() {
         return generic();
     }
-    let [Instr::Switch {
+    let [Node::Instr(Instr::Switch {
         count,
         sig,
         span,
         under_cond: false,
-    }, input @ ..] = input
+    }), input @ ..] = input
     else {
         return generic();
     };
@@ -2621,11 +2715,11 @@ fn under_switch_pattern<'a>(
     let mut undo_sig: Option<Signature> = None;
     for f in &funcs {
         // Calc under f
-        let f_instrs = f.instrs(&comp.asm).to_vec();
-        let (before, after) = under_instrs(&f_instrs, g_sig, comp).map_err(|e| e.func(f))?;
+        let f_nodes = f.nodes();
+        let (before, after) = under_nodes(f_nodes, g_sig, comp).map_err(|e| e.func(f))?;
         f_befores.push(make_fn(before, f.flags, *span, comp)?);
         let f_after = make_fn(after, f.flags, *span, comp)?;
-        let f_after_sig = f_after.signature();
+        let f_after_sig = f_after.sig;
         f_afters.push(f_after);
         // Aggregate sigs
         let undo_sig = undo_sig.get_or_insert(f_after_sig);
@@ -2639,28 +2733,28 @@ fn under_switch_pattern<'a>(
     }
 
     let mut befores = EcoVec::with_capacity(funcs.len() + 2);
-    befores.extend(f_befores.into_iter().map(Instr::PushFunc));
-    befores.push(Instr::Switch {
+    befores.extend(f_befores.into_iter().map(Node::Func));
+    befores.push(Node::Instr(Instr::Switch {
         count: *count,
         sig: *sig,
         span: *span,
         under_cond: true,
-    });
+    }));
 
     let mut afters = EcoVec::with_capacity(funcs.len() + 2);
-    afters.extend(f_afters.into_iter().map(Instr::PushFunc));
+    afters.extend(f_afters.into_iter().map(Node::Func));
     afters.extend([
-        Instr::PopTemp {
+        Node::Instr(Instr::PopTemp {
             stack: TempStack::Under,
             count: 1,
             span: *span,
-        },
-        Instr::Switch {
+        }),
+        Node::Instr(Instr::Switch {
             count: *count,
             sig: undo_sig.ok_or(Generic)?,
             span: *span,
             under_cond: false,
-        },
+        }),
     ]);
     Ok((input, (befores, afters)))
 }
@@ -2668,37 +2762,44 @@ fn under_switch_pattern<'a>(
 macro_rules! partition_group {
     ($name:ident, $prim:ident, $impl_prim1:ident, $impl_prim2:ident) => {
         fn $name<'a>(
-            input: &'a [Instr],
+            input: &'a [Node],
             g_sig: Signature,
             comp: &mut Compiler,
-        ) -> InversionResult<(&'a [Instr], Under)> {
-            let &[Instr::PushFunc(ref f), Instr::Prim(Primitive::$prim, span), ref input @ ..] =
-                input
-            else {
+        ) -> InversionResult<(&'a [Node], Under)> {
+            let [Node::Mod(Primitive::$prim, args, span), input @ ..] = input else {
+                return generic();
+            };
+            let ([f], span) = (args.as_slice(), *span) else {
                 return generic();
             };
-            let instrs = f.instrs(&comp.asm).to_vec();
-            let (f_before, f_after) = under_instrs(&instrs, g_sig, comp).map_err(|e| e.func(f))?;
+            let nodes = f.nodes();
+            let (f_before, f_after) = under_nodes(&nodes, g_sig, comp).map_err(|e| e.func(f))?;
             let befores = eco_vec![
-                Instr::CopyToTemp {
+                Node::Instr(Instr::CopyToTemp {
                     stack: TempStack::Under,
                     count: 2,
                     span,
-                },
-                Instr::PushFunc(make_fn(f_before, f.flags, span, comp)?),
-                Instr::Prim(Primitive::$prim, span),
+                }),
+                Node::Mod(
+                    Primitive::$prim,
+                    eco_vec![make_fn(f_before, f.flags, span, comp)?],
+                    span
+                ),
             ];
             let afters = eco_vec![
-                Instr::PushFunc(make_fn(f_after, f.flags, span, comp)?),
-                Instr::ImplPrim(ImplPrimitive::$impl_prim1, span),
-                Instr::push_inline(1, span),
-                Instr::PopTemp {
+                Node::ImplMod(
+                    ImplPrimitive::$impl_prim1,
+                    eco_vec![make_fn(f_after, f.flags, span, comp)?],
+                    span
+                ),
+                Node::Instr(Instr::push_inline(1, span)),
+                Node::Instr(Instr::PopTemp {
                     stack: TempStack::Under,
                     count: 2,
                     span,
-                },
-                Instr::pop_inline(1, span),
-                Instr::ImplPrim(ImplPrimitive::$impl_prim2, span),
+                }),
+                Node::Instr(Instr::pop_inline(1, span)),
+                Node::Instr(Instr::ImplPrim(ImplPrimitive::$impl_prim2, span)),
             ];
             Ok((input, (befores, afters)))
         }
@@ -2714,129 +2815,115 @@ partition_group!(
 partition_group!(under_group_pattern, Group, UndoGroup1, UndoGroup2);
 
 fn try_array_wrap<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> Option<(&'a [Instr], &'a [Instr], usize, bool)> {
-    let [Instr::BeginArray, input @ ..] = input else {
+) -> Option<(&'a [Node], &'a [Node], usize, bool)> {
+    let [Node::Array { inner, boxed, span }, input @ ..] = input else {
         return None;
     };
-    let mut depth = 1;
-    for (i, instr) in input.iter().enumerate() {
-        match instr {
-            Instr::BeginArray => depth += 1,
-            Instr::EndArray { span, boxed } => {
-                depth -= 1;
-                if depth == 0 {
-                    let (inner, input) = input.split_at(i);
-                    let input = &input[1..];
-                    return Some((input, inner, *span, *boxed));
-                }
-            }
-            _ => {}
-        }
-    }
-    None
+    Some((input, inner.as_slice(), *span, *boxed))
 }
 
 fn invert_array_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     let (input, inner, span, unbox) = try_array_wrap(input, comp).ok_or(Generic)?;
-    let count = instrs_signature(inner)?.outputs;
-    let mut instrs = invert_instrs(inner, comp)?;
-    instrs.insert(0, Instr::Unpack { count, span, unbox });
-    Ok((input, instrs))
+    let count = nodes_signature(inner)?.outputs;
+    let mut nodes = invert_nodes(inner, comp)?;
+    nodes.insert(0, Node::Instr(Instr::Unpack { count, span, unbox }));
+    Ok((input, nodes))
 }
 
 fn under_array_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     let (input, inner, span, unbox) = try_array_wrap(input, comp).ok_or(Generic)?;
-    let (mut befores, mut afters) = under_instrs(inner, g_sig, comp)?;
-    let count = instrs_signature(&befores)?.outputs;
-    befores.insert(0, Instr::BeginArray);
-    befores.push(Instr::EndArray { span, boxed: unbox });
-    afters.insert(0, Instr::Unpack { count, span, unbox });
+    let (befores, mut afters) = under_nodes(inner, g_sig, comp)?;
+    let count = nodes_signature(&befores)?.outputs;
+    let befores = eco_vec![Node::Array {
+        inner: Box::new(Node::Run(befores)),
+        boxed: unbox,
+        span,
+    }];
+    afters.insert(0, Node::Instr(Instr::Unpack { count, span, unbox }));
     Ok((input, (befores, afters)))
 }
 
 fn invert_unpack_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::Unpack { count, span, unbox }, input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Instr(Instr::Unpack { count, span, unbox }), input @ ..] = input else {
         return generic();
     };
-    let mut instrs = invert_instrs(input, comp)?;
-    instrs.insert(0, Instr::BeginArray);
+    let mut nodes = invert_nodes(input, comp)?;
     if *count > 0 {
-        instrs.push(Instr::TouchStack {
+        nodes.push(Node::Instr(Instr::TouchStack {
             count: *count,
             span: *span,
-        });
+        }));
     }
-    instrs.push(Instr::EndArray {
-        span: *span,
+    let array = Node::Array {
+        inner: Box::new(Node::Run(nodes)),
         boxed: *unbox,
-    });
-    Ok((&[], instrs))
+        span: *span,
+    };
+    Ok((&[], eco_vec![array]))
 }
 
 fn under_unpack_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [unpack @ Instr::Unpack { count, span, unbox }, input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [unpack @ Node::Instr(Instr::Unpack { count, span, unbox }), input @ ..] = input else {
         return generic();
     };
-    let (mut befores, mut afters) = under_instrs(input, g_sig, comp)?;
+    let (mut befores, mut afters) = under_nodes(input, g_sig, comp)?;
     befores.insert(0, unpack.clone());
-    afters.insert(0, Instr::BeginArray);
-    afters.extend([
-        Instr::TouchStack {
-            count: *count,
-            span: *span,
-        },
-        Instr::EndArray {
-            span: *span,
-            boxed: *unbox,
-        },
-    ]);
+    afters.push(Node::Instr(Instr::TouchStack {
+        count: *count,
+        span: *span,
+    }));
+    let afters = eco_vec![Node::Array {
+        inner: Box::new(Node::Run(afters)),
+        boxed: *unbox,
+        span: *span,
+    }];
     Ok((&[], (befores, afters)))
 }
 
 fn under_touch_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [instr @ Instr::TouchStack { count, span }, input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [instr @ Node::Instr(Instr::TouchStack { count, span }), input @ ..] = input else {
         return generic();
     };
     let mut afters = eco_vec![];
     let new_count = (*count + g_sig.outputs).saturating_sub(g_sig.args);
     if new_count > 0 {
-        afters.push(Instr::TouchStack {
+        afters.push(Node::Instr(Instr::TouchStack {
             count: new_count,
             span: *span,
-        });
+        }));
     }
     Ok((input, (eco_vec![instr.clone()], afters)))
 }
 
 fn invert_split_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     _: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::CopyToTemp {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Instr(Instr::CopyToTemp {
         stack: TempStack::Inline,
         count: 1,
         ..
-    }, input @ ..] = input
+    }), input @ ..] = input
     else {
         return generic();
     };
@@ -2845,155 +2932,163 @@ fn invert_split_pattern<'a>(
         .position(|instr| {
             matches!(
                 instr,
-                Instr::PopTemp {
+                Node::Instr(Instr::PopTemp {
                     stack: TempStack::Inline,
                     count: 1,
                     ..
-                }
+                })
             )
         })
         .ok_or(Generic)?;
     let (a, b) = input.split_at(pop_pos);
     let b = &b[1..];
-    let (input, instrs) = match (a, b) {
+    let (input, nodes) = match (a, b) {
         (
-            [Instr::Prim(Primitive::Deduplicate, _)],
-            [Instr::Prim(Primitive::Classify, span), input @ ..],
-        ) => (input, eco_vec![Instr::Prim(Primitive::Select, *span)]),
+            [Node::Instr(Instr::Prim(Primitive::Deduplicate, _))],
+            [Node::Instr(Instr::Prim(Primitive::Classify, span)), input @ ..],
+        ) => (
+            input,
+            eco_vec![Node::Instr(Instr::Prim(Primitive::Select, *span))],
+        ),
         _ => return generic(),
     };
-    Ok((input, instrs))
+    Ok((input, nodes))
 }
 
 fn invert_scan_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(f), instr, input @ ..] = input else {
-        return generic();
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let (args, span, un, input) = match input {
+        [Node::Mod(Primitive::Scan, args, span), input @ ..] => (args, span, false, input),
+        [Node::ImplMod(ImplPrimitive::UnScan, args, span), input @ ..] => (args, span, true, input),
+        _ => return generic(),
     };
-    let (Instr::Prim(Primitive::Scan, span) | Instr::ImplPrim(ImplPrimitive::UnScan, span)) = instr
-    else {
+    let [f] = args.as_slice() else {
         return generic();
     };
-    let un = matches!(instr, Instr::ImplPrim(ImplPrimitive::UnScan, _));
     let inverse = match f.as_flipped_primitive(&comp.asm) {
-        Some((Primitive::Add, false)) if !un => eco_vec![Instr::Prim(Primitive::Sub, *span)],
-        Some((Primitive::Mul, false)) if !un => eco_vec![Instr::Prim(Primitive::Div, *span)],
-        Some((Primitive::Sub, false)) if un => eco_vec![Instr::Prim(Primitive::Add, *span)],
-        Some((Primitive::Div, false)) if un => eco_vec![Instr::Prim(Primitive::Mul, *span)],
-        Some((Primitive::Eq, false)) => eco_vec![Instr::Prim(Primitive::Eq, *span)],
-        Some((Primitive::Ne, false)) => eco_vec![Instr::Prim(Primitive::Ne, *span)],
+        Some((Primitive::Add, false)) if !un => {
+            eco_vec![Node::Instr(Instr::Prim(Primitive::Sub, *span))]
+        }
+        Some((Primitive::Mul, false)) if !un => {
+            eco_vec![Node::Instr(Instr::Prim(Primitive::Div, *span))]
+        }
+        Some((Primitive::Sub, false)) if un => {
+            eco_vec![Node::Instr(Instr::Prim(Primitive::Add, *span))]
+        }
+        Some((Primitive::Div, false)) if un => {
+            eco_vec![Node::Instr(Instr::Prim(Primitive::Mul, *span))]
+        }
+        Some((Primitive::Eq, false)) => eco_vec![Node::Instr(Instr::Prim(Primitive::Eq, *span))],
+        Some((Primitive::Ne, false)) => eco_vec![Node::Instr(Instr::Prim(Primitive::Ne, *span))],
         _ => {
-            let instrs = f.instrs(&comp.asm).to_vec();
-            invert_instrs(&instrs, comp).map_err(|e| e.func(f))?
+            let nodes = f.nodes();
+            invert_nodes(nodes, comp).map_err(|e| e.func(f))?
         }
     };
-    let inverse = make_fn(inverse, f.flags, *span, comp)?;
+    let args = eco_vec![make_fn(inverse, f.flags, *span, comp)?];
     Ok((
         input,
-        eco_vec![
-            Instr::PushFunc(inverse),
-            if un {
-                Instr::Prim(Primitive::Scan, *span)
-            } else {
-                Instr::ImplPrim(ImplPrimitive::UnScan, *span)
-            }
-        ],
+        eco_vec![if un {
+            Node::Mod(Primitive::Scan, args, *span)
+        } else {
+            Node::ImplMod(ImplPrimitive::UnScan, args, *span)
+        }],
     ))
 }
 
 fn anti_repeat_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
     match input {
-        [Instr::PushFunc(f), repeat @ Instr::Prim(Primitive::Repeat, span), input @ ..] => {
-            let f_instrs = f.instrs(&comp.asm).to_vec();
-            let instrs = invert_instrs(&f_instrs, comp).map_err(|e| e.func(f))?;
-            let inverse = make_fn(instrs, f.flags, *span, comp)?;
-            Ok((input, eco_vec![Instr::PushFunc(inverse), repeat.clone()]))
-        }
-        [Instr::PushFunc(inv), Instr::PushFunc(f), Instr::ImplPrim(ImplPrimitive::RepeatWithInverse, span), input @ ..] =>
-        {
-            let instrs = eco_vec![
-                Instr::PushFunc(f.clone()),
-                Instr::PushFunc(inv.clone()),
-                Instr::ImplPrim(ImplPrimitive::RepeatWithInverse, *span),
-            ];
-            Ok((input, instrs))
+        [Node::Mod(Primitive::Repeat, args, span), input @ ..] => {
+            let [f] = args.as_slice() else {
+                return generic();
+            };
+            let f_nodes = f.nodes();
+            let nodes = invert_nodes(f_nodes, comp).map_err(|e| e.func(f))?;
+            let inverse = make_fn(nodes, f.flags, *span, comp)?;
+            let args = eco_vec![inverse];
+            Ok((input, eco_vec![Node::Mod(Primitive::Repeat, args, *span)]))
+        }
+        [Node::ImplMod(ImplPrimitive::RepeatWithInverse, args, span), input @ ..] => {
+            let [f, inv] = args.as_slice() else {
+                return generic();
+            };
+            let args = eco_vec![inv.clone(), f.clone()];
+            let node = Node::ImplMod(ImplPrimitive::RepeatWithInverse, args, *span);
+            Ok((input, eco_vec![node]))
         }
         _ => generic(),
     }
 }
 
 fn under_repeat_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
+) -> InversionResult<(&'a [Node], Under)> {
     use ImplPrimitive::*;
     use Instr::*;
     use Primitive::*;
-    Ok(match input {
-        [PushFunc(f), Prim(Repeat, span), input @ ..]
-        | [PushFunc(_), PushFunc(f), ImplPrim(RepeatWithInverse, span), input @ ..] => {
-            let instrs = f.instrs(&comp.asm).to_vec();
-            let (befores, afters) = under_instrs(&instrs, g_sig, comp).map_err(|e| e.func(f))?;
-            let befores = eco_vec![
-                CopyToTemp {
-                    stack: TempStack::Under,
-                    count: 1,
-                    span: *span
-                },
-                PushFunc(make_fn(befores, f.flags, *span, comp)?),
-                Prim(Repeat, *span)
-            ];
-            let afters = eco_vec![
-                PopTemp {
-                    stack: TempStack::Under,
-                    count: 1,
-                    span: *span,
-                },
-                PushFunc(make_fn(afters, f.flags, *span, comp)?),
-                Prim(Repeat, *span)
-            ];
-            (input, (befores, afters))
+    let (push, args, span, input) = match input {
+        [Node::Mod(Repeat, args, span) | Node::ImplMod(RepeatWithInverse, args, span), input @ ..] => {
+            (None, args, *span, input)
         }
-        [push @ Push(_), PushFunc(f), Prim(Repeat, span), input @ ..]
-        | [push @ Push(_), PushFunc(_), PushFunc(f), ImplPrim(RepeatWithInverse, span), input @ ..] =>
-        {
-            let instrs = f.instrs(&comp.asm).to_vec();
-            let (befores, afters) = under_instrs(&instrs, g_sig, comp).map_err(|e| e.func(f))?;
-            let befores = eco_vec![
-                push.clone(),
-                PushFunc(make_fn(befores, f.flags, *span, comp)?),
-                Prim(Repeat, *span)
-            ];
-            let afters = eco_vec![
-                push.clone(),
-                PushFunc(make_fn(afters, f.flags, *span, comp)?),
-                Prim(Repeat, *span)
-            ];
-            (input, (befores, afters))
+        [push @ Node::Instr(Push(_)), Node::Mod(Repeat, args, span) | Node::ImplMod(RepeatWithInverse, args, span), input @ ..] => {
+            (Some(push), args, *span, input)
         }
         _ => return generic(),
+    };
+    let Some(f) = args.first() else {
+        return generic();
+    };
+    let nodes = f.nodes();
+    let (befores, afters) = under_nodes(nodes, g_sig, comp).map_err(|e| e.func(f))?;
+    let before_fn = make_fn(befores, f.flags, span, comp)?;
+    let after_fn = make_fn(afters, f.flags, span, comp)?;
+    Ok(if let Some(push) = push {
+        let befores = eco_vec![push.clone(), Node::Mod(Repeat, eco_vec![before_fn], span)];
+        let afters = eco_vec![push.clone(), Node::Mod(Repeat, eco_vec![after_fn], span)];
+        (input, (befores, afters))
+    } else {
+        let befores = eco_vec![
+            Node::Instr(CopyToTemp {
+                stack: TempStack::Under,
+                count: 1,
+                span
+            }),
+            Node::Mod(Repeat, eco_vec![before_fn], span)
+        ];
+        let afters = eco_vec![
+            Node::Instr(PopTemp {
+                stack: TempStack::Under,
+                count: 1,
+                span,
+            }),
+            Node::Mod(Repeat, eco_vec![after_fn], span)
+        ];
+        (input, (befores, afters))
     })
 }
 
 fn under_fold_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     g_sig: Signature,
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], Under)> {
-    let [Instr::PushFunc(f), fold @ Instr::Prim(Primitive::Fold, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], Under)> {
+    let [Node::Mod(Primitive::Fold, args, span), input @ ..] = input else {
         return generic();
     };
-    let span = *span;
-    let inner = f.instrs(&comp.asm).to_vec();
-    let (inner_befores, inner_afters) = under_instrs(&inner, g_sig, comp).map_err(|e| e.func(f))?;
-    let inner_befores_sig = instrs_signature(&inner_befores)?;
-    let inner_afters_sig = instrs_signature(&inner_afters)?;
+    let ([f], span) = (args.as_slice(), *span) else {
+        return generic();
+    };
+    let inner = f.nodes();
+    let (inner_befores, inner_afters) = under_nodes(inner, g_sig, comp).map_err(|e| e.func(f))?;
+    let inner_befores_sig = nodes_signature(&inner_befores)?;
+    let inner_afters_sig = nodes_signature(&inner_afters)?;
     if inner_befores_sig.outputs > inner_befores_sig.args
         || inner_afters_sig.outputs > inner_afters_sig.args
     {
@@ -3002,63 +3097,62 @@ fn under_fold_pattern<'a>(
     let befores_func = make_fn(inner_befores, f.flags, span, comp)?;
     let afters_func = make_fn(inner_afters, f.flags, span, comp)?;
     let befores = eco_vec![
-        Instr::Prim(Primitive::Dup, span),
-        Instr::Prim(Primitive::Len, span),
-        Instr::PushTemp {
+        Node::Instr(Instr::Prim(Primitive::Dup, span)),
+        Node::Instr(Instr::Prim(Primitive::Len, span)),
+        Node::Instr(Instr::PushTemp {
             stack: TempStack::Inline,
             span,
             count: 1,
-        },
-        Instr::PushFunc(befores_func),
-        fold.clone()
+        }),
+        Node::Mod(Primitive::Fold, eco_vec![befores_func], span)
     ];
     let afters = eco_vec![
-        Instr::pop_inline(1, span),
-        Instr::PushFunc(afters_func),
-        Instr::Prim(Primitive::Repeat, span)
+        Node::Instr(Instr::pop_inline(1, span)),
+        Node::Mod(Primitive::Repeat, eco_vec![afters_func], span)
     ];
     Ok((input, (befores, afters)))
 }
 
 fn invert_reduce_mul_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::PushFunc(f), Instr::Prim(Primitive::Reduce, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Mod(Primitive::Reduce, args, span), input @ ..] = input else {
         return generic();
     };
-    let Some((Primitive::Mul, _)) = f.as_flipped_primitive(&comp.asm) else {
+    let Some((Primitive::Mul, _)) = (args.first()).and_then(|f| f.as_flipped_primitive(&comp.asm))
+    else {
         return generic();
     };
-    let instrs = eco_vec![Instr::ImplPrim(ImplPrimitive::Primes, *span)];
-    Ok((input, instrs))
+    let nodes = eco_vec![Node::Instr(Instr::ImplPrim(ImplPrimitive::Primes, *span))];
+    Ok((input, nodes))
 }
 
 fn invert_primes_pattern<'a>(
-    input: &'a [Instr],
+    input: &'a [Node],
     comp: &mut Compiler,
-) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
-    let [Instr::ImplPrim(ImplPrimitive::Primes, span), input @ ..] = input else {
+) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
+    let [Node::Instr(Instr::ImplPrim(ImplPrimitive::Primes, span)), input @ ..] = input else {
         return generic();
     };
     let f = make_fn(
-        eco_vec![Instr::Prim(Primitive::Mul, *span)],
+        eco_vec![Node::Instr(Instr::Prim(Primitive::Mul, *span))],
         FunctionFlags::default(),
         *span,
         comp,
     )?;
     Ok((
         input,
-        eco_vec![Instr::PushFunc(f), Instr::Prim(Primitive::Reduce, *span)],
+        eco_vec![Node::Mod(Primitive::Reduce, eco_vec![f], *span)],
     ))
 }
 
 impl<A: InvertPattern, B: InvertPattern> InvertPattern for (A, B) {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let (a, b) = self;
         let (input, mut a) = a.invert_extract(input, comp)?;
         let (input, b) = b.invert_extract(input, comp)?;
@@ -3072,10 +3166,10 @@ struct Either<A, B>(A, B);
 impl<A: UnderPattern, B: UnderPattern> UnderPattern for Either<A, B> {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         g_sig: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let Either(a, b) = self;
         a.under_extract(input, g_sig, comp)
             .or_else(|a| b.under_extract(input, g_sig, comp).map_err(|b| a.max(b)))
@@ -3085,10 +3179,10 @@ impl<A: UnderPattern, B: UnderPattern> UnderPattern for Either<A, B> {
 impl<A: UnderPattern, B: UnderPattern> UnderPattern for (A, B) {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         g_sig: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let (a, b) = self;
         let (input, (mut a_before, a_after)) = a.under_extract(input, g_sig, comp)?;
         let (input, (b_before, mut b_after)) = b.under_extract(input, g_sig, comp)?;
@@ -3101,9 +3195,9 @@ impl<A: UnderPattern, B: UnderPattern> UnderPattern for (A, B) {
 impl<A: InvertPattern, B: InvertPattern, C: InvertPattern> InvertPattern for (A, B, C) {
     fn invert_extract<'a>(
         &self,
-        mut input: &'a [Instr],
+        mut input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let (a, b, c) = self;
         let (inp, mut a) = a.invert_extract(input, comp)?;
         input = inp;
@@ -3121,9 +3215,9 @@ struct IgnoreMany<T>(T);
 impl<T: InvertPattern> InvertPattern for IgnoreMany<T> {
     fn invert_extract<'a>(
         &self,
-        mut input: &'a [Instr],
+        mut input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         while let Ok((inp, _)) = self.0.invert_extract(input, comp) {
             input = inp;
         }
@@ -3134,14 +3228,15 @@ impl<T: InvertPattern> InvertPattern for IgnoreMany<T> {
 impl InvertPattern for Primitive {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         _: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let next = input.first().ok_or(Generic)?;
         match next {
-            Instr::Prim(prim, span) if prim == self => {
-                Ok((&input[1..], eco_vec![Instr::Prim(*prim, *span)]))
-            }
+            Node::Instr(Instr::Prim(prim, span)) if prim == self => Ok((
+                &input[1..],
+                eco_vec![Node::Instr(Instr::Prim(*prim, *span))],
+            )),
             _ => generic(),
         }
     }
@@ -3153,9 +3248,9 @@ where
 {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         _: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let (a, b) = *self;
         if a.len() > input.len() {
             return generic();
@@ -3163,7 +3258,9 @@ where
         let mut spans = Vec::new();
         for (instr, prim) in input.iter().zip(a.iter()) {
             match instr {
-                Instr::Prim(instr_prim, span) if instr_prim == prim => spans.push(*span),
+                Node::Instr(Instr::Prim(instr_prim, span)) if instr_prim == prim => {
+                    spans.push(*span)
+                }
                 _ => return generic(),
             }
         }
@@ -3171,7 +3268,7 @@ where
             &input[a.len()..],
             b.iter()
                 .zip(spans.iter().cycle())
-                .map(|(p, s)| p.as_instr(*s))
+                .map(|(p, s)| Node::Instr(p.as_instr(*s)))
                 .collect(),
         ))
     }
@@ -3183,9 +3280,9 @@ where
 {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         _: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let (a, b) = *self;
         if a.len() > input.len() {
             return generic();
@@ -3193,7 +3290,9 @@ where
         let mut spans = Vec::new();
         for (instr, prim) in input.iter().zip(a.iter()) {
             match instr {
-                Instr::ImplPrim(instr_prim, span) if instr_prim == prim => spans.push(*span),
+                Node::Instr(Instr::ImplPrim(instr_prim, span)) if instr_prim == prim => {
+                    spans.push(*span)
+                }
                 _ => return generic(),
             }
         }
@@ -3201,7 +3300,7 @@ where
             &input[a.len()..],
             b.iter()
                 .zip(spans.iter().cycle())
-                .map(|(p, s)| p.as_instr(*s))
+                .map(|(p, s)| Node::Instr(p.as_instr(*s)))
                 .collect(),
         ))
     }
@@ -3214,10 +3313,10 @@ where
 {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         _: Signature,
         _: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let (a, b, c) = *self;
         if a.len() > input.len() {
             return generic();
@@ -3225,7 +3324,9 @@ where
         let mut spans = Vec::new();
         for (instr, prim) in input.iter().zip(a.iter()) {
             match instr {
-                Instr::Prim(instr_prim, span) if instr_prim == prim => spans.push(*span),
+                Node::Instr(Instr::Prim(instr_prim, span)) if instr_prim == prim => {
+                    spans.push(*span)
+                }
                 _ => return generic(),
             }
         }
@@ -3234,11 +3335,11 @@ where
             (
                 b.iter()
                     .zip(spans.iter().cycle())
-                    .map(|(p, s)| p.as_instr(*s))
+                    .map(|(p, s)| Node::Instr(p.as_instr(*s)))
                     .collect(),
                 c.iter()
                     .zip(spans.iter().cycle())
-                    .map(|(p, s)| p.as_instr(*s))
+                    .map(|(p, s)| Node::Instr(p.as_instr(*s)))
                     .collect(),
             ),
         ))
@@ -3252,10 +3353,10 @@ where
 {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         _: Signature,
         _: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let (a, b, c) = *self;
         if a.len() > input.len() {
             return generic();
@@ -3263,7 +3364,9 @@ where
         let mut spans = Vec::new();
         for (instr, prim) in input.iter().zip(a.iter()) {
             match instr {
-                Instr::ImplPrim(instr_prim, span) if instr_prim == prim => spans.push(*span),
+                Node::Instr(Instr::ImplPrim(instr_prim, span)) if instr_prim == prim => {
+                    spans.push(*span)
+                }
                 _ => return generic(),
             }
         }
@@ -3272,11 +3375,11 @@ where
             (
                 b.iter()
                     .zip(spans.iter().cycle())
-                    .map(|(p, s)| p.as_instr(*s))
+                    .map(|(p, s)| Node::Instr(p.as_instr(*s)))
                     .collect(),
                 c.iter()
                     .zip(spans.iter().cycle())
-                    .map(|(p, s)| p.as_instr(*s))
+                    .map(|(p, s)| Node::Instr(p.as_instr(*s)))
                     .collect(),
             ),
         ))
@@ -3289,9 +3392,9 @@ where
 {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let (a, b) = self;
         (a.as_ref(), b.as_ref()).invert_extract(input, comp)
     }
@@ -3303,9 +3406,9 @@ where
 {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         let (a, b) = self;
         (a.as_ref(), b.as_ref()).invert_extract(input, comp)
     }
@@ -3319,10 +3422,10 @@ where
 {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         g_sig: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let (a, b, c) = self;
         (a.as_ref(), b.as_ref(), c.as_ref()).under_extract(input, g_sig, comp)
     }
@@ -3336,10 +3439,10 @@ where
 {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         g_sig: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let (a, b, c) = self;
         (a.as_ref(), b.as_ref(), c.as_ref()).under_extract(input, g_sig, comp)
     }
@@ -3353,14 +3456,13 @@ impl<F> fmt::Debug for InvertPatternFn<F> {
 }
 impl<F> InvertPattern for InvertPatternFn<F>
 where
-    F: for<'a> Fn(&'a [Instr], &mut Compiler) -> InversionResult<(&'a [Instr], EcoVec<Instr>)>
-        + Sync,
+    F: for<'a> Fn(&'a [Node], &mut Compiler) -> InversionResult<(&'a [Node], EcoVec<Node>)> + Sync,
 {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         (self.0)(input, comp)
     }
 }
@@ -3373,14 +3475,14 @@ impl<F> fmt::Debug for UnderPatternFn<F> {
 }
 impl<F> UnderPattern for UnderPatternFn<F>
 where
-    F: for<'a> Fn(&'a [Instr], Signature, &mut Compiler) -> InversionResult<(&'a [Instr], Under)>,
+    F: for<'a> Fn(&'a [Node], Signature, &mut Compiler) -> InversionResult<(&'a [Node], Under)>,
 {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         g_sig: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         (self.0)(input, g_sig, comp)
     }
 }
@@ -3390,42 +3492,25 @@ struct Val;
 impl InvertPattern for Val {
     fn invert_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], EcoVec<Instr>)> {
+    ) -> InversionResult<(&'a [Node], EcoVec<Node>)> {
         if input.is_empty() {
             return generic();
         }
         for len in (1..input.len()).rev() {
             let chunk = &input[..len];
-            if let Some(sig) = instrs_clean_signature(chunk) {
-                if sig == (0, 1) && instrs_are_pure(chunk, &comp.asm, Purity::Pure) {
+            if let Some(sig) = nodes_clean_signature(chunk) {
+                if sig == (0, 1) && nodes_are_pure(chunk, &comp.asm, Purity::Pure) {
                     return Ok((&input[len..], chunk.into()));
                 }
             }
         }
         match input.first() {
-            Some(instr @ Instr::Push(_)) => Ok((&input[1..], eco_vec![instr.clone()])),
-            Some(Instr::BeginArray) => {
-                let mut depth = 1;
-                let mut i = 1;
-                loop {
-                    let instr = input.get(i).ok_or(Generic)?;
-                    match instr {
-                        Instr::EndArray { .. } => {
-                            depth -= 1;
-                            if depth == 0 {
-                                break;
-                            }
-                        }
-                        Instr::BeginArray => depth += 1,
-                        _ => (),
-                    }
-                    i += 1;
-                }
-                let inner = &input[1..i];
-                if instrs_signature(inner)? == (0, 1) {
-                    Ok((&input[i + 1..], input[..=i].into()))
+            Some(instr @ Node::Instr(Instr::Push(_))) => Ok((&input[1..], eco_vec![instr.clone()])),
+            Some(node @ Node::Array { inner, .. }) => {
+                if nodes_signature(inner.as_slice())? == (0, 1) {
+                    Ok((&input[1..], eco_vec![node.clone()]))
                 } else {
                     generic()
                 }
@@ -3437,10 +3522,10 @@ impl InvertPattern for Val {
 impl UnderPattern for Val {
     fn under_extract<'a>(
         &self,
-        input: &'a [Instr],
+        input: &'a [Node],
         _: Signature,
         comp: &mut Compiler,
-    ) -> InversionResult<(&'a [Instr], Under)> {
+    ) -> InversionResult<(&'a [Node], Under)> {
         let (input, inverted) = self.invert_extract(input, comp)?;
         Ok((input, (inverted, EcoVec::new())))
     }
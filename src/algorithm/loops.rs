@@ -25,6 +25,99 @@ pub fn flip<A, B, C>(f: impl Fn(A, B) -> C + Copy) -> impl Fn(B, A) -> C + Copy
 }
 
 pub fn repeat(with_inverse: bool, env: &mut Uiua) -> UiuaResult {
+    match start_repeat(with_inverse, env)? {
+        Some(repeat) => repeat.run(env),
+        None => Ok(()),
+    }
+}
+
+/// A loop that calls its functions one at a time
+///
+/// This lets the interpreter pause a loop between calls.
+#[derive(Debug, Clone)]
+pub(crate) enum Loop {
+    Repeat {
+        f: Function,
+        remaining: usize,
+    },
+    Converge {
+        f: Function,
+        prev: Value,
+        called: bool,
+    },
+    Do {
+        cond: Function,
+        body: Function,
+        copy_count: usize,
+        called_cond: bool,
+    },
+}
+
+impl Loop {
+    /// Get the next function to call, or `None` if the loop is done
+    pub(crate) fn next(&mut self, env: &mut Uiua) -> UiuaResult<Option<Function>> {
+        Ok(match self {
+            Loop::Repeat { f, remaining } => {
+                if *remaining == 0 {
+                    return Ok(None);
+                }
+                *remaining -= 1;
+                Some(f.clone())
+            }
+            Loop::Converge { f, prev, called } => {
+                if *called {
+                    let next = env.pop("converging function result")?;
+                    if next == *prev {
+                        env.push(next);
+                        return Ok(None);
+                    }
+                    env.push(next.clone());
+                    *prev = next;
+                }
+                *called = true;
+                Some(f.clone())
+            }
+            Loop::Do {
+                cond,
+                body,
+                copy_count,
+                called_cond,
+            } => {
+                if *called_cond {
+                    *called_cond = false;
+                    // Break if condition is false
+                    let cond = (env.pop("do condition")?)
+                        .as_bool(env, "Do condition must be a boolean")?;
+                    return Ok(cond.then(|| body.clone()));
+                }
+                // Make sure there are enough values
+                if env.stack().len() < *copy_count {
+                    // Pop until it fails
+                    for i in 0..*copy_count {
+                        env.pop(i + 1)?;
+                    }
+                }
+                // Copy necessary condition args
+                env.dup_n(*copy_count)?;
+                *called_cond = true;
+                Some(cond.clone())
+            }
+        })
+    }
+    /// Run the loop to completion
+    pub(crate) fn run(mut self, env: &mut Uiua) -> UiuaResult {
+        while let Some(f) = self.next(env)? {
+            env.call(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Start a repeat
+///
+/// Returns the loop to run if the repetition count is a scalar.
+/// Otherwise, the repeat is run to completion.
+pub(crate) fn start_repeat(with_inverse: bool, env: &mut Uiua) -> UiuaResult<Option<Loop>> {
     crate::profile_function!();
     let f = env.pop_function()?;
     let inv = with_inverse.then(|| env.pop_function()).transpose()?;
@@ -46,7 +139,7 @@ pub fn repeat(with_inverse: bool, env: &mut Uiua) -> UiuaResult {
     if n.rank() == 0 {
         // Scalar repeat
         let n = rep_count(n, env)?;
-        repeat_impl(f, inv, n.data[0], env)
+        repeat_loop(f, inv, n.data[0], env).map(Some)
     } else {
         // Array
         let sig = f.signature();
@@ -136,11 +229,15 @@ pub fn repeat(with_inverse: bool, env: &mut Uiua) -> UiuaResult {
             }
             env.push(new_value);
         }
-        Ok(())
+        Ok(None)
     }
 }
 
 fn repeat_impl(f: Function, inv: Option<Function>, n: f64, env: &mut Uiua) -> UiuaResult {
+    repeat_loop(f, inv, n, env)?.run(env)
+}
+
+fn repeat_loop(f: Function, inv: Option<Function>, n: f64, env: &mut Uiua) -> UiuaResult<Loop> {
     let sig = f.signature();
     let (f, n) = if n >= 0.0 {
         (f, n)
@@ -170,20 +267,13 @@ fn repeat_impl(f: Function, inv: Option<Function>, n: f64, env: &mut Uiua) -> Ui
                 Primitive::Repeat.format()
             )));
         }
-        let mut prev = env.pop(1)?;
+        let prev = env.pop(1)?;
         env.push(prev.clone());
-        loop {
-            env.call(f.clone())?;
-            let next = env.pop("converging function result")?;
-            let converged = next == prev;
-            if converged {
-                env.push(next);
-                break;
-            } else {
-                env.push(next.clone());
-                prev = next;
-            }
-        }
+        Ok(Loop::Converge {
+            f,
+            prev,
+            called: false,
+        })
     } else {
         // Normal repeat
         if n.fract() != 0.0 {
@@ -199,14 +289,16 @@ fn repeat_impl(f: Function, inv: Option<Function>, n: f64, env: &mut Uiua) -> Ui
                 )));
             }
         }
-        for _ in 0..n {
-            env.call(f.clone())?;
-        }
+        Ok(Loop::Repeat { f, remaining: n })
     }
-    Ok(())
 }
 
 pub fn do_(env: &mut Uiua) -> UiuaResult {
+    start_do(env)?.run(env)
+}
+
+/// Start a do loop
+pub(crate) fn start_do(env: &mut Uiua) -> UiuaResult<Loop> {
     crate::profile_function!();
     let body = env.pop_function()?;
     let cond = env.pop_function()?;
@@ -238,27 +330,12 @@ pub fn do_(env: &mut Uiua) -> UiuaResult {
         }
         _ => {}
     }
-    loop {
-        // Make sure there are enough values
-        if env.stack().len() < copy_count {
-            // Pop until it fails
-            for i in 0..copy_count {
-                env.pop(i + 1)?;
-            }
-        }
-        // Copy necessary condition args
-        env.dup_n(copy_count)?;
-        // Call condition
-        env.call(cond.clone())?;
-        // Break if condition is false
-        let cond = (env.pop("do condition")?).as_bool(env, "Do condition must be a boolean")?;
-        if !cond {
-            break;
-        }
-        // Call body
-        env.call(body.clone())?;
-    }
-    Ok(())
+    Ok(Loop::Do {
+        cond,
+        body,
+        copy_count,
+        called_cond: false,
+    })
 }
 
 pub fn partition(env: &mut Uiua) -> UiuaResult {
@@ -21,14 +21,17 @@ use crate::{
     Value,
 };
 
+mod binary;
 mod dyadic;
 pub mod encode;
 pub mod invert;
 pub mod loops;
 pub mod map;
 mod monadic;
+mod npy;
 pub mod permute;
 pub mod pervade;
+pub mod plot;
 pub mod reduce;
 pub mod table;
 pub mod zip;
@@ -101,7 +104,9 @@ impl fmt::Display for FillShapeError {
 }
 
 pub fn validate_size<T>(sizes: impl IntoIterator<Item = usize>, env: &Uiua) -> UiuaResult<usize> {
-    validate_size_of::<T>(sizes).map_err(|e| env.error(e))
+    let elements = validate_size_of::<T>(sizes).map_err(|e| env.error(e))?;
+    env.respect_memory_limit(elements * size_of::<T>())?;
+    Ok(elements)
 }
 
 pub fn validate_size_of<T>(sizes: impl IntoIterator<Item = usize>) -> Result<usize, SizeError> {
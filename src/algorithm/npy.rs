@@ -0,0 +1,376 @@
+//! En/decode Uiua arrays to/from NumPy's `.npy` and `.npz` formats
+
+use ecow::EcoVec;
+
+use crate::{Array, ArrayValue, Boxed, Complex, Shape, Uiua, UiuaResult, Value};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+#[cfg(feature = "npz")]
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+impl Value {
+    /// Encode the value as `.npy` bytes, or as `.npz` bytes if it is a map
+    pub(crate) fn to_npy(&self, env: &Uiua) -> UiuaResult<Vec<u8>> {
+        if self.is_map() {
+            self.to_npz(env)
+        } else {
+            npy_bytes(self).map_err(|e| env.error(e))
+        }
+    }
+    /// Decode `.npy` or `.npz` bytes
+    pub(crate) fn from_npy(bytes: &[u8], env: &Uiua) -> UiuaResult<Self> {
+        #[cfg(feature = "npz")]
+        if bytes.starts_with(ZIP_MAGIC) {
+            return Self::from_npz(bytes, env);
+        }
+        npy_bytes_to_value(bytes).map_err(|e| env.error(e))
+    }
+    fn to_npz(&self, env: &Uiua) -> UiuaResult<Vec<u8>> {
+        #[cfg(not(feature = "npz"))]
+        return Err(env.error("NPZ encoding is not enabled in this environment"));
+        #[cfg(feature = "npz")]
+        {
+            use std::io::{Cursor, Write};
+
+            use ::zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+            let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+            // NumPy's savez does not compress
+            let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+            for (key, val) in self.map_kv() {
+                let name = key.as_string(env, "NPZ array names must be strings")?;
+                let bytes = npy_bytes(&val.unboxed()).map_err(|e| env.error(e))?;
+                (writer.start_file(format!("{name}.npy"), options)).map_err(|e| env.error(e))?;
+                writer.write_all(&bytes).map_err(|e| env.error(e))?;
+            }
+            let bytes = writer.finish().map_err(|e| env.error(e))?;
+            Ok(bytes.into_inner())
+        }
+    }
+    #[cfg(feature = "npz")]
+    fn from_npz(bytes: &[u8], env: &Uiua) -> UiuaResult<Self> {
+        use std::io::{Cursor, Read};
+
+        use ::zip::ZipArchive;
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| env.error(e))?;
+        let mut names = Vec::with_capacity(archive.len());
+        let mut arrays = EcoVec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|e| env.error(e))?;
+            let name = file.name();
+            let name = name.strip_suffix(".npy").unwrap_or(name).to_string();
+            let mut npy = Vec::new();
+            file.read_to_end(&mut npy).map_err(|e| env.error(e))?;
+            let val = npy_bytes_to_value(&npy)
+                .map_err(|e| env.error(format!("Cannot decode {name} in NPZ archive: {e}")))?;
+            names.push(name);
+            arrays.push(Boxed(val));
+        }
+        let mut map = Value::from(Array::new(arrays.len(), arrays));
+        map.map(names.into_iter().collect(), env)?;
+        Ok(map)
+    }
+}
+
+/// Encode a value as `.npy` bytes
+///
+/// Arrays are always written in C order.
+fn npy_bytes(val: &Value) -> Result<Vec<u8>, String> {
+    let (descr, data): (String, Vec<u8>) = match val {
+        Value::Byte(arr) => ("|u1".into(), arr.data.to_vec()),
+        Value::Num(arr) => {
+            let data = arr.data.iter().flat_map(|n| n.to_le_bytes()).collect();
+            ("<f8".into(), data)
+        }
+        Value::Complex(arr) => {
+            let data = (arr.data.iter())
+                .flat_map(|c| c.re.to_le_bytes().into_iter().chain(c.im.to_le_bytes()))
+                .collect();
+            ("<c16".into(), data)
+        }
+        Value::Char(arr) => {
+            let data = (arr.data.iter())
+                .flat_map(|&c| (c as u32).to_le_bytes())
+                .collect();
+            ("<U1".into(), data)
+        }
+        Value::Box(arr) => {
+            // Boxed strings become a fixed-width string array
+            let mut strings = Vec::with_capacity(arr.data.len());
+            for Boxed(val) in &arr.data {
+                match val {
+                    Value::Char(s) if s.rank() <= 1 => strings.push(s.data.as_slice()),
+                    val => {
+                        return Err(format!(
+                            "Only boxed strings can be encoded as NPY, \
+                            but the array contains a {} {}",
+                            val.shape(),
+                            val.type_name()
+                        ))
+                    }
+                }
+            }
+            let width = strings.iter().map(|s| s.len()).max().unwrap_or(0).max(1);
+            let mut data = Vec::with_capacity(strings.len() * width * 4);
+            for s in strings {
+                let padding = width - s.len();
+                for c in s.iter().map(|&c| c as u32).chain((0..padding).map(|_| 0)) {
+                    data.extend(c.to_le_bytes());
+                }
+            }
+            (format!("<U{width}"), data)
+        }
+    };
+    let shape = match val.shape().dims() {
+        [n] => format!("({n},)"),
+        dims => {
+            let dims: Vec<String> = dims.iter().map(ToString::to_string).collect();
+            format!("({})", dims.join(", "))
+        }
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+    // The header is padded with spaces and a newline so the data is 64-byte aligned
+    let version: u8 = if header.len() + 64 <= u16::MAX as usize {
+        1
+    } else {
+        2
+    };
+    let prefix_len = NPY_MAGIC.len() + 2 + if version == 1 { 2 } else { 4 };
+    let padding = (64 - (prefix_len + header.len() + 1) % 64) % 64;
+    header.extend(std::iter::repeat(' ').take(padding));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(prefix_len + header.len() + data.len());
+    bytes.extend(NPY_MAGIC);
+    bytes.extend([version, 0]);
+    if version == 1 {
+        bytes.extend((header.len() as u16).to_le_bytes());
+    } else {
+        bytes.extend((header.len() as u32).to_le_bytes());
+    }
+    bytes.extend(header.as_bytes());
+    bytes.extend(data);
+    Ok(bytes)
+}
+
+/// Decode `.npy` bytes into a value
+fn npy_bytes_to_value(bytes: &[u8]) -> Result<Value, String> {
+    let truncated = || "NPY data is truncated".to_string();
+    let bytes = (bytes.strip_prefix(NPY_MAGIC)).ok_or("Bytes are not valid NPY data")?;
+    let (header_len, bytes) = match bytes {
+        [1, _, a, b, rest @ ..] => (u16::from_le_bytes([*a, *b]) as usize, rest),
+        [2 | 3, _, a, b, c, d, rest @ ..] => (u32::from_le_bytes([*a, *b, *c, *d]) as usize, rest),
+        [1..=3, ..] => return Err(truncated()),
+        [major, minor, ..] => return Err(format!("NPY version {major}.{minor} is not supported")),
+        _ => return Err(truncated()),
+    };
+    if bytes.len() < header_len {
+        return Err(truncated());
+    }
+    let (header, data) = bytes.split_at(header_len);
+    let header = String::from_utf8_lossy(header);
+
+    // Parse the header dictionary
+    let descr = header_value(&header, "descr")?;
+    let descr = (descr.strip_prefix(['\'', '"']))
+        .and_then(|s| s.split(['\'', '"']).next())
+        .ok_or("Structured NPY dtypes are not supported")?;
+    let fortran_order = header_value(&header, "fortran_order")?.starts_with("True");
+    let shape = header_value(&header, "shape")?;
+    let shape: Shape = (shape.strip_prefix('('))
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| format!("Invalid NPY shape {shape:?}"))?
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.trim_end_matches('L').parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid NPY shape: {e}"))?;
+
+    // Parse the dtype
+    let unsupported = || format!("NPY dtype {descr:?} is not supported");
+    let (big_endian, dtype) = match descr.strip_prefix('>') {
+        Some(dtype) => (true, dtype),
+        None => (false, descr.trim_start_matches(['<', '|', '='])),
+    };
+    let kind = dtype.get(..1).ok_or_else(unsupported)?;
+    let size: usize = dtype[1..].parse().map_err(|_| unsupported())?;
+    let item_size = if kind == "U" {
+        size.checked_mul(4).ok_or_else(unsupported)?
+    } else {
+        size
+    };
+    let byte_count = (shape.iter())
+        .try_fold(item_size, |acc, &dim| acc.checked_mul(dim))
+        .ok_or_else(truncated)?;
+    let data = data.get(..byte_count).ok_or_else(truncated)?;
+    let order = Order {
+        shape,
+        fortran: fortran_order,
+    };
+
+    Ok(match (kind, size) {
+        ("b" | "u", 1) => order.array(data.iter().copied().collect()).into(),
+        ("i", 1) => order.nums(data, big_endian, |b| i8::from_le_bytes(b) as f64),
+        ("u", 2) => order.nums(data, big_endian, |b| u16::from_le_bytes(b) as f64),
+        ("i", 2) => order.nums(data, big_endian, |b| i16::from_le_bytes(b) as f64),
+        ("u", 4) => order.nums(data, big_endian, |b| u32::from_le_bytes(b) as f64),
+        ("i", 4) => order.nums(data, big_endian, |b| i32::from_le_bytes(b) as f64),
+        ("u", 8) => order.nums(data, big_endian, |b| u64::from_le_bytes(b) as f64),
+        ("i", 8) => order.nums(data, big_endian, |b| i64::from_le_bytes(b) as f64),
+        ("f", 4) => order.nums(data, big_endian, |b| f32::from_le_bytes(b) as f64),
+        ("f", 8) => order.nums(data, big_endian, f64::from_le_bytes),
+        ("c", 8) => {
+            let parts: Vec<f64> = (data.chunks_exact(4))
+                .map(|b| f32::from_le_bytes(endian(b, big_endian)) as f64)
+                .collect();
+            order.complexes(&parts).into()
+        }
+        ("c", 16) => {
+            let parts: Vec<f64> = (data.chunks_exact(8))
+                .map(|b| f64::from_le_bytes(endian(b, big_endian)))
+                .collect();
+            order.complexes(&parts).into()
+        }
+        ("U", 1..) => {
+            let chars: Vec<char> = (data.chunks_exact(4))
+                .map(|b| u32::from_le_bytes(endian(b, big_endian)))
+                .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect();
+            if size == 1 {
+                order.array(chars.into_iter().collect()).into()
+            } else {
+                // Wider strings become boxed strings without their padding
+                let strings = (chars.chunks_exact(size))
+                    .map(|s| {
+                        let len = s.iter().rposition(|&c| c != '\0').map_or(0, |i| i + 1);
+                        Boxed(s[..len].iter().copied().collect())
+                    })
+                    .collect();
+                order.array(strings).into()
+            }
+        }
+        _ => return Err(unsupported()),
+    })
+}
+
+/// Find the value of a key in an NPY header
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, String> {
+    [format!("'{key}':"), format!("\"{key}\":")]
+        .iter()
+        .find_map(|pat| header.find(pat.as_str()).map(|i| &header[i + pat.len()..]))
+        .map(str::trim_start)
+        .ok_or_else(|| format!("NPY header is missing {key:?}"))
+}
+
+/// Convert bytes of an element to little-endian order
+fn endian<const N: usize>(bytes: &[u8], big_endian: bool) -> [u8; N] {
+    let mut bytes: [u8; N] = bytes.try_into().unwrap();
+    if big_endian {
+        bytes.reverse();
+    }
+    bytes
+}
+
+/// The shape and memory order of NPY data
+struct Order {
+    shape: Shape,
+    fortran: bool,
+}
+
+impl Order {
+    fn nums<const N: usize>(
+        &self,
+        data: &[u8],
+        big_endian: bool,
+        f: impl Fn([u8; N]) -> f64,
+    ) -> Value {
+        let nums = (data.chunks_exact(N)).map(|b| f(endian(b, big_endian)));
+        self.array(nums.collect()).into()
+    }
+    fn complexes(&self, parts: &[f64]) -> Array<Complex> {
+        let data = (parts.chunks_exact(2)).map(|c| Complex::new(c[0], c[1]));
+        self.array(data.collect())
+    }
+    /// Create an array, converting from Fortran order if necessary
+    fn array<T: ArrayValue>(&self, data: EcoVec<T>) -> Array<T> {
+        if !self.fortran || self.shape.len() < 2 {
+            return Array::new(self.shape.clone(), data);
+        }
+        // In Fortran order, the first axis varies fastest
+        let mut strides = vec![1; self.shape.len()];
+        for i in 1..self.shape.len() {
+            strides[i] = strides[i - 1] * self.shape[i - 1];
+        }
+        let mut index = vec![0; self.shape.len()];
+        let mut c_data = EcoVec::with_capacity(data.len());
+        for _ in 0..data.len() {
+            let offset: usize = index.iter().zip(&strides).map(|(i, s)| i * s).sum();
+            c_data.push(data[offset].clone());
+            // Increment the C-order index
+            for (i, &dim) in index.iter_mut().zip(self.shape.iter()).rev() {
+                *i += 1;
+                if *i < dim {
+                    break;
+                }
+                *i = 0;
+            }
+        }
+        Array::new(self.shape.clone(), c_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fortran_order() {
+        // np.save(f, np.asfortranarray(np.arange(6, dtype='i4').reshape(2, 3)))
+        let header = "{'descr': '<i4', 'fortran_order': True, 'shape': (2, 3), }";
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        for n in [0i32, 3, 1, 4, 2, 5] {
+            bytes.extend(n.to_le_bytes());
+        }
+        let val = npy_bytes_to_value(&bytes).unwrap();
+        let expected: Value = [[0.0, 1.0, 2.0], [3.0, 4.0, 5.0]].into();
+        assert_eq!(val, expected);
+    }
+
+    #[test]
+    fn big_endian() {
+        let header = "{'descr': '>u2', 'fortran_order': False, 'shape': (2,), }";
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        bytes.extend([1, 0, 0, 2]);
+        let val = npy_bytes_to_value(&bytes).unwrap();
+        assert_eq!(val, Value::from([256.0, 2.0]));
+    }
+
+    #[test]
+    fn huge_item_size() {
+        let header = "{'descr': '<U4611686018427387904', 'fortran_order': False, 'shape': (1,), }";
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((header.len() as u16).to_le_bytes());
+        bytes.extend(header.as_bytes());
+        let err = npy_bytes_to_value(&bytes).unwrap_err();
+        assert!(err.contains("not supported"), "{err}");
+    }
+
+    #[test]
+    fn header_alignment() {
+        for val in [Value::from(5.0), Value::from([1u8, 2, 3]), "hello".into()] {
+            let bytes = npy_bytes(&val).unwrap();
+            let header_len = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+            assert_eq!((10 + header_len) % 64, 0);
+            assert_eq!(npy_bytes_to_value(&bytes).unwrap(), val);
+        }
+    }
+}
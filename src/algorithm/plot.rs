@@ -0,0 +1,594 @@
+//! Rendering charts to image arrays
+
+use ecow::EcoVec;
+
+use crate::{
+    algorithm::{validate_size, FillContext},
+    Array, Shape, Uiua, UiuaResult, Value,
+};
+
+type Color = [f64; 4];
+
+/// The colors of series that are not given a color
+const PALETTE: [Color; 6] = [
+    [0.12, 0.47, 0.71, 1.0],
+    [1.0, 0.5, 0.05, 1.0],
+    [0.17, 0.63, 0.17, 1.0],
+    [0.84, 0.15, 0.16, 1.0],
+    [0.58, 0.4, 0.74, 1.0],
+    [0.55, 0.34, 0.29, 1.0],
+];
+
+/// The length of axis tick marks in pixels
+const TICK_LEN: f64 = 4.0;
+/// The space around the plot area in pixels
+const PADDING: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChartKind {
+    Line,
+    Scatter,
+    Bar,
+    Histogram,
+}
+
+impl ChartKind {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "line" => ChartKind::Line,
+            "scatter" => ChartKind::Scatter,
+            "bar" => ChartKind::Bar,
+            "hist" => ChartKind::Histogram,
+            _ => return None,
+        })
+    }
+}
+
+pub(crate) fn plot(options: Value, data: Value, env: &Uiua) -> UiuaResult<Value> {
+    // Default options
+    let mut kind = ChartKind::Line;
+    let mut height = 300.0;
+    let mut width = 400.0;
+    let mut label_size = 12.0;
+    let mut bins = 10;
+    let mut colors: Vec<Color> = Vec::new();
+
+    // Parse options
+    let options: Vec<Value> = match options {
+        Value::Char(arr) if arr.rank() == 1 => vec![arr.into()],
+        options => options.into_rows().map(Value::unboxed).collect(),
+    };
+    let mut scalar_index = 0;
+    let mut set_kind = false;
+    let mut set_size = false;
+    for (i, row) in options.into_iter().enumerate() {
+        if let Value::Char(_) = row {
+            if set_kind {
+                return Err(env.error("Cannot set chart kind twice"));
+            }
+            let name = row.as_string(env, "Chart kind must be a string")?;
+            kind = ChartKind::from_name(&name).ok_or_else(|| {
+                env.error(format!(
+                    "Unknown chart kind {name:?}. \
+                    Valid kinds are \"line\", \"scatter\", \"bar\", and \"hist\""
+                ))
+            })?;
+            set_kind = true;
+            continue;
+        }
+        let nums = row.as_nums(env, "Options must be numbers or strings")?;
+        match &**row.shape() {
+            [] => {
+                match scalar_index {
+                    0 => {
+                        label_size = nums[0];
+                        if label_size <= 0.0 || label_size.is_nan() {
+                            return Err(env.error("Label size must be positive"));
+                        }
+                    }
+                    1 => {
+                        let n = nums[0];
+                        if n < 1.0 || n.fract() != 0.0 {
+                            return Err(env.error("Bin count must be a positive integer"));
+                        }
+                        bins = n as usize;
+                    }
+                    n => {
+                        return Err(
+                            env.error(format!("{} is too many scalar options to plot", n + 1))
+                        )
+                    }
+                }
+                scalar_index += 1;
+            }
+            [2] => {
+                if set_size {
+                    return Err(env.error("Cannot set plot size twice"));
+                }
+                for n in &nums {
+                    if *n <= 0.0 || !n.is_finite() {
+                        return Err(env.error("Plot size must be positive and finite"));
+                    }
+                }
+                height = nums[0];
+                width = nums[1];
+                set_size = true;
+            }
+            [3] | [4] => colors.push([
+                nums[0],
+                nums[1],
+                nums[2],
+                nums.get(3).copied().unwrap_or(1.0),
+            ]),
+            _ => {
+                return Err(env.error(format!(
+                    "Plot options must be strings or have shape [], [2], [3], or [4], \
+                    but option {i} has shape {}",
+                    row.shape()
+                )))
+            }
+        }
+    }
+
+    // Get colors
+    let background = match env.array_fill::<f64>() {
+        Ok(fill) => match &*fill.shape {
+            [] | [1] => [fill.data[0], fill.data[0], fill.data[0], 1.0],
+            [3] | [4] => [
+                fill.data[0],
+                fill.data[1],
+                fill.data[2],
+                fill.data.get(3).copied().unwrap_or(1.0),
+            ],
+            _ => return Err(env.error("Fill color must be a list of 3 or 4 numbers")),
+        },
+        Err(_) => [1.0; 4],
+    };
+    let luminance = 0.2126 * background[0] + 0.7152 * background[1] + 0.0722 * background[2];
+    let foreground = if background[3] > 0.5 && luminance < 0.5 {
+        [1.0, 1.0, 1.0, 1.0]
+    } else {
+        [0.0, 0.0, 0.0, 1.0]
+    };
+    let grid_color = [foreground[0], foreground[1], foreground[2], 0.15];
+    let series_color = |i: usize| {
+        (colors.get(i).copied()).unwrap_or_else(|| PALETTE[(i - colors.len()) % PALETTE.len()])
+    };
+
+    // Get the data
+    let data = data.as_number_array(
+        env,
+        "Plot data must be a numeric array",
+        |_| true,
+        |_| true,
+        |n| n,
+    )?;
+    let indexed = |ys: &[f64]| -> Vec<[f64; 2]> {
+        (ys.iter().enumerate())
+            .map(|(i, &y)| [i as f64, y])
+            .collect()
+    };
+    let paired = |xys: &[f64]| -> Vec<[f64; 2]> {
+        let (xs, ys) = xys.split_at(xys.len() / 2);
+        xs.iter().zip(ys).map(|(&x, &y)| [x, y]).collect()
+    };
+    let series: Vec<Vec<[f64; 2]>> = match (kind, &*data.shape) {
+        (ChartKind::Histogram, [_, ..]) => vec![indexed(&data.data[..])],
+        (ChartKind::Scatter, [2, _]) => vec![paired(&data.data[..])],
+        (ChartKind::Scatter, [_, 2, _]) => data.row_slices().map(paired).collect(),
+        (_, [_]) => vec![indexed(&data.data[..])],
+        (_, [_, _]) => data.row_slices().map(indexed).collect(),
+        _ => return Err(env.error(format!("Cannot plot data with shape {}", data.shape))),
+    };
+    let finite = |f: fn(&[f64; 2]) -> f64| {
+        (series.iter().flatten())
+            .map(f)
+            .filter(|n| n.is_finite())
+            .collect::<Vec<_>>()
+    };
+    let xs = finite(|[x, _]| *x);
+    let ys = finite(|[_, y]| *y);
+
+    // Build the axes
+    let mut counts = Vec::new();
+    let (x_axis, y_axis) = match kind {
+        ChartKind::Line | ChartKind::Scatter => {
+            let (x_min, x_max) = range(&xs);
+            let (y_min, y_max) = range(&ys);
+            (Axis::nice(x_min, x_max, 0.0), Axis::nice(y_min, y_max, 0.0))
+        }
+        ChartKind::Bar => {
+            let n = series.iter().map(Vec::len).max().unwrap_or(0).max(1);
+            let (y_min, y_max) = range(&ys);
+            (
+                Axis::categories(n),
+                Axis::nice(y_min.min(0.0), y_max.max(0.0), 0.0),
+            )
+        }
+        ChartKind::Histogram => {
+            let (min, max) = range(&ys);
+            validate_size::<usize>([bins], env)?;
+            counts = vec![0usize; bins];
+            for &y in &ys {
+                let i = ((y - min) / (max - min) * bins as f64).floor() as usize;
+                counts[i.min(bins - 1)] += 1;
+            }
+            let max_count = counts.iter().max().copied().unwrap_or(0).max(1);
+            (
+                Axis::exact(min, max),
+                Axis::nice(0.0, max_count as f64, 1.0),
+            )
+        }
+    };
+
+    // Lay out the plot area
+    let render = |ticks: &[(f64, String)]| -> Vec<(f64, Option<Label>)> {
+        (ticks.iter())
+            .map(|(value, text)| (*value, render_label(text, label_size)))
+            .collect()
+    };
+    let x_labels = render(&x_axis.ticks);
+    let y_labels = render(&y_axis.ticks);
+    let label_width = |labels: &[(f64, Option<Label>)]| {
+        (labels.iter().flat_map(|(_, l)| l).map(|l| l.width))
+            .max()
+            .unwrap_or(0) as f64
+    };
+    let label_height = |labels: &[(f64, Option<Label>)]| {
+        (labels.iter().flat_map(|(_, l)| l).map(|l| l.height))
+            .max()
+            .unwrap_or(0) as f64
+    };
+    let left = PADDING + label_width(&y_labels) + TICK_LEN * 2.0;
+    let right = PADDING
+        + (x_labels.last().and_then(|(_, l)| l.as_ref())).map_or(0.0, |l| l.width as f64 / 2.0);
+    let top = PADDING + label_height(&y_labels) / 2.0;
+    let bottom = PADDING + label_height(&x_labels) + TICK_LEN * 2.0;
+    let (px0, px1) = (left.floor() + 0.5, (width - right).floor() + 0.5);
+    let (py0, py1) = (top.floor() + 0.5, (height - bottom).floor() + 0.5);
+    if px1 - px0 < 1.0 || py1 - py0 < 1.0 {
+        return Err(env.error(format!(
+            "Plot size {height}×{width} is too small to fit its labels"
+        )));
+    }
+    let to_x = |x: f64| px0 + (x - x_axis.min) / (x_axis.max - x_axis.min) * (px1 - px0);
+    let to_y = |y: f64| py1 - (y - y_axis.min) / (y_axis.max - y_axis.min) * (py1 - py0);
+
+    // Draw the grid
+    let (height, width) = (height as usize, width as usize);
+    validate_size::<f64>([height, width, 4], env)?;
+    let mut canvas = Canvas::new(width, height, background);
+    for &(y, _) in &y_labels {
+        let y = to_y(y).floor() + 0.5;
+        canvas.line([px0, y], [px1, y], 1.0, grid_color);
+    }
+    if kind != ChartKind::Bar {
+        for &(x, _) in &x_labels {
+            let x = to_x(x).floor() + 0.5;
+            canvas.line([x, py0], [x, py1], 1.0, grid_color);
+        }
+    }
+
+    // Draw the data
+    match kind {
+        ChartKind::Line => {
+            for (i, points) in series.iter().enumerate() {
+                let color = series_color(i);
+                for pair in points.windows(2) {
+                    let [[ax, ay], [bx, by]] = [pair[0], pair[1]];
+                    canvas.line([to_x(ax), to_y(ay)], [to_x(bx), to_y(by)], 2.0, color);
+                }
+                if let [[x, y]] = points.as_slice() {
+                    canvas.circle([to_x(*x), to_y(*y)], 2.0, color);
+                }
+            }
+        }
+        ChartKind::Scatter => {
+            for (i, points) in series.iter().enumerate() {
+                let color = series_color(i);
+                for &[x, y] in points {
+                    canvas.circle([to_x(x), to_y(y)], 3.0, color);
+                }
+            }
+        }
+        ChartKind::Bar => {
+            let group_width = 0.8;
+            let bar_width = group_width / series.len() as f64;
+            for (i, points) in series.iter().enumerate() {
+                let color = series_color(i);
+                for &[x, y] in points {
+                    let start = x - group_width / 2.0 + i as f64 * bar_width;
+                    let end = start + bar_width;
+                    let [x0, x1] = [to_x(start), to_x(end)];
+                    let [y0, y1] = [to_y(y.max(0.0)), to_y(y.min(0.0))];
+                    canvas.fill_rect([x0, y0], [x1, y1], color);
+                }
+            }
+        }
+        ChartKind::Histogram => {
+            let color = series_color(0);
+            let bin_width = (x_axis.max - x_axis.min) / bins as f64;
+            for (i, &count) in counts.iter().enumerate() {
+                let start = x_axis.min + i as f64 * bin_width;
+                let [x0, x1] = [to_x(start) + 0.5, to_x(start + bin_width) - 0.5];
+                canvas.fill_rect([x0, to_y(count as f64)], [x1, to_y(0.0)], color);
+            }
+        }
+    }
+
+    // Draw the axes
+    canvas.line([px0, py1], [px1, py1], 1.0, foreground);
+    canvas.line([px0, py0], [px0, py1], 1.0, foreground);
+    for (y, label) in &y_labels {
+        let y = to_y(*y).floor() + 0.5;
+        canvas.line([px0 - TICK_LEN, y], [px0, y], 1.0, foreground);
+        if let Some(label) = label {
+            let x = px0 - TICK_LEN * 2.0 - label.width as f64;
+            canvas.label(label, [x, y - label.height as f64 / 2.0], foreground);
+        }
+    }
+    let mut labels_end = f64::NEG_INFINITY;
+    for (x, label) in &x_labels {
+        let x = to_x(*x).floor() + 0.5;
+        canvas.line([x, py1], [x, py1 + TICK_LEN], 1.0, foreground);
+        if let Some(label) = label {
+            // Skip labels that would overlap the previous one
+            let start = x - label.width as f64 / 2.0;
+            if start < labels_end + TICK_LEN {
+                continue;
+            }
+            labels_end = start + label.width as f64;
+            canvas.label(label, [start, py1 + TICK_LEN * 2.0], foreground);
+        }
+    }
+
+    let shape = Shape::from_iter([height, width, 4]);
+    Ok(Array::new(shape, canvas.data.into_iter().collect::<EcoVec<_>>()).into())
+}
+
+/// Get the range of some values
+///
+/// The range is never empty.
+fn range(values: &[f64]) -> (f64, f64) {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if values.is_empty() {
+        (0.0, 1.0)
+    } else if min == max {
+        (min - 0.5, max + 0.5)
+    } else {
+        (min, max)
+    }
+}
+
+/// The range and ticks of an axis
+struct Axis {
+    min: f64,
+    max: f64,
+    ticks: Vec<(f64, String)>,
+}
+
+impl Axis {
+    /// An axis that covers a range, extended to round numbers
+    fn nice(min: f64, max: f64, min_step: f64) -> Self {
+        let step = nice_step(min, max).max(min_step);
+        let min = (min / step).floor() * step;
+        let max = (max / step).ceil() * step;
+        Self::with_ticks(min, max, step)
+    }
+    /// An axis that covers exactly a range
+    fn exact(min: f64, max: f64) -> Self {
+        Self::with_ticks(min, max, nice_step(min, max))
+    }
+    /// An axis with a tick at each of some categories
+    fn categories(n: usize) -> Self {
+        Axis {
+            min: -0.5,
+            max: n as f64 - 0.5,
+            ticks: (0..n).map(|i| (i as f64, i.to_string())).collect(),
+        }
+    }
+    fn with_ticks(min: f64, max: f64, step: f64) -> Self {
+        let first = (min / step).ceil() * step;
+        let count = ((max - first) / step + 1e-9).floor() as usize;
+        let ticks = (0..=count)
+            .map(|i| {
+                let value = first + i as f64 * step;
+                (value, tick_label(value, step))
+            })
+            .collect();
+        Axis { min, max, ticks }
+    }
+}
+
+/// Get a step between ticks that gives about 5 round-numbered ticks
+fn nice_step(min: f64, max: f64) -> f64 {
+    let raw = (max - min) / 5.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    magnitude
+        * if normalized < 1.5 {
+            1.0
+        } else if normalized < 3.0 {
+            2.0
+        } else if normalized < 7.0 {
+            5.0
+        } else {
+            10.0
+        }
+}
+
+fn tick_label(value: f64, step: f64) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let value = if value.abs() < step * 1e-6 {
+        0.0
+    } else {
+        value
+    };
+    format!("{value:.decimals$}").replace('-', "¯")
+}
+
+/// Rendered text
+struct Label {
+    width: usize,
+    height: usize,
+    /// The opacity of each pixel
+    coverage: Vec<f64>,
+}
+
+#[cfg(feature = "font_shaping")]
+fn render_label(text: &str, size: f64) -> Option<Label> {
+    use cosmic_text::*;
+
+    super::encode::with_font_system(|system, swash_cache| {
+        let size = size as f32;
+        let mut buffer = Buffer::new(system, Metrics::new(size, size));
+        let mut buffer = buffer.borrow_with(system);
+        buffer.set_size(None, None);
+        buffer.set_text(text, Attrs::new(), Shaping::Advanced);
+        buffer.shape_until_scroll(true);
+        let width = (buffer.layout_runs())
+            .map(|run| run.line_w)
+            .fold(0.0, f32::max)
+            .ceil() as usize;
+        let height = size.ceil() as usize;
+        let mut coverage = vec![0.0; width * height];
+        buffer.draw(
+            swash_cache,
+            Color::rgb(0xFF, 0xFF, 0xFF),
+            |x, y, _, _, color| {
+                if x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+                    coverage[y as usize * width + x as usize] = color.a() as f64 / 255.0;
+                }
+            },
+        );
+        Some(Label {
+            width,
+            height,
+            coverage,
+        })
+    })
+}
+
+/// Without font shaping, plots have no labels
+#[cfg(not(feature = "font_shaping"))]
+fn render_label(_text: &str, _size: f64) -> Option<Label> {
+    None
+}
+
+/// An RGBA image that shapes can be drawn on
+///
+/// Coordinates are in pixels, and the center of the top-left pixel is at `[0.5, 0.5]`.
+struct Canvas {
+    width: usize,
+    height: usize,
+    data: Vec<f64>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Color) -> Self {
+        Canvas {
+            width,
+            height,
+            data: background.repeat(width * height),
+        }
+    }
+    /// Blend a color over a pixel
+    fn blend(&mut self, x: usize, y: usize, color: Color, coverage: f64) {
+        let a = color[3] * coverage;
+        let i = (y * self.width + x) * 4;
+        let pixel = &mut self.data[i..i + 4];
+        let dst_a = pixel[3];
+        let out_a = a + dst_a * (1.0 - a);
+        if out_a > 0.0 {
+            for (p, c) in pixel.iter_mut().zip(color).take(3) {
+                *p = (c * a + *p * dst_a * (1.0 - a)) / out_a;
+            }
+        }
+        pixel[3] = out_a;
+    }
+    /// Get the pixel ranges that cover a box, clipped to the canvas
+    fn bounds(
+        &self,
+        [x0, y0]: [f64; 2],
+        [x1, y1]: [f64; 2],
+    ) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let clip = |n: f64, max: usize| (n.max(0.0) as usize).min(max);
+        (
+            clip(x0.floor(), self.width)..clip(x1.ceil(), self.width),
+            clip(y0.floor(), self.height)..clip(y1.ceil(), self.height),
+        )
+    }
+    fn line(&mut self, [ax, ay]: [f64; 2], [bx, by]: [f64; 2], thickness: f64, color: Color) {
+        if ![ax, ay, bx, by].iter().all(|n| n.is_finite()) {
+            return;
+        }
+        let r = thickness / 2.0;
+        let (xs, ys) = self.bounds(
+            [ax.min(bx) - r - 1.0, ay.min(by) - r - 1.0],
+            [ax.max(bx) + r + 1.0, ay.max(by) + r + 1.0],
+        );
+        let [dx, dy] = [bx - ax, by - ay];
+        let len_sq = dx * dx + dy * dy;
+        for y in ys {
+            for x in xs.clone() {
+                let [px, py] = [x as f64 + 0.5, y as f64 + 0.5];
+                let t = if len_sq == 0.0 {
+                    0.0
+                } else {
+                    (((px - ax) * dx + (py - ay) * dy) / len_sq).clamp(0.0, 1.0)
+                };
+                let dist = (ax + t * dx - px).hypot(ay + t * dy - py);
+                let coverage = (r + 0.5 - dist).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+    fn circle(&mut self, [cx, cy]: [f64; 2], radius: f64, color: Color) {
+        if !cx.is_finite() || !cy.is_finite() {
+            return;
+        }
+        let r = radius + 1.0;
+        let (xs, ys) = self.bounds([cx - r, cy - r], [cx + r, cy + r]);
+        for y in ys {
+            for x in xs.clone() {
+                let dist = (x as f64 + 0.5 - cx).hypot(y as f64 + 0.5 - cy);
+                let coverage = (radius + 0.5 - dist).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+    fn fill_rect(&mut self, [x0, y0]: [f64; 2], [x1, y1]: [f64; 2], color: Color) {
+        if ![x0, y0, x1, y1].iter().all(|n| n.is_finite()) {
+            return;
+        }
+        let (xs, ys) = self.bounds([x0, y0], [x1, y1]);
+        for y in ys {
+            let cover_y = (y1.min(y as f64 + 1.0) - y0.max(y as f64)).clamp(0.0, 1.0);
+            for x in xs.clone() {
+                let cover_x = (x1.min(x as f64 + 1.0) - x0.max(x as f64)).clamp(0.0, 1.0);
+                if cover_x * cover_y > 0.0 {
+                    self.blend(x, y, color, cover_x * cover_y);
+                }
+            }
+        }
+    }
+    /// Draw a label with its top-left corner at a point
+    fn label(&mut self, label: &Label, [x, y]: [f64; 2], color: Color) {
+        let [x, y] = [x.round() as i64, y.round() as i64];
+        for (i, &coverage) in label.coverage.iter().enumerate() {
+            let px = x + (i % label.width) as i64;
+            let py = y + (i / label.width) as i64;
+            if coverage > 0.0
+                && (0..self.width as i64).contains(&px)
+                && (0..self.height as i64).contains(&py)
+            {
+                self.blend(px as usize, py as usize, color, coverage);
+            }
+        }
+    }
+}
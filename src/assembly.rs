@@ -1,12 +1,12 @@
-use std::{fmt, path::PathBuf, str::FromStr, sync::Arc};
+use std::{fmt, mem::replace, path::PathBuf, str::FromStr, sync::Arc};
 
 use dashmap::DashMap;
 use ecow::{eco_vec, EcoString, EcoVec};
 use serde::*;
 
 use crate::{
-    is_ident_char, CodeSpan, FuncSlice, Function, InputSrc, Instr, IntoInputSrc, LocalName, Module,
-    Signature, Span, Uiua, UiuaResult, Value,
+    algorithm::invert::CustomInverse, is_ident_char, CodeSpan, FuncSlice, Function, InputSrc,
+    Instr, IntoInputSrc, LocalName, Module, Primitive, Signature, Span, Uiua, UiuaResult, Value,
 };
 
 /// A compiled Uiua assembly
@@ -141,6 +141,144 @@ impl Assembly {
             self.remove_slice(slice);
         }
     }
+    /// Remove bindings and functions that can never be run
+    ///
+    /// Code is reachable if it is in a top-level slice or a public binding,
+    /// or if it is referenced by other reachable code.
+    /// Code macros are only run by the compiler, so they are always removed.
+    /// Private constants that are never read are removed as well.
+    /// If their values are computed at runtime, they are still computed and then popped.
+    /// Instructions and bindings are renumbered to fill the gaps.
+    ///
+    /// The assembly should not be passed back to a [`Compiler`](crate::Compiler) afterwards,
+    /// as the compiler's names would refer to the old binding indices.
+    pub fn remove_unreachable(&mut self) {
+        // Mark reachable instructions and bindings
+        let mut live_instrs = vec![false; self.instrs.len()];
+        let mut live_bindings = vec![false; self.bindings.len()];
+        let mut slices = self.top_slices.clone();
+        let mut bindings: Vec<usize> = (self.bindings.iter().enumerate())
+            .filter(|(_, binding)| binding.public)
+            .map(|(i, _)| i)
+            .collect();
+        loop {
+            if let Some(slice) = slices.pop() {
+                // Keep the comments that delimit function bodies
+                if slice.len > 0 && slice.start > 0 {
+                    if let Some((Instr::Comment(before), Instr::Comment(after))) = self
+                        .instrs
+                        .get(slice.start - 1)
+                        .zip(self.instrs.get(slice.end()))
+                    {
+                        if before.starts_with('(') && after.ends_with(')') {
+                            live_instrs[slice.start - 1] = true;
+                            live_instrs[slice.end()] = true;
+                        }
+                    }
+                }
+                for i in slice.start..slice.end() {
+                    if replace(&mut live_instrs[i], true) {
+                        continue;
+                    }
+                    match &self.instrs[i] {
+                        Instr::PushFunc(f) => slices.push(f.slice),
+                        Instr::CustomInverse(cust, _) => {
+                            slices.extend(custom_inverse_funcs(cust).map(|f| f.slice))
+                        }
+                        Instr::CallGlobal { index, .. } => bindings.push(*index),
+                        // Writing a constant does not make it reachable
+                        Instr::BindGlobal { index, .. }
+                            if !self.bindings[*index].kind.is_constant() =>
+                        {
+                            bindings.push(*index)
+                        }
+                        _ => {}
+                    }
+                }
+            } else if let Some(index) = bindings.pop() {
+                if let BindingKind::CodeMacro(_) = self.bindings[index].kind {
+                    continue;
+                }
+                if replace(&mut live_bindings[index], true) {
+                    continue;
+                }
+                match &self.bindings[index].kind {
+                    BindingKind::Func(f) => slices.push(f.slice),
+                    BindingKind::Module(module) => bindings.extend(
+                        (module.names.values())
+                            .filter(|local| local.public)
+                            .map(|local| local.index),
+                    ),
+                    _ => {}
+                }
+            } else {
+                break;
+            }
+        }
+
+        // Map old indices to new ones
+        let mut new_instr_indices = Vec::with_capacity(self.instrs.len() + 1);
+        let mut count = 0;
+        for &live in &live_instrs {
+            new_instr_indices.push(count);
+            count += live as usize;
+        }
+        new_instr_indices.push(count);
+        let mut new_binding_indices = Vec::with_capacity(self.bindings.len());
+        let mut count = 0;
+        for &live in &live_bindings {
+            new_binding_indices.push(live.then_some(count));
+            count += live as usize;
+        }
+        let remap_slice = |slice: &mut FuncSlice| slice.start = new_instr_indices[slice.start];
+        let remap_binding = |index: &mut usize| {
+            *index = new_binding_indices[*index].expect("reachable binding was removed")
+        };
+
+        // Remove and renumber
+        let mut instrs: EcoVec<Instr> = (self.instrs.iter().zip(&live_instrs))
+            .filter(|(_, live)| **live)
+            .map(|(instr, _)| instr.clone())
+            .collect();
+        for instr in instrs.make_mut() {
+            match instr {
+                Instr::PushFunc(f) => remap_slice(&mut f.slice),
+                Instr::CustomInverse(cust, _) => {
+                    for f in custom_inverse_funcs_mut(cust) {
+                        remap_slice(&mut f.slice);
+                    }
+                }
+                Instr::CallGlobal { index, .. } => remap_binding(index),
+                // Unread constants are still computed, but their values are discarded
+                &mut Instr::BindGlobal { index, span } if new_binding_indices[index].is_none() => {
+                    *instr = Instr::Prim(Primitive::Pop, span)
+                }
+                Instr::BindGlobal { index, .. } => remap_binding(index),
+                _ => {}
+            }
+        }
+        let mut bindings: EcoVec<BindingInfo> = (self.bindings.iter().zip(&live_bindings))
+            .filter(|(_, live)| **live)
+            .map(|(binding, _)| binding.clone())
+            .collect();
+        for binding in bindings.make_mut() {
+            match &mut binding.kind {
+                BindingKind::Func(f) => remap_slice(&mut f.slice),
+                BindingKind::Module(module) => {
+                    (module.names).retain(|_, local| new_binding_indices[local.index].is_some());
+                    for local in module.names.values_mut() {
+                        remap_binding(&mut local.index);
+                    }
+                }
+                _ => {}
+            }
+        }
+        for slice in &mut self.top_slices {
+            remap_slice(slice);
+        }
+        self.instrs = instrs;
+        self.bindings = bindings;
+    }
     pub(crate) fn remove_slice(&mut self, removed: FuncSlice) {
         // Remove instrs
         let after: Vec<_> = self.instrs[removed.end()..].to_vec();
@@ -182,7 +320,10 @@ impl Assembly {
     pub fn remove_top_level(&mut self) {
         self.top_slices.clear();
     }
-    /// Parse a `.uasm` file into an assembly
+    /// Parse the text representation of an assembly
+    ///
+    /// This format is human-readable but unstable.
+    /// For a stable encoding, use [`Assembly::from_binary`].
     pub fn from_uasm(src: &str) -> Result<Self, String> {
         let rest = src;
         let (instrs_src, rest) = rest
@@ -296,7 +437,10 @@ impl Assembly {
             dynamic_functions: EcoVec::new(),
         })
     }
-    /// Serialize the assembly into a `.uasm` file
+    /// Serialize the assembly into its text representation
+    ///
+    /// This format is human-readable but unstable.
+    /// For a stable encoding, use [`Assembly::to_binary`].
     pub fn to_uasm(&self) -> String {
         let mut uasm = String::new();
 
@@ -382,6 +526,132 @@ impl Assembly {
 
         uasm
     }
+    /// Serialize the assembly into the versioned binary format used by `.uasm` files
+    pub fn to_binary(&self) -> Result<Vec<u8>, String> {
+        let body = BinaryAssembly {
+            instrs: self.instrs.clone(),
+            top_slices: self.top_slices.clone(),
+            bindings: self.bindings.clone(),
+            spans: self.spans.clone(),
+            files: (self.inputs.files.iter())
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect(),
+            strings: self.inputs.strings.clone(),
+            macros: (self.inputs.macros.iter())
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect(),
+        };
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(ASSEMBLY_MAGIC);
+        bytes.extend_from_slice(&ASSEMBLY_FORMAT_VERSION.to_le_bytes());
+        bytes.push(crate::VERSION.len() as u8);
+        bytes.extend_from_slice(crate::VERSION.as_bytes());
+        ciborium::into_writer(&body, &mut bytes)
+            .map_err(|e| format!("Failed to serialize assembly: {e}"))?;
+        Ok(bytes)
+    }
+    /// Deserialize an assembly from the versioned binary format used by `.uasm` files
+    ///
+    /// Fails if the data was written in a different format version.
+    /// Assemblies built by other versions of Uiua load as long as the format version matches.
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, String> {
+        let (header, body) = AssemblyHeader::split(bytes)?;
+        if header.format_version != ASSEMBLY_FORMAT_VERSION {
+            return Err(format!(
+                "Assembly uses format version {}, but this version of Uiua \
+                reads format version {ASSEMBLY_FORMAT_VERSION}. \
+                Rebuild it with `uiua build`.",
+                header.format_version
+            ));
+        }
+        let body: BinaryAssembly =
+            ciborium::from_reader(body).map_err(|e| format!("Assembly is corrupted: {e}"))?;
+        Ok(Self {
+            instrs: body.instrs,
+            top_slices: body.top_slices,
+            bindings: body.bindings,
+            spans: body.spans,
+            inputs: Inputs {
+                files: body.files.into_iter().collect(),
+                strings: body.strings,
+                macros: body.macros.into_iter().collect(),
+            },
+            dynamic_functions: EcoVec::new(),
+        })
+    }
+}
+
+fn custom_inverse_funcs(cust: &CustomInverse) -> impl Iterator<Item = &Function> {
+    (cust.un.iter())
+        .chain(cust.anti.iter())
+        .chain(cust.under.iter().flat_map(|(a, b)| [a, b]))
+}
+
+fn custom_inverse_funcs_mut(cust: &mut CustomInverse) -> impl Iterator<Item = &mut Function> {
+    (cust.un.iter_mut())
+        .chain(cust.anti.iter_mut())
+        .chain(cust.under.iter_mut().flat_map(|(a, b)| [a, b]))
+}
+
+/// The bytes at the start of every binary assembly
+const ASSEMBLY_MAGIC: &[u8; 4] = b"UASM";
+
+/// The version of the binary assembly format
+///
+/// This is incremented whenever the encoding of an [`Assembly`] changes.
+/// The encoding of every instruction is pinned by a golden file in the tests,
+/// which must be replaced along with this version.
+pub const ASSEMBLY_FORMAT_VERSION: u16 = 1;
+
+/// The header of a binary assembly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblyHeader {
+    /// The version of the binary format
+    pub format_version: u16,
+    /// The version of Uiua that built the assembly
+    pub uiua_version: String,
+}
+
+impl AssemblyHeader {
+    /// Read the header of a binary assembly
+    pub fn read(bytes: &[u8]) -> Result<Self, String> {
+        Self::split(bytes).map(|(header, _)| header)
+    }
+    fn split(bytes: &[u8]) -> Result<(Self, &[u8]), String> {
+        let not_binary = || {
+            "Data is not a binary Uiua assembly. \
+            If it was built by an older version of Uiua, \
+            rebuild it with `uiua build`."
+                .to_string()
+        };
+        let bytes = bytes.strip_prefix(ASSEMBLY_MAGIC).ok_or_else(not_binary)?;
+        let [v0, v1, len, bytes @ ..] = bytes else {
+            return Err(not_binary());
+        };
+        let format_version = u16::from_le_bytes([*v0, *v1]);
+        let len = *len as usize;
+        if bytes.len() < len {
+            return Err(not_binary());
+        }
+        let (uiua_version, body) = bytes.split_at(len);
+        let uiua_version = String::from_utf8(uiua_version.to_vec()).map_err(|_| not_binary())?;
+        let header = AssemblyHeader {
+            format_version,
+            uiua_version,
+        };
+        Ok((header, body))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryAssembly {
+    instrs: EcoVec<Instr>,
+    top_slices: Vec<FuncSlice>,
+    bindings: EcoVec<BindingInfo>,
+    spans: EcoVec<Span>,
+    files: Vec<(PathBuf, EcoString)>,
+    strings: EcoVec<EcoString>,
+    macros: Vec<(CodeSpan, EcoString)>,
 }
 
 impl AsRef<Assembly> for Assembly {
@@ -397,7 +667,7 @@ impl AsMut<Assembly> for Assembly {
 }
 
 /// Information about a binding
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BindingInfo {
     /// The binding kind
     pub kind: BindingKind,
@@ -713,3 +983,382 @@ impl Inputs {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::{
+        Array, Boxed, Compiler, DynamicFunction, FunctionId, ImplPrimitive, Loc, Primitive,
+        TempStack,
+    };
+
+    const INSTR_VARIANT_COUNT: usize = 27;
+
+    /// Adding an [`Instr`] variant breaks this match,
+    /// which is a reminder to add it to [`every_instr`]
+    fn variant_index(instr: &Instr) -> usize {
+        match instr {
+            Instr::Comment(_) => 0,
+            Instr::Push(_) => 1,
+            Instr::CallGlobal { .. } => 2,
+            Instr::BindGlobal { .. } => 3,
+            Instr::BeginArray => 4,
+            Instr::EndArray { .. } => 5,
+            Instr::Prim(..) => 6,
+            Instr::ImplPrim(..) => 7,
+            Instr::Call(_) => 8,
+            Instr::CallRecursive(_) => 9,
+            Instr::Recur(_) => 10,
+            Instr::PushFunc(_) => 11,
+            Instr::Switch { .. } => 12,
+            Instr::Format { .. } => 13,
+            Instr::MatchFormatPattern { .. } => 14,
+            Instr::Label { .. } => 15,
+            Instr::ValidateType { .. } => 16,
+            Instr::Dynamic(_) => 17,
+            Instr::Unpack { .. } => 18,
+            Instr::TouchStack { .. } => 19,
+            Instr::PushTemp { .. } => 20,
+            Instr::PopTemp { .. } => 21,
+            Instr::CopyToTemp { .. } => 22,
+            Instr::SetOutputComment { .. } => 23,
+            Instr::PushSig(_) => 24,
+            Instr::PopSig => 25,
+            Instr::CustomInverse(..) => 26,
+        }
+    }
+
+    fn span(start: u32, end: u32) -> CodeSpan {
+        let loc = |pos: u32| Loc {
+            line: 1,
+            col: pos as u16 + 1,
+            byte_pos: pos,
+            char_pos: pos,
+        };
+        CodeSpan {
+            src: InputSrc::Str(0),
+            start: loc(start),
+            end: loc(end),
+        }
+    }
+
+    fn func(name: &str, sig: Signature, start: usize) -> Function {
+        Function::new(
+            FunctionId::Named(name.into()),
+            sig,
+            FuncSlice { start, len: 1 },
+            start as u64,
+        )
+    }
+
+    fn values() -> Vec<Value> {
+        let mut labeled = Value::from([1.5, f64::NAN, f64::INFINITY, -f64::INFINITY]);
+        labeled.meta_mut().label = Some("nums".into());
+        let mut map = Array::<f64>::from_iter([1.0, 2.0]);
+        map.map("ab".into(), &()).unwrap();
+        vec![
+            5.0.into(),
+            Value::from([[1u8, 2, 3], [4, 5, 6]]),
+            "hello, world".into(),
+            'x'.into(),
+            Array::<Boxed>::from_iter([Boxed(1.0.into()), Boxed("two".into())]).into(),
+            labeled,
+            map.into(),
+        ]
+    }
+
+    fn every_instr() -> Vec<Instr> {
+        let sig = Signature::new(2, 1);
+        let mut instrs = vec![Instr::Comment("a comment".into())];
+        instrs.extend(values().into_iter().map(Instr::Push));
+        instrs.extend([
+            Instr::CallGlobal {
+                index: 3,
+                call: true,
+                sig,
+            },
+            Instr::BindGlobal { index: 4, span: 1 },
+            Instr::BeginArray,
+            Instr::EndArray {
+                boxed: true,
+                span: 2,
+            },
+            Instr::Prim(Primitive::Add, 1),
+            Instr::ImplPrim(ImplPrimitive::UnBits, 2),
+            Instr::Call(1),
+            Instr::CallRecursive(2),
+            Instr::Recur(1),
+            Instr::PushFunc(func("F", sig, 0)),
+            Instr::Switch {
+                count: 2,
+                sig,
+                under_cond: true,
+                span: 2,
+            },
+            Instr::Format {
+                parts: ["a", "b"].into_iter().map(Into::into).collect(),
+                span: 1,
+            },
+            Instr::MatchFormatPattern {
+                parts: ["c", "", "d"].into_iter().map(Into::into).collect(),
+                span: 2,
+            },
+            Instr::Label {
+                label: "lbl".into(),
+                remove: true,
+                span: 1,
+            },
+            Instr::ValidateType {
+                index: 1,
+                type_num: 2,
+                name: "Foo".into(),
+                span: 2,
+            },
+            Instr::Dynamic(DynamicFunction::from((0, sig))),
+            Instr::Unpack {
+                count: 3,
+                unbox: true,
+                span: 1,
+            },
+            Instr::TouchStack { count: 2, span: 2 },
+            Instr::PushTemp {
+                stack: TempStack::Under,
+                count: 1,
+                span: 1,
+            },
+            Instr::PopTemp {
+                stack: TempStack::Inline,
+                count: 2,
+                span: 2,
+            },
+            Instr::CopyToTemp {
+                stack: TempStack::Under,
+                count: 3,
+                span: 1,
+            },
+            Instr::SetOutputComment { i: 1, n: 2 },
+            Instr::PushSig(sig),
+            Instr::PopSig,
+            Instr::CustomInverse(
+                CustomInverse {
+                    un: Some(func("Un", sig, 1)),
+                    under: Some((func("Do", sig, 2), func("Undo", sig, 3))),
+                    anti: None,
+                },
+                2,
+            ),
+        ]);
+        instrs
+    }
+
+    fn test_assembly() -> Assembly {
+        let mut asm = Assembly {
+            instrs: every_instr().into_iter().collect(),
+            top_slices: vec![FuncSlice { start: 0, len: 3 }],
+            ..Assembly::default()
+        };
+        asm.spans
+            .extend([Span::Code(span(0, 3)), Span::Code(span(4, 9))]);
+        asm.inputs.strings.push("some code".into());
+        asm.inputs
+            .files
+            .insert("main.ua".into(), "more code".into());
+        asm.inputs.macros.insert(span(4, 9), "macro code".into());
+        asm.bindings.push(BindingInfo {
+            kind: BindingKind::Func(func("F", Signature::new(2, 1), 0)),
+            public: true,
+            span: span(0, 1),
+            comment: Some("Adds things".into()),
+        });
+        asm.bindings.push(BindingInfo {
+            kind: BindingKind::Const(Some("hi".into())),
+            public: false,
+            span: span(2, 3),
+            comment: None,
+        });
+        asm.bindings.push(BindingInfo {
+            kind: BindingKind::CodeMacro(FuncSlice { start: 1, len: 2 }),
+            public: true,
+            span: span(4, 5),
+            comment: None,
+        });
+        asm
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let asm = test_assembly();
+        let covered: HashSet<usize> = asm.instrs.iter().map(variant_index).collect();
+        assert_eq!(
+            covered.len(),
+            INSTR_VARIANT_COUNT,
+            "not every instr is tested"
+        );
+
+        let decoded = Assembly::from_binary(&asm.to_binary().unwrap()).unwrap();
+
+        assert_eq!(asm.instrs.len(), decoded.instrs.len());
+        for (a, b) in asm.instrs.iter().zip(&decoded.instrs) {
+            assert_eq!(a, b);
+            // Equality ignores spans, so compare the full representations
+            assert_eq!(
+                serde_json::to_string(a).unwrap(),
+                serde_json::to_string(b).unwrap(),
+                "{a:?} changed in round trip"
+            );
+        }
+        for (a, b) in values().iter().zip(&decoded.instrs[1..]) {
+            let Instr::Push(b) = b else {
+                panic!("expected push, found {b:?}");
+            };
+            assert_eq!(a, b);
+            assert_eq!(a.type_id(), b.type_id());
+            assert_eq!(a.meta().label, b.meta().label);
+        }
+        assert_eq!(asm.top_slices, decoded.top_slices);
+        assert_eq!(asm.spans, decoded.spans);
+        assert_eq!(
+            serde_json::to_string(&asm.bindings).unwrap(),
+            serde_json::to_string(&decoded.bindings).unwrap()
+        );
+        assert_eq!(asm.inputs.strings, decoded.inputs.strings);
+        let file = InputSrc::File(std::path::Path::new("main.ua").into());
+        assert_eq!(asm.inputs.get(&file), decoded.inputs.get(&file));
+        let mac = InputSrc::Macro(span(4, 9).into());
+        assert_eq!(asm.inputs.get(&mac), decoded.inputs.get(&mac));
+    }
+
+    #[test]
+    fn binary_header() {
+        let bytes = test_assembly().to_binary().unwrap();
+        let header = AssemblyHeader::read(&bytes).unwrap();
+        assert_eq!(header.format_version, ASSEMBLY_FORMAT_VERSION);
+        assert_eq!(header.uiua_version, crate::VERSION);
+    }
+
+    #[test]
+    fn binary_golden() {
+        // If the encoding changes on purpose, bump `ASSEMBLY_FORMAT_VERSION`
+        // and replace this file with the output of `test_assembly().to_binary()`
+        let golden: &[u8] = include_bytes!("../tests/assembly_v1.uasm");
+        let golden_header = AssemblyHeader::read(golden).unwrap();
+        assert_eq!(
+            golden_header.format_version, ASSEMBLY_FORMAT_VERSION,
+            "the golden assembly must be replaced when the format version changes"
+        );
+        let bytes = test_assembly().to_binary().unwrap();
+        let body = |bytes| AssemblyHeader::split(bytes).unwrap().1;
+        assert!(
+            body(golden) == body(&bytes),
+            "the encoding of assemblies changed without a new format version"
+        );
+        // Assemblies from other versions of Uiua still load
+        let decoded = Assembly::from_binary(golden).unwrap();
+        assert_eq!(decoded.instrs, test_assembly().instrs);
+    }
+
+    #[test]
+    fn binary_mismatches() {
+        fn binary_err(bytes: &[u8]) -> String {
+            let Err(err) = Assembly::from_binary(bytes) else {
+                panic!("Loading the assembly should have failed");
+            };
+            err
+        }
+        let bytes = test_assembly().to_binary().unwrap();
+
+        let err = binary_err(test_assembly().to_uasm().as_bytes());
+        assert!(err.contains("not a binary Uiua assembly"), "{err}");
+
+        let mut wrong_format = bytes.clone();
+        wrong_format[ASSEMBLY_MAGIC.len()..][..2]
+            .copy_from_slice(&(ASSEMBLY_FORMAT_VERSION + 1).to_le_bytes());
+        let err = binary_err(&wrong_format);
+        assert!(err.contains("format version"), "{err}");
+
+        // Only the format version decides whether an assembly can be loaded
+        let mut other_version = bytes.clone();
+        let version_start = ASSEMBLY_MAGIC.len() + 3;
+        other_version[version_start] = b'X';
+        let header = AssemblyHeader::read(&other_version).unwrap();
+        assert!(header.uiua_version.starts_with('X'));
+        Assembly::from_binary(&other_version).unwrap();
+
+        let err = binary_err(&bytes[..bytes.len() / 2]);
+        assert!(err.contains("corrupted"), "{err}");
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let code = "\
+F ↚ +1
+G ↚ ×2
+H ← G
+I ← ⍜(⊏0|H) [1 2 3]
+H 3 I";
+        let asm = Compiler::new().load_str(code).unwrap().finish();
+        let mut pruned = asm.clone();
+        pruned.remove_unreachable();
+        assert!(pruned.instrs.len() < asm.instrs.len());
+        assert!(pruned.bindings.len() < asm.bindings.len());
+        assert!(pruned.bindings.iter().all(|binding| binding.public));
+
+        let mut expected = Uiua::with_safe_sys();
+        expected.run_asm(&asm).unwrap();
+        let mut actual = Uiua::with_safe_sys();
+        actual.run_asm(&pruned).unwrap();
+        assert_eq!(expected.take_stack(), actual.take_stack());
+    }
+
+    #[test]
+    fn unreachable_constants() {
+        let code = "\
+A ↚ 5
+B ↚ ⚂
+C ↚ ⚂
+D ← ×2 C
+D";
+        let asm = Compiler::new().load_str(code).unwrap().finish();
+        let mut pruned = asm.clone();
+        pruned.remove_unreachable();
+        assert_eq!(pruned.bindings.len(), 2);
+        assert!(pruned.bindings[0].kind.is_constant());
+        assert!(pruned.bindings[1].public);
+        let rands = |asm: &Assembly| {
+            (asm.instrs.iter())
+                .filter(|instr| matches!(instr, Instr::Prim(Primitive::Rand, _)))
+                .count()
+        };
+        assert_eq!(rands(&asm), 2);
+        assert_eq!(rands(&pruned), 2);
+
+        let mut actual = Uiua::with_safe_sys();
+        actual.run_asm(&pruned).unwrap();
+        assert_eq!(actual.take_stack().len(), 1);
+    }
+
+    #[test]
+    fn unreachable_code_macros() {
+        let code = "\
+M! ←^ ⇌
+F ← +1
+G ← M!(×2|F)
+G 5";
+        let asm = Compiler::new().load_str(code).unwrap().finish();
+        let pruned = (Compiler::new().remove_unreachable(true))
+            .load_str(code)
+            .unwrap()
+            .finish();
+        assert!(pruned.instrs.len() < asm.instrs.len());
+        assert!((pruned.bindings.iter())
+            .all(|binding| !matches!(binding.kind, BindingKind::CodeMacro(_))));
+
+        let mut expected = Uiua::with_safe_sys();
+        expected.run_asm(&asm).unwrap();
+        let mut actual = Uiua::with_safe_sys();
+        actual.run_asm(&pruned).unwrap();
+        assert_eq!(expected.take_stack(), actual.take_stack());
+    }
+}
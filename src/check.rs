@@ -11,7 +11,7 @@ use std::{
 
 use enum_iterator::Sequence;
 
-use crate::{function::*, Array, ImplPrimitive, Instr, Primitive, TempStack, Value};
+use crate::{function::*, Array, ImplPrimitive, Instr, Node, Primitive, SysOp, TempStack, Value};
 
 /// Count the number of arguments and outputs of a function.
 pub(crate) fn instrs_signature(instrs: &[Instr]) -> Result<Signature, SigCheckError> {
@@ -27,6 +27,15 @@ pub(crate) fn instrs_signature(instrs: &[Instr]) -> Result<Signature, SigCheckEr
     Ok(env.sig())
 }
 
+/// Count the number of arguments and outputs of some nodes
+pub(crate) fn nodes_signature(nodes: &[Node]) -> Result<Signature, SigCheckError> {
+    if let [Node::Instr(instr)] = nodes {
+        return instrs_signature(std::slice::from_ref(instr));
+    }
+    let env = VirtualEnv::from_nodes(nodes)?;
+    Ok(env.sig())
+}
+
 /// The the signature of some instructions, but only
 /// if the temp stack signatures are `|0.0`
 pub(crate) fn instrs_clean_signature(instrs: &[Instr]) -> Option<Signature> {
@@ -38,6 +47,17 @@ pub(crate) fn instrs_clean_signature(instrs: &[Instr]) -> Option<Signature> {
     Some(sig.stack)
 }
 
+/// The the signature of some nodes, but only
+/// if the temp stack signatures are `|0.0`
+pub(crate) fn nodes_clean_signature(nodes: &[Node]) -> Option<Signature> {
+    let sig = nodes_all_signatures(nodes).ok()?;
+    if sig.functions_left != 0 || sig.temps.iter().any(|&sig| sig != (0, 0)) || sig.array_stack != 0
+    {
+        return None;
+    }
+    Some(sig.stack)
+}
+
 pub(crate) fn instrs_temp_signatures(
     instrs: &[Instr],
 ) -> Result<[Signature; TempStack::CARDINALITY], SigCheckError> {
@@ -54,18 +74,32 @@ pub(crate) struct AllSignatures {
 }
 
 pub(crate) fn instrs_all_signatures(instrs: &[Instr]) -> Result<AllSignatures, SigCheckError> {
+    let mut hasher = DefaultHasher::new();
+    instrs.hash(&mut hasher);
+    cached_all_signatures(hasher.finish(), || VirtualEnv::from_instrs(instrs))
+}
+
+pub(crate) fn nodes_all_signatures(nodes: &[Node]) -> Result<AllSignatures, SigCheckError> {
+    let mut hasher = DefaultHasher::new();
+    // Keep node hashes apart from instruction hashes
+    "nodes".hash(&mut hasher);
+    nodes.hash(&mut hasher);
+    cached_all_signatures(hasher.finish(), || VirtualEnv::from_nodes(nodes))
+}
+
+fn cached_all_signatures(
+    hash: u64,
+    env: impl FnOnce() -> Result<VirtualEnv, SigCheckError>,
+) -> Result<AllSignatures, SigCheckError> {
     type AllSigsCache = HashMap<u64, AllSignatures>;
     thread_local! {
         static CACHE: RefCell<AllSigsCache> = RefCell::new(AllSigsCache::new());
     }
-    let mut hasher = DefaultHasher::new();
-    instrs.hash(&mut hasher);
-    let hash = hasher.finish();
     CACHE.with(|cache| {
         if let Some(sigs) = cache.borrow().get(&hash) {
             return Ok(*sigs);
         }
-        let env = VirtualEnv::from_instrs(instrs)?;
+        let env = env()?;
         let sigs = AllSignatures {
             stack: env.sig(),
             temps: env.temp_signatures(),
@@ -212,8 +246,8 @@ fn derive_sig(min_height: usize, final_height: i32) -> Signature {
 }
 
 impl VirtualEnv {
-    fn from_instrs(instrs: &[Instr]) -> Result<Self, SigCheckError> {
-        let mut env = VirtualEnv {
+    fn new() -> Self {
+        VirtualEnv {
             stack: Vec::new(),
             height: 0,
             temp_stacks: Default::default(),
@@ -222,10 +256,18 @@ impl VirtualEnv {
             array_stack: Vec::new(),
             min_height: 0,
             temp_min_heights: [0; TempStack::CARDINALITY],
-        };
+        }
+    }
+    fn from_instrs(instrs: &[Instr]) -> Result<Self, SigCheckError> {
+        let mut env = VirtualEnv::new();
         env.instrs(instrs)?;
         Ok(env)
     }
+    fn from_nodes(nodes: &[Node]) -> Result<Self, SigCheckError> {
+        let mut env = VirtualEnv::new();
+        env.nodes(nodes)?;
+        Ok(env)
+    }
     fn sig(&self) -> Signature {
         derive_sig(self.min_height, self.height)
     }
@@ -275,6 +317,78 @@ impl VirtualEnv {
         }
         Ok(())
     }
+    fn nodes(&mut self, nodes: &[Node]) -> Result<(), SigCheckError> {
+        let mut i = 0;
+        while i < nodes.len() {
+            match &nodes[i] {
+                Node::Instr(Instr::PushSig(sig)) => {
+                    let mut depth = 0;
+                    i += 1;
+                    while i < nodes.len() {
+                        match &nodes[i] {
+                            Node::Instr(Instr::PushSig(_)) => depth += 1,
+                            Node::Instr(Instr::PopSig) => {
+                                if depth == 0 {
+                                    break;
+                                } else {
+                                    depth -= 1;
+                                }
+                            }
+                            _ => {}
+                        }
+                        i += 1;
+                    }
+                    self.handle_sig(*sig);
+                }
+                Node::Instr(Instr::PopSig) => {
+                    return Err(SigCheckError::from(
+                        "PopSig without PushSig. \
+                        It is a bug in the interpreter for you to see this message.",
+                    ))
+                }
+                node => self.node(node)?,
+            }
+            i += 1;
+        }
+        Ok(())
+    }
+    fn node(&mut self, node: &Node) -> Result<(), SigCheckError> {
+        match node {
+            Node::Run(nodes) => self.nodes(nodes)?,
+            Node::Instr(instr) => self.instr(instr)?,
+            Node::Array { inner, .. } => {
+                self.array_stack.push(self.height);
+                self.nodes(inner.as_slice())?;
+                self.end_array()?;
+            }
+            Node::Func(f) => self.function_stack.push(f.sig),
+            Node::Mod(prim, args, span) => {
+                (self.function_stack).extend(args.iter().rev().map(|f| f.sig));
+                self.instr(&Instr::Prim(*prim, *span))?;
+            }
+            Node::ImplMod(prim, args, span) => {
+                (self.function_stack).extend(args.iter().rev().map(|f| f.sig));
+                self.instr(&Instr::ImplPrim(*prim, *span))?;
+            }
+            Node::CustomInverse { .. } => {
+                let sig = self.pop_func()?;
+                self.handle_sig(sig)
+            }
+        }
+        Ok(())
+    }
+    fn end_array(&mut self) -> Result<(), SigCheckError> {
+        let bottom = (self.array_stack.pop()).ok_or("EndArray without BeginArray")?;
+        let stack_bottom = (bottom.max(0) as usize).min(self.stack.len());
+        let mut items: Vec<_> = (self.stack.drain(stack_bottom..))
+            .chain(repeat(BasicValue::Other).take((-bottom).max(0) as usize))
+            .collect();
+        self.height = bottom;
+        self.set_min_height();
+        items.reverse();
+        self.push(BasicValue::Arr(items));
+        Ok(())
+    }
     fn instr(&mut self, instr: &Instr) -> Result<(), SigCheckError> {
         use Primitive::*;
         match instr {
@@ -291,17 +405,7 @@ impl VirtualEnv {
                 self.pop();
             }
             Instr::BeginArray => self.array_stack.push(self.height),
-            Instr::EndArray { .. } => {
-                let bottom = (self.array_stack.pop()).ok_or("EndArray without BeginArray")?;
-                let stack_bottom = (bottom.max(0) as usize).min(self.stack.len());
-                let mut items: Vec<_> = (self.stack.drain(stack_bottom..))
-                    .chain(repeat(BasicValue::Other).take((-bottom).max(0) as usize))
-                    .collect();
-                self.height = bottom;
-                self.set_min_height();
-                items.reverse();
-                self.push(BasicValue::Arr(items));
-            }
+            Instr::EndArray { .. } => self.end_array()?,
             Instr::ImplPrim(ImplPrimitive::EndRandArray, _) => {
                 let _len = self.pop();
                 let bottom = (self.array_stack.pop()).ok_or("EndRandArray without BeginArray")?;
@@ -402,6 +506,10 @@ impl VirtualEnv {
                     let sig = self.pop_func()?;
                     self.handle_args_outputs(sig.args, 1);
                 }
+                Sys(SysOp::TimedSpawn) => {
+                    let sig = self.pop_func()?;
+                    self.handle_args_outputs(sig.args + 1, 1);
+                }
                 Repeat => {
                     let f = self.pop_func()?;
                     let n = self.pop();
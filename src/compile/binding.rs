@@ -69,7 +69,7 @@ impl Compiler {
             }
             let mut new_func = self.compile_words(binding.words, true)?;
             new_func.flags |= flags;
-            let sig = match instrs_signature(&new_func.instrs) {
+            let sig = match nodes_signature(&new_func.nodes) {
                 Ok(s) => {
                     if let Some(declared) = binding.signature {
                         if s != declared.value {
@@ -114,7 +114,9 @@ impl Compiler {
                     ),
                 );
             }
-            let function = self.make_function(FunctionId::Named(name.clone()), sig, new_func);
+            let function = self
+                .make_function(FunctionId::Named(name.clone()), sig, new_func)
+                .lower(&mut self.asm);
             self.scope.names.insert(name.clone(), local);
             self.asm.add_binding_at(
                 local,
@@ -240,11 +242,11 @@ impl Compiler {
             Box::new(
                 move |mut new_func: NewFunction, sig: Signature, comp: &mut Compiler| {
                     // Diagnostic for function that doesn't consume its arguments
-                    if let [Instr::Prim(Primitive::Dup, span), rest @ ..] =
-                        new_func.instrs.as_slice()
+                    if let [Node::Instr(Instr::Prim(Primitive::Dup, span)), rest @ ..] =
+                        new_func.nodes.as_slice()
                     {
                         if let Span::Code(dup_span) = comp.get_span(*span) {
-                            if let Ok(rest_sig) = instrs_signature(rest) {
+                            if let Ok(rest_sig) = nodes_signature(rest) {
                                 if rest_sig.args == sig.args && rest_sig.outputs + 1 == sig.outputs
                                 {
                                     comp.emit_diagnostic(
@@ -258,7 +260,8 @@ impl Compiler {
                         }
                     }
                     new_func.flags |= flags;
-                    comp.make_function(FunctionId::Named(name), sig, new_func)
+                    (comp.make_function(FunctionId::Named(name), sig, new_func))
+                        .lower(&mut comp.asm)
                 },
             )
         };
@@ -293,34 +296,50 @@ impl Compiler {
                 let mut f = make(new_func, sig, comp);
                 f.flags |= FunctionFlags::RECURSIVE;
                 let flags = f.flags;
-                let instrs = eco_vec![Instr::PushFunc(f), Instr::CallRecursive(spandex)];
-                let new_func = NewFunction { instrs, flags };
-                comp.make_function(FunctionId::Named(name.clone()), sig, new_func)
+                let nodes = eco_vec![
+                    Node::Instr(Instr::PushFunc(f)),
+                    Node::Instr(Instr::CallRecursive(spandex))
+                ];
+                let new_func = NewFunction { nodes, flags };
+                (comp.make_function(FunctionId::Named(name.clone()), sig, new_func))
+                    .lower(&mut comp.asm)
             });
         }
 
         // Resolve signature
-        match instrs_signature(&new_func.instrs) {
+        match nodes_signature(&new_func.nodes) {
             Ok(mut sig) => {
-                #[rustfmt::skip]
                 let is_obverse = matches!(
-                    new_func.instrs.as_slice(),
-                    [Instr::PushFunc(_), Instr::Prim(Primitive::Obverse, _)]
+                    new_func.nodes.as_slice(),
+                    [Node::Mod(Primitive::Obverse, ..)]
                 );
-                #[rustfmt::skip]
                 let is_setinv = matches!(
-                    new_func.instrs.as_slice(),
-                    [Instr::PushFunc(_), Instr::PushFunc(_), Instr::Prim(Primitive::SetInverse, _)]
+                    new_func.nodes.as_slice(),
+                    [Node::Mod(Primitive::SetInverse, ..)]
                 );
-                #[rustfmt::skip]
                 let is_setund = matches!(
-                    new_func.instrs.as_slice(),
-                    [Instr::PushFunc(_), Instr::PushFunc(_), Instr::PushFunc(_), Instr::Prim(Primitive::SetUnder, _)]
+                    new_func.nodes.as_slice(),
+                    [Node::Mod(Primitive::SetUnder, ..)]
                 );
-                if let [Instr::PushFunc(f)] = new_func.instrs.as_slice() {
+                if let [Node::Func(f)] = new_func.nodes.as_slice() {
                     // Binding is a single inline function
                     let func = if self_referenced {
-                        make_fn(f.new_func(&self.asm), f.signature(), self)
+                        make_fn(f.clone().into(), f.sig, self)
+                    } else {
+                        let mut func = f.clone();
+                        func.id = FunctionId::Named(name.clone());
+                        func.lower(&mut self.asm)
+                    };
+                    sig = f.sig;
+                    self.compile_bind_function(name, local, func, spandex, comment.as_deref())?;
+                } else if let [Node::Instr(Instr::PushFunc(f))] = new_func.nodes.as_slice() {
+                    // Binding is a single function that is already bound
+                    let func = if self_referenced {
+                        make_fn(
+                            FuncNode::from_function(f, &self.asm).into(),
+                            f.signature(),
+                            self,
+                        )
                     } else {
                         let mut func = f.clone();
                         func.id = FunctionId::Named(name.clone());
@@ -329,18 +348,20 @@ impl Compiler {
                     sig = f.signature();
                     self.compile_bind_function(name, local, func, spandex, comment.as_deref())?;
                 } else if sig == (0, 1) && !is_obverse && !is_setinv && !is_setund {
-                    if let &[Instr::Prim(Primitive::Tag, span)] = new_func.instrs.as_slice() {
-                        new_func.instrs.push(Instr::Label {
+                    if let &[Node::Instr(Instr::Prim(Primitive::Tag, span))] =
+                        new_func.nodes.as_slice()
+                    {
+                        new_func.nodes.push(Node::Instr(Instr::Label {
                             label: name.clone(),
                             span,
                             remove: false,
-                        })
+                        }))
                     }
                     // Binding is a constant
-                    let val = if let [Instr::Push(v)] = new_func.instrs.as_slice() {
+                    let val = if let [Node::Instr(Instr::Push(v))] = new_func.nodes.as_slice() {
                         Some(v.clone())
                     } else {
-                        match self.comptime_instrs(new_func.instrs.clone()) {
+                        match self.comptime_nodes(new_func.nodes.clone()) {
                             Ok(Some(vals)) => vals.into_iter().next(),
                             Ok(None) => None,
                             Err(e) => {
@@ -360,21 +381,14 @@ impl Compiler {
                         }
                     } else {
                         // Add binding instrs to top slices
-                        new_func.instrs.push(Instr::BindGlobal {
+                        new_func.nodes.push(Node::Instr(Instr::BindGlobal {
                             span: spandex,
                             index: local.index,
-                        });
-                        let start = self.asm.instrs.len();
-                        (self.asm.instrs).extend(optimize_instrs(new_func.instrs, true, &self.asm));
-                        let end = self.asm.instrs.len();
-                        if end != start {
-                            self.asm.top_slices.push(FuncSlice {
-                                start,
-                                len: end - start,
-                            });
-                        }
+                        }));
+                        let nodes = optimize_nodes(new_func.nodes, true, &self.asm);
+                        self.push_top_slice(nodes);
                     }
-                } else if new_func.instrs.is_empty() {
+                } else if new_func.nodes.is_empty() {
                     // Binding binds the value above
                     match &mut self.scope.stack_height {
                         Ok(height) => {
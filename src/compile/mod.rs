@@ -4,10 +4,9 @@ mod modifier;
 use std::{
     cell::RefCell,
     cmp::Ordering,
-    collections::{hash_map::DefaultHasher, BTreeSet, HashMap, HashSet, VecDeque},
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
     env::current_dir,
     fmt, fs,
-    hash::{Hash, Hasher},
     iter::{once, repeat},
     mem::{replace, take},
     panic::{catch_unwind, AssertUnwindSafe},
@@ -23,23 +22,24 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     algorithm::{
-        invert::{anti_instrs, invert_instrs, under_instrs, CustomInverse},
+        invert::{anti_nodes, invert_nodes, lift_funcs, under_nodes},
         IgnoreError,
     },
     ast::*,
-    check::{instrs_clean_signature, instrs_signature, SigCheckError, SigCheckErrorKind},
+    check::{nodes_clean_signature, nodes_signature, SigCheckError, SigCheckErrorKind},
     format::{format_word, format_words},
     function::*,
     ident_modifier_args,
     instr::*,
+    ir::nodes_as_flipped_primitive,
     lex::{CodeSpan, Sp, Span},
     lsp::{CodeMeta, ImportSrc, SigDecl},
-    optimize::{optimize_instrs, optimize_instrs_mut},
+    optimize::{optimize_nodes, optimize_nodes_mut},
     parse::{count_placeholders, flip_unsplit_lines, parse, split_words},
     Array, Assembly, BindingKind, Boxed, Diagnostic, DiagnosticKind, DocComment, DocCommentSig,
-    GitTarget, Ident, ImplPrimitive, InputSrc, IntoInputSrc, IntoSysBackend, Primitive, RunMode,
-    SemanticComment, SysBackend, Uiua, UiuaError, UiuaErrorKind, UiuaResult, Value, CONSTANTS,
-    EXAMPLE_UA, SUBSCRIPT_NUMS, VERSION,
+    FuncNode, GitTarget, Ident, ImplPrimitive, InputSrc, IntoInputSrc, IntoSysBackend, Node,
+    Primitive, RunMode, SemanticComment, SysBackend, Uiua, UiuaError, UiuaErrorKind, UiuaResult,
+    Value, CONSTANTS, EXAMPLE_UA, SUBSCRIPT_NUMS, VERSION,
 };
 
 /// Wrap a block in a closure call to reduce stack size
@@ -85,10 +85,10 @@ pub struct Compiler {
     in_inverse: bool,
     /// Whether the compiler is in a try
     in_try: bool,
-    /// Map of instructions to undered functions that created them
+    /// Map of nodes to undered functions that created them
     ///
     /// This is used to under functions that have already been inlined
-    pub(crate) undered_funcs: HashMap<EcoVec<Instr>, UnderedFunctions>,
+    pub(crate) undered_funcs: HashMap<EcoVec<Node>, UnderedFunctions>,
     /// Accumulated errors
     errors: Vec<UiuaError>,
     /// Primitives that have emitted errors because they are deprecated
@@ -101,6 +101,8 @@ pub struct Compiler {
     comptime: bool,
     /// The comptime mode
     pre_eval_mode: PreEvalMode,
+    /// Whether to remove unreachable code when finishing
+    remove_unreachable: bool,
     /// The interpreter used for comptime code
     macro_env: Uiua,
 }
@@ -130,6 +132,7 @@ impl Default for Compiler {
             print_diagnostics: false,
             comptime: true,
             pre_eval_mode: PreEvalMode::default(),
+            remove_unreachable: false,
             macro_env: Uiua::default(),
         }
     }
@@ -137,27 +140,46 @@ impl Default for Compiler {
 
 /// A function that is under construction
 ///
-/// Has a list of instructions but also tracks flags
+/// Has a list of nodes but also tracks flags
 #[derive(Debug, Clone, Default)]
 pub(crate) struct NewFunction {
-    pub instrs: EcoVec<Instr>,
+    pub nodes: EcoVec<Node>,
     pub flags: FunctionFlags,
 }
 
-impl From<EcoVec<Instr>> for NewFunction {
-    fn from(instrs: EcoVec<Instr>) -> Self {
+impl From<EcoVec<Node>> for NewFunction {
+    fn from(nodes: EcoVec<Node>) -> Self {
         Self {
-            instrs,
+            nodes,
             flags: FunctionFlags::default(),
         }
     }
 }
 
+impl From<EcoVec<Instr>> for NewFunction {
+    fn from(instrs: EcoVec<Instr>) -> Self {
+        instrs
+            .into_iter()
+            .map(Node::Instr)
+            .collect::<EcoVec<_>>()
+            .into()
+    }
+}
+
 impl From<FunctionFlags> for NewFunction {
     fn from(flags: FunctionFlags) -> Self {
         Self {
             flags,
-            instrs: EcoVec::new(),
+            nodes: EcoVec::new(),
+        }
+    }
+}
+
+impl From<FuncNode> for NewFunction {
+    fn from(f: FuncNode) -> Self {
+        Self {
+            nodes: f.body.into_vec(),
+            flags: f.flags,
         }
     }
 }
@@ -219,9 +241,9 @@ struct CurrentBinding {
 
 #[derive(Debug, Clone)]
 pub(crate) struct UnderedFunctions {
-    pub f: EcoVec<Instr>,
+    pub f: EcoVec<Node>,
     pub f_sig: Signature,
-    pub g: EcoVec<Instr>,
+    pub g: EcoVec<Node>,
     pub g_sig: Signature,
     pub span: usize,
 }
@@ -316,11 +338,11 @@ const MAX_PRE_EVAL_ELEMS: usize = 1000;
 const MAX_PRE_EVAL_RANK: usize = 4;
 
 impl PreEvalMode {
-    fn matches_instrs(&self, instrs: &[Instr], asm: &Assembly) -> bool {
-        if instrs.iter().any(|instr| {
+    fn matches_nodes(&self, nodes: &[Node], asm: &Assembly) -> bool {
+        if nodes.iter().any(|node| {
             matches!(
-                instr,
-                Instr::Push(val)
+                node,
+                Node::Instr(Instr::Push(val))
                     if val.element_count() > MAX_PRE_EVAL_ELEMS
                     || val.rank() > MAX_PRE_EVAL_RANK
             )
@@ -329,12 +351,11 @@ impl PreEvalMode {
         }
         match self {
             PreEvalMode::Normal | PreEvalMode::Line => {
-                instrs_are_pure(instrs, asm, Purity::Pure) && instrs_are_limit_bounded(instrs, asm)
+                nodes_are_pure(nodes, asm, Purity::Pure) && nodes_are_limit_bounded(nodes, asm)
             }
             PreEvalMode::Lazy => false,
             PreEvalMode::Lsp => {
-                instrs_are_pure(instrs, asm, Purity::Impure)
-                    && instrs_are_limit_bounded(instrs, asm)
+                nodes_are_pure(nodes, asm, Purity::Impure) && nodes_are_limit_bounded(nodes, asm)
             }
         }
     }
@@ -374,7 +395,11 @@ impl Compiler {
     }
     /// Take a completed assembly from the compiler
     pub fn finish(&mut self) -> Assembly {
-        take(&mut self.asm)
+        let mut asm = take(&mut self.asm);
+        if self.remove_unreachable {
+            asm.remove_unreachable();
+        }
+        asm
     }
     /// Set whether to evaluate `comptime`
     pub fn comptime(&mut self, comptime: bool) -> &mut Self {
@@ -395,6 +420,15 @@ impl Compiler {
         self.print_diagnostics = print_diagnostics;
         self
     }
+    /// Set whether [`Compiler::finish`] removes code that can never be run
+    ///
+    /// See [`Assembly::remove_unreachable`]
+    ///
+    /// Defaults to false
+    pub fn remove_unreachable(&mut self, remove_unreachable: bool) -> &mut Self {
+        self.remove_unreachable = remove_unreachable;
+        self
+    }
     /// Set the run mode
     pub fn mode(&mut self, mode: RunMode) -> &mut Self {
         self.mode = mode;
@@ -688,9 +722,9 @@ code:
                 let new_func = self.compile_words(line, true)?;
                 let instr_count_after = self.asm.instrs.len();
                 let binding_count_after = self.asm.bindings.len();
-                let (mut new_func, pre_eval_errors) = self.pre_eval_instrs(new_func);
+                let (mut new_func, pre_eval_errors) = self.pre_eval_nodes(new_func);
                 let mut line_eval_errored = false;
-                match instrs_signature(&new_func.instrs) {
+                match nodes_signature(&new_func.nodes) {
                     Ok(sig) => {
                         // Check doc comment sig
                         if let Some(comment_sig) = line_sig_comment {
@@ -712,14 +746,15 @@ code:
                                     .scopes()
                                     .any(|sc| sc.kind == ScopeKind::File(FileScopeKind::Git))
                             {
-                                if let Some(Instr::Prim(Primitive::Assert, span)) =
-                                    new_func.instrs.last()
+                                if let Some(Node::Instr(Instr::Prim(Primitive::Assert, span))) =
+                                    new_func.nodes.last()
                                 {
                                     let span = *span;
-                                    new_func.instrs.pop();
-                                    new_func
-                                        .instrs
-                                        .push(Instr::ImplPrim(ImplPrimitive::TestAssert, span));
+                                    new_func.nodes.pop();
+                                    new_func.nodes.push(Node::Instr(Instr::ImplPrim(
+                                        ImplPrimitive::TestAssert,
+                                        span,
+                                    )));
                                 }
                             }
                         }
@@ -729,7 +764,7 @@ code:
                         // - there are at least as many push instructions preceding the current line as there are arguments to the line
                         // - the words create no bindings
                         if self.pre_eval_mode != PreEvalMode::Line
-                            && !new_func.instrs.is_empty()
+                            && !new_func.nodes.is_empty()
                             && instr_count_before >= sig.args
                             && instr_count_after >= instr_count_before
                             && binding_count_before == binding_count_after
@@ -737,14 +772,16 @@ code:
                                 .take(sig.args)
                                 .all(|instr| matches!(instr, Instr::Push(_)))
                         {
-                            // The instructions for evaluation are the preceding push
+                            // The nodes for evaluation are the preceding push
                             // instructions, followed by the current line
-                            let mut comp_instrs = EcoVec::from(
-                                &self.asm.instrs[instr_count_before.saturating_sub(sig.args)
-                                    ..instr_count_before],
-                            );
-                            comp_instrs.extend(new_func.instrs.iter().cloned());
-                            match self.comptime_instrs(comp_instrs) {
+                            let mut comp_nodes: EcoVec<Node> = (self.asm.instrs
+                                [instr_count_before.saturating_sub(sig.args)..instr_count_before])
+                                .iter()
+                                .cloned()
+                                .map(Node::Instr)
+                                .collect();
+                            comp_nodes.extend(new_func.nodes.iter().cloned());
+                            match self.comptime_nodes(comp_nodes) {
                                 Ok(Some(vals)) => {
                                     // Track top level values
                                     if !all_literal {
@@ -765,8 +802,10 @@ code:
                                             self.asm.top_slices.pop();
                                         }
                                     }
-                                    // Set instrs
-                                    new_func.instrs = vals.into_iter().map(Instr::push).collect();
+                                    // Set nodes
+                                    new_func.nodes = (vals.into_iter())
+                                        .map(|val| Node::Instr(Instr::push(val)))
+                                        .collect();
                                 }
                                 Ok(None) => {}
                                 Err(e) => {
@@ -781,42 +820,35 @@ code:
                 if !line_eval_errored {
                     self.errors.extend(pre_eval_errors);
                 }
-                let start = self.asm.instrs.len();
-                self.asm.instrs.extend(new_func.instrs);
-                let end = self.asm.instrs.len();
-                if end != start {
-                    self.asm.top_slices.push(FuncSlice {
-                        start,
-                        len: end - start,
-                    });
-                }
+                self.push_top_slice(new_func.nodes);
             });
         }
         Ok(())
     }
+    /// Lower some nodes into a new top-level slice
+    fn push_top_slice(&mut self, nodes: EcoVec<Node>) {
+        let instrs = Node::Run(nodes).lower(&mut self.asm);
+        let start = self.asm.instrs.len();
+        self.asm.instrs.extend(instrs);
+        let end = self.asm.instrs.len();
+        if end != start {
+            self.asm.top_slices.push(FuncSlice {
+                start,
+                len: end - start,
+            });
+        }
+    }
     #[must_use]
     pub(crate) fn make_function(
         &mut self,
         id: FunctionId,
         sig: Signature,
         new_func: NewFunction,
-    ) -> Function {
-        let (new_func, errors) = self.pre_eval_instrs(new_func);
+    ) -> FuncNode {
+        let (new_func, errors) = self.pre_eval_nodes(new_func);
         self.errors.extend(errors);
-        let len = new_func.instrs.len();
-        (self.asm.instrs).push(Instr::Comment(format!("({id}").into()));
-        let start = if len == 0 { 0 } else { self.asm.instrs.len() };
-        let mut hasher = DefaultHasher::new();
-        new_func.instrs.hash(&mut hasher);
-        let hash = hasher.finish();
-        self.asm.instrs.extend(new_func.instrs);
-        (self.asm.instrs).push(Instr::Comment(format!("{id})").into()));
-        let slice = FuncSlice { start, len };
-        // println!(
-        //     "make function: {id} {sig} {slice:?} {:?}",
-        //     self.asm.instrs(slice)
-        // );
-        Function::new(id, sig, slice, hash).with_flags(new_func.flags)
+        // println!("make function: {id} {sig} {:?}", new_func.nodes);
+        FuncNode::new(id, sig, new_func.nodes, new_func.flags)
     }
     fn compile_bind_function(
         &mut self,
@@ -1002,7 +1034,7 @@ code:
         let is_empty = line.iter().all(|w| !w.value.is_code());
         let new_func = self.compile_words(line, call)?;
         if let Some(comment_sig) = comment_sig {
-            if let Ok(sig) = instrs_signature(&new_func.instrs) {
+            if let Ok(sig) = nodes_signature(&new_func.nodes) {
                 if !is_empty && !comment_sig.value.matches_sig(sig) {
                     self.emit_diagnostic(
                         format!("Line signature {sig} does not match comment"),
@@ -1018,26 +1050,24 @@ code:
         let span = word.span.clone();
         let mut new_func = self.compile_words(vec![word], true)?;
         let mut sig = None;
-        // Extract function instrs if possible
-        if let [Instr::PushFunc(f)] = new_func.instrs.as_slice() {
-            sig = Some(f.signature());
-            let slice = f.slice;
-            new_func.flags = f.flags;
-            new_func.instrs = f.instrs(&self.asm).into();
-            if slice.start + slice.len >= self.asm.instrs.len() - 1 {
-                self.asm.instrs.truncate(slice.start);
-                if matches!(self.asm.instrs.last(), Some(Instr::Comment(com)) if com.starts_with('('))
-                {
-                    self.asm.instrs.pop();
-                }
+        // Extract function nodes if possible
+        match new_func.nodes.as_slice() {
+            [Node::Func(f)] => {
+                sig = Some(f.sig);
+                new_func = f.clone().into();
+            }
+            [Node::Instr(Instr::PushFunc(f))] => {
+                sig = Some(f.signature());
+                new_func = FuncNode::from_function(f, &self.asm).into();
             }
+            _ => {}
         }
         let sig = if let Some(sig) = sig {
             sig
         } else {
-            self.sig_of(&new_func.instrs, &span)?
+            self.sig_of(&new_func.nodes, &span)?
         };
-        new_func.instrs = optimize_instrs(new_func.instrs, false, &self.asm);
+        new_func.nodes = optimize_nodes(new_func.nodes, false, &self.asm);
         Ok((new_func, sig))
     }
     fn words(&mut self, mut words: Vec<Sp<Word>>, call: bool) -> UiuaResult {
@@ -1100,14 +1130,14 @@ code:
                 _ => {}
             }
 
-            let start = self.new_functions.last().unwrap().instrs.len();
+            let start = self.new_functions.last().unwrap().nodes.len();
 
             // Compile the word
             self.word(word, call)?;
 
             let new_function = self.new_functions.last().unwrap();
-            let sig = if new_function.instrs.len() >= start {
-                instrs_signature(&new_function.instrs[start..]).ok()
+            let sig = if new_function.nodes.len() >= start {
+                nodes_signature(&new_function.nodes[start..]).ok()
             } else {
                 None
             };
@@ -1116,34 +1146,38 @@ code:
         }
         Ok(())
     }
-    /// Push an instruction to the current function being compiled
+    /// Push a node to the current function being compiled
     ///
-    /// Also performs some optimizations if the instruction and the previous
-    /// instruction form some known pattern
-    fn push_instr(&mut self, instr: Instr) {
+    /// Also performs some optimizations if the node and the previous
+    /// nodes form some known pattern
+    fn push_node(&mut self, node: Node) {
         let new_func = self.new_functions.last_mut().unwrap();
-        optimize_instrs_mut(&mut new_func.instrs, instr, false, &self.asm);
+        optimize_nodes_mut(&mut new_func.nodes, node, false, &self.asm);
+    }
+    /// Push an instruction to the current function being compiled
+    fn push_instr(&mut self, instr: Instr) {
+        self.push_node(Node::Instr(instr));
     }
-    fn push_all_instrs(&mut self, new_func: impl Into<NewFunction>) {
+    fn push_all_nodes(&mut self, new_func: impl Into<NewFunction>) {
         let new_func = new_func.into();
         let curr = self.new_functions.last_mut().unwrap();
         curr.flags |= new_func.flags;
-        for instr in new_func.instrs {
-            optimize_instrs_mut(&mut curr.instrs, instr, false, &self.asm);
+        for node in new_func.nodes {
+            optimize_nodes_mut(&mut curr.nodes, node, false, &self.asm);
         }
     }
     fn word(&mut self, word: Sp<Word>, call: bool) -> UiuaResult {
         match word.value {
             Word::Number(_, n) => {
-                let mut instr = Instr::push(n);
+                let mut node = Node::Instr(Instr::push(n));
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         Signature::new(0, 1),
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::Char(c) => {
                 let val: Value = if c.chars().count() == 1 {
@@ -1151,26 +1185,26 @@ code:
                 } else {
                     c.into()
                 };
-                let mut instr = Instr::push(val);
+                let mut node = Node::Instr(Instr::push(val));
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         Signature::new(0, 1),
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::String(s) => {
-                let mut instr = Instr::push(s);
+                let mut node = Node::Instr(Instr::push(s));
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         Signature::new(0, 1),
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::MultilineString(lines) => {
                 let mut s = EcoVec::new();
@@ -1180,44 +1214,44 @@ code:
                     }
                     s.extend(line.value.chars());
                 }
-                let mut instr = Instr::push(s);
+                let mut node = Node::Instr(Instr::push(s));
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         Signature::new(0, 1),
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::Label(label) => {
-                let mut instr = Instr::Label {
+                let mut node = Node::Instr(Instr::Label {
                     label: label.into(),
                     span: self.add_span(word.span.clone()),
                     remove: false,
-                };
+                });
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         Signature::new(0, 1),
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::FormatString(frags) => {
                 let signature = Signature::new(frags.len() - 1, 1);
                 let parts = frags.into_iter().map(Into::into).collect();
                 let span = self.add_span(word.span.clone());
-                let mut instr = Instr::Format { parts, span };
+                let mut node = Node::Instr(Instr::Format { parts, span });
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         signature,
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::MultilineFormatString(lines) => {
                 let signature = Signature::new(
@@ -1239,15 +1273,15 @@ code:
                     }
                 }
                 parts.push(curr_part);
-                let mut instr = Instr::Format { parts, span };
+                let mut node = Node::Instr(Instr::Format { parts, span });
                 if !call {
-                    instr = Instr::PushFunc(self.make_function(
+                    node = Node::Func(self.make_function(
                         FunctionId::Anonymous(word.span.clone()),
                         signature,
-                        eco_vec![instr].into(),
+                        eco_vec![node].into(),
                     ));
                 }
-                self.push_instr(instr);
+                self.push_node(node);
             }
             Word::Ref(r) => self.reference(r, call)?,
             Word::IncompleteRef { path, in_macro_arg } => {
@@ -1271,24 +1305,24 @@ code:
                 // Track span for LSP
                 let just_spans: Vec<_> = items.iter().map(|w| w.span.clone()).collect();
                 // Compile individual items
-                let op_instrs = items
+                let op_nodes = items
                     .into_iter()
                     .rev()
                     .map(|word| self.compile_operand_word(word))
                     .collect::<UiuaResult<Vec<_>>>()?;
                 // Check item sigs
-                let has_functions = op_instrs.iter().any(|(_, sig)| sig.args > 0);
+                let has_functions = op_nodes.iter().any(|(_, sig)| sig.args > 0);
                 if has_functions {
                     return Err(
                         self.fatal_error(word.span.clone(), "Functions are not allowed in strands")
                     );
                 }
                 self.code_meta.strands.insert(word.span.clone(), just_spans);
-                // Flatten instrs
-                let (inner, flags): (Vec<Instr>, FunctionFlags) = op_instrs.into_iter().fold(
-                    (Vec::new(), FunctionFlags::default()),
+                // Flatten nodes
+                let (inner, flags): (EcoVec<Node>, FunctionFlags) = op_nodes.into_iter().fold(
+                    (EcoVec::new(), FunctionFlags::default()),
                     |(mut inner, mut flags), (nf, _)| {
-                        inner.extend(nf.instrs);
+                        inner.extend(nf.nodes);
                         flags |= nf.flags;
                         (inner, flags)
                     },
@@ -1298,12 +1332,11 @@ code:
                 if !call {
                     self.new_functions.push(NewFunction::from(flags));
                 }
-                self.push_instr(Instr::BeginArray);
                 // Diagnostic for strand of characters
                 if !inner.is_empty()
-                    && inner.iter().all(
-                        |instr| matches!(instr, Instr::Push(Value::Char(arr)) if arr.rank() == 0),
-                    )
+                    && inner.iter().all(|node| {
+                        matches!(node, Node::Instr(Instr::Push(Value::Char(arr))) if arr.rank() == 0)
+                    })
                 {
                     self.emit_diagnostic(
                         "Stranded characters should instead be written as a string",
@@ -1313,20 +1346,18 @@ code:
                 }
 
                 let span_index = self.add_span(word.span.clone());
-                let new_func = self.new_functions.last_mut().unwrap();
                 // Inline constant arrays
-                if call && inner.iter().all(|instr| matches!(instr, Instr::Push(_))) {
+                if call && (inner.iter()).all(|node| matches!(node, Node::Instr(Instr::Push(_)))) {
                     let values: Vec<_> = inner
                         .iter()
                         .rev()
-                        .map(|instr| match instr {
-                            Instr::Push(v) => v.clone(),
+                        .map(|node| match node {
+                            Node::Instr(Instr::Push(v)) => v.clone(),
                             _ => unreachable!(),
                         })
                         .collect();
                     match Value::from_row_values(values, &(&word.span, &self.asm.inputs)) {
                         Ok(val) => {
-                            new_func.instrs.pop();
                             self.push_instr(Instr::push(val));
                             return Ok(());
                         }
@@ -1335,16 +1366,16 @@ code:
                     }
                 }
                 // Normal case
-                new_func.instrs.extend(inner);
-                self.push_instr(Instr::EndArray {
-                    span: span_index,
+                self.push_node(Node::Array {
+                    inner: Box::new(Node::Run(inner)),
                     boxed: false,
+                    span: span_index,
                 });
                 if !call {
                     let new_func = self.new_functions.pop().unwrap();
-                    let sig = instrs_signature(&new_func.instrs).unwrap();
+                    let sig = nodes_signature(&new_func.nodes).unwrap();
                     let func = self.make_function(FunctionId::Anonymous(word.span), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }
             Word::Array(arr) => {
@@ -1366,13 +1397,12 @@ code:
                 if !call {
                     self.new_functions.push(NewFunction::default());
                 }
-                self.push_instr(Instr::BeginArray);
-                let mut inner = Vec::new();
+                let mut inner = EcoVec::new();
                 let mut flags = FunctionFlags::default();
                 let line_count = arr.lines.len();
                 for line in arr.lines.into_iter().rev() {
                     let nf = self.compile_line(line, true)?;
-                    inner.extend(nf.instrs);
+                    inner.extend(nf.nodes);
                     flags |= nf.flags;
                 }
                 // Validate inner loop correctness
@@ -1393,9 +1423,9 @@ code:
                 if line_count <= 1
                     && !arr.boxes
                     && !inner.is_empty()
-                    && inner.iter().all(
-                        |instr| matches!(instr, Instr::Push(Value::Char(arr)) if arr.rank() == 0),
-                    )
+                    && inner.iter().all(|node| {
+                        matches!(node, Node::Instr(Instr::Push(Value::Char(arr))) if arr.rank() == 0)
+                    })
                 {
                     self.emit_diagnostic(
                         "An array of characters should instead be written as a string",
@@ -1404,13 +1434,12 @@ code:
                     );
                 }
                 let span = self.add_span(word.span.clone());
-                let new_func = self.new_functions.last_mut().unwrap();
-                new_func.flags |= flags;
+                self.new_functions.last_mut().unwrap().flags |= flags;
                 // Inline constant arrays
-                if call && inner.iter().all(|instr| matches!(instr, Instr::Push(_))) {
+                if call && (inner.iter()).all(|node| matches!(node, Node::Instr(Instr::Push(_)))) {
                     let empty = inner.is_empty();
-                    let values = inner.iter().rev().map(|instr| match instr {
-                        Instr::Push(v) => v.clone(),
+                    let values = inner.iter().rev().map(|node| match node {
+                        Node::Instr(Instr::Push(v)) => v.clone(),
                         _ => unreachable!(),
                     });
                     let res = if arr.boxes {
@@ -1435,7 +1464,6 @@ code:
                             self.code_meta
                                 .array_shapes
                                 .insert(word.span.clone(), val.shape().clone());
-                            new_func.instrs.pop();
                             self.push_instr(Instr::push(val));
                             return Ok(());
                         }
@@ -1444,16 +1472,16 @@ code:
                     }
                 }
                 // Normal case
-                new_func.instrs.extend(inner);
-                self.push_instr(Instr::EndArray {
-                    span,
+                self.push_node(Node::Array {
+                    inner: Box::new(Node::Run(inner)),
                     boxed: arr.boxes,
+                    span,
                 });
                 if !call {
                     let new_func = self.new_functions.pop().unwrap();
-                    let sig = instrs_signature(&new_func.instrs).unwrap_or(Signature::new(0, 0));
+                    let sig = nodes_signature(&new_func.nodes).unwrap_or(Signature::new(0, 0));
                     let func = self.make_function(FunctionId::Anonymous(word.span), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }
             Word::Func(func) => self.func(func, word.span, call)?,
@@ -1506,8 +1534,8 @@ code:
     }
     /// Emit a warning if a loop inside an array could
     /// potentially pull in a variable number of values
-    fn validate_array_loop_sig(&mut self, instrs: &[Instr], span: &CodeSpan) -> Option<Signature> {
-        let inner_sig = instrs_signature(instrs);
+    fn validate_array_loop_sig(&mut self, nodes: &[Node], span: &CodeSpan) -> Option<Signature> {
+        let inner_sig = nodes_signature(nodes);
         if self.current_bindings.is_empty() && !matches!(self.scope.kind, ScopeKind::Temp(_)) {
             return inner_sig.ok();
         }
@@ -1515,21 +1543,15 @@ code:
             // Case where repeat's function has a balanced signature
             // This is fine in other contexts, so an error is not returned
             // from the signature check, but it is not okay in an array.
-            if let Some(i) = instrs
-                .iter()
-                .position(|instr| {
-                    matches!(
-                        instr,
-                        Instr::Prim(Primitive::Repeat, _)
-                            | Instr::ImplPrim(ImplPrimitive::RepeatWithInverse, _)
-                    )
-                })
-                .filter(|&i| i > 0)
-            {
-                if let Instr::PushFunc(f) = &instrs[i - 1] {
-                    let body_sig = f.signature();
-                    let before_sig = instrs_signature(&instrs[..i - 1]).ok()?;
-                    let after_sig = instrs_signature(&instrs[i + 1..]).ok()?;
+            if let Some((i, args)) = nodes.iter().enumerate().find_map(|(i, node)| match node {
+                Node::Mod(Primitive::Repeat, args, _)
+                | Node::ImplMod(ImplPrimitive::RepeatWithInverse, args, _) => Some((i, args)),
+                _ => None,
+            }) {
+                if let Some(f) = args.first() {
+                    let body_sig = f.sig;
+                    let before_sig = nodes_signature(&nodes[..i]).ok()?;
+                    let after_sig = nodes_signature(&nodes[i + 1..]).ok()?;
                     if body_sig.args == body_sig.outputs
                         && before_sig.args < body_sig.args
                         && before_sig.outputs <= body_sig.args
@@ -1552,12 +1574,12 @@ code:
             }
             return inner_sig.ok();
         };
-        let before_sig = (0..instrs.len())
+        let before_sig = (0..nodes.len())
             .rev()
-            .find_map(|i| instrs_signature(&instrs[..i]).ok())
+            .find_map(|i| nodes_signature(&nodes[..i]).ok())
             .unwrap();
-        let after_sig = (0..=instrs.len())
-            .find_map(|i| instrs_signature(&instrs[i..]).ok())
+        let after_sig = (0..=nodes.len())
+            .find_map(|i| nodes_signature(&nodes[i..]).ok())
             .unwrap();
         match e.kind {
             SigCheckErrorKind::LoopVariable { sig: body_sig, inf } => {
@@ -1792,11 +1814,11 @@ code:
             (self.code_meta.global_references).insert(span.clone(), curr.global_index);
             let instr = Instr::Recur(self.add_span(span.clone()));
             if call {
-                self.push_all_instrs(eco_vec![Instr::PushSig(sig), instr, Instr::PopSig]);
+                self.push_all_nodes(eco_vec![Instr::PushSig(sig), instr, Instr::PopSig]);
             } else {
                 let f =
                     self.make_function(FunctionId::Anonymous(span), sig, eco_vec![instr].into());
-                self.push_instr(Instr::PushFunc(f));
+                self.push_node(Node::Func(f));
             }
         } else if let Some(local) = self.find_name(&ident, skip_local) {
             // Name exists in scope
@@ -1821,7 +1843,7 @@ code:
                     Signature::new(0, 1),
                     eco_vec![instr].into(),
                 );
-                self.push_instr(Instr::PushFunc(f));
+                self.push_node(Node::Func(f));
             }
         } else {
             return Err(self.fatal_error(span, format!("Unknown identifier `{ident}`")));
@@ -1846,7 +1868,7 @@ code:
                     Signature::new(0, 1),
                     eco_vec![Instr::push(val)].into(),
                 );
-                self.push_instr(Instr::PushFunc(f));
+                self.push_node(Node::Func(f));
             }
             BindingKind::Const(None) if call => self.push_instr(Instr::CallGlobal {
                 index,
@@ -1864,17 +1886,22 @@ code:
                     }]
                     .into(),
                 );
-                self.push_instr(Instr::PushFunc(f));
-            }
-            BindingKind::Func(f) if self.inlinable(f.instrs(&self.asm), f.flags) => {
-                if call {
-                    // Inline instructions
-                    self.push_all_instrs(EcoVec::from(f.instrs(&self.asm)));
-                } else {
-                    self.push_instr(Instr::PushFunc(f));
-                }
+                self.push_node(Node::Func(f));
             }
             BindingKind::Func(f) => {
+                // Only small functions are lifted to check if they can be inlined
+                let inlined = (f.instrs(&self.asm).len() <= MAX_INLINE_LEN)
+                    .then(|| FuncNode::from_function(&f, &self.asm))
+                    .filter(|func| self.inlinable(func.nodes(), f.flags));
+                if let Some(func) = inlined {
+                    if call {
+                        // Inline nodes
+                        self.push_all_nodes(func.body.into_vec());
+                    } else {
+                        self.push_node(Node::Func(func));
+                    }
+                    return;
+                }
                 if let Some(new_func) = self.new_functions.last_mut() {
                     if f.flags.track_caller() {
                         new_func.flags |= FunctionFlags::NO_PRE_EVAL;
@@ -1936,25 +1963,28 @@ code:
         }
 
         if call {
-            let (_, _, instrs) = self.compile_func_instrs(func, span, false)?;
-            self.push_all_instrs(instrs);
+            let (_, _, new_func) = self.compile_func_nodes(func, span, false)?;
+            self.push_all_nodes(new_func);
         } else {
-            let function = self.compile_func(func, span.clone())?;
-            self.push_instr(Instr::PushFunc(function));
+            let node = self.compile_func(func, span.clone())?;
+            self.push_node(node);
         }
         Ok(())
     }
-    fn compile_func(&mut self, func: Func, span: CodeSpan) -> UiuaResult<Function> {
-        let (id, sig, new_func) = self.compile_func_instrs(func, span, true)?;
+    /// Compile a function that is pushed rather than called
+    fn compile_func(&mut self, func: Func, span: CodeSpan) -> UiuaResult<Node> {
+        let (id, sig, new_func) = self.compile_func_nodes(func, span, true)?;
         let sig = sig.unwrap();
 
-        if let [Instr::PushFunc(f), Instr::Call(_)] = new_func.instrs.as_slice() {
-            return Ok(Function::clone(f));
+        match new_func.nodes.as_slice() {
+            [node @ Node::Instr(Instr::PushFunc(_)), Node::Instr(Instr::Call(_))]
+            | [node @ Node::Func(_), Node::Instr(Instr::Call(_))] => return Ok(node.clone()),
+            _ => {}
         }
 
-        Ok(self.make_function(id, sig, new_func))
+        Ok(Node::Func(self.make_function(id, sig, new_func)))
     }
-    fn compile_func_instrs(
+    fn compile_func_nodes(
         &mut self,
         func: Func,
         span: CodeSpan,
@@ -1963,12 +1993,12 @@ code:
         let mut new_func = NewFunction::default();
         for line in func.lines {
             let nf = self.compile_line(line, true)?;
-            new_func.instrs.extend(nf.instrs);
+            new_func.nodes.extend(nf.nodes);
             new_func.flags |= nf.flags;
         }
 
         // Validate signature
-        let sig = match instrs_signature(&new_func.instrs) {
+        let sig = match nodes_signature(&new_func.nodes) {
             Ok(mut sig) => {
                 if let Some(declared_sig) = &func.signature {
                     if declared_sig.value == sig {
@@ -2040,23 +2070,27 @@ code:
         for (i, branch) in branches.into_iter().enumerate() {
             let span = branch.span.clone();
             let (new_func, sig) = self.compile_operand_word(branch)?;
-            let is_flex = (new_func.instrs.iter())
-                .rposition(|instr| matches!(instr, Instr::Prim(Primitive::Assert, _)))
+            let is_flex = (new_func.nodes.iter())
+                .rposition(|node| {
+                    matches!(node, Node::Instr(Instr::Prim(Primitive::Assert, _)))
+                })
                 .is_some_and(|end| {
                     (0..end).rev().any(|start| {
-                        let sub = &new_func.instrs[start..end];
+                        let sub = &new_func.nodes[start..end];
                         match sub {
-                            [Instr::Push(val), Instr::Prim(Primitive::Dup, _)]
-                            | [Instr::Push(val), Instr::Push(..)]
+                            [Node::Instr(Instr::Push(val)), Node::Instr(Instr::Prim(Primitive::Dup, _))]
+                            | [Node::Instr(Instr::Push(val)), Node::Instr(Instr::Push(..))]
                                 if val != &Value::from(1) =>
                             {
                                 return true;
                             }
-                            [Instr::Format { .. }, Instr::Prim(Primitive::Dup, _)] => return true,
+                            [Node::Instr(Instr::Format { .. }), Node::Instr(Instr::Prim(Primitive::Dup, _))] => {
+                                return true
+                            }
                             _ => (),
                         }
-                        if !(instrs_are_pure(sub, &self.asm, Purity::Pure)
-                            && instrs_signature(sub).is_ok_and(|sig| sig == (0, 2)))
+                        if !(nodes_are_pure(sub, &self.asm, Purity::Pure)
+                            && nodes_signature(sub).is_ok_and(|sig| sig == (0, 2)))
                         {
                             return false;
                         }
@@ -2066,6 +2100,7 @@ code:
                             Signature::new(0, 2),
                             EcoVec::from(sub).into(),
                         );
+                        let func = func.lower(&mut comp.asm);
                         comp.macro_env.asm = comp.asm.clone();
                         comp.macro_env
                             .call(func)
@@ -2115,7 +2150,7 @@ code:
         for (new_func, sig, span) in functions {
             let id = FunctionId::Anonymous(span);
             let function = self.make_function(id, sig, new_func);
-            self.push_instr(Instr::PushFunc(function));
+            self.push_node(Node::Func(function));
         }
 
         let span_idx = self.add_span(span.clone());
@@ -2127,7 +2162,7 @@ code:
         });
         if !call {
             let new_func = self.new_functions.pop().unwrap();
-            let sig = match instrs_signature(&new_func.instrs) {
+            let sig = match nodes_signature(&new_func.nodes) {
                 Ok(sig) => sig,
                 Err(e) => {
                     return Err(self.fatal_error(
@@ -2144,7 +2179,7 @@ code:
                 }
             };
             let function = self.make_function(FunctionId::Anonymous(span), sig, new_func);
-            self.push_instr(Instr::PushFunc(function));
+            self.push_node(Node::Func(function));
         }
         Ok(())
     }
@@ -2198,12 +2233,13 @@ code:
         span: &CodeSpan,
         call: bool,
     ) -> UiuaResult {
+        let new_func = NewFunction::from(instrs);
         if call {
-            self.push_all_instrs(instrs);
+            self.push_all_nodes(new_func);
         } else {
-            let sig = self.sig_of(&instrs, span)?;
-            let func = self.make_function(function_id, sig, instrs.into());
-            self.push_instr(Instr::PushFunc(func));
+            let sig = self.sig_of(&new_func.nodes, span)?;
+            let func = self.make_function(function_id, sig, new_func);
+            self.push_node(Node::Func(func));
         }
         Ok(())
     }
@@ -2295,20 +2331,12 @@ code:
                         2 => self.primitive(Primitive::Couple, span, true)?,
                         n => {
                             let span = self.add_span(span.clone());
-                            self.push_instr(Instr::BeginArray);
-                            if n > 0 {
-                                self.push_instr(Instr::TouchStack { count: n, span });
-                            }
-                            self.push_instr(Instr::EndArray { boxed: false, span });
+                            self.push_node(touch_array(n, false, span));
                         }
                     },
                     Primitive::Box => {
                         let span = self.add_span(span.clone());
-                        self.push_instr(Instr::BeginArray);
-                        if n > 0 {
-                            self.push_instr(Instr::TouchStack { count: n, span });
-                        }
-                        self.push_instr(Instr::EndArray { boxed: true, span });
+                        self.push_node(touch_array(n, true, span));
                     }
                     Primitive::Stack => {
                         let span = self.add_span(span.clone());
@@ -2331,9 +2359,9 @@ code:
                 }
                 if !call {
                     let new_func = self.new_functions.pop().unwrap();
-                    let sig = self.sig_of(&new_func.instrs, &sp)?;
+                    let sig = self.sig_of(&new_func.nodes, &sp)?;
                     let func = self.make_function(FunctionId::Anonymous(sp), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }
             _ => {
@@ -2343,28 +2371,11 @@ code:
         }
         Ok(())
     }
-    pub(crate) fn inlinable(&self, instrs: &[Instr], flags: FunctionFlags) -> bool {
-        use ImplPrimitive::*;
-        use Primitive::*;
+    pub(crate) fn inlinable(&self, nodes: &[Node], flags: FunctionFlags) -> bool {
         if flags.track_caller() || flags.no_inline() || flags.no_pre_eval() {
             return false;
         }
-        if instrs.len() > 10 {
-            return false;
-        }
-        for instr in instrs {
-            match instr {
-                Instr::Prim(Trace | Dump | Stack | Assert, _) => return false,
-                Instr::ImplPrim(UnDump | UnStack | TraceN { .. }, _) => return false,
-                Instr::PushFunc(f)
-                    if !self.inlinable(f.instrs(&self.asm), FunctionFlags::default()) =>
-                {
-                    return false
-                }
-                _ => {}
-            }
-        }
-        true
+        nodes_instr_len(nodes) <= MAX_INLINE_LEN && nodes.iter().all(node_is_inlinable)
     }
     /// Get all diagnostics
     pub fn diagnostics(&self) -> &BTreeSet<Diagnostic> {
@@ -2468,6 +2479,7 @@ code:
             signature,
             eco_vec![Instr::Dynamic(DynamicFunction { index, signature })].into(),
         )
+        .lower(&mut self.asm)
     }
     /// Bind a function in the current scope
     ///
@@ -2498,43 +2510,45 @@ code:
         self.bind_function(name, function)
     }
     #[must_use]
-    fn pre_eval_instrs(&mut self, mut new_func: NewFunction) -> (NewFunction, Vec<UiuaError>) {
+    fn pre_eval_nodes(&mut self, mut new_func: NewFunction) -> (NewFunction, Vec<UiuaError>) {
         let mut errors = Vec::new();
-        new_func.instrs = optimize_instrs(new_func.instrs, true, &self.asm);
+        new_func.nodes = optimize_nodes(new_func.nodes, true, &self.asm);
         if self.in_inverse
             || self.pre_eval_mode == PreEvalMode::Lazy
-            || (new_func.instrs.iter()).all(|instr| matches!(instr, Instr::Push(_)))
+            || (new_func.nodes.iter()).all(|node| matches!(node, Node::Instr(Instr::Push(_))))
             || new_func.flags.no_inline()
             || new_func.flags.track_caller()
             || new_func.flags.no_pre_eval()
         {
             return (new_func, errors);
         }
-        let allow_error = instrs_are_pure(&new_func.instrs, &self.asm, Purity::Pure);
+        let allow_error = nodes_are_pure(&new_func.nodes, &self.asm, Purity::Pure);
         // println!("pre eval {:?}", new_func);
         let mut start = 0;
-        let mut new_instrs: Option<EcoVec<Instr>> = None;
-        'start: while start < new_func.instrs.len() {
-            for end in (start + 1..=new_func.instrs.len()).rev() {
-                let section = &new_func.instrs[start..end];
-                if !instrs_can_pre_eval(section, &self.asm) {
+        let mut new_nodes: Option<EcoVec<Node>> = None;
+        'start: while start < new_func.nodes.len() {
+            for end in (start + 1..=new_func.nodes.len()).rev() {
+                let section = &new_func.nodes[start..end];
+                if !nodes_can_pre_eval(section, &self.asm) {
                     continue;
                 }
-                if instrs_are_pure(section, &self.asm, Purity::Pure)
-                    && instrs_clean_signature(section)
+                if nodes_are_pure(section, &self.asm, Purity::Pure)
+                    && nodes_clean_signature(section)
                         .is_some_and(|sig| sig.args == 0 && sig.outputs > 0)
                 {
                     let mut success = false;
-                    match self.comptime_instrs(section.into()) {
+                    match self.comptime_nodes(section.into()) {
                         Ok(Some(values)) => {
                             // println!("section: {section:?}");
                             // println!("values: {values:?}");
                             for val in &values {
                                 val.validate_shape();
                             }
-                            let new_instrs =
-                                new_instrs.get_or_insert_with(|| new_func.instrs[..start].into());
-                            new_instrs.extend(values.into_iter().map(Instr::Push));
+                            let new_nodes =
+                                new_nodes.get_or_insert_with(|| new_func.nodes[..start].into());
+                            new_nodes.extend(
+                                values.into_iter().map(|val| Node::Instr(Instr::Push(val))),
+                            );
                             success = true;
                         }
                         Ok(None) => {}
@@ -2542,49 +2556,53 @@ code:
                         Err(e) => errors.push(e),
                     }
                     if !success {
-                        if let Some(new_instrs) = &mut new_instrs {
-                            new_instrs.extend(section.iter().cloned());
+                        if let Some(new_nodes) = &mut new_nodes {
+                            new_nodes.extend(section.iter().cloned());
                         }
                     }
                     start = end;
                     continue 'start;
                 }
             }
-            if let Some(new_instrs) = &mut new_instrs {
-                new_instrs.push(new_func.instrs[start].clone())
+            if let Some(new_nodes) = &mut new_nodes {
+                new_nodes.push(new_func.nodes[start].clone())
             }
             start += 1;
         }
-        // if let Some(new_instrs) = &new_instrs {
-        //     println!("eval: {new_instrs:?}")
+        // if let Some(new_nodes) = &new_nodes {
+        //     println!("eval: {new_nodes:?}")
         // }
-        new_func.instrs = new_instrs.unwrap_or(new_func.instrs);
+        new_func.nodes = new_nodes.unwrap_or(new_func.nodes);
         (new_func, errors)
     }
-    fn comptime_instrs(&mut self, instrs: EcoVec<Instr>) -> UiuaResult<Option<Vec<Value>>> {
-        if !self.pre_eval_mode.matches_instrs(&instrs, &self.asm) {
+    fn comptime_nodes(&mut self, nodes: EcoVec<Node>) -> UiuaResult<Option<Vec<Value>>> {
+        if !self.pre_eval_mode.matches_nodes(&nodes, &self.asm) {
             return Ok(None);
         }
-        if instrs.iter().all(|instr| matches!(instr, Instr::Push(_))) {
+        if nodes
+            .iter()
+            .all(|node| matches!(node, Node::Instr(Instr::Push(_))))
+        {
             return Ok(Some(
-                (instrs.into_iter())
-                    .map(|instr| match instr {
-                        Instr::Push(val) => val,
+                (nodes.into_iter())
+                    .map(|node| match node {
+                        Node::Instr(Instr::Push(val)) => val,
                         _ => unreachable!(),
                     })
                     .collect(),
             ));
         }
         thread_local! {
-            static CACHE: RefCell<HashMap<EcoVec<Instr>, Option<Vec<Value>>>> = RefCell::new(HashMap::new());
+            static CACHE: RefCell<HashMap<EcoVec<Node>, Option<Vec<Value>>>> = RefCell::new(HashMap::new());
         }
         CACHE.with(|cache| {
-            let instrs = optimize_instrs(instrs, true, &self.asm);
-            if let Some(stack) = cache.borrow().get(&instrs) {
+            let nodes = optimize_nodes(nodes, true, &self.asm);
+            if let Some(stack) = cache.borrow().get(&nodes) {
                 return Ok(stack.clone());
             }
             let mut asm = self.asm.clone();
             asm.top_slices.clear();
+            let instrs = Node::Run(nodes.clone()).lower(&mut asm);
             let start = asm.instrs.len();
             let len = instrs.len();
             asm.instrs.extend(instrs.iter().cloned());
@@ -2612,19 +2630,19 @@ code:
                     } else {
                         Some(stack)
                     };
-                    cache.borrow_mut().insert(instrs, res.clone());
+                    cache.borrow_mut().insert(nodes, res.clone());
                     Ok(res)
                 }
                 Err(e) if matches!(e.kind, UiuaErrorKind::Timeout(..)) => {
-                    cache.borrow_mut().insert(instrs, None);
+                    cache.borrow_mut().insert(nodes, None);
                     Ok(None)
                 }
                 Err(e) => Err(e),
             }
         })
     }
-    fn sig_of(&self, instrs: &[Instr], span: &CodeSpan) -> UiuaResult<Signature> {
-        instrs_signature(instrs).map_err(|e| {
+    fn sig_of(&self, nodes: &[Node], span: &CodeSpan) -> UiuaResult<Signature> {
+        nodes_signature(nodes).map_err(|e| {
             self.fatal_error(
                 span.clone(),
                 format!("Cannot infer function signature: {e}"),
@@ -2633,50 +2651,95 @@ code:
     }
 }
 
-fn instrs_can_pre_eval(instrs: &[Instr], asm: &Assembly) -> bool {
-    use Primitive::*;
-    if instrs.is_empty() {
+fn nodes_can_pre_eval(nodes: &[Node], asm: &Assembly) -> bool {
+    if nodes.is_empty() {
         return true;
     }
-    // Begin and end array instructions must be balanced
-    let begin_array_pos = (instrs.iter()).position(|instr| matches!(instr, Instr::BeginArray));
-    let begin_array_count = (instrs.iter())
-        .filter(|instr| matches!(instr, Instr::BeginArray))
-        .count();
-    let end_array_pos = (instrs.iter()).position(|instr| matches!(instr, Instr::EndArray { .. }));
-    let end_array_count = (instrs.iter())
-        .filter(|instr| matches!(instr, Instr::EndArray { .. }))
-        .count();
-    let array_allowed = begin_array_count == end_array_count
-        && match (begin_array_pos, end_array_pos) {
-            (Some(0), Some(end)) => end == instrs.len() - 1,
-            (None, None) => true,
-            _ => false,
-        };
-    if !array_allowed
-        || matches!(
-            instrs.last().unwrap(),
-            Instr::PushFunc(_) | Instr::BeginArray
-        )
-        || instrs.iter().all(|instr| matches!(instr, Instr::Push(_)))
-        || instrs.iter().any(|instr| {
-            matches!(
-                instr,
-                Instr::Prim(Obverse | SetInverse | SetUnder, _)
-                    | Instr::ImplPrim(ImplPrimitive::UnPop, _)
-            )
-        })
+    if matches!(
+        nodes.last().unwrap(),
+        Node::Func(_) | Node::Instr(Instr::PushFunc(_) | Instr::BeginArray)
+    ) || (nodes.iter()).all(|node| matches!(node, Node::Instr(Instr::Push(_))))
     {
         return false;
     }
-    for instr in instrs {
-        if let Instr::PushFunc(f) = instr {
-            if !instrs_can_pre_eval(f.instrs(asm), asm) {
-                return false;
-            }
-        }
+    nodes.iter().all(|node| node_can_pre_eval(node, asm))
+}
+
+fn node_can_pre_eval(node: &Node, asm: &Assembly) -> bool {
+    use Primitive::*;
+    match node {
+        Node::Run(nodes) => nodes.iter().all(|node| node_can_pre_eval(node, asm)),
+        // Arrays that were not lifted may be split
+        Node::Instr(
+            Instr::BeginArray
+            | Instr::EndArray { .. }
+            | Instr::ImplPrim(ImplPrimitive::EndRandArray, _),
+        ) => false,
+        Node::Instr(
+            Instr::Prim(Obverse | SetInverse | SetUnder, _)
+            | Instr::ImplPrim(ImplPrimitive::UnPop, _),
+        ) => false,
+        Node::Mod(Obverse | SetInverse | SetUnder, ..) => false,
+        Node::Instr(Instr::PushFunc(f)) => {
+            nodes_can_pre_eval(FuncNode::from_function(f, asm).nodes(), asm)
+        }
+        Node::Array { inner, .. } => node_can_pre_eval(inner, asm),
+        Node::Func(f) => nodes_can_pre_eval(f.nodes(), asm),
+        Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => {
+            (args.iter()).all(|f| nodes_can_pre_eval(f.nodes(), asm))
+        }
+        _ => true,
     }
-    true
+}
+
+/// An array of the top `n` values on the stack
+fn touch_array(n: usize, boxed: bool, span: usize) -> Node {
+    let inner = if n > 0 {
+        Node::Instr(Instr::TouchStack { count: n, span })
+    } else {
+        Node::default()
+    };
+    Node::Array {
+        inner: Box::new(inner),
+        boxed,
+        span,
+    }
+}
+
+/// The maximum number of instructions in a function that is inlined
+const MAX_INLINE_LEN: usize = 10;
+
+/// The number of instructions the nodes lower to, not counting nested function bodies
+fn nodes_instr_len(nodes: &[Node]) -> usize {
+    (nodes.iter())
+        .map(|node| match node {
+            Node::Run(nodes) => nodes_instr_len(nodes),
+            Node::Array { inner, .. } => nodes_instr_len(inner.as_slice()) + 2,
+            Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => args.len() + 1,
+            _ => 1,
+        })
+        .sum()
+}
+
+fn node_is_inlinable(node: &Node) -> bool {
+    use ImplPrimitive::*;
+    use Primitive::*;
+    match node {
+        Node::Run(nodes) => nodes.iter().all(node_is_inlinable),
+        Node::Instr(Instr::Prim(Trace | Dump | Stack | Assert, _)) => false,
+        Node::Instr(Instr::ImplPrim(UnDump | UnStack | TraceN { .. }, _)) => false,
+        Node::Mod(Dump, ..) | Node::ImplMod(UnDump, ..) => false,
+        // Functions that could not be lifted are recursive
+        Node::Instr(Instr::PushFunc(_)) => false,
+        Node::Array { inner, .. } => node_is_inlinable(inner),
+        Node::Func(f) => func_is_inlinable(f),
+        Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => args.iter().all(func_is_inlinable),
+        _ => true,
+    }
+}
+
+fn func_is_inlinable(f: &FuncNode) -> bool {
+    nodes_instr_len(f.nodes()) <= MAX_INLINE_LEN && node_is_inlinable(&f.body)
 }
 
 fn words_look_pervasive(words: &[Sp<Word>]) -> bool {
@@ -151,89 +151,89 @@ impl Compiler {
                         let func = self.make_function(span.into(), sig, nf);
                         funcs.push(func);
                     }
-                    let mut cust = CustomInverse::default();
+                    let (mut un, mut under, mut anti) = (None, None, None);
                     let f;
                     match funcs.as_slice() {
                         [a, b] => {
                             f = a.clone();
-                            if a.signature() == b.signature().inverse() {
-                                cust.un = Some(b.clone());
-                            } else if a.signature().anti().is_some_and(|sig| sig == b.signature()) {
-                                cust.anti = Some(b.clone());
+                            if a.sig == b.sig.inverse() {
+                                un = Some(b.clone());
+                            } else if a.sig.anti().is_some_and(|sig| sig == b.sig) {
+                                anti = Some(b.clone());
                             } else {
-                                cust.under = Some((a.clone(), b.clone()));
+                                under = Some((a.clone(), b.clone()));
                             }
                         }
                         [a, b, c] => {
                             f = a.clone();
-                            if !b.instrs(&self.asm).is_empty() && !c.instrs(&self.asm).is_empty() {
-                                cust.under = Some((b.clone(), c.clone()));
+                            if !b.nodes().is_empty() && !c.nodes().is_empty() {
+                                under = Some((b.clone(), c.clone()));
                             }
                         }
                         [a, b, c, d] => {
                             f = a.clone();
-                            if !b.instrs(&self.asm).is_empty() {
-                                if !a.signature().is_compatible_with(b.signature().inverse()) {
+                            if !b.nodes().is_empty() {
+                                if !a.sig.is_compatible_with(b.sig.inverse()) {
                                     self.emit_diagnostic(
                                         format!(
                                             "First and second functions must have opposite signatures, \
                                             but their signatures are {} and {}",
-                                            a.signature(),
-                                            b.signature()
+                                            a.sig,
+                                            b.sig
                                         ),
                                         DiagnosticKind::Warning,
                                         modifier.span.clone(),
                                     );
                                 }
-                                cust.un = Some(b.clone());
+                                un = Some(b.clone());
                             }
-                            if !d.instrs(&self.asm).is_empty() {
-                                if !c.instrs(&self.asm).is_empty() {
-                                    cust.under = Some((c.clone(), d.clone()));
+                            if !d.nodes().is_empty() {
+                                if !c.nodes().is_empty() {
+                                    under = Some((c.clone(), d.clone()));
                                 }
-                                if a.signature().anti().is_some_and(|sig| sig == d.signature()) {
-                                    cust.anti = Some(d.clone());
+                                if a.sig.anti().is_some_and(|sig| sig == d.sig) {
+                                    anti = Some(d.clone());
                                 }
                             }
                         }
                         [a, b, c, d, e] => {
                             f = a.clone();
-                            if !b.instrs(&self.asm).is_empty() {
-                                if !a.signature().is_compatible_with(b.signature().inverse()) {
+                            if !b.nodes().is_empty() {
+                                if !a.sig.is_compatible_with(b.sig.inverse()) {
                                     self.emit_diagnostic(
                                         format!(
                                             "First and second functions must have opposite signatures, \
                                             but their signatures are {} and {}",
-                                            a.signature(),
-                                            b.signature()
+                                            a.sig,
+                                            b.sig
                                         ),
                                         DiagnosticKind::Warning,
                                         modifier.span.clone(),
                                     );
                                 }
-                                cust.un = Some(b.clone());
+                                un = Some(b.clone());
                             }
-                            if !c.instrs(&self.asm).is_empty() && !d.instrs(&self.asm).is_empty() {
-                                cust.under = Some((c.clone(), d.clone()));
+                            if !c.nodes().is_empty() && !d.nodes().is_empty() {
+                                under = Some((c.clone(), d.clone()));
                             }
-                            if !e.instrs(&self.asm).is_empty() {
-                                match a.signature().anti() {
+                            if !e.nodes().is_empty() {
+                                match a.sig.anti() {
                                     None => self.emit_diagnostic(
                                         format!(
                                             "An anti inverse is specified, but the first \
                                             function's signature {} cannot have an anti inverse",
-                                            a.signature()
+                                            a.sig
                                         ),
                                         DiagnosticKind::Warning,
                                         modifier.span.clone(),
                                     ),
-                                    Some(sig) if sig != e.signature() => {
+                                    Some(sig) if sig != e.sig => {
                                         self.emit_diagnostic(
                                             format!(
                                                 "The first function's signature implies an \
                                                 anti inverse with signature {sig}, but the \
                                                 fifth function's signature is {}",
-                                                e.signature()
+                                                e.sig
                                             ),
                                             DiagnosticKind::Warning,
                                             modifier.span.clone(),
@@ -241,7 +241,7 @@ impl Compiler {
                                     }
                                     Some(_) => {}
                                 }
-                                cust.anti = Some(e.clone());
+                                anti = Some(e.clone());
                             }
                         }
                         funcs => {
@@ -255,18 +255,24 @@ impl Compiler {
                             ))
                         }
                     };
-                    let sig = f.signature();
+                    let sig = f.sig;
                     let spandex = self.add_span(modifier.span.clone());
-                    let instrs = eco_vec![Instr::PushFunc(f), Instr::CustomInverse(cust, spandex)];
+                    let cust = Node::CustomInverse {
+                        un,
+                        under,
+                        anti,
+                        span: spandex,
+                    };
+                    let nodes = eco_vec![Node::Func(f), cust];
                     if call {
-                        self.push_all_instrs(instrs);
+                        self.push_all_nodes(nodes);
                     } else {
                         let func = self.make_function(
                             FunctionId::Anonymous(modifier.span.clone()),
                             sig,
-                            instrs.into(),
+                            nodes.into(),
                         );
-                        self.push_instr(Instr::PushFunc(func));
+                        self.push_node(Node::Func(func));
                     }
                     Ok(true)
                 }
@@ -418,24 +424,24 @@ impl Compiler {
             let span = self.add_span(modified.modifier.span.clone());
             match prim {
                 Primitive::Rows if n == 0 => {
-                    before = eco_vec![Instr::Prim(Primitive::Fix, span),];
-                    after = eco_vec![Instr::ImplPrim(ImplPrimitive::UndoFix, span),];
+                    before = eco_vec![Node::Instr(Instr::Prim(Primitive::Fix, span)),];
+                    after = eco_vec![Node::Instr(Instr::ImplPrim(ImplPrimitive::UndoFix, span)),];
                 }
                 Primitive::Rows => {
                     let stack = TempStack::Under;
                     let count = 2;
                     let n = -(n as i32);
                     before = eco_vec![
-                        Instr::Prim(Primitive::Dup, span),
-                        Instr::Prim(Primitive::Shape, span),
-                        Instr::push(n),
-                        Instr::PushTemp { stack, count, span },
-                        Instr::push(n),
-                        Instr::Prim(Primitive::Rerank, span),
+                        Node::Instr(Instr::Prim(Primitive::Dup, span)),
+                        Node::Instr(Instr::Prim(Primitive::Shape, span)),
+                        Node::Instr(Instr::push(n)),
+                        Node::Instr(Instr::PushTemp { stack, count, span }),
+                        Node::Instr(Instr::push(n)),
+                        Node::Instr(Instr::Prim(Primitive::Rerank, span)),
                     ];
                     after = eco_vec![
-                        Instr::PopTemp { stack, count, span },
-                        Instr::ImplPrim(ImplPrimitive::UndoRerank, span)
+                        Node::Instr(Instr::PopTemp { stack, count, span }),
+                        Node::Instr(Instr::ImplPrim(ImplPrimitive::UndoRerank, span))
                     ];
                 }
                 Primitive::Each => {
@@ -443,19 +449,19 @@ impl Compiler {
                     let count = 2;
                     prim = Primitive::Rows;
                     before = eco_vec![
-                        Instr::Prim(Primitive::Dup, span),
-                        Instr::Prim(Primitive::Shape, span),
-                        Instr::push(n),
-                        Instr::PushTemp { stack, count, span },
-                        Instr::push(n),
-                        Instr::Prim(Primitive::Rerank, span),
+                        Node::Instr(Instr::Prim(Primitive::Dup, span)),
+                        Node::Instr(Instr::Prim(Primitive::Shape, span)),
+                        Node::Instr(Instr::push(n)),
+                        Node::Instr(Instr::PushTemp { stack, count, span }),
+                        Node::Instr(Instr::push(n)),
+                        Node::Instr(Instr::Prim(Primitive::Rerank, span)),
                     ];
                     after = eco_vec![
-                        Instr::PopTemp { stack, count, span },
-                        Instr::ImplPrim(ImplPrimitive::UndoRerank, span)
+                        Node::Instr(Instr::PopTemp { stack, count, span }),
+                        Node::Instr(Instr::ImplPrim(ImplPrimitive::UndoRerank, span))
                     ];
                 }
-                Primitive::Tuples => before = eco_vec![Instr::push(n)],
+                Primitive::Tuples => before = eco_vec![Node::Instr(Instr::push(n))],
                 _ => {}
             }
         }
@@ -466,16 +472,16 @@ impl Compiler {
         if !call {
             self.new_functions.push(NewFunction::default());
         }
-        self.push_all_instrs(before);
-        self.push_all_instrs(operands_func);
+        self.push_all_nodes(before);
+        self.push_all_nodes(operands_func);
         self.primitive(prim, modified.modifier.span.clone(), true)?;
-        self.push_all_instrs(after);
+        self.push_all_nodes(after);
         if !call {
             let new_func = self.new_functions.pop().unwrap();
-            let sig = self.sig_of(&new_func.instrs, &modified.modifier.span)?;
+            let sig = self.sig_of(&new_func.nodes, &modified.modifier.span)?;
             let func =
                 self.make_function(modified.modifier.span.clone().clone().into(), sig, new_func);
-            self.push_instr(Instr::PushFunc(func));
+            self.push_node(Node::Func(func));
         }
 
         Ok(())
@@ -533,28 +539,28 @@ impl Compiler {
         self.handle_primitive_deprecation(prim, &modified.modifier.span);
 
         macro_rules! finish {
-            ($instrs:expr, $sig:expr) => {{
+            ($nodes:expr, $sig:expr) => {{
                 if call {
-                    self.push_all_instrs($instrs);
+                    self.push_all_nodes($nodes);
                 } else {
                     let func = self.make_function(
                         FunctionId::Anonymous(modified.modifier.span.clone()),
                         $sig,
-                        $instrs.into(),
+                        $nodes.into(),
                     );
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }};
         }
         /// Modify instructions for `on`, and return the new signature
-        fn on(instrs: &mut EcoVec<Instr>, sig: Signature, span: usize) -> Signature {
+        fn on(nodes: &mut EcoVec<Node>, sig: Signature, span: usize) -> Signature {
             let save = if sig.args == 0 {
-                Instr::push_inline(1, span)
+                Node::Instr(Instr::push_inline(1, span))
             } else {
-                Instr::copy_inline(span)
+                Node::Instr(Instr::copy_inline(span))
             };
-            instrs.insert(0, save);
-            instrs.push(Instr::pop_inline(1, span));
+            nodes.insert(0, save);
+            nodes.push(Node::Instr(Instr::pop_inline(1, span)));
             Signature::new(sig.args.max(1), sig.outputs + 1)
         }
         match prim {
@@ -562,13 +568,13 @@ impl Compiler {
                 // Compile operands
                 let (mut new_func, mut sig) =
                     self.compile_operand_word(modified.operands[0].clone())?;
-                let instrs = &mut new_func.instrs;
+                let nodes = &mut new_func.nodes;
                 // Dip (|1 …) . diagnostic
                 if sig.args == 1 {
                     match prim {
                         Dip => {
-                            if let Some(Instr::Prim(Dup, dup_span)) =
-                                self.new_functions.last().and_then(|nf| nf.instrs.last())
+                            if let Some(Node::Instr(Instr::Prim(Dup, dup_span))) =
+                                self.new_functions.last().and_then(|nf| nf.nodes.last())
                             {
                                 if let Span::Code(dup_span) = self.get_span(*dup_span) {
                                     let span = modified.modifier.span.clone().merge(dup_span);
@@ -587,69 +593,70 @@ impl Compiler {
                 let span = self.add_span(modified.modifier.span.clone());
                 let sig = match prim {
                     Dip => {
-                        instrs.insert(0, Instr::push_inline(1, span));
-                        instrs.push(Instr::pop_inline(1, span));
+                        nodes.insert(0, Node::Instr(Instr::push_inline(1, span)));
+                        nodes.push(Node::Instr(Instr::pop_inline(1, span)));
                         Signature::new(sig.args + 1, sig.outputs + 1)
                     }
                     Gap => {
-                        instrs.insert(0, Instr::Prim(Pop, span));
+                        nodes.insert(0, Node::Instr(Instr::Prim(Pop, span)));
                         Signature::new(sig.args + 1, sig.outputs)
                     }
-                    On => on(instrs, sig, span),
+                    On => on(nodes, sig, span),
                     By => {
                         if sig.args > 0 {
                             let mut i = 0;
                             if sig.args > 1 {
-                                instrs.insert(i, Instr::push_inline(sig.args - 1, span));
+                                nodes
+                                    .insert(i, Node::Instr(Instr::push_inline(sig.args - 1, span)));
                                 i += 1;
                             }
-                            instrs.insert(i, Instr::Prim(Dup, span));
+                            nodes.insert(i, Node::Instr(Instr::Prim(Dup, span)));
                             i += 1;
                             if sig.args > 1 {
-                                instrs.insert(i, Instr::pop_inline(sig.args - 1, span));
+                                nodes.insert(i, Node::Instr(Instr::pop_inline(sig.args - 1, span)));
                             }
                         } else {
-                            instrs.insert(0, Instr::TouchStack { count: 1, span })
+                            nodes.insert(0, Node::Instr(Instr::TouchStack { count: 1, span }))
                         }
                         Signature::new(sig.args.max(1), sig.outputs + 1)
                     }
                     With => {
                         if sig.args < 2 {
-                            instrs.insert(0, Instr::TouchStack { count: 2, span });
+                            nodes.insert(0, Node::Instr(Instr::TouchStack { count: 2, span }));
                             sig.outputs += 2 - sig.args;
                             sig.args = 2;
                         }
                         let mut prefix = eco_vec![
-                            Instr::push_inline(sig.args - 1, span),
-                            Instr::Prim(Dup, span),
-                            Instr::pop_inline(sig.args - 1, span)
+                            Node::Instr(Instr::push_inline(sig.args - 1, span)),
+                            Node::Instr(Instr::Prim(Dup, span)),
+                            Node::Instr(Instr::pop_inline(sig.args - 1, span))
                         ];
                         if sig.outputs >= 2 {
-                            instrs.push(Instr::push_inline(sig.outputs - 1, span));
+                            nodes.push(Node::Instr(Instr::push_inline(sig.outputs - 1, span)));
                             for _ in 0..sig.outputs - 1 {
-                                instrs.push(Instr::Prim(Flip, span));
-                                instrs.push(Instr::pop_inline(1, span));
+                                nodes.push(Node::Instr(Instr::Prim(Flip, span)));
+                                nodes.push(Node::Instr(Instr::pop_inline(1, span)));
                             }
                         }
-                        instrs.push(Instr::Prim(Flip, span));
-                        prefix.extend(take(instrs));
-                        *instrs = prefix;
+                        nodes.push(Node::Instr(Instr::Prim(Flip, span)));
+                        prefix.extend(take(nodes));
+                        *nodes = prefix;
                         Signature::new(sig.args.max(1), sig.outputs + 1)
                     }
                     Off => {
                         if sig.args < 2 {
-                            instrs.insert(0, Instr::TouchStack { count: 2, span });
+                            nodes.insert(0, Node::Instr(Instr::TouchStack { count: 2, span }));
                             sig.outputs += 2 - sig.args;
                             sig.args = 2;
                         }
-                        let mut prefix = eco_vec![Instr::Prim(Dup, span)];
+                        let mut prefix = eco_vec![Node::Instr(Instr::Prim(Dup, span))];
                         for _ in 0..sig.args - 1 {
-                            prefix.push(Instr::push_inline(1, span));
-                            prefix.push(Instr::Prim(Flip, span));
+                            prefix.push(Node::Instr(Instr::push_inline(1, span)));
+                            prefix.push(Node::Instr(Instr::Prim(Flip, span)));
                         }
-                        prefix.push(Instr::pop_inline(sig.args - 1, span));
-                        prefix.extend(take(instrs));
-                        *instrs = prefix;
+                        prefix.push(Node::Instr(Instr::pop_inline(sig.args - 1, span)));
+                        prefix.extend(take(nodes));
+                        *nodes = prefix;
                         Signature::new(sig.args.max(1), sig.outputs + 1)
                     }
                     Above => {
@@ -657,15 +664,15 @@ impl Compiler {
                             sig.args += 1;
                             sig.outputs += 1;
                         }
-                        instrs.insert(
+                        nodes.insert(
                             0,
-                            Instr::CopyToTemp {
+                            Node::Instr(Instr::CopyToTemp {
                                 stack: TempStack::Inline,
                                 count: sig.args,
                                 span,
-                            },
+                            }),
                         );
-                        instrs.push(Instr::pop_inline(sig.args, span));
+                        nodes.push(Node::Instr(Instr::pop_inline(sig.args, span)));
                         Signature::new(sig.args, sig.outputs + sig.args)
                     }
                     Below => {
@@ -673,25 +680,25 @@ impl Compiler {
                             sig.args += 1;
                             sig.outputs += 1;
                         }
-                        instrs.insert(
+                        nodes.insert(
                             0,
-                            Instr::CopyToTemp {
+                            Node::Instr(Instr::CopyToTemp {
                                 stack: TempStack::Inline,
                                 count: sig.args,
                                 span,
-                            },
+                            }),
                         );
-                        instrs.insert(1, Instr::pop_inline(sig.args, span));
+                        nodes.insert(1, Node::Instr(Instr::pop_inline(sig.args, span)));
                         Signature::new(sig.args, sig.outputs + sig.args)
                     }
                     _ => unreachable!(),
                 };
                 if call {
-                    self.push_all_instrs(new_func);
+                    self.push_all_nodes(new_func);
                 } else {
                     let func =
                         self.make_function(modified.modifier.span.clone().into(), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }),
             Backward => wrap!(|| {
@@ -708,14 +715,16 @@ impl Compiler {
                     );
                 }
                 let spandex = self.add_span(modified.modifier.span.clone());
-                new_func.instrs.insert(0, Instr::Prim(Flip, spandex));
-                let sig = self.sig_of(&new_func.instrs, &modified.modifier.span)?;
+                new_func
+                    .nodes
+                    .insert(0, Node::Instr(Instr::Prim(Flip, spandex)));
+                let sig = self.sig_of(&new_func.nodes, &modified.modifier.span)?;
                 if call {
-                    self.push_all_instrs(new_func);
+                    self.push_all_nodes(new_func);
                 } else {
                     let func =
                         self.make_function(modified.modifier.span.clone().into(), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }),
             Fork => wrap!(|| {
@@ -732,46 +741,46 @@ impl Compiler {
                 let (a_nf, a_sig) = self.compile_operand_word(first_op)?;
                 let (b_nf, b_sig) = self.compile_operand_word(operands.next().unwrap())?;
                 let span = self.add_span(modified.modifier.span.clone());
-                let mut instrs = EcoVec::new();
+                let mut nodes = EcoVec::new();
                 let mut flags = FunctionFlags::default();
                 flags |= a_nf.flags;
                 flags |= b_nf.flags;
                 if a_sig.args > 0 {
-                    instrs.push(Instr::CopyToTemp {
+                    nodes.push(Node::Instr(Instr::CopyToTemp {
                         stack: TempStack::Inline,
                         count: a_sig.args,
                         span,
-                    });
+                    }));
                 }
                 if a_sig.args > b_sig.args {
                     let diff = a_sig.args - b_sig.args;
                     if b_sig.args > 0 {
-                        instrs.push(Instr::PushTemp {
+                        nodes.push(Node::Instr(Instr::PushTemp {
                             stack: TempStack::Inline,
                             count: b_sig.args,
                             span,
-                        });
+                        }));
                     }
                     for _ in 0..diff {
-                        instrs.push(Instr::Prim(Pop, span));
+                        nodes.push(Node::Instr(Instr::Prim(Pop, span)));
                     }
                     if b_sig.args > 0 {
-                        instrs.push(Instr::pop_inline(b_sig.args, span));
+                        nodes.push(Node::Instr(Instr::pop_inline(b_sig.args, span)));
                     }
                 }
-                instrs.extend(b_nf.instrs);
+                nodes.extend(b_nf.nodes);
                 if a_sig.args > 0 {
-                    instrs.push(Instr::pop_inline(a_sig.args, span));
+                    nodes.push(Node::Instr(Instr::pop_inline(a_sig.args, span)));
                 }
-                instrs.extend(a_nf.instrs);
+                nodes.extend(a_nf.nodes);
                 let sig = Signature::new(a_sig.args.max(b_sig.args), a_sig.outputs + b_sig.outputs);
-                let new_func = NewFunction { instrs, flags };
+                let new_func = NewFunction { nodes, flags };
                 if call {
-                    self.push_all_instrs(new_func);
+                    self.push_all_nodes(new_func);
                 } else {
                     let func =
                         self.make_function(modified.modifier.span.clone().into(), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }),
             Bracket => wrap!(|| {
@@ -782,25 +791,25 @@ impl Compiler {
                 let mut flags = FunctionFlags::default();
                 flags |= a_nf.flags;
                 flags |= b_nf.flags;
-                let mut instrs = eco_vec![Instr::PushTemp {
+                let mut nodes = eco_vec![Node::Instr(Instr::PushTemp {
                     stack: TempStack::Inline,
                     count: a_sig.args,
                     span,
-                }];
-                instrs.extend(b_nf.instrs);
-                instrs.push(Instr::pop_inline(a_sig.args, span));
-                instrs.extend(a_nf.instrs);
+                })];
+                nodes.extend(b_nf.nodes);
+                nodes.push(Node::Instr(Instr::pop_inline(a_sig.args, span)));
+                nodes.extend(a_nf.nodes);
                 let sig = Signature::new(a_sig.args + b_sig.args, a_sig.outputs + b_sig.outputs);
-                let new_func = NewFunction { instrs, flags };
+                let new_func = NewFunction { nodes, flags };
                 if call {
-                    self.push_all_instrs(new_func);
+                    self.push_all_nodes(new_func);
                 } else {
                     let func = self.make_function(
                         FunctionId::Anonymous(modified.modifier.span.clone()),
                         sig,
                         new_func,
                     );
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }),
             Repeat => wrap!(|| {
@@ -811,13 +820,13 @@ impl Compiler {
                 let (new_func, sig) = self.compile_operand_word(operand)?;
                 let flags = new_func.flags;
                 let spandex = self.add_span(modified.modifier.span.clone());
-                let instrs = 'instrs: {
+                let nodes = 'nodes: {
                     if let Some(curr) = self.new_functions.last_mut() {
-                        if let [.., Instr::Push(val)] = curr.instrs.as_slice() {
+                        if let [.., Node::Instr(Instr::Push(val))] = curr.nodes.as_slice() {
                             if let Ok(n) = val.as_int(&IgnoreError, "") {
-                                if n >= 0 && n.unsigned_abs() * new_func.instrs.len() <= 20 {
-                                    curr.instrs.pop();
-                                    break 'instrs repeat(&new_func.instrs)
+                                if n >= 0 && n.unsigned_abs() * new_func.nodes.len() <= 20 {
+                                    curr.nodes.pop();
+                                    break 'nodes repeat(&new_func.nodes)
                                         .take(n.unsigned_abs())
                                         .flatten()
                                         .cloned()
@@ -826,9 +835,9 @@ impl Compiler {
                             }
                         }
                     }
-                    if let Some((inverse, inv_sig)) = invert_instrs(&new_func.instrs, self)
+                    if let Some((inverse, inv_sig)) = invert_nodes(&new_func.nodes, self)
                         .ok()
-                        .and_then(|inv| instrs_signature(&inv).ok().map(|sig| (inv, sig)))
+                        .and_then(|inv| nodes_signature(&inv).ok().map(|sig| (inv, sig)))
                         .filter(|(_, inv_sig)| sig.is_compatible_with(*inv_sig))
                     {
                         // If an inverse for repeat's function exists we use a special
@@ -837,29 +846,29 @@ impl Compiler {
                         let func = self.make_function(id, sig, new_func);
                         let inv_id = FunctionId::Anonymous(modified.modifier.span.clone());
                         let inv_new_func = NewFunction {
-                            instrs: inverse,
+                            nodes: inverse,
                             flags,
                         };
                         let inv = self.make_function(inv_id, inv_sig, inv_new_func);
-                        eco_vec![
-                            Instr::PushFunc(inv),
-                            Instr::PushFunc(func),
-                            Instr::ImplPrim(ImplPrimitive::RepeatWithInverse, spandex)
-                        ]
+                        eco_vec![Node::ImplMod(
+                            ImplPrimitive::RepeatWithInverse,
+                            eco_vec![func, inv],
+                            spandex
+                        )]
                     } else {
                         let id = FunctionId::Anonymous(modified.modifier.span.clone());
                         let func = self.make_function(id, sig, new_func);
-                        eco_vec![Instr::PushFunc(func), Instr::Prim(Repeat, spandex)]
+                        eco_vec![Node::Mod(Repeat, eco_vec![func], spandex)]
                     }
                 };
-                let new_func = NewFunction { instrs, flags };
+                let new_func = NewFunction { nodes, flags };
                 if call {
-                    self.push_all_instrs(new_func);
+                    self.push_all_nodes(new_func);
                 } else {
-                    let sig = self.sig_of(&new_func.instrs, &modified.modifier.span)?;
+                    let sig = self.sig_of(&new_func.nodes, &modified.modifier.span)?;
                     let func =
                         self.make_function(modified.modifier.span.clone().into(), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }),
             Un if !self.in_inverse => wrap!(|| {
@@ -873,10 +882,10 @@ impl Compiler {
                 let (mut new_func, _) = f_res?;
 
                 self.add_span(span.clone());
-                match invert_instrs(&new_func.instrs, self) {
+                match invert_nodes(&new_func.nodes, self) {
                     Ok(inverted) => {
                         let sig = self.sig_of(&inverted, &span)?;
-                        new_func.instrs = inverted;
+                        new_func.nodes = inverted;
                         finish!(new_func, sig);
                     }
                     Err(e) => return Err(self.fatal_error(span, e)),
@@ -904,10 +913,10 @@ impl Compiler {
                     );
                 }
 
-                match anti_instrs(&new_func.instrs, self) {
+                match anti_nodes(&new_func.nodes, self) {
                     Ok(inverted) => {
                         let sig = self.sig_of(&inverted, &span)?;
-                        new_func.instrs = inverted;
+                        new_func.nodes = inverted;
                         finish!(new_func, sig);
                     }
                     Err(e) => return Err(self.fatal_error(span, e)),
@@ -929,7 +938,7 @@ impl Compiler {
                 let (f_new_func, f_new_sig) = f_res?;
 
                 // Under pop diagnostic
-                if let [Instr::Prim(Pop, _)] = f_new_func.instrs.as_slice() {
+                if let [Node::Instr(Instr::Prim(Pop, _))] = f_new_func.nodes.as_slice() {
                     self.emit_diagnostic(
                         format!("Prefer {} over `⍜◌` for clarity", Dip.format()),
                         DiagnosticKind::Style,
@@ -937,39 +946,39 @@ impl Compiler {
                     );
                 }
 
-                match under_instrs(&f_new_func.instrs, g_sig, self) {
+                match under_nodes(&f_new_func.nodes, g_sig, self) {
                     Ok((f_before, f_after)) => {
-                        let mut instrs = f_before;
-                        instrs.extend(g_new_func.instrs.iter().cloned());
-                        instrs.extend(f_after);
+                        let mut nodes = f_before;
+                        nodes.extend(g_new_func.nodes.iter().cloned());
+                        nodes.extend(f_after);
 
                         // Register undered functions
                         let span = self.add_span(modified.modifier.span.clone());
                         self.undered_funcs.insert(
-                            instrs.clone(),
+                            lift_funcs(&nodes, &self.asm),
                             UnderedFunctions {
-                                f: f_new_func.instrs,
+                                f: f_new_func.nodes,
                                 f_sig: f_new_sig,
-                                g: g_new_func.instrs,
+                                g: g_new_func.nodes,
                                 g_sig,
                                 span,
                             },
                         );
 
                         let new_func = NewFunction {
-                            instrs,
+                            nodes,
                             flags: f_new_func.flags | g_new_func.flags,
                         };
                         if call {
-                            self.push_all_instrs(new_func);
+                            self.push_all_nodes(new_func);
                         } else {
-                            let sig = self.sig_of(&new_func.instrs, &modified.modifier.span)?;
+                            let sig = self.sig_of(&new_func.nodes, &modified.modifier.span)?;
                             let func = self.make_function(
                                 modified.modifier.span.clone().into(),
                                 sig,
                                 new_func,
                             );
-                            self.push_instr(Instr::PushFunc(func));
+                            self.push_node(Node::Func(func));
                         }
                     }
                     Err(e) => return Err(self.fatal_error(f_span, e)),
@@ -981,16 +990,18 @@ impl Compiler {
                 let spandex = self.add_span(span.clone());
                 let (func, sig) = self.compile_operand_word(operand)?;
                 let func = self.make_function(span.clone().into(), sig, func);
-                let cust = CustomInverse {
+                let cust = Node::CustomInverse {
+                    un: None,
                     under: Some((func.clone(), func.clone())),
-                    ..Default::default()
+                    anti: None,
+                    span: spandex,
                 };
-                let instrs = eco_vec![Instr::PushFunc(func), Instr::CustomInverse(cust, spandex)];
+                let nodes = eco_vec![Node::Func(func), cust];
                 if call {
-                    self.push_all_instrs(instrs);
+                    self.push_all_nodes(nodes);
                 } else {
-                    let func = self.make_function(FunctionId::Anonymous(span), sig, instrs.into());
-                    self.push_instr(Instr::PushFunc(func));
+                    let func = self.make_function(FunctionId::Anonymous(span), sig, nodes.into());
+                    self.push_node(Node::Func(func));
                 }
             }),
             SetInverse => {
@@ -1023,11 +1034,11 @@ impl Compiler {
                     self.make_function(inverse_span.into(), inverse_sig, inverse_instrs);
                 let spandex = self.add_span(modified.modifier.span.clone());
                 finish!(
-                    eco_vec![
-                        Instr::PushFunc(inverse_func),
-                        Instr::PushFunc(normal_func),
-                        Instr::Prim(Primitive::SetInverse, spandex),
-                    ],
+                    eco_vec![Node::Mod(
+                        Primitive::SetInverse,
+                        eco_vec![normal_func, inverse_func],
+                        spandex
+                    )],
                     normal_sig
                 )
             }
@@ -1047,23 +1058,23 @@ impl Compiler {
                     Ordering::Less => {
                         try_sig.args += handler_sig.outputs - try_sig.outputs;
                         try_sig.outputs = handler_sig.outputs;
-                        try_new_func.instrs.insert(
+                        try_new_func.nodes.insert(
                             0,
-                            Instr::TouchStack {
+                            Node::Instr(Instr::TouchStack {
                                 count: try_sig.args,
                                 span,
-                            },
+                            }),
                         );
                     }
                     Ordering::Greater => {
                         handler_sig.args += try_sig.outputs - handler_sig.outputs;
                         handler_sig.outputs = try_sig.outputs;
-                        handler_new_func.instrs.insert(
+                        handler_new_func.nodes.insert(
                             0,
-                            Instr::TouchStack {
+                            Node::Instr(Instr::TouchStack {
                                 count: handler_sig.args,
                                 span,
-                            },
+                            }),
                         );
                     }
                 }
@@ -1085,11 +1096,11 @@ impl Compiler {
                 let handler_func =
                     self.make_function(handler_span.into(), handler_sig, handler_new_func);
                 finish!(
-                    eco_vec![
-                        Instr::PushFunc(handler_func),
-                        Instr::PushFunc(tried_func),
-                        Instr::Prim(Primitive::Try, span),
-                    ],
+                    eco_vec![Node::Mod(
+                        Primitive::Try,
+                        eco_vec![tried_func, handler_func],
+                        span
+                    )],
                     try_sig
                 );
             }),
@@ -1102,43 +1113,45 @@ impl Compiler {
                 let n = subscript.unwrap_or(2);
                 let operand = modified.code_operands().next().unwrap().clone();
                 let (mut new_func, sig) = self.compile_operand_word(operand)?;
-                if let [Instr::Prim(Trace, span)] = new_func.instrs.as_slice() {
+                if let [Node::Instr(Instr::Prim(Trace, span))] = new_func.nodes.as_slice() {
                     finish!(
-                        eco_vec![Instr::ImplPrim(
+                        eco_vec![Node::Instr(Instr::ImplPrim(
                             ImplPrimitive::TraceN {
                                 n,
                                 inverse: false,
                                 stack_sub: false
                             },
                             *span
-                        )],
+                        ))],
                         Signature::new(n, n)
                     )
                 } else {
                     let span = self.add_span(modified.modifier.span.clone());
-                    let instrs = take(&mut new_func.instrs);
+                    let nodes = take(&mut new_func.nodes);
                     for _ in 0..n.saturating_sub(1) {
-                        new_func.instrs.push(Instr::PushTemp {
+                        new_func.nodes.push(Node::Instr(Instr::PushTemp {
                             stack: TempStack::Inline,
                             count: sig.args,
                             span,
-                        });
+                        }));
                     }
                     for _ in 0..n.saturating_sub(1) {
-                        new_func.instrs.extend(instrs.iter().cloned());
-                        new_func.instrs.push(Instr::pop_inline(sig.args, span));
+                        new_func.nodes.extend(nodes.iter().cloned());
+                        new_func
+                            .nodes
+                            .push(Node::Instr(Instr::pop_inline(sig.args, span)));
                     }
-                    new_func.instrs.extend(instrs);
+                    new_func.nodes.extend(nodes);
                     let sig = Signature::new(sig.args * n, sig.outputs * n);
                     if call {
-                        self.push_all_instrs(new_func);
+                        self.push_all_nodes(new_func);
                     } else {
                         let func = self.make_function(
                             modified.modifier.span.clone().into(),
                             sig,
                             new_func,
                         );
-                        self.push_instr(Instr::PushFunc(func));
+                        self.push_node(Node::Func(func));
                     }
                 }
             }),
@@ -1177,16 +1190,16 @@ impl Compiler {
                     fill_sig,
                     fill_instrs,
                 );
-                self.push_instr(Instr::PushFunc(fill_func));
+                self.push_node(Node::Func(fill_func));
 
                 let span = self.add_span(modified.modifier.span.clone());
                 self.push_instr(Instr::Prim(Primitive::Fill, span));
                 if !call {
                     let new_func = self.new_functions.pop().unwrap();
-                    let sig = self.sig_of(&new_func.instrs, &modified.modifier.span)?;
+                    let sig = self.sig_of(&new_func.nodes, &modified.modifier.span)?;
                     let func =
                         self.make_function(modified.modifier.span.clone().into(), sig, new_func);
-                    self.push_instr(Instr::PushFunc(func));
+                    self.push_node(Node::Func(func));
                 }
             }),
             Comptime => {
@@ -1210,11 +1223,12 @@ impl Compiler {
                 let content_func =
                     self.make_function(m.modifier.span.clone().into(), sig, content_instrs);
                 let span = self.add_span(modified.modifier.span.clone());
-                let instrs = eco_vec![
-                    Instr::PushFunc(content_func),
-                    Instr::ImplPrim(ImplPrimitive::ReduceContent, span),
-                ];
-                finish!(instrs, Signature::new(1, 1));
+                let nodes = eco_vec![Node::ImplMod(
+                    ImplPrimitive::ReduceContent,
+                    eco_vec![content_func],
+                    span
+                )];
+                finish!(nodes, Signature::new(1, 1));
                 Ok(true)
             }),
             Each => wrap!(|| -> UiuaResult<bool> {
@@ -1226,9 +1240,8 @@ impl Compiler {
                 let (new_func, sig) = self.compile_operand_word(operand)?;
                 let span = modified.modifier.span.clone();
                 self.emit_diagnostic(
-                    if let Some((prim, _)) =
-                        instrs_as_flipped_primitive(&new_func.instrs, &self.asm)
-                            .filter(|(prim, _)| prim.class().is_pervasive())
+                    if let Some((prim, _)) = nodes_as_flipped_primitive(&new_func.nodes, &self.asm)
+                        .filter(|(prim, _)| prim.class().is_pervasive())
                     {
                         format!(
                             "{} is pervasive, so {} is redundant here.",
@@ -1282,18 +1295,18 @@ impl Compiler {
                 let span = self.add_span(modified.modifier.span.clone());
                 if sig.args > 0 {
                     if sig.args > 1 {
-                        prefix.push(Instr::push_inline(sig.args - 1, span));
+                        prefix.push(Node::Instr(Instr::push_inline(sig.args - 1, span)));
                         for _ in 0..sig.args - 1 {
                             prefix.extend([
-                                Instr::ImplPrim(ImplPrimitive::UnBox, span),
-                                Instr::pop_inline(1, span),
+                                Node::Instr(Instr::ImplPrim(ImplPrimitive::UnBox, span)),
+                                Node::Instr(Instr::pop_inline(1, span)),
                             ]);
                         }
                     }
-                    prefix.push(Instr::ImplPrim(ImplPrimitive::UnBox, span));
+                    prefix.push(Node::Instr(Instr::ImplPrim(ImplPrimitive::UnBox, span)));
                 }
-                prefix.extend(new_func.instrs);
-                new_func.instrs = prefix;
+                prefix.extend(new_func.nodes);
+                new_func.nodes = prefix;
                 finish!(new_func, sig);
             }),
             Fold => wrap!(|| {
@@ -1311,13 +1324,13 @@ impl Compiler {
                 }
                 let func = self.make_function(op_span.into(), sig, new_func);
                 let spandex = self.add_span(modified.modifier.span.clone());
-                let instrs = eco_vec![Instr::PushFunc(func), Instr::Prim(Primitive::Fold, spandex)];
-                finish!(instrs, sig);
+                let nodes = eco_vec![Node::Mod(Primitive::Fold, eco_vec![func], spandex)];
+                finish!(nodes, sig);
             }),
             Stringify => wrap!(|| {
                 let operand = modified.code_operands().next().unwrap();
                 let s = format_word(operand, &self.asm.inputs);
-                let instr = Instr::Push(s.into());
+                let instr = Node::Instr(Instr::Push(s.into()));
                 finish!(eco_vec![instr], Signature::new(0, 1));
             }),
             Quote => wrap!(|| {
@@ -1325,11 +1338,11 @@ impl Compiler {
                 self.new_functions.push(NewFunction::default());
                 self.do_comptime(prim, operand, &modified.modifier.span, true)?;
                 let new_func = self.new_functions.pop().unwrap();
-                let code: String = match new_func.instrs.as_slice() {
-                    [Instr::Push(Value::Char(chars))] if chars.rank() == 1 => {
+                let code: String = match new_func.nodes.as_slice() {
+                    [Node::Instr(Instr::Push(Value::Char(chars)))] if chars.rank() == 1 => {
                         chars.data.iter().collect()
                     }
-                    [Instr::Push(Value::Char(chars))] => {
+                    [Node::Instr(Instr::Push(Value::Char(chars)))] => {
                         return Err(self.fatal_error(
                             modified.modifier.span.clone(),
                             format!(
@@ -1339,7 +1352,7 @@ impl Compiler {
                             ),
                         ))
                     }
-                    [Instr::Push(value)] => {
+                    [Node::Instr(Instr::Push(value))] => {
                         return Err(self.fatal_error(
                             modified.modifier.span.clone(),
                             format!(
@@ -1361,11 +1374,11 @@ impl Compiler {
             Sig => wrap!(|| {
                 let operand = modified.code_operands().next().unwrap().clone();
                 let (_, sig) = self.compile_operand_word(operand)?;
-                let instrs = eco_vec![
-                    Instr::Push(sig.outputs.into()),
-                    Instr::Push(sig.args.into()),
+                let nodes = eco_vec![
+                    Node::Instr(Instr::Push(sig.outputs.into())),
+                    Node::Instr(Instr::Push(sig.args.into())),
                 ];
-                finish!(instrs, Signature::new(0, 2));
+                finish!(nodes, Signature::new(0, 2));
             }),
             Struct => self.struct_(
                 &modified.modifier.span,
@@ -1402,7 +1415,7 @@ impl Compiler {
                 ScopeKind::Temp(Some(mac_local)) if mac_local.macro_index == local.index => {
                     // Recursive
                     let new_func = self.compile_words(operands, false)?;
-                    self.push_all_instrs(new_func);
+                    self.push_all_nodes(new_func);
                     if let Some(sig) = mac.sig {
                         self.push_instr(Instr::CallGlobal {
                             index: mac_local.expansion_index,
@@ -1452,17 +1465,20 @@ impl Compiler {
                     })?;
                     new_func.flags |= mac.flags;
                     // Add
-                    let sig = self.sig_of(&new_func.instrs, &modifier_span)?;
+                    let sig = self.sig_of(&new_func.nodes, &modifier_span)?;
                     let mut func =
                         self.make_function(FunctionId::Macro(r.name.span), sig, new_func);
                     if mac.recursive {
                         func.flags |= FunctionFlags::RECURSIVE;
                     }
                     if let Some(macro_local) = macro_local {
+                        let func = func.lower(&mut self.asm);
                         self.asm.bindings.make_mut()[macro_local.expansion_index].kind =
                             BindingKind::Func(func.clone());
+                        self.push_instr(Instr::PushFunc(func));
+                    } else {
+                        self.push_node(Node::Func(func));
                     }
-                    self.push_instr(Instr::PushFunc(func));
                     if call {
                         let span = self.add_span(modifier_span);
                         self.push_instr(if mac.recursive {
@@ -1618,9 +1634,9 @@ impl Compiler {
             self.ident(r.name.value.clone(), r.name.span, true, r.in_macro_arg)?;
             if !call {
                 let new_func = self.new_functions.pop().unwrap();
-                let sig = self.sig_of(&new_func.instrs, &modifier_span)?;
+                let sig = self.sig_of(&new_func.nodes, &modifier_span)?;
                 let func = self.make_function(modifier_span.into(), sig, new_func);
-                self.push_instr(Instr::PushFunc(func));
+                self.push_node(Node::Func(func));
             }
         }
         self.macro_depth -= 1;
@@ -1642,7 +1658,7 @@ impl Compiler {
                     span: usize,
                     global_index: usize,
                     type_num: Option<u8>,
-                    init: Option<(EcoVec<Instr>, Signature)>,
+                    init: Option<(EcoVec<Node>, Signature)>,
                 }
                 let mut fields = Vec::new();
                 let module_name = if let ScopeKind::Module(name) = &self.scope.kind {
@@ -1675,11 +1691,14 @@ impl Compiler {
                                 continue;
                             }
                             let (_, sig, new_func) =
-                                self.compile_func_instrs(func, word.span.clone(), true)?;
-                            let mut instrs = new_func.instrs;
+                                self.compile_func_nodes(func, word.span.clone(), true)?;
+                            let mut nodes = new_func.nodes;
                             let sig = sig.unwrap();
                             if sig == (1, 0) {
-                                instrs.insert(0, Instr::Prim(Primitive::Dup, field.span));
+                                nodes.insert(
+                                    0,
+                                    Node::Instr(Instr::Prim(Primitive::Dup, field.span)),
+                                );
                             } else if sig.outputs != 1 {
                                 self.add_error(
                                     word.span,
@@ -1689,7 +1708,7 @@ impl Compiler {
                                     ),
                                 );
                             }
-                            field.init = Some((instrs, sig));
+                            field.init = Some((nodes, sig));
                         }
                         Word::Number(_, n) if !fields.is_empty() => {
                             let field = fields.last_mut().unwrap();
@@ -1741,28 +1760,32 @@ impl Compiler {
                     let name = &field.name;
                     let id = FunctionId::Named(name.clone());
                     let span = field.span;
-                    let mut instrs = eco_vec![Instr::push(i), Instr::Prim(Primitive::Pick, span)];
+                    let mut nodes = eco_vec![
+                        Node::Instr(Instr::push(i)),
+                        Node::Instr(Instr::Prim(Primitive::Pick, span))
+                    ];
                     if arr.boxes {
-                        instrs.push(Instr::ImplPrim(ImplPrimitive::UnBox, span));
-                        instrs.push(Instr::Label {
+                        nodes.push(Node::Instr(Instr::ImplPrim(ImplPrimitive::UnBox, span)));
+                        nodes.push(Node::Instr(Instr::Label {
                             label: name.clone(),
                             span,
                             remove: true,
-                        });
+                        }));
                     }
                     if let Some(type_num) = field.type_num {
-                        instrs.push(Instr::ValidateType {
+                        nodes.push(Node::Instr(Instr::ValidateType {
                             index: i + 1,
                             name: field.name.clone(),
                             type_num,
                             span,
-                        });
+                        }));
                     }
                     let new_func = NewFunction {
-                        instrs,
+                        nodes,
                         flags: FunctionFlags::TRACK_CALLER,
                     };
-                    let func = self.make_function(id, Signature::new(1, 1), new_func);
+                    let func = (self.make_function(id, Signature::new(1, 1), new_func))
+                        .lower(&mut self.asm);
                     let local = LocalName {
                         index: self.next_global,
                         public: true,
@@ -1799,7 +1822,7 @@ impl Compiler {
                 );
 
                 // Make constructor
-                let mut instrs = eco_vec![Instr::BeginArray];
+                let mut nodes = EcoVec::new();
                 let constructor_args: usize = fields
                     .iter()
                     .map(|f| f.init.as_ref().map(|(_, sig)| sig.args).unwrap_or(1))
@@ -1813,74 +1836,76 @@ impl Compiler {
                         for field in &fields {
                             if let Some((_, sig)) = field.init {
                                 if sig.args > 0 {
-                                    instrs.push(Instr::PushTemp {
+                                    nodes.push(Node::Instr(Instr::PushTemp {
                                         stack: TempStack::Inline,
                                         count: sig.args,
                                         span,
-                                    })
+                                    }))
                                 }
                             } else {
-                                instrs.push(Instr::PushTemp {
+                                nodes.push(Node::Instr(Instr::PushTemp {
                                     stack: TempStack::Inline,
                                     count: 1,
                                     span,
-                                });
+                                }));
                             }
                         }
                     } else if fields.len() > 1 {
                         for _ in 0..fields.len() - 1 {
-                            instrs.push(Instr::PushTemp {
+                            nodes.push(Node::Instr(Instr::PushTemp {
                                 stack: TempStack::Inline,
                                 count: 1,
                                 span,
-                            });
+                            }));
                         }
                     }
                     for (i, field) in fields.iter().rev().enumerate() {
                         if let Some((init, sig)) = &field.init {
                             if sig.args > 0 {
-                                instrs.push(Instr::pop_inline(sig.args, span));
+                                nodes.push(Node::Instr(Instr::pop_inline(sig.args, span)));
                             }
-                            instrs.extend_from_slice(init);
+                            nodes.extend_from_slice(init);
                         } else if i > 0 || has_inits {
-                            instrs.push(Instr::pop_inline(1, span));
+                            nodes.push(Node::Instr(Instr::pop_inline(1, span)));
                             self.code_meta
                                 .global_references
                                 .insert(field.name_span.clone(), field.global_index);
                         }
                         if let Some(type_num) = field.type_num {
-                            instrs.push(Instr::ValidateType {
+                            nodes.push(Node::Instr(Instr::ValidateType {
                                 index: i + 1,
                                 name: field.name.clone(),
                                 type_num,
                                 span,
-                            });
+                            }));
                         }
                         if arr.boxes {
-                            instrs.push(Instr::Label {
+                            nodes.push(Node::Instr(Instr::Label {
                                 label: field.name.clone(),
                                 span,
                                 remove: false,
-                            });
+                            }));
                         }
                     }
                 } else {
-                    instrs.push(Instr::TouchStack {
+                    nodes.push(Node::Instr(Instr::TouchStack {
                         count: fields.len(),
                         span,
-                    });
+                    }));
                 }
-                instrs.push(Instr::EndArray {
+                let nodes = eco_vec![Node::Array {
+                    inner: Box::new(Node::Run(nodes)),
                     boxed: arr.boxes,
                     span,
-                });
+                }];
                 let name = Ident::from("New");
                 let id = FunctionId::Named(name.clone());
                 let new_func = NewFunction {
-                    instrs,
+                    nodes,
                     flags: FunctionFlags::TRACK_CALLER,
                 };
-                let func = self.make_function(id, Signature::new(constructor_args, 1), new_func);
+                let func = (self.make_function(id, Signature::new(constructor_args, 1), new_func))
+                    .lower(&mut self.asm);
                 let local = LocalName {
                     index: self.next_global,
                     public: true,
@@ -1924,7 +1949,8 @@ impl Compiler {
                         comp.global_index(field.global_index, field.name_span.clone(), true);
                         let mut new_func = comp.new_functions.pop().unwrap();
                         new_func.flags |= FunctionFlags::TRACK_CALLER;
-                        let func = comp.make_function(id, Signature::new(0, 1), new_func);
+                        let func = (comp.make_function(id, Signature::new(0, 1), new_func))
+                            .lower(&mut comp.asm);
                         let local = LocalName {
                             index: comp.next_global,
                             public: true,
@@ -2151,12 +2177,13 @@ impl Compiler {
             }
         }
         instrs.make_mut().reverse();
+        let nodes = Node::from_instrs(&instrs, &self.asm).into_vec();
         if call {
-            self.push_all_instrs(instrs);
+            self.push_all_nodes(nodes);
         } else {
-            let sig = self.sig_of(&instrs, span)?;
-            let func = self.make_function(FunctionId::Anonymous(span.clone()), sig, instrs.into());
-            self.push_instr(Instr::PushFunc(func));
+            let sig = self.sig_of(&nodes, span)?;
+            let func = self.make_function(FunctionId::Anonymous(span.clone()), sig, nodes.into());
+            self.push_node(Node::Func(func));
         }
         Ok(())
     }
@@ -2182,8 +2209,8 @@ impl Compiler {
                 ),
             ));
         }
-        new_func.instrs = optimize_instrs(new_func.instrs, true, &comp.asm);
-        if let Some(index) = instrs_unbound_index(&new_func.instrs, &comp.asm) {
+        new_func.nodes = optimize_nodes(new_func.nodes, true, &comp.asm);
+        if let Some(index) = nodes_unbound_index(&new_func.nodes, &comp.asm) {
             let name = comp.scope.names.iter().find_map(|(ident, local)| {
                 if local.index == index {
                     Some(ident)
@@ -2198,12 +2225,7 @@ impl Compiler {
             };
             return Err(self.fatal_error(span.clone(), message));
         }
-        let start = comp.asm.instrs.len();
-        let len = new_func.instrs.len();
-        comp.asm.instrs.extend(new_func.instrs);
-        if len > 0 {
-            comp.asm.top_slices.push(FuncSlice { start, len });
-        }
+        comp.push_top_slice(new_func.nodes);
         let values = match comp.macro_env.run_asm(&comp.asm) {
             Ok(_) => comp.macro_env.take_stack(),
             Err(e) => {
@@ -2224,7 +2246,7 @@ impl Compiler {
             let new_func = self.new_functions.pop().unwrap();
             let sig = Signature::new(0, val_count);
             let func = self.make_function(FunctionId::Anonymous(span.clone()), sig, new_func);
-            self.push_instr(Instr::PushFunc(func));
+            self.push_node(Node::Func(func));
         }
         Ok(())
     }
@@ -2254,6 +2276,25 @@ impl Compiler {
     }
 }
 
+fn nodes_unbound_index(nodes: &[Node], asm: &Assembly) -> Option<usize> {
+    for node in nodes {
+        let index = match node {
+            Node::Run(nodes) => nodes_unbound_index(nodes, asm),
+            Node::Instr(instr) => instrs_unbound_index(slice::from_ref(instr), asm),
+            Node::Array { inner, .. } => nodes_unbound_index(inner.as_slice(), asm),
+            Node::Func(f) => nodes_unbound_index(f.nodes(), asm),
+            Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => {
+                (args.iter()).find_map(|f| nodes_unbound_index(f.nodes(), asm))
+            }
+            Node::CustomInverse { .. } => None,
+        };
+        if index.is_some() {
+            return index;
+        }
+    }
+    None
+}
+
 fn instrs_unbound_index(instrs: &[Instr], asm: &Assembly) -> Option<usize> {
     use Instr::*;
     for instr in instrs {
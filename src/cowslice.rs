@@ -5,6 +5,7 @@ use std::{
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
+    mem::size_of,
     ops::{Bound, Deref, RangeBounds},
     ptr,
 };
@@ -26,6 +27,8 @@ macro_rules! cowslice {
 pub(crate) use cowslice;
 use ecow::EcoVec;
 
+use crate::memory::count_alloc;
+
 /// The backing buffer for Uiua's arrays' data
 ///
 /// `CowSlice`s are reference-counted buffers that also have associated start and end indices.
@@ -42,6 +45,7 @@ impl<T> CowSlice<T> {
     }
     #[inline]
     pub fn with_capacity(capacity: usize) -> Self {
+        count_alloc::<T>(capacity);
         Self {
             data: EcoVec::with_capacity(capacity),
             start: 0,
@@ -65,10 +69,16 @@ impl<T> CowSlice<T> {
             && self.start == other.start
             && self.end == other.end
     }
+    /// Get the address and size in bytes of the underlying buffer
+    pub(crate) fn allocation(&self) -> (usize, usize) {
+        let bytes = self.data.capacity() * size_of::<T>();
+        (self.data.as_ptr() as usize, bytes)
+    }
 }
 
 impl<T: Clone> CowSlice<T> {
     pub fn from_elem(elem: T, len: usize) -> Self {
+        count_alloc::<T>(len);
         Self {
             data: EcoVec::from_elem(elem, len),
             start: 0,
@@ -83,6 +93,7 @@ impl<T: Clone> CowSlice<T> {
     }
     pub fn as_mut_slice(&mut self) -> &mut [T] {
         if !self.data.is_unique() {
+            count_alloc::<T>(self.len());
             let mut new_data = EcoVec::with_capacity(self.len());
             new_data.extend_from_slice(&*self);
             self.data = new_data;
@@ -146,8 +157,10 @@ impl<T: Clone> CowSlice<T> {
         F: FnOnce(&mut EcoVec<T>) -> R,
     {
         if self.data.is_unique() && self.start == 0 && self.end == self.data.len() {
+            let capacity = self.data.capacity();
             let res = f(&mut self.data);
             self.end = self.data.len();
+            count_alloc::<T>(self.data.capacity().saturating_sub(capacity));
             res
         } else {
             let mut vec = EcoVec::from(&**self);
@@ -162,8 +175,10 @@ impl<T: Clone> CowSlice<T> {
         F: FnOnce(&mut EcoVec<T>) -> R,
     {
         if self.data.is_unique() && self.end == self.data.len() {
+            let capacity = self.data.capacity();
             let res = f(&mut self.data);
             self.end = self.data.len();
+            count_alloc::<T>(self.data.capacity().saturating_sub(capacity));
             res
         } else {
             let mut vec = EcoVec::from(&**self);
@@ -357,6 +372,7 @@ impl<T: Clone> From<CowSlice<T>> for Vec<T> {
 
 impl<T: Clone> From<EcoVec<T>> for CowSlice<T> {
     fn from(data: EcoVec<T>) -> Self {
+        count_alloc::<T>(data.capacity());
         Self {
             start: 0,
             end: data.len(),
@@ -377,6 +393,7 @@ impl<T: Clone> From<CowSlice<T>> for EcoVec<T> {
 
 impl<'a, T: Clone> From<&'a [T]> for CowSlice<T> {
     fn from(slice: &'a [T]) -> Self {
+        count_alloc::<T>(slice.len());
         Self {
             start: 0,
             end: slice.len(),
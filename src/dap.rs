@@ -0,0 +1,731 @@
+//! Uiua's Debug Adapter Protocol (DAP) implementation
+//!
+//! The debug adapter communicates over stdin and stdout.
+//! It supports line breakpoints, stepping by line or by instruction,
+//! and inspection of the stack, temp stacks, and call stack of a stopped program.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    io::{self, BufRead, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use parking_lot::Mutex;
+use serde_json::{json, Value as Json};
+
+use crate::{
+    Assembly, CallFrame, Compiler, FileMetadata, GitTarget, Handle, HttpRequest, HttpResponse,
+    InputSrc, NativeSys, Span, SysBackend, TempStack, Uiua, UiuaErrorKind, UiuaResult, Value,
+};
+
+/// The id of the only thread that can be debugged
+const THREAD_ID: u64 = 1;
+
+/// Run the debug adapter over stdin and stdout
+#[doc(hidden)]
+pub fn run_debug_adapter() {
+    serve(io::stdin().lock(), io::stdout());
+}
+
+/// Handle requests until the client disconnects
+fn serve(mut reader: impl BufRead, writer: impl Write + Send + 'static) {
+    let client = Client::new(writer);
+    let (resume_send, resume_recv) = crossbeam_channel::unbounded();
+    let debugger = Arc::new(Debugger::new(client.clone(), resume_recv));
+    let mut launch = None;
+    let mut program = None;
+    while let Some(request) = read_message(&mut reader) {
+        if request["type"] != "request" {
+            continue;
+        }
+        let command = request["command"].as_str().unwrap_or_default();
+        let args = &request["arguments"];
+        let res = match command {
+            "initialize" => Ok(json!({
+                "supportsConfigurationDoneRequest": true,
+                "supportsSteppingGranularity": true,
+                "supportsTerminateRequest": true,
+            })),
+            "launch" => Launch::new(args, &client).map(|l| {
+                launch = Some(l);
+                json!({})
+            }),
+            "setBreakpoints" => debugger.set_breakpoints(args),
+            "configurationDone" => {
+                if let Some(launch) = launch.take() {
+                    program = Some(launch.spawn(debugger.clone(), client.clone()));
+                }
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] })),
+            "stackTrace" => Ok(debugger.stack_trace()),
+            "scopes" => Ok(json!({
+                "scopes": [
+                    { "name": "Stack", "variablesReference": 1, "expensive": false },
+                    { "name": "Under stack", "variablesReference": 2, "expensive": false },
+                    { "name": "Inline stack", "variablesReference": 3, "expensive": false },
+                ]
+            })),
+            "variables" => Ok(debugger.variables(args["variablesReference"].as_u64())),
+            "continue" => {
+                debugger.resume(&resume_send, Resume::Continue);
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" | "stepOut" => {
+                let resume = match (command, args["granularity"].as_str()) {
+                    ("stepOut", _) => Resume::StepOut,
+                    (_, Some("instruction")) => Resume::Instruction,
+                    ("next", _) => Resume::Next,
+                    _ => Resume::StepIn,
+                };
+                debugger.resume(&resume_send, resume);
+                Ok(json!({}))
+            }
+            "pause" => {
+                debugger.pause.store(true, Ordering::Relaxed);
+                Ok(json!({}))
+            }
+            "disconnect" | "terminate" => {
+                debugger.terminate.store(true, Ordering::Relaxed);
+                debugger.resume(&resume_send, Resume::Stop);
+                Ok(json!({}))
+            }
+            _ => Err(format!("Unsupported request `{command}`")),
+        };
+        client.respond(&request, res);
+        match command {
+            "initialize" => client.event("initialized", json!({})),
+            "disconnect" => break,
+            _ => {}
+        }
+    }
+    // A program that is still stopped is terminated
+    drop(resume_send);
+    if let Some(program) = program {
+        _ = program.join();
+    }
+}
+
+/// Read a message with a `Content-Length` header
+fn read_message(reader: &mut impl BufRead) -> Option<Json> {
+    let mut len = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(n) = line.strip_prefix("Content-Length:") {
+            len = n.trim().parse().ok();
+        }
+    }
+    let mut body = vec![0; len?];
+    reader.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+/// Sends messages to the client
+#[derive(Clone)]
+struct Client {
+    out: Arc<Mutex<(Box<dyn Write + Send>, u64)>>,
+}
+
+impl Client {
+    fn new(writer: impl Write + Send + 'static) -> Self {
+        Client {
+            out: Arc::new(Mutex::new((Box::new(writer), 0))),
+        }
+    }
+    fn send(&self, mut message: Json) {
+        let mut out = self.out.lock();
+        out.1 += 1;
+        message["seq"] = json!(out.1);
+        let body = message.to_string();
+        _ = write!(out.0, "Content-Length: {}\r\n\r\n{body}", body.len());
+        _ = out.0.flush();
+    }
+    fn respond(&self, request: &Json, res: Result<Json, String>) {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request["seq"],
+            "command": request["command"],
+            "success": res.is_ok(),
+        });
+        match res {
+            Ok(body) => response["body"] = body,
+            Err(message) => response["message"] = json!(message),
+        }
+        self.send(response);
+    }
+    fn event(&self, event: &str, body: Json) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+    fn output(&self, category: &str, output: impl Into<String>) {
+        let output = output.into();
+        self.event("output", json!({ "category": category, "output": output }));
+    }
+}
+
+/// A program that is ready to be run
+struct Launch {
+    path: PathBuf,
+    args: Vec<String>,
+    stop_on_entry: bool,
+    asm: Assembly,
+}
+
+impl Launch {
+    fn new(args: &Json, client: &Client) -> Result<Self, String> {
+        let path: PathBuf = (args["program"].as_str())
+            .ok_or("Launch requires a `program` path")?
+            .into();
+        let asm = Compiler::with_backend(DapSys::new(client))
+            .load_file(&path)
+            .map_err(|e| e.to_string())?
+            .finish();
+        let program_args = (args["args"].as_array().into_iter().flatten())
+            .filter_map(|arg| arg.as_str().map(Into::into))
+            .collect();
+        Ok(Launch {
+            path,
+            args: program_args,
+            stop_on_entry: args["stopOnEntry"].as_bool().unwrap_or(false),
+            asm,
+        })
+    }
+    fn spawn(self, debugger: Arc<Debugger>, client: Client) -> thread::JoinHandle<()> {
+        debugger.entry.store(self.stop_on_entry, Ordering::Relaxed);
+        thread::spawn(move || {
+            let mut env = Uiua::with_backend(DapSys::new(&client))
+                .with_file_path(&self.path)
+                .with_args(self.args)
+                .with_debug_hook(move |env| debugger.before_instr(env));
+            let exit_code = match env.run_asm(self.asm) {
+                Ok(()) => {
+                    for val in env.take_stack() {
+                        client.output("stdout", format!("{}\n", val.show()));
+                    }
+                    0
+                }
+                Err(e) => {
+                    client.output("stderr", format!("{e}\n"));
+                    1
+                }
+            };
+            client.event("exited", json!({ "exitCode": exit_code }));
+            client.event("terminated", json!({}));
+        })
+    }
+}
+
+/// How execution should proceed after stopping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resume {
+    /// Run until a breakpoint
+    Continue,
+    /// Stop at the next instruction
+    Instruction,
+    /// Stop at the next line
+    StepIn,
+    /// Stop at the next line in the current function or a caller
+    Next,
+    /// Stop after returning from the current function
+    StepOut,
+    /// Stop execution
+    Stop,
+}
+
+/// Where execution last resumed from
+struct Step {
+    resume: Resume,
+    depth: usize,
+    line: Option<(PathBuf, usize)>,
+}
+
+/// The state of a stopped program
+struct Snapshot {
+    /// Innermost first
+    frames: Vec<CallFrame>,
+    stack: Vec<Value>,
+    under: Vec<Value>,
+    inline: Vec<Value>,
+}
+
+/// State shared between the request loop and the running program
+struct Debugger {
+    client: Client,
+    /// Breakpoint lines by canonical file path
+    breakpoints: Mutex<HashMap<PathBuf, Vec<usize>>>,
+    resume: Receiver<Resume>,
+    step: Mutex<Step>,
+    /// The line of the previous instruction in each call frame, outermost first
+    ///
+    /// Returning to a line from a call does not hit its breakpoint again.
+    last_lines: Mutex<Vec<Option<(PathBuf, usize)>>>,
+    stopped: Mutex<Option<Snapshot>>,
+    entry: AtomicBool,
+    pause: AtomicBool,
+    terminate: AtomicBool,
+}
+
+impl Debugger {
+    fn new(client: Client, resume: Receiver<Resume>) -> Self {
+        Debugger {
+            client,
+            breakpoints: Mutex::new(HashMap::new()),
+            resume,
+            step: Mutex::new(Step {
+                resume: Resume::Continue,
+                depth: 0,
+                line: None,
+            }),
+            last_lines: Mutex::new(Vec::new()),
+            stopped: Mutex::new(None),
+            entry: AtomicBool::new(false),
+            pause: AtomicBool::new(false),
+            terminate: AtomicBool::new(false),
+        }
+    }
+    fn set_breakpoints(&self, args: &Json) -> Result<Json, String> {
+        let path = (args["source"]["path"].as_str()).ok_or("Breakpoints require a source path")?;
+        let path = Path::new(path);
+        let path = path.canonicalize().unwrap_or_else(|_| path.into());
+        let lines: Vec<usize> = (args["breakpoints"].as_array().into_iter().flatten())
+            .filter_map(|bp| bp["line"].as_u64())
+            .map(|line| line as usize)
+            .collect();
+        let breakpoints: Vec<Json> = (lines.iter())
+            .map(|line| json!({ "verified": true, "line": line }))
+            .collect();
+        self.breakpoints.lock().insert(path, lines);
+        Ok(json!({ "breakpoints": breakpoints }))
+    }
+    fn resume(&self, send: &Sender<Resume>, resume: Resume) {
+        // Only a stopped program waits to be resumed
+        if self.stopped.lock().is_some() {
+            _ = send.send(resume);
+        }
+    }
+    fn is_breakpoint(&self, (path, line): &(PathBuf, usize)) -> bool {
+        let breakpoints = self.breakpoints.lock();
+        if !breakpoints.values().any(|lines| lines.contains(line)) {
+            return false;
+        }
+        let path = path.canonicalize().unwrap_or_else(|_| path.clone());
+        (breakpoints.get(&path)).is_some_and(|lines| lines.contains(line))
+    }
+    /// Called before each instruction, blocking while the program is stopped
+    fn before_instr(&self, env: &Uiua) -> UiuaResult {
+        if self.terminate.load(Ordering::Relaxed) {
+            return Err(UiuaErrorKind::Interrupted.into());
+        }
+        let frames = env.call_frame_info();
+        let depth = frames.len();
+        let line = (frames.last())
+            .and_then(|frame| frame.span.as_ref())
+            .and_then(span_line);
+        let mut last_lines = self.last_lines.lock();
+        last_lines.resize(depth, None);
+        let new_line = line.is_some() && last_lines.last() != Some(&line);
+        if let Some(last_line) = last_lines.last_mut().filter(|_| line.is_some()) {
+            *last_line = line.clone();
+        }
+        drop(last_lines);
+        let reason = if self.entry.swap(false, Ordering::Relaxed) {
+            Some("entry")
+        } else if self.pause.swap(false, Ordering::Relaxed) {
+            Some("pause")
+        } else if new_line && line.as_ref().is_some_and(|line| self.is_breakpoint(line)) {
+            Some("breakpoint")
+        } else {
+            let step = self.step.lock();
+            let moved = line.is_some() && (line != step.line || depth != step.depth);
+            let stop = match step.resume {
+                Resume::Continue | Resume::Stop => false,
+                Resume::Instruction => true,
+                Resume::StepIn => moved,
+                Resume::Next => moved && depth <= step.depth,
+                Resume::StepOut => line.is_some() && depth < step.depth,
+            };
+            stop.then_some("step")
+        };
+        let Some(reason) = reason else {
+            return Ok(());
+        };
+        *self.stopped.lock() = Some(Snapshot {
+            frames: frames.into_iter().rev().collect(),
+            stack: env.stack().to_vec(),
+            under: env.temp_stack(TempStack::Under).to_vec(),
+            inline: env.temp_stack(TempStack::Inline).to_vec(),
+        });
+        self.client.event(
+            "stopped",
+            json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true }),
+        );
+        let resume = self.resume.recv().unwrap_or(Resume::Stop);
+        *self.stopped.lock() = None;
+        if resume == Resume::Stop {
+            return Err(UiuaErrorKind::Interrupted.into());
+        }
+        *self.step.lock() = Step {
+            resume,
+            depth,
+            line,
+        };
+        Ok(())
+    }
+    fn stack_trace(&self) -> Json {
+        let stopped = self.stopped.lock();
+        let frames = stopped.as_ref().map_or(&[][..], |s| &s.frames[..]);
+        let frames: Vec<Json> = (frames.iter().enumerate())
+            .map(|(i, frame)| {
+                let mut json =
+                    json!({ "id": i, "name": frame.id.to_string(), "line": 0, "column": 0 });
+                if let Some(Span::Code(span)) = &frame.span {
+                    json["line"] = json!(span.start.line);
+                    json["column"] = json!(span.start.col);
+                    if let InputSrc::File(path) = &span.src {
+                        let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+                        json["source"] = json!({ "path": path });
+                    }
+                }
+                json
+            })
+            .collect();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+    fn variables(&self, reference: Option<u64>) -> Json {
+        let stopped = self.stopped.lock();
+        let values = match (stopped.as_ref(), reference) {
+            (Some(s), Some(1)) => &s.stack[..],
+            (Some(s), Some(2)) => &s.under[..],
+            (Some(s), Some(3)) => &s.inline[..],
+            _ => &[][..],
+        };
+        // The top of the stack is shown first
+        let variables: Vec<Json> = (values.iter().rev().enumerate())
+            .map(|(i, val)| {
+                json!({
+                    "name": i.to_string(),
+                    "value": val.show(),
+                    "type": val.shape_string(),
+                    "variablesReference": 0,
+                })
+            })
+            .collect();
+        json!({ "variables": variables })
+    }
+}
+
+/// Get the file and line of a span
+fn span_line(span: &Span) -> Option<(PathBuf, usize)> {
+    match span {
+        Span::Code(span) => match &span.src {
+            InputSrc::File(path) => Some((path.to_path_buf(), span.start.line as usize)),
+            _ => None,
+        },
+        Span::Builtin => None,
+    }
+}
+
+/// A native system backend that sends output to the client
+///
+/// Stdin and stdout are used to talk to the client,
+/// so things that would use them directly are not supported.
+struct DapSys {
+    client: Client,
+}
+
+impl DapSys {
+    fn new(client: &Client) -> Self {
+        DapSys {
+            client: client.clone(),
+        }
+    }
+}
+
+macro_rules! delegate_to_native {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(fn $name(&self $(, $arg: $ty)*) -> $ret {
+            NativeSys.$name($($arg),*)
+        })*
+    };
+}
+
+impl SysBackend for DapSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.client.output("stdout", s);
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.client.output("stderr", s);
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        self.client.output("console", s);
+    }
+    delegate_to_native! {
+        fn var(&self, name: &str) -> Option<String>;
+        fn file_exists(&self, path: &str) -> bool;
+        fn list_dir(&self, path: &str) -> Result<Vec<String>, String>;
+        fn is_file(&self, path: &str) -> Result<bool, String>;
+        fn file_metadata(&self, path: &str) -> Result<FileMetadata, String>;
+        fn delete(&self, path: &str) -> Result<(), String>;
+        fn trash(&self, path: &str) -> Result<(), String>;
+        fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String>;
+        fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String>;
+        fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String>;
+        fn create_file(&self, path: &Path) -> Result<Handle, String>;
+        fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String>;
+        fn make_dir(&self, path: &Path) -> Result<(), String>;
+        fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String>;
+        fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String>;
+        fn sleep(&self, seconds: f64) -> Result<(), String>;
+        fn now(&self) -> f64;
+        fn tcp_listen(&self, addr: &str) -> Result<Handle, String>;
+        fn tcp_accept(&self, handle: Handle) -> Result<Handle, String>;
+        fn tcp_connect(&self, addr: &str) -> Result<Handle, String>;
+        fn tcp_addr(&self, handle: Handle) -> Result<SocketAddr, String>;
+        fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String>;
+        fn tcp_set_read_timeout(&self, handle: Handle, timeout: Option<Duration>) -> Result<(), String>;
+        fn tcp_set_write_timeout(&self, handle: Handle, timeout: Option<Duration>) -> Result<(), String>;
+        fn udp_bind(&self, addr: &str) -> Result<Handle, String>;
+        fn udp_connect(&self, addr: &str) -> Result<Handle, String>;
+        fn udp_send(&self, handle: Handle, data: &[u8], addr: &str) -> Result<(), String>;
+        fn udp_receive(&self, handle: Handle, max_len: usize) -> Result<(Vec<u8>, SocketAddr), String>;
+        fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String>;
+        fn close(&self, handle: Handle) -> Result<(), String>;
+        fn run_command_capture(&self, command: &str, args: &[&str]) -> Result<(i32, String, String), String>;
+        fn change_directory(&self, path: &str) -> Result<(), String>;
+        fn https_get(&self, request: &str, handle: Handle) -> Result<String, String>;
+        fn http_request(&self, request: &HttpRequest) -> Result<HttpResponse, String>;
+        fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String>;
+        fn timezone(&self) -> Result<f64, String>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufReader, Cursor},
+        time::Instant,
+    };
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuf {
+        fn messages(&self) -> Vec<Json> {
+            let mut reader = Cursor::new(self.0.lock().clone());
+            let mut messages = Vec::new();
+            while let Some(message) = read_message(&mut reader) {
+                messages.push(message);
+            }
+            messages
+        }
+    }
+
+    /// Requests sent while the adapter is running
+    struct RequestReader {
+        recv: Receiver<Vec<u8>>,
+        buf: Cursor<Vec<u8>>,
+    }
+
+    impl io::Read for RequestReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            loop {
+                let n = self.buf.read(buf)?;
+                if n > 0 || buf.is_empty() {
+                    return Ok(n);
+                }
+                match self.recv.recv() {
+                    Ok(bytes) => self.buf = Cursor::new(bytes),
+                    Err(_) => return Ok(0),
+                }
+            }
+        }
+    }
+
+    /// A debug session driven one request at a time
+    struct Session {
+        send: Sender<Vec<u8>>,
+        out: SharedBuf,
+        seq: usize,
+        server: thread::JoinHandle<()>,
+    }
+
+    impl Session {
+        fn launch(path: &Path, breakpoint_line: usize) -> Self {
+            let (send, recv) = crossbeam_channel::unbounded();
+            let out = SharedBuf::default();
+            let reader = RequestReader {
+                recv,
+                buf: Cursor::new(Vec::new()),
+            };
+            let server_out = out.clone();
+            let server = thread::spawn(move || serve(BufReader::new(reader), server_out));
+            let mut session = Session {
+                send,
+                out,
+                seq: 0,
+                server,
+            };
+            let path_str = path.to_string_lossy();
+            session.request("initialize", json!({ "adapterID": "uiua" }));
+            session.request("launch", json!({ "program": path_str }));
+            session.request(
+                "setBreakpoints",
+                json!({ "source": { "path": path_str }, "breakpoints": [{ "line": breakpoint_line }] }),
+            );
+            session.request("configurationDone", json!({}));
+            session
+        }
+        fn request(&mut self, command: &str, arguments: Json) {
+            self.seq += 1;
+            _ = self
+                .send
+                .send(request(self.seq, command, arguments).into_bytes());
+        }
+        /// Wait for the `n`th message that matches a predicate
+        fn wait_for(&self, n: usize, f: impl Fn(&Json) -> bool) -> Json {
+            let start = Instant::now();
+            loop {
+                let messages = self.out.messages();
+                if let Some(message) = messages.iter().filter(|m| f(m)).nth(n - 1) {
+                    return message.clone();
+                }
+                assert!(
+                    start.elapsed() < Duration::from_secs(10),
+                    "Timed out waiting for a message in {messages:#?}"
+                );
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        fn wait_stopped(&self, n: usize) -> Json {
+            self.wait_for(n, |m| m["event"] == "stopped")
+        }
+        /// Get the line of the innermost frame of the stopped program
+        fn top_line(&mut self) -> u64 {
+            self.request("stackTrace", json!({ "threadId": THREAD_ID }));
+            let seq = self.seq;
+            let response = self.wait_for(1, |m| m["request_seq"] == seq);
+            response["body"]["stackFrames"][0]["line"].as_u64().unwrap()
+        }
+        fn finish(self) -> Vec<Json> {
+            drop(self.send);
+            self.server.join().unwrap();
+            self.out.messages()
+        }
+    }
+
+    fn request(seq: usize, command: &str, arguments: Json) -> String {
+        let body =
+            json!({ "seq": seq, "type": "request", "command": command, "arguments": arguments })
+                .to_string();
+        format!("Content-Length: {}\r\n\r\n{body}", body.len())
+    }
+
+    #[test]
+    fn breakpoint_session() {
+        let path = std::env::temp_dir().join("uiua_dap_breakpoint_session.ua");
+        std::fs::write(&path, "A ← ⚂\nB ← +1 A\n&p ≥1 B\n").unwrap();
+        let path_str = path.to_string_lossy();
+        let input = [
+            request(1, "initialize", json!({ "adapterID": "uiua" })),
+            request(2, "launch", json!({ "program": path_str })),
+            request(
+                3,
+                "setBreakpoints",
+                json!({ "source": { "path": path_str }, "breakpoints": [{ "line": 2 }] }),
+            ),
+            request(4, "configurationDone", json!({})),
+        ]
+        .concat();
+        let out = SharedBuf::default();
+        serve(Cursor::new(input), out.clone());
+        _ = std::fs::remove_file(&path);
+
+        let messages = out.messages();
+        let responses: Vec<&Json> = (messages.iter())
+            .filter(|m| m["type"] == "response")
+            .collect();
+        assert_eq!(responses.len(), 4);
+        assert!(
+            responses.iter().all(|r| r["success"] == true),
+            "{responses:?}"
+        );
+        let events: Vec<&str> = (messages.iter())
+            .filter_map(|m| m["event"].as_str())
+            .collect();
+        assert!(events.contains(&"initialized"), "{events:?}");
+        assert!(events.contains(&"terminated"), "{events:?}");
+        let stopped = (messages.iter())
+            .find(|m| m["event"] == "stopped")
+            .expect("program did not stop");
+        assert_eq!(stopped["body"]["reason"], "breakpoint");
+    }
+
+    #[test]
+    fn step_session() {
+        let path = std::env::temp_dir().join("uiua_dap_step_session.ua");
+        std::fs::write(&path, "# No inline!\nF ← +1\n&p F ⌊⚂\n&p 5\n").unwrap();
+
+        // Stepping in enters the called function
+        let mut session = Session::launch(&path, 3);
+        assert_eq!(session.wait_stopped(1)["body"]["reason"], "breakpoint");
+        assert_eq!(session.top_line(), 3);
+        session.request("stepIn", json!({ "threadId": THREAD_ID }));
+        assert_eq!(session.wait_stopped(2)["body"]["reason"], "step");
+        assert_eq!(session.top_line(), 2);
+        session.request("continue", json!({ "threadId": THREAD_ID }));
+        session.wait_for(1, |m| m["event"] == "terminated");
+        session.finish();
+
+        // Stepping over runs the called function without stopping in it
+        let mut session = Session::launch(&path, 3);
+        session.wait_stopped(1);
+        session.request("next", json!({ "threadId": THREAD_ID }));
+        assert_eq!(session.wait_stopped(2)["body"]["reason"], "step");
+        assert_eq!(session.top_line(), 4);
+        session.request("continue", json!({ "threadId": THREAD_ID }));
+        session.wait_for(1, |m| m["event"] == "terminated");
+        let messages = session.finish();
+        _ = std::fs::remove_file(&path);
+
+        let output: String = (messages.iter())
+            .filter(|m| m["event"] == "output" && m["body"]["category"] == "stdout")
+            .filter_map(|m| m["body"]["output"].as_str())
+            .collect();
+        assert_eq!(output, "1\n5\n");
+    }
+}
@@ -0,0 +1,204 @@
+//! Modules that are compiled and loaded while a program is running
+
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+
+use crate::{
+    Assembly, BindingKind, Compiler, Function, Ident, RunMode, Signature, Uiua, UiuaResult, Value,
+};
+
+/// The names and signatures that a [`DynamicModule`] must provide
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleInterface {
+    /// The required names and their signatures
+    pub items: IndexMap<Ident, Signature>,
+}
+
+impl ModuleInterface {
+    /// Create an empty interface
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Require a name with a signature
+    pub fn with(mut self, name: impl Into<Ident>, sig: Signature) -> Self {
+        self.items.insert(name.into(), sig);
+        self
+    }
+    /// Parse an interface from a map of names to `[args outputs]` pairs
+    pub(crate) fn from_value(val: &Value, env: &Uiua) -> UiuaResult<Self> {
+        if !val.is_map() {
+            return Err(env.error(
+                "Module interface must be a map from names to signatures, \
+                but it is not a map",
+            ));
+        }
+        let mut interface = Self::new();
+        for (key, sig) in val.map_kv() {
+            let name = key.as_string(env, "Module interface names must be strings")?;
+            let nats = sig.as_nats(env, "Module interface signatures must be natural numbers")?;
+            let sig = match nats.as_slice() {
+                &[args, outputs] => Signature::new(args, outputs),
+                _ => {
+                    return Err(env.error(format!(
+                        "Module interface signatures must be [args outputs] pairs, \
+                        but {name}'s has shape {}",
+                        sig.shape()
+                    )))
+                }
+            };
+            interface = interface.with(name, sig);
+        }
+        Ok(interface)
+    }
+}
+
+/// A module that was compiled and loaded at runtime
+///
+/// Only the items of the [`ModuleInterface`] it was loaded with can be accessed.
+#[derive(Clone)]
+pub struct DynamicModule {
+    path: PathBuf,
+    asm: Assembly,
+    items: IndexMap<Ident, (Signature, ModuleItem)>,
+}
+
+#[derive(Clone)]
+enum ModuleItem {
+    Func(Function),
+    Const(Value),
+}
+
+impl DynamicModule {
+    /// Compile and run a Uiua file, then verify that it provides an interface
+    ///
+    /// The file is read with `env`'s system backend,
+    /// and its top-level code runs under `env`'s limits.
+    pub fn load(env: &Uiua, path: &Path, interface: &ModuleInterface) -> UiuaResult<Self> {
+        let bytes = (env.rt.backend)
+            .file_read_all(path)
+            .map_err(|e| env.error(e))?;
+        let code = String::from_utf8(bytes)
+            .map_err(|e| env.error(format!("Module {} is not valid UTF-8: {e}", path.display())))?;
+        let asm = Compiler::with_backend(env.rt.backend.clone())
+            .mode(RunMode::Normal)
+            .load_str_src(&code, path)?
+            .finish();
+        // The module runs under the caller's limits
+        let mut module_env = env.nested(asm).with_file_path(path);
+        let res = module_env.run_top_slices();
+        module_env.end_run(res)?;
+        let asm = module_env.take_asm();
+
+        let mut items = IndexMap::new();
+        for (name, sig) in &interface.items {
+            // Later bindings shadow earlier ones
+            let binding = (asm.bindings.iter().rev())
+                .filter(|binding| binding.public)
+                .find(|binding| binding.span.as_str(&asm.inputs, |s| s == name.as_str()));
+            let Some(binding) = binding else {
+                return Err(env.error(format!("Module {} does not define {name}", path.display())));
+            };
+            let (found, item) = match &binding.kind {
+                BindingKind::Func(f) => (f.signature(), ModuleItem::Func(f.clone())),
+                BindingKind::Const(Some(val)) => {
+                    (Signature::new(0, 1), ModuleItem::Const(val.clone()))
+                }
+                _ => {
+                    return Err(env.error(format!(
+                        "{name} in module {} is not a function or constant",
+                        path.display()
+                    )))
+                }
+            };
+            if found != *sig {
+                return Err(env.error(format!(
+                    "{name} in module {} has signature {found}, \
+                    but the interface requires {sig}",
+                    path.display()
+                )));
+            }
+            items.insert(name.clone(), (found, item));
+        }
+        Ok(DynamicModule {
+            path: path.into(),
+            asm,
+            items,
+        })
+    }
+    /// Get the path the module was loaded from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Call one of the module's interface items
+    ///
+    /// Arguments are given in the order they would be popped from the stack,
+    /// and outputs are returned in the same order.
+    /// The item runs under `env`'s limits.
+    pub fn call(&self, env: &Uiua, name: &str, args: Vec<Value>) -> UiuaResult<Vec<Value>> {
+        let Some((sig, item)) = self.items.get(name) else {
+            return Err(env.error(format!(
+                "Module {} has no interface item {name}",
+                self.path.display()
+            )));
+        };
+        if args.len() != sig.args {
+            return Err(env.error(format!(
+                "{name} takes {} argument{}, but {} {} given",
+                sig.args,
+                if sig.args == 1 { "" } else { "s" },
+                args.len(),
+                if args.len() == 1 { "was" } else { "were" }
+            )));
+        }
+        match item {
+            ModuleItem::Const(val) => Ok(vec![val.clone()]),
+            ModuleItem::Func(f) => {
+                let mut call_env = env.nested(self.asm.clone());
+                for arg in args.into_iter().rev() {
+                    call_env.push(arg);
+                }
+                call_env.call(f.clone())?;
+                let mut outputs = call_env.take_stack();
+                outputs.reverse();
+                Ok(outputs)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "native_sys"))]
+mod tests {
+    use std::{env, fs};
+
+    use crate::NativeSys;
+
+    use super::*;
+
+    #[test]
+    fn limits() {
+        let dir = env::temp_dir().join("uiua_dynamic_module_limits");
+        fs::create_dir_all(&dir).unwrap();
+        let looping = dir.join("looping.ua");
+        fs::write(&looping, "⍢(+⚂|1) 0\nX ← 5").unwrap();
+        let calling = dir.join("calling.ua");
+        fs::write(&calling, "F ← ⍢(+1|1)").unwrap();
+        let run = |code: String| {
+            let mut env = Uiua::with_backend(NativeSys).with_fuel_limit(100_000);
+            env.run_str(&code).map(drop)
+        };
+
+        // Loading
+        let code = format!("&modl map {{\"X\"}} [0_1] {:?}", looping.display());
+        let err = run(code).unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{err}");
+
+        // Calling
+        let code = format!(
+            "M ← &modl map {{\"F\"}} [1_1] {:?}\n&modc \"F\" [0] M",
+            calling.display()
+        );
+        let err = run(code).unwrap_err();
+        assert!(err.to_string().contains("fuel"), "{err}");
+    }
+}
@@ -43,6 +43,8 @@ pub enum UiuaErrorKind {
     Throw(Box<Value>, Span, Box<Inputs>),
     /// Maximum execution time exceeded
     Timeout(Span, Box<Inputs>),
+    /// Fuel limit exceeded
+    OutOfFuel(Span, Box<Inputs>),
     /// The compiler panicked
     CompilerPanic(String),
     /// The program was interrupted
@@ -99,6 +101,7 @@ impl fmt::Display for UiuaError {
             UiuaErrorKind::Run(error, _) => write!(f, "{error}"),
             UiuaErrorKind::Throw(value, span, _) => write!(f, "{span}: {value}"),
             UiuaErrorKind::Timeout(..) => write!(f, "Maximum execution time exceeded"),
+            UiuaErrorKind::OutOfFuel(..) => write!(f, "Out of fuel"),
             UiuaErrorKind::CompilerPanic(message) => message.fmt(f),
             UiuaErrorKind::Interrupted => write!(f, "# Program interrupted"),
         }
@@ -274,6 +277,9 @@ impl UiuaError {
                 inputs,
                 [("Maximum execution time exceeded", span.clone())],
             ),
+            UiuaErrorKind::OutOfFuel(span, inputs) => {
+                Report::new_multi(kind, inputs, [("Out of fuel", span.clone())])
+            }
             UiuaErrorKind::CompilerPanic(message) => Report::new(kind, message),
             UiuaErrorKind::Load(..) | UiuaErrorKind::Format(..) => {
                 Report::new(kind, self.to_string())
@@ -291,7 +297,8 @@ impl UiuaError {
             UiuaErrorKind::Parse(_, inputs)
             | UiuaErrorKind::Run(_, inputs)
             | UiuaErrorKind::Throw(_, _, inputs)
-            | UiuaErrorKind::Timeout(_, inputs) => inputs,
+            | UiuaErrorKind::Timeout(_, inputs)
+            | UiuaErrorKind::OutOfFuel(_, inputs) => inputs,
             _ => &default_inputs,
         };
         for (info, span) in &self.infos {
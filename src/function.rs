@@ -11,7 +11,7 @@ use serde_tuple::*;
 use crate::{
     lex::CodeSpan,
     primitive::{ImplPrimitive, Primitive},
-    Assembly, Ident, Instr, NewFunction,
+    Assembly, Ident, Instr,
 };
 
 /// A Uiua function
@@ -334,12 +334,6 @@ impl Function {
         );
         &asm.instrs[self.slice.start..end]
     }
-    pub(crate) fn new_func(&self, asm: &Assembly) -> NewFunction {
-        NewFunction {
-            instrs: self.instrs(asm).into(),
-            flags: self.flags,
-        }
-    }
     /// Get a mutable slice of the function's instructions
     pub fn instrs_mut<'a>(&self, asm: &'a mut Assembly) -> &'a mut [Instr] {
         asm.instrs_mut(self.slice)
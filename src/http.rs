@@ -0,0 +1,621 @@
+//! A minimal HTTP/1.1 client, and request parsing for servers
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{sys::is_http_token, HttpRequest, HttpResponse};
+
+/// The maximum size of a message's start line and headers
+const MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// Make an HTTP request, following redirects if the request allows it
+///
+/// `check` is called with the host and port of each server before connecting to it.
+pub fn request(
+    req: &HttpRequest,
+    check: impl Fn(&str, u16) -> Result<(), String>,
+) -> Result<HttpResponse, String> {
+    let mut url = Url::parse(&req.url)?;
+    if !is_http_token(&req.method) {
+        return Err(format!("{:?} is not a valid HTTP method", req.method));
+    }
+    let mut method = req.method.clone();
+    let mut body = req.body.as_slice();
+    let mut headers = req.headers.clone();
+    let mut redirects = 0;
+    loop {
+        check(&url.host, url.port)?;
+        let (status, res_headers, res_body) =
+            request_once(&url, &method, &headers, body, req.timeout)?;
+        let location = (res_headers.iter())
+            .find(|(name, _)| name == "location")
+            .map(|(_, value)| value.clone());
+        match location {
+            Some(location)
+                if [301, 302, 303, 307, 308].contains(&status) && redirects < req.redirects =>
+            {
+                redirects += 1;
+                let next = url.join(&location)?;
+                // A redirect to a different server, or from HTTPS to HTTP,
+                // should not be sent the old host or any credentials
+                if next.authority != url.authority || (url.tls && !next.tls) {
+                    headers.retain(|(name, _)| {
+                        !["host", "authorization", "cookie", "proxy-authorization"]
+                            .iter()
+                            .any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+                    });
+                }
+                url = next;
+                if status == 303 || ([301, 302].contains(&status) && method == "POST") {
+                    method = "GET".into();
+                    body = &[];
+                    headers.retain(|(name, _)| {
+                        !name.eq_ignore_ascii_case("content-length")
+                            && !name.eq_ignore_ascii_case("content-type")
+                    });
+                }
+            }
+            _ => {
+                return Ok(HttpResponse {
+                    status,
+                    headers: res_headers,
+                    body: res_body,
+                    url: url.to_string(),
+                })
+            }
+        }
+    }
+}
+
+type ResponseParts = (u16, Vec<(String, String)>, Vec<u8>);
+
+fn request_once(
+    url: &Url,
+    method: &str,
+    headers: &[(String, String)],
+    body: &[u8],
+    timeout: Option<Duration>,
+) -> Result<ResponseParts, String> {
+    let stream = connect(url, timeout)?;
+    (stream.set_read_timeout(timeout)).map_err(|e| e.to_string())?;
+    (stream.set_write_timeout(timeout)).map_err(|e| e.to_string())?;
+
+    // Build the request
+    let has = |name: &str| headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name));
+    let mut head = format!("{method} {} HTTP/1.1\r\n", url.target);
+    if !has("host") {
+        head.push_str(&format!("Host: {}\r\n", url.authority));
+    }
+    if !has("connection") {
+        head.push_str("Connection: close\r\n");
+    }
+    if !has("content-length") && !has("transfer-encoding") && !body.is_empty() {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    for (name, value) in headers {
+        if !is_http_token(name) || value.contains(['\r', '\n']) {
+            return Err(format!("Invalid HTTP header {name:?}"));
+        }
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    let mut request = head.into_bytes();
+    request.extend_from_slice(body);
+
+    let head_only = method.eq_ignore_ascii_case("HEAD");
+    if url.tls {
+        static CLIENT_CONFIG: Lazy<Arc<rustls::ClientConfig>> = Lazy::new(|| {
+            let store =
+                rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            rustls::ClientConfig::builder()
+                .with_root_certificates(store)
+                .with_no_client_auth()
+                .into()
+        });
+        let server_name =
+            rustls::pki_types::ServerName::try_from(url.host.clone()).map_err(|e| e.to_string())?;
+        let conn = rustls::ClientConnection::new(CLIENT_CONFIG.clone(), server_name)
+            .map_err(|e| e.to_string())?;
+        let mut stream = rustls::StreamOwned::new(conn, stream);
+        stream.write_all(&request).map_err(|e| e.to_string())?;
+        read_response(stream, head_only)
+    } else {
+        (&stream).write_all(&request).map_err(|e| e.to_string())?;
+        read_response(stream, head_only)
+    }
+}
+
+/// Connect to a URL's server, giving up on each of its addresses after the timeout
+fn connect(url: &Url, timeout: Option<Duration>) -> Result<TcpStream, String> {
+    let addr = (url.host.as_str(), url.port);
+    let Some(timeout) = timeout else {
+        return TcpStream::connect(addr).map_err(|e| e.to_string());
+    };
+    let mut error = format!("{} did not resolve to any addresses", url.host);
+    for addr in addr.to_socket_addrs().map_err(|e| e.to_string())? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e.to_string(),
+        }
+    }
+    Err(error)
+}
+
+fn read_response(stream: impl Read, head_only: bool) -> Result<ResponseParts, String> {
+    let mut reader = BufReader::new(stream);
+
+    // Read the status line and headers
+    let mut head = Vec::new();
+    loop {
+        let len = head.len();
+        (reader.by_ref().take(MAX_HEAD_LEN as u64))
+            .read_until(b'\n', &mut head)
+            .map_err(|e| e.to_string())?;
+        if head.len() == len {
+            return Err("Connection closed before the response was complete".into());
+        }
+        if head.ends_with(b"\r\n\r\n") || head.ends_with(b"\n\n") {
+            break;
+        }
+        if head.len() >= MAX_HEAD_LEN {
+            return Err("HTTP response headers are too long".into());
+        }
+    }
+    let mut header_buf = [httparse::EMPTY_HEADER; 128];
+    let mut res = httparse::Response::new(&mut header_buf);
+    match res.parse(&head) {
+        Ok(httparse::Status::Complete(_)) => {}
+        Ok(httparse::Status::Partial) => return Err("Incomplete HTTP response".into()),
+        Err(e) => return Err(format!("Invalid HTTP response: {e}")),
+    }
+    let status = res.code.unwrap_or_default();
+    let headers: Vec<(String, String)> = (res.headers.iter())
+        .map(|h| {
+            let value = String::from_utf8_lossy(h.value).into_owned();
+            (h.name.to_ascii_lowercase(), value)
+        })
+        .collect();
+    let header = |name: &str| {
+        (headers.iter())
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.trim())
+    };
+
+    // Read the body
+    let mut body = Vec::new();
+    if head_only || (100..200).contains(&status) || status == 204 || status == 304 {
+        return Ok((status, headers, body));
+    }
+    if header("transfer-encoding").is_some_and(|te| te.to_ascii_lowercase().ends_with("chunked")) {
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).map_err(|e| e.to_string())?;
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| format!("Invalid HTTP chunk size {size:?}"))?;
+            if size == 0 {
+                break;
+            }
+            let start = body.len();
+            (reader.by_ref().take(size as u64))
+                .read_to_end(&mut body)
+                .map_err(|e| e.to_string())?;
+            if body.len() - start != size {
+                return Err("Connection closed in the middle of a chunk".into());
+            }
+            reader.read_line(&mut line).map_err(|e| e.to_string())?;
+        }
+    } else if let Some(len) = header("content-length") {
+        let len: u64 = (len.parse()).map_err(|_| format!("Invalid HTTP content length {len:?}"))?;
+        (reader.by_ref().take(len))
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+        if body.len() as u64 != len {
+            return Err("Connection closed before the response body was complete".into());
+        }
+    } else {
+        match reader.read_to_end(&mut body) {
+            Ok(_) => {}
+            // Some servers close TLS connections without notifying
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {}
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Ok((status, headers, body))
+}
+
+/// A request read by a server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerRequest {
+    pub method: String,
+    /// The path, without the query
+    pub path: String,
+    /// The decoded query parameters
+    pub query: Vec<(String, String)>,
+    /// The header names, in lowercase, and values
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Read an HTTP request from a stream
+///
+/// `read` should read up to the given number of bytes, returning none at the end of the stream.
+/// Bytes after the end of the request are not read, so pipelined requests can be read one at a time.
+pub fn read_request(
+    mut read: impl FnMut(usize) -> Result<Vec<u8>, String>,
+) -> Result<ServerRequest, String> {
+    // Read the request line and headers
+    let mut head = Vec::new();
+    loop {
+        let line =
+            read_line(&mut read)?.ok_or("Connection closed before the request was complete")?;
+        head.extend_from_slice(&line);
+        if line == b"\r\n" || line == b"\n" {
+            break;
+        }
+        if head.len() >= MAX_HEAD_LEN {
+            return Err("HTTP request headers are too long".into());
+        }
+    }
+    let mut header_buf = [httparse::EMPTY_HEADER; 128];
+    let mut req = httparse::Request::new(&mut header_buf);
+    match req.parse(&head) {
+        Ok(httparse::Status::Complete(_)) => {}
+        Ok(httparse::Status::Partial) => return Err("Incomplete HTTP request".into()),
+        Err(e) => return Err(format!("Invalid HTTP request: {e}")),
+    }
+    let target = req.path.unwrap_or("/");
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = (query.split('&'))
+        .filter(|param| !param.is_empty())
+        .map(|param| {
+            let (name, value) = param.split_once('=').unwrap_or((param, ""));
+            (decode_query(name), decode_query(value))
+        })
+        .collect();
+    let headers: Vec<(String, String)> = (req.headers.iter())
+        .map(|h| {
+            let value = String::from_utf8_lossy(h.value).into_owned();
+            (h.name.to_ascii_lowercase(), value)
+        })
+        .collect();
+    let header = |name: &str| {
+        (headers.iter())
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.trim())
+    };
+
+    // Read the body
+    let mut body = Vec::new();
+    if header("transfer-encoding").is_some_and(|te| te.to_ascii_lowercase().ends_with("chunked")) {
+        loop {
+            let line = read_line(&mut read)?.ok_or("Connection closed in the middle of a chunk")?;
+            let line = String::from_utf8_lossy(&line);
+            let size = line.split(';').next().unwrap_or("").trim();
+            let size = usize::from_str_radix(size, 16)
+                .map_err(|_| format!("Invalid HTTP chunk size {size:?}"))?;
+            if size == 0 {
+                // Skip any trailers
+                while read_line(&mut read)?.is_some_and(|line| line != b"\r\n" && line != b"\n") {}
+                break;
+            }
+            read_body(&mut read, size, &mut body)?;
+            read_line(&mut read)?;
+        }
+    } else if let Some(len) = header("content-length") {
+        let len = (len.parse()).map_err(|_| format!("Invalid HTTP content length {len:?}"))?;
+        read_body(&mut read, len, &mut body)?;
+    }
+    Ok(ServerRequest {
+        method: req.method.unwrap_or_default().into(),
+        path: path.into(),
+        query,
+        headers,
+        body,
+    })
+}
+
+/// Read a line, including its line ending
+///
+/// Returns `None` if the stream ends first.
+fn read_line(
+    read: &mut impl FnMut(usize) -> Result<Vec<u8>, String>,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut line = Vec::new();
+    while !line.ends_with(b"\n") {
+        let byte = read(1)?;
+        if byte.is_empty() {
+            return Ok(None);
+        }
+        line.extend(byte);
+        if line.len() > MAX_HEAD_LEN {
+            return Err("HTTP request line is too long".into());
+        }
+    }
+    Ok(Some(line))
+}
+
+fn read_body(
+    read: &mut impl FnMut(usize) -> Result<Vec<u8>, String>,
+    len: usize,
+    body: &mut Vec<u8>,
+) -> Result<(), String> {
+    let end = body.len() + len;
+    while body.len() < end {
+        let bytes = read((end - body.len()).min(1 << 16))?;
+        if bytes.is_empty() {
+            return Err("Connection closed before the request body was complete".into());
+        }
+        body.extend(bytes);
+    }
+    Ok(())
+}
+
+/// Decode a percent-encoded query parameter, where `+` is a space
+fn decode_query(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes.get(i + 1..i + 3))
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'+', _) => decoded.push(b' '),
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 2;
+            }
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A parsed HTTP or HTTPS URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Url {
+    tls: bool,
+    /// The host and port as written in the URL
+    authority: String,
+    host: String,
+    port: u16,
+    /// The path and query
+    target: String,
+}
+
+impl Url {
+    fn parse(url: &str) -> Result<Self, String> {
+        // These could be used to inject extra lines into the request
+        if url.contains(|c: char| c.is_control() || c.is_whitespace()) {
+            return Err(format!(
+                "URL {url:?} contains whitespace or control characters"
+            ));
+        }
+        let (tls, rest) = if let Some(rest) = strip_prefix_ignore_case(url, "https://") {
+            (true, rest)
+        } else if let Some(rest) = strip_prefix_ignore_case(url, "http://") {
+            (false, rest)
+        } else {
+            return Err(format!(
+                "URL {url:?} must start with \"http://\" or \"https://\""
+            ));
+        };
+        let rest = rest.split('#').next().unwrap_or_default();
+        let split = rest.find(['/', '?']).unwrap_or(rest.len());
+        let (authority, target) = rest.split_at(split);
+        if authority.is_empty() || authority.contains('@') {
+            return Err(format!("URL {url:?} has an invalid host"));
+        }
+        let target = match target {
+            "" => "/".into(),
+            t if t.starts_with('?') => format!("/{t}"),
+            t => t.into(),
+        };
+        // IPv6 addresses are written in brackets
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !port.contains(']') => {
+                let port =
+                    (port.parse()).map_err(|_| format!("URL {url:?} has an invalid port"))?;
+                (host, port)
+            }
+            _ => (authority, if tls { 443 } else { 80 }),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        Ok(Url {
+            tls,
+            authority: authority.into(),
+            host: host.into(),
+            port,
+            target,
+        })
+    }
+    /// Resolve a redirect location relative to this URL
+    fn join(&self, location: &str) -> Result<Self, String> {
+        if location.contains("://") {
+            return Url::parse(location);
+        }
+        let scheme = if self.tls { "https" } else { "http" };
+        let target = if let Some(rest) = location.strip_prefix("//") {
+            return Url::parse(&format!("{scheme}://{rest}"));
+        } else if location.starts_with('/') {
+            location.to_string()
+        } else {
+            let path = self.target.split('?').next().unwrap_or_default();
+            let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+            format!("{dir}{location}")
+        };
+        Url::parse(&format!("{scheme}://{}{target}", self.authority))
+    }
+}
+
+impl std::fmt::Display for Url {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let scheme = if self.tls { "https" } else { "http" };
+        write!(f, "{scheme}://{}{}", self.authority, self.target)
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    (s.get(..prefix.len()))
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &s[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_url() {
+        let url = Url::parse("http://localhost:8080/a/b?c=d#e").unwrap();
+        assert!(!url.tls);
+        assert_eq!(url.host, "localhost");
+        assert_eq!(url.port, 8080);
+        assert_eq!(url.target, "/a/b?c=d");
+
+        let url = Url::parse("HTTPS://example.com?q").unwrap();
+        assert!(url.tls);
+        assert_eq!(url.port, 443);
+        assert_eq!(url.target, "/?q");
+
+        let url = Url::parse("http://[::1]:3000").unwrap();
+        assert_eq!(url.host, "::1");
+        assert_eq!(url.port, 3000);
+        assert_eq!(Url::parse("http://[::1]").unwrap().port, 80);
+
+        assert!(Url::parse("ftp://example.com").is_err());
+        assert!(Url::parse("http://user@example.com").is_err());
+        assert!(Url::parse("http://example.com/a b").is_err());
+        assert!(Url::parse("http://example.com/\r\nHost: evil").is_err());
+        assert!(Url::parse("http://example.com\t/").is_err());
+    }
+
+    #[test]
+    fn invalid_method() {
+        let req = |method: &str| HttpRequest {
+            method: method.into(),
+            url: "http://127.0.0.1:9".into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            redirects: 0,
+            timeout: None,
+        };
+        let check = |_: &str, _| Err("checked".to_string());
+        for method in ["", "GET / HTTP/1.1\r\nX:", "GET X", "G\0T"] {
+            let err = request(&req(method), check).unwrap_err();
+            assert!(err.contains("not a valid HTTP method"), "{err}");
+        }
+        // Valid methods get as far as the check
+        assert_eq!(request(&req("PATCH"), check).unwrap_err(), "checked");
+    }
+
+    #[test]
+    fn join_url() {
+        let url = Url::parse("http://example.com/a/b?c").unwrap();
+        let join = |loc| url.join(loc).unwrap().to_string();
+        assert_eq!(join("/x"), "http://example.com/x");
+        assert_eq!(join("x?y"), "http://example.com/a/x?y");
+        assert_eq!(join("//other.com/x"), "http://other.com/x");
+        assert_eq!(join("https://other.com"), "https://other.com/");
+    }
+
+    #[test]
+    fn redirect_credentials() {
+        use std::{net::TcpListener, thread};
+
+        // Respond to one request and return its head
+        fn serve(listener: TcpListener, response: String) -> thread::JoinHandle<String> {
+            thread::spawn(move || {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut head = String::new();
+                while !head.ends_with("\r\n\r\n") {
+                    reader.read_line(&mut head).unwrap();
+                }
+                (&stream).write_all(response.as_bytes()).unwrap();
+                head
+            })
+        }
+        let first = TcpListener::bind("127.0.0.1:0").unwrap();
+        let second = TcpListener::bind("127.0.0.1:0").unwrap();
+        let first_addr = first.local_addr().unwrap();
+        let second_addr = second.local_addr().unwrap();
+        let first = serve(
+            first,
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: http://{second_addr}/b\r\n\
+                Content-Length: 0\r\n\r\n"
+            ),
+        );
+        let second = serve(
+            second,
+            "HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".into(),
+        );
+        let headers = [
+            ("Authorization", "secret"),
+            ("Cookie", "a=b"),
+            ("Proxy-Authorization", "proxy"),
+            ("X-Keep", "kept"),
+        ];
+        let req = HttpRequest {
+            method: "GET".into(),
+            url: format!("http://{first_addr}/a"),
+            headers: (headers.iter())
+                .map(|&(name, value)| (name.into(), value.into()))
+                .collect(),
+            body: Vec::new(),
+            redirects: 1,
+            timeout: Some(Duration::from_secs(10)),
+        };
+        let res = request(&req, |_, _| Ok(())).unwrap();
+        assert_eq!(res.status, 200);
+        let first = first.join().unwrap();
+        let second = second.join().unwrap();
+        assert!(first.contains("Authorization: secret"), "{first}");
+        for sensitive in ["secret", "a=b", "proxy"] {
+            assert!(!second.contains(sensitive), "{second}");
+        }
+        assert!(second.contains("X-Keep: kept"), "{second}");
+        assert!(second.contains(&format!("Host: {second_addr}")), "{second}");
+    }
+
+    #[test]
+    fn chunked_response() {
+        let res = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nX-A: b\r\n\r\n\
+            5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n";
+        let (status, headers, body) = read_response(&res[..], false).unwrap();
+        assert_eq!(status, 200);
+        assert!(headers.contains(&("x-a".into(), "b".into())));
+        assert_eq!(body, b"hello, world");
+    }
+
+    #[test]
+    fn server_request() {
+        let mut stream: &[u8] = b"POST /a%20b?x=1&y=a+b%21&z HTTP/1.1\r\nHost: h\r\n\
+            Transfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n";
+        let mut read = |len: usize| {
+            let (bytes, rest) = stream.split_at(len.min(stream.len()));
+            stream = rest;
+            Ok(bytes.to_vec())
+        };
+        let req = read_request(&mut read).unwrap();
+        assert_eq!(req.method, "POST");
+        assert_eq!(req.path, "/a%20b");
+        let query = [("x", "1"), ("y", "a b!"), ("z", "")];
+        let query: Vec<(String, String)> =
+            (query.iter()).map(|&(k, v)| (k.into(), v.into())).collect();
+        assert_eq!(req.query, query);
+        assert_eq!(req.headers[0], ("host".into(), "h".into()));
+        assert_eq!(req.body, b"abc");
+        assert_eq!(read_request(&mut read).unwrap().method, "GET");
+        assert!(read_request(&mut read).is_err());
+    }
+}
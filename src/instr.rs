@@ -10,11 +10,11 @@ use serde::*;
 
 use crate::{
     algorithm::invert::CustomInverse,
-    check::instrs_signature,
+    check::{instrs_signature, nodes_signature},
     function::*,
     primitive::{ImplPrimitive, Primitive},
     value::Value,
-    Assembly, BindingKind, Ident,
+    Assembly, BindingKind, Ident, Node,
 };
 
 macro_rules! instr {
@@ -323,40 +323,6 @@ macro_rules! instr_span {
 instr_span!(span_impl, &Self, &usize);
 instr_span!(span_mut, &mut Self, &mut usize);
 
-pub(crate) struct FmtInstrs<'a>(pub &'a [Instr], pub &'a Assembly);
-impl<'a> fmt::Debug for FmtInstrs<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "(")?;
-        for (i, instr) in self.0.iter().enumerate() {
-            if i > 0 {
-                write!(f, " ")?;
-            }
-            match instr {
-                Instr::PushFunc(func) => {
-                    FmtInstrs(func.instrs(self.1), self.1).fmt(f)?;
-                }
-                instr => instr.fmt(f)?,
-            }
-        }
-        write!(f, ")")?;
-        Ok(())
-    }
-}
-impl<'a> fmt::Display for FmtInstrs<'a> {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for (i, instr) in self.0.iter().enumerate() {
-            if i > 0 {
-                write!(f, " ")?;
-            }
-            match instr {
-                Instr::PushFunc(func) => FmtInstrs(func.instrs(self.1), self.1).fmt(f),
-                instr => instr.fmt(f),
-            }?
-        }
-        Ok(())
-    }
-}
-
 /// Levels of purity for an operation
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Purity {
@@ -379,87 +345,190 @@ fn instrs_are_pure_impl<'a>(
     visited: &mut HashSet<&'a FunctionId>,
 ) -> bool {
     'instrs: for (i, instr) in instrs.iter().enumerate() {
-        match instr {
-            Instr::CallGlobal { index, .. } => {
-                if let Some(binding) = asm.bindings.get(*index) {
-                    match &binding.kind {
-                        BindingKind::Const(Some(_)) => {}
-                        BindingKind::Func(f) => {
-                            if visited.insert(&f.id)
-                                && !instrs_are_pure_impl(f.instrs(asm), asm, min_purity, visited)
-                            {
-                                return false;
-                            }
-                        }
-                        _ => return false,
-                    }
+        if let Instr::BindGlobal { .. } = instr {
+            let prev = &instrs[..i];
+            for j in (0..i).rev() {
+                let frag = &prev[j..i];
+                if instrs_signature(frag).is_ok_and(|sig| sig == (0, 1))
+                    && instrs_are_pure_impl(frag, asm, min_purity, visited)
+                {
+                    continue 'instrs;
                 }
             }
-            Instr::BindGlobal { .. } => {
-                let prev = &instrs[..i];
+            return false;
+        }
+        if !instr_is_pure(instr, asm, min_purity, visited) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether some nodes are pure
+pub(crate) fn nodes_are_pure(nodes: &[Node], asm: &Assembly, min_purity: Purity) -> bool {
+    nodes_are_pure_impl(nodes, asm, min_purity, &mut HashSet::new())
+}
+fn nodes_are_pure_impl<'a>(
+    nodes: &'a [Node],
+    asm: &'a Assembly,
+    min_purity: Purity,
+    visited: &mut HashSet<&'a FunctionId>,
+) -> bool {
+    'nodes: for (i, node) in nodes.iter().enumerate() {
+        match node {
+            Node::Run(nodes) => {
+                if !nodes_are_pure_impl(nodes, asm, min_purity, visited) {
+                    return false;
+                }
+            }
+            Node::Instr(Instr::BindGlobal { .. }) => {
+                let prev = &nodes[..i];
                 for j in (0..i).rev() {
                     let frag = &prev[j..i];
-                    if instrs_signature(frag).is_ok_and(|sig| sig == (0, 1))
-                        && instrs_are_pure_impl(frag, asm, min_purity, visited)
+                    if nodes_signature(frag).is_ok_and(|sig| sig == (0, 1))
+                        && nodes_are_pure_impl(frag, asm, min_purity, visited)
                     {
-                        continue 'instrs;
+                        continue 'nodes;
                     }
                 }
                 return false;
             }
-            Instr::Prim(prim, _) => {
-                if prim.purity() < min_purity {
+            Node::Instr(instr) => {
+                if !instr_is_pure(instr, asm, min_purity, visited) {
+                    return false;
+                }
+            }
+            Node::Array { inner, .. } => {
+                if !nodes_are_pure_impl(inner.as_slice(), asm, min_purity, visited) {
                     return false;
                 }
             }
-            Instr::ImplPrim(prim, _) => {
-                if prim.purity() < min_purity {
+            Node::Func(f) => {
+                if !nodes_are_pure_impl(f.nodes(), asm, min_purity, visited) {
                     return false;
                 }
             }
-            Instr::PushFunc(f) => {
-                if visited.insert(&f.id)
-                    && !instrs_are_pure_impl(f.instrs(asm), asm, min_purity, visited)
+            Node::Mod(prim, args, _) => {
+                if prim.purity() < min_purity
+                    || !(args.iter())
+                        .all(|f| nodes_are_pure_impl(f.nodes(), asm, min_purity, visited))
                 {
                     return false;
                 }
             }
-            Instr::Dynamic(_) => return false,
-            Instr::SetOutputComment { .. } => return false,
-            _ => {}
+            Node::ImplMod(prim, args, _) => {
+                if prim.purity() < min_purity
+                    || !(args.iter())
+                        .all(|f| nodes_are_pure_impl(f.nodes(), asm, min_purity, visited))
+                {
+                    return false;
+                }
+            }
+            Node::CustomInverse { .. } => {}
+        }
+    }
+    true
+}
+
+fn instr_is_pure<'a>(
+    instr: &'a Instr,
+    asm: &'a Assembly,
+    min_purity: Purity,
+    visited: &mut HashSet<&'a FunctionId>,
+) -> bool {
+    match instr {
+        Instr::CallGlobal { index, .. } => {
+            if let Some(binding) = asm.bindings.get(*index) {
+                match &binding.kind {
+                    BindingKind::Const(Some(_)) => {}
+                    BindingKind::Func(f) => {
+                        if visited.insert(&f.id)
+                            && !instrs_are_pure_impl(f.instrs(asm), asm, min_purity, visited)
+                        {
+                            return false;
+                        }
+                    }
+                    _ => return false,
+                }
+            }
+        }
+        Instr::Prim(prim, _) => {
+            if prim.purity() < min_purity {
+                return false;
+            }
         }
+        Instr::ImplPrim(prim, _) => {
+            if prim.purity() < min_purity {
+                return false;
+            }
+        }
+        Instr::PushFunc(f) => {
+            if visited.insert(&f.id)
+                && !instrs_are_pure_impl(f.instrs(asm), asm, min_purity, visited)
+            {
+                return false;
+            }
+        }
+        Instr::Dynamic(_) => return false,
+        Instr::SetOutputComment { .. } => return false,
+        _ => {}
     }
     true
 }
 
 /// Whether some instructions can be propertly bounded by the runtime execution limit
 pub(crate) fn instrs_are_limit_bounded(instrs: &[Instr], asm: &Assembly) -> bool {
+    instrs
+        .iter()
+        .all(|instr| instr_is_limit_bounded(instr, asm))
+}
+
+/// Whether some nodes can be propertly bounded by the runtime execution limit
+pub(crate) fn nodes_are_limit_bounded(nodes: &[Node], asm: &Assembly) -> bool {
+    nodes.iter().all(|node| match node {
+        Node::Run(nodes) => nodes_are_limit_bounded(nodes, asm),
+        Node::Instr(instr) => instr_is_limit_bounded(instr, asm),
+        Node::Array { inner, .. } => nodes_are_limit_bounded(inner.as_slice(), asm),
+        Node::Func(f) => !f.is_recursive() && nodes_are_limit_bounded(f.nodes(), asm),
+        Node::Mod(prim, args, span) => {
+            instr_is_limit_bounded(&Instr::Prim(*prim, *span), asm)
+                && (args.iter())
+                    .all(|f| !f.is_recursive() && nodes_are_limit_bounded(f.nodes(), asm))
+        }
+        Node::ImplMod(prim, args, span) => {
+            instr_is_limit_bounded(&Instr::ImplPrim(*prim, *span), asm)
+                && (args.iter())
+                    .all(|f| !f.is_recursive() && nodes_are_limit_bounded(f.nodes(), asm))
+        }
+        Node::CustomInverse { .. } => true,
+    })
+}
+
+fn instr_is_limit_bounded(instr: &Instr, asm: &Assembly) -> bool {
     use Primitive::*;
-    for instr in instrs {
-        match instr {
-            Instr::CallGlobal { index, .. } => {
-                if let Some(binding) = asm.bindings.get(*index) {
-                    match &binding.kind {
-                        BindingKind::Const(Some(_)) => {}
-                        BindingKind::Func(f) => {
-                            if !instrs_are_limit_bounded(f.instrs(asm), asm) {
-                                return false;
-                            }
+    match instr {
+        Instr::CallGlobal { index, .. } => {
+            if let Some(binding) = asm.bindings.get(*index) {
+                match &binding.kind {
+                    BindingKind::Const(Some(_)) => {}
+                    BindingKind::Func(f) => {
+                        if !instrs_are_limit_bounded(f.instrs(asm), asm) {
+                            return false;
                         }
-                        _ => return false,
                     }
+                    _ => return false,
                 }
             }
-            Instr::Prim(Send | Recv, _) => return false,
-            Instr::Prim(Sys(op), _) if op.purity() <= Purity::Mutating => return false,
-            Instr::PushFunc(f) => {
-                if f.is_recursive() || !instrs_are_limit_bounded(f.instrs(asm), asm) {
-                    return false;
-                }
+        }
+        Instr::Prim(Send | Recv, _) => return false,
+        Instr::Prim(Sys(op), _) if op.purity() <= Purity::Mutating => return false,
+        Instr::PushFunc(f) => {
+            if f.is_recursive() || !instrs_are_limit_bounded(f.instrs(asm), asm) {
+                return false;
             }
-            Instr::Dynamic(_) => return false,
-            _ => {}
         }
+        Instr::Dynamic(_) => return false,
+        _ => {}
     }
     true
 }
@@ -0,0 +1,519 @@
+//! A tree-shaped intermediate representation of compiled code
+//!
+//! An [`Assembly`] stores code as flat [`Instr`] slices, and functions refer to their bodies by [`FuncSlice`].
+//! [`Node`] nests array literals and function bodies so that passes can inspect and rewrite code structurally.
+//! A modifier and the functions it takes as operands form a single [`Node::Mod`] or [`Node::ImplMod`].
+//! A [`Node`] is lifted from instructions with [`Node::from_instrs`] and lowered back with [`Node::lower`].
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    slice,
+};
+
+use ecow::EcoVec;
+
+use crate::{
+    algorithm::invert::CustomInverse, function::FunctionFlags, Assembly, FuncSlice, Function,
+    FunctionId, ImplPrimitive, Instr, Primitive, Signature,
+};
+
+/// A node in the intermediate representation
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Node {
+    /// A sequence of nodes that run in order
+    Run(EcoVec<Node>),
+    /// An instruction with no nested code
+    Instr(Instr),
+    /// An array literal
+    Array {
+        /// The code that produces the array's rows
+        inner: Box<Node>,
+        /// Whether the rows are boxed
+        boxed: bool,
+        /// The span index
+        span: usize,
+    },
+    /// A function pushed onto the function stack
+    Func(FuncNode),
+    /// A modifier applied to its function operands
+    ///
+    /// The operands are in source order, so the first one is on top of the function stack.
+    Mod(Primitive, EcoVec<FuncNode>, usize),
+    /// An implementation modifier applied to its function operands
+    ///
+    /// The operands are in source order, like those of [`Node::Mod`].
+    ImplMod(ImplPrimitive, EcoVec<FuncNode>, usize),
+    /// A call to a function with custom inverses
+    CustomInverse {
+        /// The function's un inverse
+        un: Option<FuncNode>,
+        /// The function's under do and undo parts
+        under: Option<(FuncNode, FuncNode)>,
+        /// The function's anti inverse
+        anti: Option<FuncNode>,
+        /// The span index
+        span: usize,
+    },
+}
+
+/// A function whose body is a [`Node`]
+#[derive(Debug, Clone)]
+pub struct FuncNode {
+    /// The function's id
+    pub id: FunctionId,
+    /// The function's signature
+    pub sig: Signature,
+    /// The function's body
+    pub body: Box<Node>,
+    pub(crate) flags: FunctionFlags,
+}
+
+impl PartialEq for FuncNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.sig == other.sig && self.body == other.body
+    }
+}
+
+impl Eq for FuncNode {}
+
+impl Hash for FuncNode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+        self.sig.hash(state);
+        self.body.hash(state);
+    }
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Node::Run(EcoVec::new())
+    }
+}
+
+impl From<Instr> for Node {
+    fn from(instr: Instr) -> Self {
+        Node::Instr(instr)
+    }
+}
+
+impl Node {
+    /// Get the nodes that run in sequence
+    ///
+    /// This is the contents of a [`Node::Run`] or a slice of just this node.
+    pub fn as_slice(&self) -> &[Node] {
+        match self {
+            Node::Run(nodes) => nodes,
+            node => slice::from_ref(node),
+        }
+    }
+    /// Turn the node into the nodes that run in sequence
+    pub fn into_vec(self) -> EcoVec<Node> {
+        match self {
+            Node::Run(nodes) => nodes,
+            node => [node].into(),
+        }
+    }
+    /// Get the instruction if this is a [`Node::Instr`]
+    pub fn as_instr(&self) -> Option<&Instr> {
+        match self {
+            Node::Instr(instr) => Some(instr),
+            _ => None,
+        }
+    }
+    /// Get the modifier's operands if this is a [`Node::Mod`] of the given primitive
+    pub fn as_mod(&self, prim: Primitive) -> Option<(&[FuncNode], usize)> {
+        match self {
+            Node::Mod(p, args, span) if *p == prim => Some((args, *span)),
+            _ => None,
+        }
+    }
+    /// Gather a modifier instruction's operands into a [`Node::Mod`] or [`Node::ImplMod`]
+    ///
+    /// The operands are taken from the end of `nodes`.
+    /// If the instruction is not a modifier, or its operands are not all [`Node::Func`]s,
+    /// the instruction is given back.
+    pub(crate) fn fold_modifier(nodes: &mut EcoVec<Node>, instr: Instr) -> Result<Node, Instr> {
+        let n = match &instr {
+            Instr::Prim(prim, _) => prim.modifier_args(),
+            Instr::ImplPrim(prim, _) => prim.modifier_args(),
+            _ => None,
+        };
+        let Some(n) = n.filter(|&n| n > 0 && n <= nodes.len()) else {
+            return Err(instr);
+        };
+        let start = nodes.len() - n;
+        if !nodes[start..]
+            .iter()
+            .all(|node| matches!(node, Node::Func(_)))
+        {
+            return Err(instr);
+        }
+        let mut args = EcoVec::with_capacity(n);
+        while nodes.len() > start {
+            let Some(Node::Func(f)) = nodes.pop() else {
+                unreachable!()
+            };
+            args.push(f);
+        }
+        Ok(match instr {
+            Instr::Prim(prim, span) => Node::Mod(prim, args, span),
+            Instr::ImplPrim(prim, span) => Node::ImplMod(prim, args, span),
+            _ => unreachable!(),
+        })
+    }
+    /// Lift a slice of instructions into a tree
+    ///
+    /// The bodies of pushed functions are lifted recursively.
+    pub fn from_instrs(instrs: &[Instr], asm: &Assembly) -> Self {
+        Self::lift(instrs, asm, &mut Vec::new())
+    }
+    fn lift(instrs: &[Instr], asm: &Assembly, lifting: &mut Vec<FuncSlice>) -> Self {
+        let mut stack: Vec<EcoVec<Node>> = vec![EcoVec::new()];
+        for instr in instrs {
+            let node = match instr {
+                Instr::BeginArray => {
+                    stack.push(EcoVec::new());
+                    continue;
+                }
+                &Instr::EndArray { boxed, span } if stack.len() > 1 => Node::Array {
+                    inner: Box::new(Node::Run(stack.pop().unwrap())),
+                    boxed,
+                    span,
+                },
+                Instr::PushFunc(f) => match FuncNode::lift(f, asm, lifting) {
+                    Some(f) => Node::Func(f),
+                    None => Node::Instr(instr.clone()),
+                },
+                Instr::CustomInverse(cust, span) => {
+                    Self::lift_custom_inverse(cust, *span, asm, lifting)
+                        .unwrap_or_else(|| Node::Instr(instr.clone()))
+                }
+                instr => match Self::fold_modifier(stack.last_mut().unwrap(), instr.clone()) {
+                    Ok(node) => node,
+                    Err(instr) => Node::Instr(instr),
+                },
+            };
+            stack.last_mut().unwrap().push(node);
+        }
+        // An array that is not closed in this slice is left flat
+        while stack.len() > 1 {
+            let nodes = stack.pop().unwrap();
+            let parent = stack.last_mut().unwrap();
+            parent.push(Node::Instr(Instr::BeginArray));
+            parent.extend(nodes);
+        }
+        Node::Run(stack.pop().unwrap())
+    }
+    fn lift_custom_inverse(
+        cust: &CustomInverse,
+        span: usize,
+        asm: &Assembly,
+        lifting: &mut Vec<FuncSlice>,
+    ) -> Option<Self> {
+        let mut lift = |f: &Function| FuncNode::lift(f, asm, lifting);
+        let un = match &cust.un {
+            Some(f) => Some(lift(f)?),
+            None => None,
+        };
+        let under = match &cust.under {
+            Some((d, u)) => Some((lift(d)?, lift(u)?)),
+            None => None,
+        };
+        let anti = match &cust.anti {
+            Some(f) => Some(lift(f)?),
+            None => None,
+        };
+        Some(Node::CustomInverse {
+            un,
+            under,
+            anti,
+            span,
+        })
+    }
+    /// Lower the tree into instructions
+    ///
+    /// The bodies of nested functions are added to the assembly.
+    pub fn lower(&self, asm: &mut Assembly) -> EcoVec<Instr> {
+        let mut instrs = EcoVec::new();
+        self.lower_into(asm, &mut instrs);
+        instrs
+    }
+    fn lower_into(&self, asm: &mut Assembly, instrs: &mut EcoVec<Instr>) {
+        match self {
+            Node::Run(nodes) => {
+                for node in nodes.iter() {
+                    node.lower_into(asm, instrs);
+                }
+            }
+            Node::Instr(instr) => instrs.push(instr.clone()),
+            Node::Array { inner, boxed, span } => {
+                instrs.push(Instr::BeginArray);
+                inner.lower_into(asm, instrs);
+                instrs.push(Instr::EndArray {
+                    boxed: *boxed,
+                    span: *span,
+                });
+            }
+            Node::Func(func) => instrs.push(Instr::PushFunc(func.lower(asm))),
+            Node::Mod(prim, args, span) => {
+                for arg in args.iter().rev() {
+                    instrs.push(Instr::PushFunc(arg.lower(asm)));
+                }
+                instrs.push(Instr::Prim(*prim, *span));
+            }
+            Node::ImplMod(prim, args, span) => {
+                for arg in args.iter().rev() {
+                    instrs.push(Instr::PushFunc(arg.lower(asm)));
+                }
+                instrs.push(Instr::ImplPrim(*prim, *span));
+            }
+            Node::CustomInverse {
+                un,
+                under,
+                anti,
+                span,
+            } => {
+                let cust = CustomInverse {
+                    un: un.as_ref().map(|f| f.lower(asm)),
+                    under: (under.as_ref()).map(|(d, u)| (d.lower(asm), u.lower(asm))),
+                    anti: anti.as_ref().map(|f| f.lower(asm)),
+                };
+                instrs.push(Instr::CustomInverse(cust, *span));
+            }
+        }
+    }
+    /// Call a function on this node and every node nested within it
+    ///
+    /// Children are visited before their parents, so the function sees already-transformed children.
+    pub fn walk_mut(&mut self, f: &mut impl FnMut(&mut Node)) {
+        match self {
+            Node::Run(nodes) => {
+                for node in nodes.make_mut() {
+                    node.walk_mut(f);
+                }
+            }
+            Node::Instr(_) => {}
+            Node::Array { inner, .. } => inner.walk_mut(f),
+            Node::Func(func) => func.body.walk_mut(f),
+            Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => {
+                for arg in args.make_mut() {
+                    arg.body.walk_mut(f);
+                }
+            }
+            Node::CustomInverse {
+                un, under, anti, ..
+            } => {
+                let funcs = un.iter_mut().chain(anti.iter_mut());
+                let funcs = funcs.chain(under.iter_mut().flat_map(|(d, u)| [d, u]));
+                for func in funcs {
+                    func.body.walk_mut(f);
+                }
+            }
+        }
+        f(self);
+    }
+}
+
+impl FuncNode {
+    pub(crate) fn new(
+        id: FunctionId,
+        sig: Signature,
+        nodes: EcoVec<Node>,
+        flags: FunctionFlags,
+    ) -> Self {
+        FuncNode {
+            id,
+            sig,
+            body: Box::new(Node::Run(nodes)),
+            flags,
+        }
+    }
+    /// Lift a function from the assembly
+    ///
+    /// Calls back into the function itself are left as instructions.
+    pub fn from_function(f: &Function, asm: &Assembly) -> Self {
+        Self::lift(f, asm, &mut Vec::new()).expect("nothing is being lifted yet")
+    }
+    /// Get the nodes of the function's body
+    pub fn nodes(&self) -> &[Node] {
+        self.body.as_slice()
+    }
+    /// Whether this function is recursive
+    pub fn is_recursive(&self) -> bool {
+        self.flags.recursive()
+    }
+    /// Try to get a lone primitive from this function
+    pub fn as_primitive(&self, asm: &Assembly) -> Option<Primitive> {
+        self.as_flipped_primitive(asm)
+            .filter(|(_, flipped)| !flipped)
+            .map(|(prim, _)| prim)
+    }
+    /// Try to get a lone implementation primitive from this function
+    pub fn as_impl_primitive(&self, asm: &Assembly) -> Option<ImplPrimitive> {
+        self.as_flipped_impl_primitive(asm)
+            .filter(|(_, flipped)| !flipped)
+            .map(|(prim, _)| prim)
+    }
+    pub(crate) fn as_flipped_primitive(&self, asm: &Assembly) -> Option<(Primitive, bool)> {
+        match &self.id {
+            FunctionId::Primitive(prim) => Some((*prim, false)),
+            _ => nodes_as_flipped_primitive(self.nodes(), asm),
+        }
+    }
+    pub(crate) fn as_flipped_impl_primitive(
+        &self,
+        asm: &Assembly,
+    ) -> Option<(ImplPrimitive, bool)> {
+        nodes_as_flipped_impl_primitive(self.nodes(), asm)
+    }
+    /// Returns `None` for functions that are already being lifted, as happens with recursion
+    fn lift(f: &Function, asm: &Assembly, lifting: &mut Vec<FuncSlice>) -> Option<Self> {
+        if lifting.contains(&f.slice) {
+            return None;
+        }
+        lifting.push(f.slice);
+        let body = Node::lift(f.instrs(asm), asm, lifting);
+        lifting.pop();
+        Some(FuncNode {
+            id: f.id.clone(),
+            sig: f.signature(),
+            body: Box::new(body),
+            flags: f.flags,
+        })
+    }
+    /// Lower the function's body into the assembly
+    pub fn lower(&self, asm: &mut Assembly) -> Function {
+        let instrs = self.body.lower(asm);
+        // This matches the layout produced by the compiler
+        let len = instrs.len();
+        (asm.instrs).push(Instr::Comment(format!("({}", self.id).into()));
+        let start = if len == 0 { 0 } else { asm.instrs.len() };
+        let mut hasher = DefaultHasher::new();
+        instrs.hash(&mut hasher);
+        let hash = hasher.finish();
+        asm.instrs.extend(instrs);
+        (asm.instrs).push(Instr::Comment(format!("{})", self.id).into()));
+        let slice = FuncSlice { start, len };
+        Function::new(self.id.clone(), self.sig, slice, hash).with_flags(self.flags)
+    }
+}
+
+pub(crate) fn nodes_as_flipped_primitive(
+    nodes: &[Node],
+    asm: &Assembly,
+) -> Option<(Primitive, bool)> {
+    use Primitive::*;
+    match nodes {
+        [Node::Instr(Instr::Prim(Flip, _)), Node::Instr(Instr::Prim(prim, _))] => {
+            Some((*prim, true))
+        }
+        [Node::Instr(Instr::Prim(prim, _))] => Some((*prim, false)),
+        [Node::Func(f), Node::Instr(Instr::Call(_))] => f.as_flipped_primitive(asm),
+        [Node::Instr(Instr::PushFunc(f)), Node::Instr(Instr::Call(_))] => {
+            f.as_flipped_primitive(asm)
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn nodes_as_flipped_impl_primitive(
+    nodes: &[Node],
+    asm: &Assembly,
+) -> Option<(ImplPrimitive, bool)> {
+    use Primitive::*;
+    match nodes {
+        [Node::Instr(Instr::ImplPrim(prim, _)), Node::Instr(Instr::Prim(Flip, _))] => {
+            Some((*prim, true))
+        }
+        [Node::Instr(Instr::ImplPrim(prim, _))] => Some((*prim, false)),
+        [Node::Func(f), Node::Instr(Instr::Call(_))] => f.as_flipped_impl_primitive(asm),
+        [Node::Instr(Instr::PushFunc(f)), Node::Instr(Instr::Call(_))] => {
+            f.as_flipped_impl_primitive(asm)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, Uiua};
+
+    fn round_trip(code: &str) {
+        let asm = Compiler::new().load_str(code).unwrap().finish();
+        let mut lowered = asm.clone();
+        lowered.top_slices.clear();
+        for &slice in &asm.top_slices {
+            let node = Node::from_instrs(asm.instrs(slice), &asm);
+            let instrs = node.lower(&mut lowered);
+            let start = lowered.instrs.len();
+            lowered.instrs.extend(instrs);
+            let slice = FuncSlice {
+                start,
+                len: lowered.instrs.len() - start,
+            };
+            lowered.top_slices.push(slice);
+            assert_eq!(node, Node::from_instrs(lowered.instrs(slice), &lowered));
+        }
+        let mut expected = Uiua::with_safe_sys();
+        expected.run_asm(&asm).unwrap();
+        let mut actual = Uiua::with_safe_sys();
+        actual.run_asm(&lowered).unwrap();
+        assert_eq!(expected.take_stack(), actual.take_stack());
+    }
+
+    #[test]
+    fn lift_lower() {
+        round_trip("[1 2 3] {\"a\" 5}");
+        round_trip("F ← ⊂⊃(+1|×2)\n≡F [1_2 3_4]");
+        round_trip("⍜(⊏0|×10) [1 2 3]");
+        round_trip("/+ [⍥(+1)3 0 ⊃(⊙◌|◌)4 5]");
+    }
+
+    #[test]
+    fn walk() {
+        let asm = (Compiler::new().load_str("[⚂ [⚂ ⚂]] ∵(+⚂) 4"))
+            .unwrap()
+            .finish();
+        let mut node = Node::from_instrs(asm.instrs(asm.top_slices[0]), &asm);
+        let mut arrays = 0;
+        let mut mods = 0;
+        node.walk_mut(&mut |node| match node {
+            Node::Array { .. } => arrays += 1,
+            Node::Mod(Primitive::Each, args, _) => mods += args.len(),
+            _ => {}
+        });
+        assert_eq!(arrays, 2);
+        assert_eq!(mods, 1);
+    }
+
+    #[test]
+    fn modifier_operands() {
+        let asm = (Compiler::new().load_str("⬚10(⊏5) [⚂ ⚂]"))
+            .unwrap()
+            .finish();
+        let node = Node::from_instrs(asm.instrs(asm.top_slices[0]), &asm);
+        let Some((args, _)) = (node.as_slice().iter()).find_map(|n| n.as_mod(Primitive::Fill))
+        else {
+            panic!("fill was not lifted into a modifier node: {node:?}");
+        };
+        let [fill, f] = args else {
+            panic!("fill should have 2 operands, but has {}", args.len());
+        };
+        // Operands are in source order
+        assert!(matches!(fill.nodes(), [Node::Instr(Instr::Push(_))]));
+        assert!(matches!(
+            f.nodes(),
+            [.., Node::Instr(Instr::Prim(Primitive::Select, _))]
+        ));
+        // Lowering pushes the operands in reverse
+        let mut lowered = asm.clone();
+        let instrs = node.lower(&mut lowered);
+        let fill_pos = (instrs.iter())
+            .position(|instr| matches!(instr, Instr::PushFunc(func) if func.signature() == (0, 1)));
+        let f_pos = (instrs.iter())
+            .position(|instr| matches!(instr, Instr::PushFunc(func) if func.signature() == (1, 1)));
+        assert!(f_pos < fill_pos, "{instrs:?}");
+    }
+}
@@ -14,7 +14,7 @@ This disables some of the features that many users are used to having, so to re-
 uiua = { version = "*", default-features = false, features = ["batteries"] }
 ```
 
-The main entry point is the [`Uiua`] struct, which is the Uiua runtime. It must be created with a [`SysBackend`]. [`Uiua::with_native_sys`] is a convenient way to create a Uiua runtime that uses the same backend as the Uiua CLI, though keep in mind it gives full access to the filesystem and TCP sockets and so probably shouldn't be used in a sandboxed environment.
+The main entry point is the [`Uiua`] struct, which is the Uiua runtime. It must be created with a [`SysBackend`]. [`Uiua::with_native_sys`] is a convenient way to create a Uiua runtime that uses the same backend as the Uiua CLI, though keep in mind it gives full access to the filesystem and TCP sockets and so probably shouldn't be used in a sandboxed environment. [`RestrictedSys`] uses the native backend but only allows access to what a [`SysPolicy`] permits. [`MemorySys`] keeps files in memory instead. [`RecordSys`] and [`ReplaySys`] record the system calls made through another backend and play them back, which makes impure programs deterministic to test.
 
 [`Value`] is the generic value type. It wraps one of five [`Array`] types.
 
@@ -109,6 +109,22 @@ let res = uiua.pop_int().unwrap();
 assert_eq!(res, 5);
 ```
 
+To run a program a little at a time, such as once per frame of a game loop, start it with [`Uiua::start`] or [`Uiua::start_call`]. The returned [`Execution`] can be advanced by a number of instructions with [`Execution::step`] or for a duration with [`Execution::run_for`], and its stack can be inspected between steps.
+```rust
+use std::time::Duration;
+use uiua::*;
+
+let asm = Compiler::new().load_str("⍥(+1):0").unwrap().finish();
+let mut uiua = Uiua::with_native_sys();
+uiua.push(1000);
+let mut exec = uiua.start(asm);
+while !exec.run_for(Duration::from_millis(16)).unwrap() {
+    println!("{:?}", exec.stack());
+}
+let mut uiua = exec.finish().unwrap();
+assert_eq!(uiua.pop_int().unwrap(), 1000);
+```
+
 You can format Uiua code with the [`mod@format`] module.
 ```rust
 use uiua::format::*;
@@ -152,15 +168,22 @@ mod check;
 mod compile;
 mod complex;
 mod cowslice;
+#[cfg(feature = "dap")]
+pub mod dap;
+mod dynamic_module;
 mod error;
 mod ffi;
 mod fill;
 pub mod format;
 mod function;
 mod grid_fmt;
+#[cfg(feature = "tls")]
+mod http;
 mod instr;
+mod ir;
 mod lex;
 pub mod lsp;
+mod memory;
 mod optimize;
 mod parse;
 mod primitive;
@@ -171,9 +194,14 @@ mod shape;
 #[cfg(feature = "stand")]
 #[doc(hidden)]
 pub mod stand;
+mod step;
 mod sys;
+mod sys_memory;
 #[cfg(feature = "native_sys")]
 mod sys_native;
+mod sys_record;
+#[cfg(feature = "native_sys")]
+mod sys_restricted;
 mod types;
 mod value;
 
@@ -184,10 +212,12 @@ pub use self::{
     assembly::*,
     boxed::*,
     compile::*,
+    dynamic_module::*,
     error::*,
     ffi::*,
     function::*,
     instr::*,
+    ir::*,
     lex::is_ident_char,
     lex::*,
     lsp::{spans, SpanKind},
@@ -196,11 +226,15 @@ pub use self::{
     run::*,
     shape::*,
     sys::*,
+    sys_memory::*,
+    sys_record::*,
     value::*,
 };
 
 #[cfg(feature = "native_sys")]
-pub use self::sys_native::*;
+pub use self::{sys_native::*, sys_restricted::*};
+
+pub use step::*;
 
 pub use complex::*;
 use ecow::EcoString;
@@ -304,6 +338,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fuel() {
+        use super::*;
+        fn run(code: &str, limit: Option<u64>) -> UiuaResult<u64> {
+            let mut comp = Compiler::new();
+            comp.pre_eval_mode(PreEvalMode::Lazy);
+            let asm = comp.load_str(code)?.finish();
+            let mut env = (Uiua::with_safe_sys().with_fuel_counting()).maybe_with_fuel_limit(limit);
+            env.run_asm(asm)?;
+            Ok(env.fuel_used())
+        }
+        // Random numbers keep the code from being evaluated at compile time
+        let small = run("+1⇡+10⌊⚂", None).unwrap();
+        let large = run("+1⇡+1000⌊⚂", None).unwrap();
+        assert_eq!(small, run("+1⇡+10⌊⚂", None).unwrap());
+        assert!(large > 10 * small, "{small} {large}");
+        run("+1⇡+1000⌊⚂", Some(large)).unwrap();
+
+        // Primitives consume fuel for the elements of their arguments and outputs
+        let range = |n: usize| run(&format!("⇡+{n}⌊⚂"), None).unwrap();
+        assert_eq!(range(1000) - range(10), 990);
+        // A pushed function, then reduce's instruction, argument, and output
+        let reduce = run("/+⇡+1000⌊⚂", None).unwrap();
+        assert_eq!(reduce - range(1000), 1 + 1 + 1000 + 1);
+        // A pushed function and a duplicate, then table's instruction, arguments, and output
+        let table = run("⊞+.⇡+20⌊⚂", None).unwrap();
+        assert_eq!(table - range(20), 1 + 1 + 1 + 2 * 20 + 20 * 20);
+        let reshape = |n: usize| run(&format!("↯+{n}⌊⚂ 1"), None).unwrap();
+        assert_eq!(reshape(200) - reshape(100), 100);
+        // Fuel is not counted without a limit unless asked for
+        let mut env = Uiua::with_safe_sys();
+        env.run_str("⇡+1000⌊⚂").unwrap();
+        assert_eq!(env.fuel_used(), 0);
+
+        // Threads count their own fuel, which is consumed when they are waited on,
+        // so they run out at the same point every time
+        let code = "wait⊟⊃spawn(/+⇡+1000⌊⚂)spawn(/+⇡+1000⌊⚂)";
+        let threads = run(code, None).unwrap();
+        assert!(threads > 2 * reduce, "{threads} {reduce}");
+        for _ in 0..10 {
+            assert_eq!(run(code, Some(threads)).unwrap(), threads);
+            let err = run(code, Some(threads - 1)).unwrap_err();
+            assert!(matches!(err.kind, UiuaErrorKind::OutOfFuel(..)), "{err}");
+        }
+
+        let err = run("+1⇡+1000⌊⚂", Some(large - 1)).unwrap_err();
+        assert!(matches!(err.kind, UiuaErrorKind::OutOfFuel(..)), "{err}");
+        let err = run("⍢(+1|1) 0", Some(10000)).unwrap_err();
+        assert!(matches!(err.kind, UiuaErrorKind::OutOfFuel(..)), "{err}");
+        // Running out of fuel cannot be caught
+        let err = run("⍣⍢(+1|1)0 0", Some(10000)).unwrap_err();
+        assert!(matches!(err.kind, UiuaErrorKind::OutOfFuel(..)), "{err}");
+    }
+
     #[test]
     fn lsp_spans() {
         use super::*;
@@ -9,13 +9,14 @@ use std::{
 };
 
 use crate::{
-    algorithm::invert::{invert_instrs, under_instrs},
+    algorithm::invert::{invert_nodes, under_nodes},
     ast::{Item, Modifier, ModuleKind, PlaceholderOp, Ref, RefComponent, Word},
     ident_modifier_args, instrs_are_pure, is_custom_glyph,
     lex::{CodeSpan, Sp},
     parse::parse,
-    Assembly, BindingInfo, BindingKind, Compiler, DocComment, Ident, InputSrc, Inputs, PreEvalMode,
-    Primitive, Purity, SafeSys, Shape, Signature, SysBackend, UiuaError, Value, CONSTANTS,
+    Assembly, BindingInfo, BindingKind, Compiler, DocComment, Ident, InputSrc, Inputs, Node,
+    PreEvalMode, Primitive, Purity, SafeSys, Shape, Signature, SysBackend, UiuaError, Value,
+    CONSTANTS,
 };
 
 /// Kinds of span in Uiua code, meant to be used in the language server or other IDE tools
@@ -172,7 +173,10 @@ struct Spanner {
 
 impl Spanner {
     fn new(src: InputSrc, input: &str, backend: impl SysBackend) -> Self {
-        let mut compiler = Compiler::with_backend(backend);
+        Self::with_compiler(src, input, Compiler::with_backend(backend))
+    }
+    /// Compile code with a compiler that may have already compiled other code
+    fn with_compiler(src: InputSrc, input: &str, mut compiler: Compiler) -> Self {
         compiler.pre_eval_mode(PreEvalMode::Lsp);
         let errors = match compiler.load_str_src(input, src.clone()) {
             Ok(_) => Vec::new(),
@@ -353,14 +357,14 @@ impl Spanner {
             BindingKind::Func(f) => BindingDocsKind::Function {
                 sig: f.signature(),
                 invertible: {
-                    let instrs = f.instrs(&self.asm);
+                    let nodes = Node::from_instrs(f.instrs(&self.asm), &self.asm);
                     let mut compiler = Compiler::new().with_assembly(self.asm.clone());
-                    invert_instrs(instrs, &mut compiler).is_ok()
+                    invert_nodes(nodes.as_slice(), &mut compiler).is_ok()
                 },
                 underable: {
-                    let instrs = f.instrs(&self.asm);
+                    let nodes = Node::from_instrs(f.instrs(&self.asm), &self.asm);
                     let mut compiler = Compiler::new().with_assembly(self.asm.clone());
-                    under_instrs(instrs, (1, 1).into(), &mut compiler).is_ok()
+                    under_nodes(nodes.as_slice(), (1, 1).into(), &mut compiler).is_ok()
                 },
                 pure: instrs_are_pure(f.instrs(&self.asm), &self.asm, Purity::Pure),
             },
@@ -606,7 +610,7 @@ pub use server::run_language_server;
 
 #[cfg(feature = "lsp")]
 mod server {
-    use std::{env::current_dir, path::Path, sync::Arc};
+    use std::{any::Any, env::current_dir, path::Path, sync::Arc};
 
     use dashmap::DashMap;
     use tower_lsp::{
@@ -625,20 +629,24 @@ mod server {
         is_ident_char,
         lex::{lex, Loc},
         primitive::{PrimClass, PrimDocFragment},
-        AsciiToken, Assembly, BindingInfo, NativeSys, PrimDocLine, Span, Token, UiuaErrorKind,
+        AsciiToken, Assembly, BindingInfo, FileMetadata, GitTarget, NativeSys, PrimDocLine, Span,
+        Token, UiuaErrorKind,
     };
 
     pub struct LspDoc {
         pub input: String,
+        pub items: Vec<Item>,
         pub spans: Vec<Sp<SpanKind>>,
         pub asm: Assembly,
         pub code_meta: CodeMeta,
         pub errors: Vec<UiuaError>,
         pub diagnostics: Vec<crate::Diagnostic>,
+        /// The canonical paths of the files this document imports, directly or indirectly
+        pub dependencies: Vec<PathBuf>,
     }
 
     impl LspDoc {
-        fn new(path: &Path, input: String) -> Self {
+        fn new(path: &Path, input: String, compiler: Compiler) -> Self {
             let path = path
                 .to_string_lossy()
                 .strip_prefix("\\\\?\\")
@@ -648,18 +656,306 @@ mod server {
                 .ok()
                 .and_then(|curr| pathdiff::diff_paths(&path, curr))
                 .unwrap_or(path);
+            let canonical_path = path.canonicalize().ok();
             let src = InputSrc::File(path.into());
             let (items, _, _) = parse(&input, src.clone(), &mut Inputs::default());
-            let spanner = Spanner::new(src, &input, NativeSys);
+            let spanner = Spanner::with_compiler(src, &input, compiler);
             let spans = spanner.items_spans(&items);
+            let dependencies = (spanner.asm.inputs.files.iter())
+                .filter_map(|entry| entry.key().canonicalize().ok())
+                .filter(|dep| Some(dep) != canonical_path.as_ref())
+                .collect();
             Self {
                 input,
+                items,
                 spans,
                 asm: spanner.asm,
                 code_meta: spanner.code_meta,
                 errors: spanner.errors,
                 diagnostics: spanner.diagnostics,
+                dependencies,
+            }
+        }
+
+        /// Get the paths of the modules this document imports, in the order they appear
+        ///
+        /// File paths are resolved so that they can be imported from anywhere.
+        fn imports(&self) -> Vec<(String, CodeSpan)> {
+            fn collect(items: &[Item], meta: &CodeMeta, imports: &mut Vec<(String, CodeSpan)>) {
+                for item in items {
+                    match item {
+                        Item::Import(import) => {
+                            let path = match meta.import_srcs.get(&import.path.span) {
+                                Some(ImportSrc::File(path)) => path.to_string_lossy().into(),
+                                Some(ImportSrc::Git(_)) => import.path.value.clone(),
+                                None => continue,
+                            };
+                            imports.push((path, import.path.span.clone()));
+                        }
+                        Item::Module(m) => collect(&m.value.items, meta, imports),
+                        _ => {}
+                    }
+                }
             }
+            let mut imports = Vec::new();
+            collect(&self.items, &self.code_meta, &mut imports);
+            imports
+        }
+        fn binding_info(&self, name: &Sp<Ident>) -> Option<&BindingInfo> {
+            (self.asm.bindings.iter()).find(|binfo| binfo.span == name.span)
+        }
+        /// Get help for the signature of the function whose arguments are at a position
+        ///
+        /// Lines and columns are 1-indexed.
+        fn signature_help(&self, path: &Path, line: usize, col: usize) -> Option<SignatureHelp> {
+            // Arguments follow the function they are passed to,
+            // so find the nearest function before the cursor on the same line
+            let called = (self.spans.iter())
+                .filter(|sp| {
+                    sp.span.end.line as usize == line
+                        && sp.span.end.col as usize <= col
+                        && sp.span.src == *path
+                })
+                .filter(|sp| match &sp.value {
+                    SpanKind::Primitive(prim, sig) => {
+                        sig.or(prim.signature()).is_some_and(|sig| sig.args > 0)
+                    }
+                    SpanKind::Ident {
+                        docs: Some(docs), ..
+                    } => matches!(docs.kind, BindingDocsKind::Function { sig, .. } if sig.args > 0),
+                    _ => false,
+                })
+                .max_by_key(|sp| sp.span.end.col)?;
+            let mut label = String::new();
+            let mut parameters = Vec::new();
+            let (sig, documentation) = match &called.value {
+                SpanKind::Primitive(prim, sig) => {
+                    label.push_str(&prim.format().to_string());
+                    let mut docs = String::new();
+                    for frag in &prim.doc().short {
+                        doc_frag_markdown(&mut docs, frag);
+                    }
+                    (sig.or(prim.signature()).unwrap(), docs)
+                }
+                SpanKind::Ident {
+                    docs: Some(docs), ..
+                } => {
+                    let BindingDocsKind::Function { sig, .. } = docs.kind else {
+                        return None;
+                    };
+                    called.span.as_str(&self.asm.inputs, |s| label.push_str(s));
+                    let comment = docs.comment.as_ref();
+                    // Name the parameters if the doc comment does
+                    if let Some(comment_sig) = comment
+                        .and_then(|c| c.sig.as_ref())
+                        .filter(|cs| cs.args.len() == sig.args)
+                    {
+                        for arg in &comment_sig.args {
+                            label.push(' ');
+                            let start = label.encode_utf16().count() as u32;
+                            label.push_str(&arg.name);
+                            if let Some(ty) = &arg.ty {
+                                label.push(':');
+                                label.push_str(ty);
+                            }
+                            let end = label.encode_utf16().count() as u32;
+                            parameters.push(ParameterInformation {
+                                label: ParameterLabel::LabelOffsets([start, end]),
+                                documentation: None,
+                            });
+                        }
+                    }
+                    let docs = comment.map(|c| c.text.to_string()).unwrap_or_default();
+                    (sig, docs)
+                }
+                _ => return None,
+            };
+            label.push_str(&format!(" {sig}"));
+            Some(SignatureHelp {
+                signatures: vec![SignatureInformation {
+                    label,
+                    documentation: (!documentation.is_empty()).then_some(
+                        Documentation::MarkupContent(MarkupContent {
+                            kind: MarkupKind::Markdown,
+                            value: documentation,
+                        }),
+                    ),
+                    parameters: (!parameters.is_empty()).then_some(parameters),
+                    active_parameter: None,
+                }],
+                active_signature: Some(0),
+                active_parameter: None,
+            })
+        }
+        /// Get the ranges that can be folded in one of the document's files
+        fn folding_ranges(&self, path: &Path) -> Vec<FoldingRange> {
+            let mut folds = BTreeMap::new();
+            let mut add_fold = |span: &CodeSpan, kind: Option<FoldingRangeKind>| {
+                if span.end.line > span.start.line {
+                    let lines = (span.start.line as u32 - 1, span.end.line as u32 - 1);
+                    folds.entry(lines).or_insert(kind);
+                }
+            };
+            // Modules and bindings
+            fn item_folds(
+                items: &[Item],
+                add_fold: &mut impl FnMut(&CodeSpan, Option<FoldingRangeKind>),
+            ) {
+                for item in items {
+                    match item {
+                        Item::Module(m) => {
+                            add_fold(&m.span, Some(FoldingRangeKind::Region));
+                            item_folds(&m.value.items, add_fold);
+                        }
+                        Item::Binding(binding) => add_fold(&binding.span(), None),
+                        Item::Import(import) => {
+                            add_fold(&import.span(), Some(FoldingRangeKind::Imports))
+                        }
+                        Item::Words(_) => {}
+                    }
+                }
+            }
+            item_folds(&self.items, &mut add_fold);
+            // Multiline functions
+            for span in self.code_meta.function_sigs.keys() {
+                if span.src == *path {
+                    add_fold(span, None);
+                }
+            }
+            // Runs of comment lines
+            let mut comment_lines = (self.spans.iter())
+                .filter(|sp| matches!(sp.value, SpanKind::Comment))
+                .map(|sp| sp.span.start.line as u32 - 1)
+                .collect::<Vec<_>>();
+            comment_lines.dedup();
+            let mut run_start = None;
+            for (i, &line) in comment_lines.iter().enumerate() {
+                let start = *run_start.get_or_insert(line);
+                let run_continues = comment_lines.get(i + 1) == Some(&(line + 1));
+                if !run_continues {
+                    if line > start {
+                        folds.insert((start, line), Some(FoldingRangeKind::Comment));
+                    }
+                    run_start = None;
+                }
+            }
+            (folds.into_iter())
+                .map(|((start_line, end_line), kind)| FoldingRange {
+                    start_line,
+                    end_line,
+                    kind,
+                    ..Default::default()
+                })
+                .collect()
+        }
+        /// Get the bindings whose names contain a lowercase query
+        fn workspace_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+            let mut symbols = Vec::new();
+            for binfo in &self.asm.bindings {
+                let InputSrc::File(file) = &binfo.span.src else {
+                    continue;
+                };
+                let name = binfo.span.as_str(&self.asm.inputs, |s| s.to_string());
+                if !name.to_lowercase().contains(query) {
+                    continue;
+                }
+                let Ok(uri) = path_to_uri(file) else {
+                    continue;
+                };
+                #[allow(deprecated)]
+                let symbol = SymbolInformation {
+                    name,
+                    kind: binding_symbol_kind(&binfo.kind),
+                    tags: None,
+                    deprecated: None,
+                    location: Location {
+                        uri,
+                        range: uiua_span_to_lsp(&binfo.span),
+                    },
+                    container_name: None,
+                };
+                symbols.push(symbol);
+            }
+            symbols
+        }
+        fn document_symbols(&self, items: &[Item]) -> Vec<DocumentSymbol> {
+            #[allow(deprecated)]
+            fn symbol(
+                name: impl Into<String>,
+                detail: Option<String>,
+                kind: SymbolKind,
+                span: &CodeSpan,
+                name_span: &CodeSpan,
+                children: Option<Vec<DocumentSymbol>>,
+            ) -> DocumentSymbol {
+                DocumentSymbol {
+                    name: name.into(),
+                    detail,
+                    kind,
+                    tags: None,
+                    deprecated: None,
+                    range: uiua_span_to_lsp(span),
+                    selection_range: uiua_span_to_lsp(name_span),
+                    children,
+                }
+            }
+            let mut symbols = Vec::new();
+            for item in items {
+                match item {
+                    Item::Binding(binding) => {
+                        let binfo = self.binding_info(&binding.name);
+                        let kind = binfo.map_or(SymbolKind::VARIABLE, |binfo| {
+                            binding_symbol_kind(&binfo.kind)
+                        });
+                        let detail = binfo
+                            .and_then(|binfo| binfo.kind.signature())
+                            .map(|sig| sig.to_string());
+                        symbols.push(symbol(
+                            binding.name.value.as_str(),
+                            detail,
+                            kind,
+                            &binding.span(),
+                            &binding.name.span,
+                            None,
+                        ));
+                    }
+                    Item::Import(import) => {
+                        if let Some(name) = &import.name {
+                            symbols.push(symbol(
+                                name.value.as_str(),
+                                Some(import.path.value.clone()),
+                                SymbolKind::MODULE,
+                                &import.span(),
+                                &name.span,
+                                None,
+                            ));
+                        }
+                    }
+                    Item::Module(m) => {
+                        let children = Some(self.document_symbols(&m.value.items));
+                        symbols.push(match &m.value.kind {
+                            ModuleKind::Named(name) => symbol(
+                                name.value.as_str(),
+                                None,
+                                SymbolKind::MODULE,
+                                &m.span,
+                                &name.span,
+                                children,
+                            ),
+                            ModuleKind::Test => symbol(
+                                "test",
+                                None,
+                                SymbolKind::NAMESPACE,
+                                &m.span,
+                                &m.value.open_span,
+                                children,
+                            ),
+                        });
+                    }
+                    Item::Words(_) => {}
+                }
+            }
+            symbols
         }
     }
 
@@ -679,7 +975,9 @@ mod server {
 
                 let (service, socket) = LspService::new(|client| Backend {
                     client,
-                    docs: DashMap::new(),
+                    docs: Default::default(),
+                    doc_paths: Default::default(),
+                    import_caches: DashMap::new(),
                 });
                 Server::new(stdin, stdout, socket)
                     .concurrency_level(1)
@@ -690,7 +988,39 @@ mod server {
 
     struct Backend {
         client: Client,
-        docs: DashMap<Url, Arc<LspDoc>>,
+        docs: Arc<DashMap<Url, Arc<LspDoc>>>,
+        /// The URIs of open documents by their canonical paths
+        doc_paths: Arc<DashMap<PathBuf, Url>>,
+        import_caches: DashMap<Url, Arc<ImportCache>>,
+    }
+
+    /// The compiled imports of a document
+    ///
+    /// Documents are compiled starting from a copy of this compiler,
+    /// so the files they import are only recompiled when one of them changes.
+    struct ImportCache {
+        /// The imports that were compiled
+        imports: Vec<(String, CodeSpan)>,
+        /// A compiler that has compiled only the imports
+        compiler: Compiler,
+    }
+
+    impl ImportCache {
+        fn new(imports: Vec<(String, CodeSpan)>, backend: LspSys) -> Option<Self> {
+            let mut compiler = Compiler::with_backend(backend);
+            compiler.pre_eval_mode(PreEvalMode::Lsp);
+            for (path, span) in &imports {
+                compiler.import_module(path, span).ok()?;
+            }
+            Some(ImportCache { imports, compiler })
+        }
+        /// Check that none of the imported files have changed
+        fn is_current(&self, backend: &LspSys) -> bool {
+            (self.compiler.asm.inputs.files.iter()).all(|entry| {
+                (backend.file_read_all(entry.key()))
+                    .is_ok_and(|bytes| bytes == entry.value().as_bytes())
+            })
+        }
     }
 
     const UIUA_NUMBER_STT: SemanticTokenType = SemanticTokenType::new("uiua_number");
@@ -753,7 +1083,7 @@ mod server {
             Ok(InitializeResult {
                 capabilities: ServerCapabilities {
                     text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                        TextDocumentSyncKind::FULL,
+                        TextDocumentSyncKind::INCREMENTAL,
                     )),
                     hover_provider: Some(HoverProviderCapability::Simple(true)),
                     completion_provider: Some(CompletionOptions {
@@ -799,6 +1129,13 @@ mod server {
                     code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                     inlay_hint_provider: Some(OneOf::Left(true)),
                     inline_value_provider: Some(OneOf::Left(true)),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(vec![" ".into()]),
+                        ..Default::default()
+                    }),
+                    folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                    document_symbol_provider: Some(OneOf::Left(true)),
+                    workspace_symbol_provider: Some(OneOf::Left(true)),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -810,17 +1147,32 @@ mod server {
         }
 
         async fn did_open(&self, params: DidOpenTextDocumentParams) {
-            let path = uri_path(&params.text_document.uri);
-            self.docs.insert(
-                params.text_document.uri,
-                LspDoc::new(&path, params.text_document.text).into(),
-            );
+            if (self.doc(&params.text_document.uri))
+                .is_some_and(|doc| doc.input == params.text_document.text)
+            {
+                return;
+            }
+            self.update_doc(params.text_document.uri, params.text_document.text);
         }
 
         async fn did_change(&self, params: DidChangeTextDocumentParams) {
-            let path = uri_path(&params.text_document.uri);
-            let doc = LspDoc::new(&path, params.content_changes[0].text.clone());
-            self.docs.insert(params.text_document.uri, doc.into());
+            let uri = params.text_document.uri;
+            let Some(doc) = self.doc(&uri) else {
+                return;
+            };
+            let mut input = doc.input.clone();
+            for change in params.content_changes {
+                if let Some(range) = change.range {
+                    let start = lsp_pos_to_byte_index(&input, range.start);
+                    let end = lsp_pos_to_byte_index(&input, range.end).max(start);
+                    input.replace_range(start..end, &change.text);
+                } else {
+                    input = change.text;
+                }
+            }
+            if input != doc.input {
+                self.update_doc(uri, input);
+            }
         }
 
         async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
@@ -1707,7 +2059,7 @@ mod server {
                     new_text: params.new_name.clone(),
                 }],
             );
-            for entry in &self.docs {
+            for entry in self.docs.iter() {
                 let uri = entry.key();
                 let doc = entry.value();
                 for (name_span, idx) in &doc.code_meta.global_references {
@@ -2076,7 +2428,7 @@ mod server {
             for (i, binfo) in doc.asm.bindings.iter().enumerate() {
                 if binfo.span.contains_line_col(line, col) && binfo.span.src == path {
                     let mut locations = Vec::new();
-                    for entry in &self.docs {
+                    for entry in self.docs.iter() {
                         let uri = entry.key();
                         let doc = entry.value();
                         for (name_span, idx) in &doc.code_meta.global_references {
@@ -2116,6 +2468,62 @@ mod server {
             Ok(Some(inline_values))
         }
 
+        async fn signature_help(
+            &self,
+            params: SignatureHelpParams,
+        ) -> Result<Option<SignatureHelp>> {
+            let Some(doc) =
+                (self.docs).get(&params.text_document_position_params.text_document.uri)
+            else {
+                return Ok(None);
+            };
+            let path = uri_path(&params.text_document_position_params.text_document.uri);
+            let (line, col) = lsp_pos_to_uiua(params.text_document_position_params.position);
+            Ok(doc.signature_help(&path, line, col))
+        }
+
+        async fn folding_range(
+            &self,
+            params: FoldingRangeParams,
+        ) -> Result<Option<Vec<FoldingRange>>> {
+            let Some(doc) = self.doc(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            let path = uri_path(&params.text_document.uri);
+            Ok(Some(doc.folding_ranges(&path)))
+        }
+
+        async fn document_symbol(
+            &self,
+            params: DocumentSymbolParams,
+        ) -> Result<Option<DocumentSymbolResponse>> {
+            let Some(doc) = self.doc(&params.text_document.uri) else {
+                return Ok(None);
+            };
+            Ok(Some(DocumentSymbolResponse::Nested(
+                doc.document_symbols(&doc.items),
+            )))
+        }
+
+        async fn symbol(
+            &self,
+            params: WorkspaceSymbolParams,
+        ) -> Result<Option<Vec<SymbolInformation>>> {
+            let query = params.query.to_lowercase();
+            let mut seen = HashSet::new();
+            let mut symbols = Vec::new();
+            for entry in self.docs.iter() {
+                // Bindings of imported files appear in every document that imports them
+                for symbol in entry.value().workspace_symbols(&query) {
+                    let Location { uri, range } = &symbol.location;
+                    if seen.insert((uri.clone(), range.start.line, range.start.character)) {
+                        symbols.push(symbol);
+                    }
+                }
+            }
+            Ok(Some(symbols))
+        }
+
         async fn shutdown(&self) -> Result<()> {
             Ok(())
         }
@@ -2125,6 +2533,51 @@ mod server {
         fn doc(&self, uri: &Url) -> Option<Arc<LspDoc>> {
             self.docs.get(uri).map(|doc| Arc::clone(&doc))
         }
+        /// Recompile a document with new text, along with any open documents that import it
+        ///
+        /// Other documents keep their cached compilation results.
+        fn update_doc(&self, uri: Url, input: String) {
+            let path = uri_path(&uri);
+            let doc = self.compile_doc(&uri, input);
+            self.docs.insert(uri.clone(), doc.into());
+            self.doc_paths.insert(path.clone(), uri.clone());
+            let dependents: Vec<(Url, Arc<LspDoc>)> = (self.docs.iter())
+                .filter(|entry| *entry.key() != uri && entry.value().dependencies.contains(&path))
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect();
+            for (uri, doc) in dependents {
+                let doc = self.compile_doc(&uri, doc.input.clone());
+                self.docs.insert(uri, doc.into());
+            }
+        }
+        /// Compile a document, reusing its compiled imports if they have not changed
+        fn compile_doc(&self, uri: &Url, input: String) -> LspDoc {
+            let path = uri_path(uri);
+            let sys = self.sys();
+            let cache = (self.import_caches.get(uri))
+                .map(|cache| Arc::clone(&cache))
+                .filter(|cache| cache.is_current(&sys));
+            let compiler = match &cache {
+                Some(cache) => cache.compiler.clone(),
+                None => Compiler::with_backend(sys),
+            };
+            let doc = LspDoc::new(&path, input, compiler);
+            let imports = doc.imports();
+            if cache.map_or(true, |cache| cache.imports != imports) {
+                match ImportCache::new(imports, self.sys()) {
+                    Some(cache) => _ = self.import_caches.insert(uri.clone(), cache.into()),
+                    None => _ = self.import_caches.remove(uri),
+                }
+            }
+            doc
+        }
+        /// A system backend that sees the unsaved text of open documents
+        fn sys(&self) -> LspSys {
+            LspSys {
+                docs: self.docs.clone(),
+                doc_paths: self.doc_paths.clone(),
+            }
+        }
         fn bindings_in_file(
             &self,
             doc_uri: &Url,
@@ -2152,6 +2605,69 @@ mod server {
         }
     }
 
+    fn binding_symbol_kind(kind: &BindingKind) -> SymbolKind {
+        match kind {
+            BindingKind::Const(_) => SymbolKind::CONSTANT,
+            BindingKind::Func(_) => SymbolKind::FUNCTION,
+            BindingKind::Import(_) | BindingKind::Module(_) => SymbolKind::MODULE,
+            BindingKind::IndexMacro(_) | BindingKind::CodeMacro(_) => SymbolKind::OPERATOR,
+        }
+    }
+
+    /// A native system backend that reads open documents from the editor rather than the disk
+    ///
+    /// Compilers that outlive a change to the documents see the change.
+    #[derive(Default)]
+    struct LspSys {
+        docs: Arc<DashMap<Url, Arc<LspDoc>>>,
+        doc_paths: Arc<DashMap<PathBuf, Url>>,
+    }
+
+    impl LspSys {
+        fn open_doc(&self, path: &Path) -> Option<Arc<LspDoc>> {
+            let path = path.canonicalize().ok()?;
+            let uri = self.doc_paths.get(&path)?;
+            self.docs.get(&*uri).map(|doc| Arc::clone(&doc))
+        }
+    }
+
+    impl SysBackend for LspSys {
+        fn any(&self) -> &dyn Any {
+            self
+        }
+        fn any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+        fn var(&self, name: &str) -> Option<String> {
+            NativeSys.var(name)
+        }
+        fn file_exists(&self, path: &str) -> bool {
+            NativeSys.file_exists(path)
+        }
+        fn is_file(&self, path: &str) -> std::result::Result<bool, String> {
+            NativeSys.is_file(path)
+        }
+        fn file_metadata(&self, path: &str) -> std::result::Result<FileMetadata, String> {
+            NativeSys.file_metadata(path)
+        }
+        fn list_dir(&self, path: &str) -> std::result::Result<Vec<String>, String> {
+            NativeSys.list_dir(path)
+        }
+        fn file_read_all(&self, path: &Path) -> std::result::Result<Vec<u8>, String> {
+            match self.open_doc(path) {
+                Some(doc) => Ok(doc.input.as_bytes().to_vec()),
+                None => NativeSys.file_read_all(path),
+            }
+        }
+        fn load_git_module(
+            &self,
+            url: &str,
+            target: GitTarget,
+        ) -> std::result::Result<PathBuf, String> {
+            NativeSys.load_git_module(url, target)
+        }
+    }
+
     fn path_to_uri(path: &Path) -> Result<Url> {
         Url::from_file_path(
             path.canonicalize()
@@ -2166,6 +2682,27 @@ mod server {
         path.canonicalize().unwrap_or(path)
     }
 
+    /// Convert an LSP position, whose character offset counts UTF-16 code units, to a byte index
+    fn lsp_pos_to_byte_index(text: &str, pos: Position) -> usize {
+        let mut line_start = 0;
+        for _ in 0..pos.line {
+            match text[line_start..].find('\n') {
+                Some(i) => line_start += i + 1,
+                None => return text.len(),
+            }
+        }
+        let line = &text[line_start..];
+        let line = &line[..line.find('\n').unwrap_or(line.len())];
+        let mut units = 0;
+        for (i, c) in line.char_indices() {
+            if units >= pos.character as usize {
+                return line_start + i;
+            }
+            units += c.len_utf16();
+        }
+        line_start + line.len()
+    }
+
     fn lsp_pos_to_uiua(pos: Position) -> (usize, usize) {
         (pos.line as usize + 1, pos.character as usize + 1)
     }
@@ -2256,4 +2793,216 @@ mod server {
         }
         value
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn pos_to_byte_index() {
+            let pos = |line, character| Position { line, character };
+            // 𝄞 is one char, but two UTF-16 code units and four bytes
+            let text = "a𝄞b\r\nxyz";
+            assert_eq!(lsp_pos_to_byte_index(text, pos(0, 0)), 0);
+            assert_eq!(lsp_pos_to_byte_index(text, pos(0, 1)), 1);
+            assert_eq!(lsp_pos_to_byte_index(text, pos(0, 3)), 5);
+            assert_eq!(lsp_pos_to_byte_index(text, pos(0, 4)), 6);
+            // The carriage return is part of the line
+            assert_eq!(lsp_pos_to_byte_index(text, pos(0, 5)), 7);
+            assert_eq!(lsp_pos_to_byte_index(text, pos(1, 0)), 8);
+            assert_eq!(lsp_pos_to_byte_index(text, pos(1, 2)), 10);
+            // Positions past the end of a line are at the end of the line
+            assert_eq!(lsp_pos_to_byte_index(text, pos(0, 50)), 7);
+            assert_eq!(lsp_pos_to_byte_index(text, pos(1, 50)), text.len());
+            assert_eq!(lsp_pos_to_byte_index(text, pos(5, 0)), text.len());
+        }
+
+        fn doc(name: &str, input: &str) -> (PathBuf, LspDoc) {
+            let dir = std::env::temp_dir().join("uiua_lsp_docs");
+            std::fs::create_dir_all(&dir).unwrap();
+            let path = dir.join(name);
+            std::fs::write(&path, input).unwrap();
+            let doc = LspDoc::new(
+                &path,
+                input.into(),
+                Compiler::with_backend(LspSys::default()),
+            );
+            assert!(doc.errors.is_empty(), "{:?}", doc.errors);
+            (path, doc)
+        }
+
+        #[test]
+        fn signature_help() {
+            let input = "\
+# Scale a value
+# Product ? Value Scale:Num
+F ← ×
+F 1 2
++ 1 2";
+            let (path, doc) = doc("signature_help.ua", input);
+            let help = doc.signature_help(&path, 4, 3).unwrap();
+            let [info] = help.signatures.as_slice() else {
+                panic!("expected one signature, got {:?}", help.signatures);
+            };
+            assert_eq!(info.label, "F Value Scale:Num |2");
+            let offsets: Vec<_> = (info.parameters.iter().flatten())
+                .map(|param| param.label.clone())
+                .collect();
+            assert_eq!(
+                offsets,
+                [
+                    ParameterLabel::LabelOffsets([2, 7]),
+                    ParameterLabel::LabelOffsets([8, 17])
+                ]
+            );
+            assert!(matches!(
+                &info.documentation,
+                Some(Documentation::MarkupContent(content)) if content.value.contains("Scale a value")
+            ));
+
+            // Primitives have no named parameters
+            let help = doc.signature_help(&path, 5, 3).unwrap();
+            assert!(help.signatures[0].label.starts_with('+'));
+            assert!(help.signatures[0].parameters.is_none());
+
+            // Nothing is called before the cursor
+            assert!(doc.signature_help(&path, 1, 1).is_none());
+        }
+
+        #[test]
+        fn folding_ranges() {
+            let input = "\
+# A comment
+# that continues
+F ← (
+  +1
+)
+---M
+  G ← 1
+  H ← (
+    ×2
+  )
+---";
+            let (path, doc) = doc("folding_ranges.ua", input);
+            let folds: Vec<_> = (doc.folding_ranges(&path).into_iter())
+                .map(|fold| (fold.start_line, fold.end_line, fold.kind))
+                .collect();
+            assert_eq!(
+                folds,
+                [
+                    (0, 1, Some(FoldingRangeKind::Comment)),
+                    (2, 4, None),
+                    (5, 10, Some(FoldingRangeKind::Region)),
+                    (7, 9, None),
+                ]
+            );
+        }
+
+        #[test]
+        fn document_symbols() {
+            let input = "\
+---M
+  F ← +1
+  ---N
+    G ← 5
+  ---
+---
+X ← 3";
+            let (_, doc) = doc("document_symbols.ua", input);
+            let symbols = doc.document_symbols(&doc.items);
+            let names = |symbols: &[DocumentSymbol]| {
+                (symbols.iter())
+                    .map(|sym| (sym.name.clone(), sym.kind))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                names(&symbols),
+                [
+                    ("M".into(), SymbolKind::MODULE),
+                    ("X".into(), SymbolKind::CONSTANT)
+                ]
+            );
+            let m = symbols[0].children.as_deref().unwrap();
+            assert_eq!(
+                names(m),
+                [
+                    ("F".into(), SymbolKind::FUNCTION),
+                    ("N".into(), SymbolKind::MODULE)
+                ]
+            );
+            let n = m[1].children.as_deref().unwrap();
+            assert_eq!(names(n), [("G".into(), SymbolKind::CONSTANT)]);
+            assert_eq!(n[0].selection_range.start, Position::new(3, 4));
+        }
+
+        #[test]
+        fn workspace_symbols() {
+            let input = "\
+Foo ← 1
+---M
+  FooBar ← +1
+---
+Baz ← 2";
+            let (path, doc) = doc("workspace_symbols.ua", input);
+            let symbols = doc.workspace_symbols("foo");
+            let names: Vec<_> = symbols.iter().map(|sym| sym.name.as_str()).collect();
+            assert_eq!(names, ["Foo", "FooBar"]);
+            let uri = path_to_uri(&path).unwrap();
+            assert!(symbols.iter().all(|sym| sym.location.uri == uri));
+            assert_eq!(symbols[1].location.range.start, Position::new(2, 2));
+            assert!(doc.workspace_symbols("qux").is_empty());
+        }
+
+        #[test]
+        fn open_docs() {
+            let (path, doc) = doc("open_docs.ua", "F ← 1");
+            std::fs::write(&path, "F ← 2").unwrap();
+            let sys = LspSys::default();
+            let uri = path_to_uri(&path).unwrap();
+            sys.docs.insert(uri.clone(), doc.into());
+            sys.doc_paths.insert(path.canonicalize().unwrap(), uri);
+
+            // Open documents are read from the editor, however the path is written
+            let dir = path.parent().unwrap();
+            let indirect = dir.join("../uiua_lsp_docs/open_docs.ua");
+            assert_eq!(sys.file_read_all(&indirect).unwrap(), "F ← 1".as_bytes());
+            // Other files are read from the disk
+            let other = dir.join("open_docs_other.ua");
+            std::fs::write(&other, "G ← 3").unwrap();
+            assert_eq!(sys.file_read_all(&other).unwrap(), "G ← 3".as_bytes());
+        }
+
+        #[test]
+        fn import_cache() {
+            let dir = std::env::temp_dir().join("uiua_lsp_import_cache");
+            std::fs::create_dir_all(&dir).unwrap();
+            let lib = dir.join("lib.ua");
+            std::fs::write(&lib, "F ← +1").unwrap();
+            let main = dir.join("main.ua");
+            let sys = LspSys::default;
+
+            let input = "~ \"lib.ua\" ~ F\nF 1";
+            let doc = LspDoc::new(&main, input.into(), Compiler::with_backend(sys()));
+            assert!(doc.errors.is_empty(), "{:?}", doc.errors);
+            let imports = doc.imports();
+            assert_eq!(imports.len(), 1);
+            let cache = ImportCache::new(imports, sys()).unwrap();
+            assert!(cache.is_current(&sys()));
+
+            // Compiling from the cache gives the same results without recompiling the import
+            let cached = LspDoc::new(&main, doc.input.clone(), cache.compiler.clone());
+            assert!(cached.errors.is_empty(), "{:?}", cached.errors);
+            assert_eq!(cached.asm.bindings.len(), doc.asm.bindings.len());
+            assert_eq!(cached.imports(), cache.imports);
+            assert_eq!(
+                cached.code_meta.global_references.len(),
+                doc.code_meta.global_references.len()
+            );
+
+            std::fs::write(&lib, "F ← +2").unwrap();
+            let current = cache.is_current(&sys());
+            _ = std::fs::remove_dir_all(&dir);
+            assert!(!current);
+        }
+    }
 }
@@ -9,6 +9,7 @@ use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
         mpsc::channel,
+        Arc,
     },
     thread::sleep,
     time::{Duration, Instant},
@@ -23,8 +24,9 @@ use rustyline::{error::ReadlineError, DefaultEditor};
 use uiua::{
     format::{format_file, format_str, FormatConfig, FormatConfigSource},
     lsp::BindingDocsKind,
-    Assembly, Compiler, NativeSys, PreEvalMode, PrimClass, Primitive, RunMode, Signature, SpanKind,
-    Uiua, UiuaError, UiuaErrorKind, UiuaResult, Value,
+    Assembly, Compiler, NativeSys, PreEvalMode, PrimClass, Primitive, RecordSys, ReplaySys,
+    RestrictedSys, RunMode, Signature, SpanKind, SysBackend, SysPolicy, Uiua, UiuaError,
+    UiuaErrorKind, UiuaResult, Value,
 };
 
 static PRESSED_CTRL_C: AtomicBool = AtomicBool::new(false);
@@ -68,11 +70,18 @@ fn main() {
         return;
     }
     #[cfg(feature = "stand")]
-    if let Some(asm) = &*uiua::stand::STAND_ASM {
-        let mut rt = Uiua::with_native_sys().with_args(env::args().skip(1).collect());
-        rt.run_asm(asm).unwrap_or_else(fail);
-        print_stack(&rt.take_stack(), true);
-        return;
+    match &*uiua::stand::STAND_ASM {
+        Ok(Some(asm)) => {
+            let mut rt = Uiua::with_native_sys().with_args(env::args().skip(1).collect());
+            rt.run_asm(asm).unwrap_or_else(fail);
+            print_stack(&rt.take_stack(), true);
+            return;
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            exit(1);
+        }
     }
     match App::try_parse() {
         Ok(app) => match app {
@@ -124,7 +133,12 @@ fn main() {
                 formatter_options,
                 time_instrs,
                 limit,
+                memory_limit,
+                fuel,
+                report_fuel,
                 mode,
+                policy_options,
+                record_options,
                 #[cfg(feature = "audio")]
                 audio_options,
                 args,
@@ -142,27 +156,38 @@ fn main() {
                 };
                 #[cfg(feature = "audio")]
                 setup_audio(audio_options);
-                let mut rt = Uiua::with_native_sys()
+                let backend = record_options.backend(policy_options.backend(&path));
+                let mut rt = Uiua::with_backend(backend)
                     .with_file_path(&path)
                     .with_args(args)
                     .time_instrs(time_instrs)
-                    .maybe_with_execution_limit(limit.map(Duration::from_secs_f64));
+                    .maybe_with_execution_limit(limit.map(Duration::from_secs_f64))
+                    .maybe_with_memory_limit(memory_limit.map(|mb| (mb * 1024.0 * 1024.0) as usize))
+                    .maybe_with_fuel_limit(fuel);
+                if report_fuel {
+                    rt = rt.with_fuel_counting();
+                }
                 if path.extension().is_some_and(|ext| ext == "uasm") {
-                    let uasm = match fs::read_to_string(&path) {
-                        Ok(json) => json,
+                    let bytes = match fs::read(&path) {
+                        Ok(bytes) => bytes,
                         Err(e) => {
                             eprintln!("Failed to read assembly: {e}");
                             return;
                         }
                     };
-                    let assembly = match Assembly::from_uasm(&uasm) {
+                    let assembly = match Assembly::from_binary(&bytes) {
                         Ok(assembly) => assembly,
                         Err(e) => {
-                            eprintln!("Failed to parse assembly: {e}");
-                            return;
+                            eprintln!("Failed to load assembly: {e}");
+                            exit(1);
                         }
                     };
-                    rt.run_asm(assembly).unwrap_or_else(fail);
+                    let res = rt.run_asm(assembly);
+                    if report_fuel {
+                        eprintln!("Fuel used: {}", rt.fuel_used());
+                    }
+                    finish_recording(&rt);
+                    res.unwrap_or_else(fail);
                 } else {
                     if !no_format {
                         let config = FormatConfig::from_source(
@@ -180,6 +205,10 @@ fn main() {
                         println!("{}", e.report());
                     }
                     rt.print_reports();
+                    if report_fuel {
+                        eprintln!("Fuel used: {}", rt.fuel_used());
+                    }
+                    finish_recording(&rt);
                     if res.is_err() {
                         exit(1);
                     }
@@ -200,16 +229,17 @@ fn main() {
                         }
                     }
                 };
-                let mut assembly = Compiler::with_backend(NativeSys)
+                let assembly = Compiler::with_backend(NativeSys)
                     .mode(RunMode::Normal)
                     .print_diagnostics(true)
+                    .remove_unreachable(true)
                     .load_file(&path)
                     .unwrap_or_else(fail)
                     .finish();
-                assembly.remove_dead_code();
                 let output = output.unwrap_or_else(|| path.with_extension("uasm"));
-                let uasm = assembly.to_uasm();
-                if let Err(e) = fs::write(output, uasm) {
+                let written = (assembly.to_binary())
+                    .and_then(|bytes| fs::write(output, bytes).map_err(|e| e.to_string()));
+                if let Err(e) = written {
                     eprintln!("Failed to write assembly: {e}");
                 }
             }
@@ -234,6 +264,7 @@ fn main() {
             App::Test {
                 path,
                 formatter_options,
+                record_options,
                 args,
             } => {
                 let path = if let Some(path) = path {
@@ -251,7 +282,7 @@ fn main() {
                     FormatConfig::from_source(formatter_options.format_config_source, Some(&path))
                         .unwrap_or_else(fail);
                 format_file(&path, &config).unwrap_or_else(fail);
-                let mut rt = Uiua::with_native_sys()
+                let mut rt = Uiua::with_backend(record_options.backend(Arc::new(NativeSys)))
                     .with_file_path(&path)
                     .with_args(args);
                 let res = rt.compile_run(|comp| {
@@ -263,6 +294,7 @@ fn main() {
                     println!("{}", e.report());
                 }
                 rt.print_reports();
+                finish_recording(&rt);
                 if res.is_err() {
                     exit(1);
                 }
@@ -291,6 +323,8 @@ fn main() {
             }
             #[cfg(feature = "lsp")]
             App::Lsp => uiua::lsp::run_language_server(),
+            #[cfg(feature = "dap")]
+            App::Dap => uiua::dap::run_debug_adapter(),
             App::Repl {
                 file,
                 formatter_options,
@@ -349,7 +383,35 @@ fn main() {
                 }
             }
             #[cfg(feature = "stand")]
-            App::Stand { main, name } => {
+            App::Stand {
+                inspect: Some(exe), ..
+            } => match uiua::stand::inspect_exe(&exe) {
+                Ok(Some(info)) => {
+                    println!("Payload format version: {}", info.format_version);
+                    println!(
+                        "Assembly format version: {}",
+                        info.asm_header.format_version
+                    );
+                    println!("Built with Uiua: {}", info.asm_header.uiua_version);
+                    println!("Compressed size: {} bytes", info.compressed_size);
+                    println!("Uncompressed size: {} bytes", info.uncompressed_size);
+                    println!("Checksum: {:08x}", info.checksum);
+                }
+                Ok(None) => {
+                    eprintln!("{} is not a standalone executable", exe.display());
+                    exit(1);
+                }
+                Err(e) => {
+                    eprintln!("{e}");
+                    exit(1);
+                }
+            },
+            #[cfg(feature = "stand")]
+            App::Stand {
+                main,
+                name,
+                inspect: None,
+            } => {
                 let main = main.unwrap_or_else(|| "main.ua".into());
                 if !main.exists() {
                     eprintln!("{} does not exist", main.display());
@@ -670,15 +732,28 @@ enum App {
         time_instrs: bool,
         #[clap(long, short = 'l', help = "Set an execution limit in seconds")]
         limit: Option<f64>,
+        #[clap(long, value_name = "MB", help = "Set a memory limit in megabytes")]
+        memory_limit: Option<f64>,
+        #[clap(
+            long,
+            help = "Set a limit on the fuel consumed by executed instructions"
+        )]
+        fuel: Option<u64>,
+        #[clap(long, help = "Print the fuel consumed after running")]
+        report_fuel: bool,
         #[clap(long, help = "Run the file in a specific mode")]
         mode: Option<RunMode>,
+        #[clap(flatten)]
+        policy_options: PolicyOptions,
+        #[clap(flatten)]
+        record_options: RecordOptions,
         #[cfg(feature = "audio")]
         #[clap(flatten)]
         audio_options: AudioOptions,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
-    #[clap(about = "Build an assembly (the .uasm format is currently unstable)")]
+    #[clap(about = "Build an assembly")]
     Build {
         path: Option<PathBuf>,
         #[clap(short, long, help = "The path to the output file")]
@@ -700,6 +775,8 @@ enum App {
         path: Option<PathBuf>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
+        #[clap(flatten)]
+        record_options: RecordOptions,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
@@ -767,10 +844,20 @@ enum App {
         main: Option<PathBuf>,
         #[clap(short = 'o', long, help = "The name of the output executable")]
         name: Option<String>,
+        #[clap(
+            long,
+            value_name = "EXE",
+            conflicts_with_all = ["main", "name"],
+            help = "Print information about the payload of a standalone executable"
+        )]
+        inspect: Option<PathBuf>,
     },
     #[cfg(feature = "lsp")]
     #[clap(about = "Run the Language Server")]
     Lsp,
+    #[cfg(feature = "dap")]
+    #[clap(about = "Run the Debug Adapter")]
+    Dap,
 }
 
 #[derive(Subcommand)]
@@ -794,6 +881,137 @@ struct FormatterOptions {
     format_config_source: FormatConfigSource,
 }
 
+#[derive(clap::Args)]
+struct PolicyOptions {
+    #[clap(
+        long,
+        help = "Only allow access to the file's directory and what the --allow options permit"
+    )]
+    restrict: bool,
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Allow reading files in a directory. Implies --restrict"
+    )]
+    allow_read: Vec<PathBuf>,
+    #[clap(
+        long,
+        value_name = "DIR",
+        help = "Allow reading and writing files in a directory. Implies --restrict"
+    )]
+    allow_write: Vec<PathBuf>,
+    #[clap(
+        long,
+        value_name = "HOST[:PORT]",
+        help = "Allow network access to a host, or any host with *. Implies --restrict"
+    )]
+    allow_net: Vec<String>,
+    #[clap(
+        long,
+        value_name = "COMMAND",
+        help = "Allow running a command. Implies --restrict"
+    )]
+    allow_run: Vec<String>,
+    #[clap(
+        long,
+        value_name = "NAME",
+        help = "Allow reading an environment variable, or any variable with *. Implies --restrict"
+    )]
+    allow_env: Vec<String>,
+    #[clap(long, help = "Allow calling foreign functions. Implies --restrict")]
+    allow_ffi: bool,
+    #[clap(long, help = "Allow exiting the process. Implies --restrict")]
+    allow_exit: bool,
+}
+
+impl PolicyOptions {
+    fn backend(self, path: &Path) -> Arc<dyn SysBackend> {
+        if !(self.restrict
+            || self.allow_ffi
+            || self.allow_exit
+            || !self.allow_read.is_empty()
+            || !self.allow_write.is_empty()
+            || !self.allow_net.is_empty()
+            || !self.allow_run.is_empty()
+            || !self.allow_env.is_empty())
+        {
+            return Arc::new(NativeSys);
+        }
+        // The file's directory must be readable for imports to work
+        let dir = (path.parent())
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut policy = SysPolicy::default()
+            .allow_read(dir)
+            .allow_ffi(self.allow_ffi)
+            .allow_exit(self.allow_exit);
+        for dir in self.allow_read {
+            policy = policy.allow_read(dir);
+        }
+        for dir in self.allow_write {
+            policy = policy.allow_write(dir);
+        }
+        for host in self.allow_net {
+            policy = policy.allow_host(host);
+        }
+        for command in self.allow_run {
+            policy = policy.allow_command(command);
+        }
+        for name in self.allow_env {
+            policy = policy.allow_var(name);
+        }
+        Arc::new(RestrictedSys::new(policy))
+    }
+}
+
+#[derive(clap::Args)]
+struct RecordOptions {
+    #[clap(
+        long,
+        value_name = "FILE",
+        conflicts_with = "replay",
+        help = "Record all system calls and their results to a file"
+    )]
+    record: Option<PathBuf>,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Replay system calls recorded with --record, failing if the program diverges"
+    )]
+    replay: Option<PathBuf>,
+}
+
+impl RecordOptions {
+    fn backend(self, inner: Arc<dyn SysBackend>) -> Arc<dyn SysBackend> {
+        let res = if let Some(path) = self.record {
+            RecordSys::create(inner, path).map(|sys| Arc::new(sys) as Arc<dyn SysBackend>)
+        } else if let Some(path) = self.replay {
+            ReplaySys::open(inner, path).map(|sys| Arc::new(sys) as Arc<dyn SysBackend>)
+        } else {
+            Ok(inner)
+        };
+        res.unwrap_or_else(|e| {
+            eprintln!("{e}");
+            exit(1)
+        })
+    }
+}
+
+/// Report recording errors and replay divergence
+fn finish_recording(rt: &Uiua) {
+    let res = if let Some(sys) = rt.downcast_backend::<RecordSys>() {
+        sys.finish()
+    } else if let Some(sys) = rt.downcast_backend::<ReplaySys>() {
+        sys.finish()
+    } else {
+        Ok(())
+    };
+    if let Err(e) = res {
+        eprintln!("{e}");
+        exit(1);
+    }
+}
+
 #[cfg(feature = "audio")]
 #[derive(clap::Args)]
 struct AudioOptions {
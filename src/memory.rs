@@ -0,0 +1,252 @@
+//! Accounting for the interpreter's memory limit
+//!
+//! Array buffers are counted against the budget of the runtime executing on the current thread
+//! as they are allocated. Counting is cheap, but the count only grows, so once it passes the limit,
+//! the runtime measures the memory its values actually use. Only if that is still over the limit
+//! is an error produced.
+//!
+//! Each thread has its own count, so measuring one thread's values does not forget the memory
+//! used by the others. The counts are summed into a total that is checked against the limit.
+
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    mem::size_of,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{cowslice::CowSlice, Boxed, Value};
+
+/// A limit on the memory used by a runtime's values
+///
+/// The threads a runtime spawns get their own budgets that share its total.
+#[derive(Debug)]
+pub(crate) struct MemoryBudget {
+    total: Arc<MemoryTotal>,
+    /// The bytes measured at this budget's last check, plus the bytes allocated since
+    used: AtomicUsize,
+}
+
+#[derive(Debug)]
+struct MemoryTotal {
+    limit: usize,
+    /// The sum of the bytes used by all budgets that share this total
+    used: AtomicUsize,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<MemoryBudget>>> = const { RefCell::new(None) };
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> Self {
+        MemoryBudget {
+            total: Arc::new(MemoryTotal {
+                limit,
+                used: AtomicUsize::new(0),
+            }),
+            used: AtomicUsize::new(0),
+        }
+    }
+    /// Make a budget for another runtime that shares this one's total
+    pub fn split(&self) -> Self {
+        MemoryBudget {
+            total: self.total.clone(),
+            used: AtomicUsize::new(0),
+        }
+    }
+    pub fn limit(&self) -> usize {
+        self.total.limit
+    }
+    /// Check if allocating some more bytes might go over the limit
+    pub fn might_exceed(&self, bytes: usize) -> bool {
+        self.total_used().saturating_add(bytes) > self.total.limit
+    }
+    /// The bytes counted by all budgets that share this one's total
+    pub fn total_used(&self) -> usize {
+        self.total.used.load(Ordering::Relaxed)
+    }
+    fn add(&self, bytes: usize) {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+        self.total.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+    /// Replace this budget's count with a measurement
+    pub fn reset(&self, used: usize) {
+        let prev = self.used.swap(used, Ordering::Relaxed);
+        if used >= prev {
+            self.total.used.fetch_add(used - prev, Ordering::Relaxed);
+        } else {
+            self.total.used.fetch_sub(prev - used, Ordering::Relaxed);
+        }
+    }
+    /// Count allocations on the current thread against this budget until the guard is dropped
+    pub fn enter(self: &Arc<Self>) -> BudgetGuard {
+        CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            if (current.as_ref()).is_some_and(|current| Arc::ptr_eq(current, self)) {
+                BudgetGuard(None)
+            } else {
+                BudgetGuard(Some(current.replace(self.clone())))
+            }
+        })
+    }
+}
+
+impl Drop for MemoryBudget {
+    fn drop(&mut self) {
+        (self.total.used).fetch_sub(*self.used.get_mut(), Ordering::Relaxed);
+    }
+}
+
+/// Restores the previous budget of the current thread when dropped
+pub(crate) struct BudgetGuard(Option<Option<Arc<MemoryBudget>>>);
+
+impl Drop for BudgetGuard {
+    fn drop(&mut self) {
+        if let Some(prev) = self.0.take() {
+            CURRENT.with(|current| *current.borrow_mut() = prev);
+        }
+    }
+}
+
+/// Count an allocation of some elements against the current thread's budget, if there is one
+#[inline]
+pub(crate) fn count_alloc<T>(count: usize) {
+    let bytes = count.saturating_mul(size_of::<T>());
+    if bytes == 0 {
+        return;
+    }
+    // The thread-local may already be destroyed if this runs in another thread-local's destructor
+    _ = CURRENT.try_with(|current| {
+        if let Some(budget) = &*current.borrow() {
+            budget.add(bytes);
+        }
+    });
+}
+
+/// Measure the bytes used by the buffers of some values
+///
+/// Buffers shared between values are only counted once.
+pub(crate) fn measure<'a>(values: impl IntoIterator<Item = &'a Value>) -> usize {
+    let mut seen = HashSet::new();
+    let mut total = 0;
+    for val in values {
+        measure_value(val, &mut seen, &mut total);
+    }
+    total
+}
+
+fn measure_value(val: &Value, seen: &mut HashSet<usize>, total: &mut usize) {
+    match val {
+        Value::Num(arr) => {
+            measure_buffer(&arr.data, seen, total);
+        }
+        Value::Byte(arr) => {
+            measure_buffer(&arr.data, seen, total);
+        }
+        Value::Complex(arr) => {
+            measure_buffer(&arr.data, seen, total);
+        }
+        Value::Char(arr) => {
+            measure_buffer(&arr.data, seen, total);
+        }
+        Value::Box(arr) => {
+            if measure_buffer(&arr.data, seen, total) {
+                for Boxed(val) in arr.data.iter() {
+                    measure_value(val, seen, total);
+                }
+            }
+        }
+    }
+    if let Some(keys) = &val.meta().map_keys {
+        measure_value(&keys.keys, seen, total);
+    }
+}
+
+/// Returns whether the buffer had not been seen before
+fn measure_buffer<T>(data: &CowSlice<T>, seen: &mut HashSet<usize>, total: &mut usize) -> bool {
+    let (addr, bytes) = data.allocation();
+    if bytes == 0 || !seen.insert(addr) {
+        return false;
+    }
+    *total += bytes;
+    true
+}
+
+/// Format a number of bytes for error messages
+pub(crate) fn format_bytes(bytes: usize) -> String {
+    if bytes < 1024 {
+        return format!("{bytes} bytes");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = "KB";
+    for next in ["MB", "GB", "TB"] {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    let size = (size * 100.0).round() / 100.0;
+    format!("{size} {unit}")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Uiua;
+
+    use super::*;
+
+    #[test]
+    fn shared_buffers() {
+        let a = Value::from_iter([1.0; 100]);
+        let b = a.clone();
+        assert_eq!(measure([&a, &b]), measure([&a]));
+        assert!(measure([&a]) >= 100 * size_of::<f64>());
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(format_bytes(10), "10 bytes");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(1024 * 1024), "1 MB");
+    }
+
+    #[test]
+    fn limit() {
+        let mut env = Uiua::with_safe_sys().with_memory_limit(1024 * 1024);
+        let Err(err) = env.run_str("↯1e6 ⚂") else {
+            panic!("The memory limit was not enforced");
+        };
+        assert!(
+            err.to_string().contains("Memory limit of 1 MB exceeded"),
+            "{err}"
+        );
+
+        // The error can be caught
+        let mut env = Uiua::with_safe_sys().with_memory_limit(1024 * 1024);
+        env.run_str("⍣(↯1e6 ⚂)0").unwrap();
+        // Memory that is no longer used does not count
+        env.run_str("⍥(+/+⇡+1000⌊⚂)1000 0").unwrap();
+    }
+
+    #[test]
+    fn threads() {
+        // Measuring a thread's values does not forget the memory used by the main thread
+        let mut env = Uiua::with_safe_sys().with_memory_limit(1024 * 1024);
+        let Err(err) = env.run_str("↯1e5 1.5\nwait spawn(⍥(◌↯4e4 ⚂)20)") else {
+            panic!("The memory limit was not enforced");
+        };
+        assert!(
+            err.to_string().contains("Memory limit of 1 MB exceeded"),
+            "{err}"
+        );
+
+        // Memory used by finished threads does not count
+        let mut env = Uiua::with_safe_sys().with_memory_limit(1024 * 1024);
+        env.run_str("⍥(◌wait spawn(↯5e4 ⚂))20 ↯1e4 1.5").unwrap();
+    }
+}
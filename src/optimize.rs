@@ -2,78 +2,126 @@ use std::fmt;
 
 use ecow::EcoVec;
 
-use crate::{Assembly, ImplPrimitive, Instr, Primitive, TempStack};
+use crate::{Assembly, ImplPrimitive, Instr, Node, Primitive, TempStack};
 
-pub(crate) fn optimize_instrs_mut(
-    instrs: &mut EcoVec<Instr>,
-    mut new: Instr,
+pub(crate) fn optimize_nodes_mut(
+    nodes: &mut EcoVec<Node>,
+    new: Node,
     maximal: bool,
     asm: &Assembly,
 ) {
+    match new {
+        Node::Run(run) => {
+            for node in run {
+                optimize_nodes_mut(nodes, node, maximal, asm);
+            }
+        }
+        Node::Instr(instr) if maximal && instr.is_compile_only() => {}
+        Node::Instr(instr) => match Node::fold_modifier(nodes, instr) {
+            Ok(node) => optimize_mod_mut(nodes, node),
+            Err(instr) => optimize_instr_mut(nodes, instr, maximal),
+        },
+        node @ (Node::Mod(..) | Node::ImplMod(..)) => optimize_mod_mut(nodes, node),
+        // End array repeat rand
+        Node::Array {
+            inner,
+            boxed: false,
+            span,
+        } => match inner.as_slice() {
+            [rest @ .., Node::Mod(Primitive::Repeat, args, span)]
+                if args.len() == 1 && args[0].as_primitive(asm) == Some(Primitive::Rand) =>
+            {
+                nodes.push(Node::Instr(Instr::BeginArray));
+                nodes.extend(rest.iter().cloned());
+                nodes.push(Node::Instr(Instr::ImplPrim(
+                    ImplPrimitive::EndRandArray,
+                    *span,
+                )));
+            }
+            _ => nodes.push(Node::Array {
+                inner,
+                boxed: false,
+                span,
+            }),
+        },
+        node => nodes.push(node),
+    }
+}
+
+fn optimize_instr_mut(nodes: &mut EcoVec<Node>, mut new: Instr, maximal: bool) {
     use ImplPrimitive::*;
     use Primitive::*;
     if let Instr::Push(val) = &mut new {
         val.compress();
     }
-    match (instrs.make_mut(), new) {
+    match (nodes.make_mut(), new) {
         // First Rise = FirstMinIndex
-        ([.., Instr::Prim(Rise, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(FirstMinIndex, span))
+        ([.., Node::Instr(Instr::Prim(Rise, _))], Instr::Prim(First, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(FirstMinIndex, span)))
         }
         // First Reverse Fall = LastMinIndex
-        ([.., Instr::Prim(Fall, _), Instr::Prim(Reverse, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(LastMinIndex, span))
+        (
+            [.., Node::Instr(Instr::Prim(Fall, _)), Node::Instr(Instr::Prim(Reverse, _))],
+            Instr::Prim(First, span),
+        ) => {
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(LastMinIndex, span)))
         }
         // First Fall = FirstMaxIndex
-        ([.., Instr::Prim(Fall, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(FirstMaxIndex, span))
+        ([.., Node::Instr(Instr::Prim(Fall, _))], Instr::Prim(First, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(FirstMaxIndex, span)))
         }
         // First Reverse Rise = LastMaxIndex
-        ([.., Instr::Prim(Rise, _), Instr::Prim(Reverse, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(LastMaxIndex, span))
+        (
+            [.., Node::Instr(Instr::Prim(Rise, _)), Node::Instr(Instr::Prim(Reverse, _))],
+            Instr::Prim(First, span),
+        ) => {
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(LastMaxIndex, span)))
         }
         // First/last/len where
-        ([.., Instr::Prim(Where, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(FirstWhere, span))
+        ([.., Node::Instr(Instr::Prim(Where, _))], Instr::Prim(First, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(FirstWhere, span)))
         }
-        ([.., Instr::Prim(Where, _), Instr::Prim(Reverse, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(LastWhere, span))
+        (
+            [.., Node::Instr(Instr::Prim(Where, _)), Node::Instr(Instr::Prim(Reverse, _))],
+            Instr::Prim(First, span),
+        ) => {
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(LastWhere, span)))
         }
-        ([.., Instr::Prim(Where, _)], Instr::Prim(Len, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(LenWhere, span))
+        ([.., Node::Instr(Instr::Prim(Where, _))], Instr::Prim(Len, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(LenWhere, span)))
         }
         // MemberOf Range
-        ([.., Instr::Prim(Range, _)], Instr::Prim(MemberOf, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(MemberOfRange, span))
+        ([.., Node::Instr(Instr::Prim(Range, _))], Instr::Prim(MemberOf, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(MemberOfRange, span)))
         }
         // First Reverse = last
-        ([.., Instr::Prim(Reverse, _)], Instr::Prim(First, span)) => {
-            instrs.pop();
-            instrs.push(Instr::Prim(Last, span))
+        ([.., Node::Instr(Instr::Prim(Reverse, _))], Instr::Prim(First, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::Prim(Last, span)))
         }
         // Count unique
-        ([.., Instr::Prim(Deduplicate, _)], Instr::Prim(Len, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(CountUnique, span))
+        ([.., Node::Instr(Instr::Prim(Deduplicate, _))], Instr::Prim(Len, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(CountUnique, span)))
         }
         // // Combine push temps
         // (
-        //     [.., Instr::PushTemp {
+        //     [.., Node::Instr(Instr::PushTemp {
         //         stack: a_stack,
         //         count: a_count,
         //         ..
-        //     }],
+        //     })],
         //     Instr::PushTemp {
         //         stack: b_stack,
         //         count: b_count,
@@ -84,11 +132,11 @@ pub(crate) fn optimize_instrs_mut(
         // }
         // // Combine pop temps
         // (
-        //     [.., Instr::PopTemp {
+        //     [.., Node::Instr(Instr::PopTemp {
         //         stack: a_stack,
         //         count: a_count,
         //         ..
-        //     }],
+        //     })],
         //     Instr::PopTemp {
         //         stack: b_stack,
         //         count: b_count,
@@ -99,11 +147,11 @@ pub(crate) fn optimize_instrs_mut(
         // }
         // Dips
         (
-            [.., Instr::PushTemp {
+            [.., Node::Instr(Instr::PushTemp {
                 stack: a_stack,
                 count: a_count,
                 ..
-            }],
+            })],
             Instr::PopTemp {
                 stack: b_stack,
                 count: b_count,
@@ -112,15 +160,15 @@ pub(crate) fn optimize_instrs_mut(
             },
         ) if maximal && *a_stack == b_stack && *a_count == b_count => {
             let count = *a_count;
-            instrs.pop();
-            instrs.push(Instr::TouchStack { count, span });
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::TouchStack { count, span }));
         }
         (
-            [.., Instr::PushTemp {
+            [.., Node::Instr(Instr::PushTemp {
                 stack: a_stack,
                 count: a_count,
                 ..
-            }, Instr::Prim(Identity, span)],
+            }), Node::Instr(Instr::Prim(Identity, span))],
             Instr::PopTemp {
                 stack: b_stack,
                 count: b_count,
@@ -129,16 +177,16 @@ pub(crate) fn optimize_instrs_mut(
         ) if maximal && *a_stack == b_stack && *a_count == b_count => {
             let span = *span;
             let count = *a_count + 1;
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::TouchStack { count, span });
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::TouchStack { count, span }));
         }
         (
-            [.., Instr::PushTemp {
+            [.., Node::Instr(Instr::PushTemp {
                 stack: a_stack,
                 count: a_count,
                 ..
-            }, Instr::TouchStack { count, span }],
+            }), Node::Instr(Instr::TouchStack { count, span })],
             Instr::PopTemp {
                 stack: b_stack,
                 count: b_count,
@@ -147,73 +195,85 @@ pub(crate) fn optimize_instrs_mut(
         ) if maximal && *a_stack == b_stack && *a_count == b_count => {
             let span = *span;
             let count = *a_count + *count;
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::TouchStack { count, span });
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::TouchStack { count, span }));
         }
         // Tranposes
-        ([.., Instr::Prim(Transpose, span)], Instr::Prim(Transpose, _)) => {
+        ([.., Node::Instr(Instr::Prim(Transpose, span))], Instr::Prim(Transpose, _)) => {
             let span = *span;
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(TransposeN(2), span));
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(TransposeN(2), span)));
         }
-        ([.., Instr::ImplPrim(TransposeN(n), _)], Instr::Prim(Transpose, _)) => {
+        ([.., Node::Instr(Instr::ImplPrim(TransposeN(n), _))], Instr::Prim(Transpose, _)) => {
             *n += 1;
             if *n == 0 {
-                instrs.pop();
+                nodes.pop();
             }
         }
-        ([.., Instr::ImplPrim(TransposeN(a), _)], Instr::ImplPrim(TransposeN(b), _)) => {
+        (
+            [.., Node::Instr(Instr::ImplPrim(TransposeN(a), _))],
+            Instr::ImplPrim(TransposeN(b), _),
+        ) => {
             *a += b;
             if *a == 0 {
-                instrs.pop();
+                nodes.pop();
             }
         }
         // Sorting
-        ([.., Instr::Prim(Dup, _), Instr::Prim(Rise, _)], Instr::Prim(Select, span)) => {
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::Prim(Sort, span));
+        (
+            [.., Node::Instr(Instr::Prim(Dup, _)), Node::Instr(Instr::Prim(Rise, _))],
+            Instr::Prim(Select, span),
+        ) => {
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::Prim(Sort, span)));
         }
-        ([.., Instr::Prim(Dup, _), Instr::Prim(Fall, _)], Instr::Prim(Select, span)) => {
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(SortDown, span));
+        (
+            [.., Node::Instr(Instr::Prim(Dup, _)), Node::Instr(Instr::Prim(Fall, _))],
+            Instr::Prim(Select, span),
+        ) => {
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(SortDown, span)));
         }
-        ([.., Instr::Prim(Sort, _)], Instr::Prim(Reverse, span)) => {
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(SortDown, span));
+        ([.., Node::Instr(Instr::Prim(Sort, _))], Instr::Prim(Reverse, span)) => {
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(SortDown, span)));
         }
         // Replace rand
-        ([.., Instr::Prim(Pop, span), Instr::Prim(Pop, _)], Instr::Prim(Rand, _)) => {
+        (
+            [.., Node::Instr(Instr::Prim(Pop, span)), Node::Instr(Instr::Prim(Pop, _))],
+            Instr::Prim(Rand, _),
+        ) => {
             let span = *span;
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(ReplaceRand2, span));
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(ReplaceRand2, span)));
         }
-        ([.., Instr::Prim(Pop, span)], Instr::ImplPrim(ReplaceRand, _)) => {
+        ([.., Node::Instr(Instr::Prim(Pop, span))], Instr::ImplPrim(ReplaceRand, _)) => {
             let span = *span;
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(ReplaceRand2, span));
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(ReplaceRand2, span)));
         }
-        ([.., Instr::Prim(Pop, span)], Instr::Prim(Rand, _)) => {
+        ([.., Node::Instr(Instr::Prim(Pop, span))], Instr::Prim(Rand, _)) => {
             let span = *span;
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(ReplaceRand, span));
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(ReplaceRand, span)));
         }
-        ([.., Instr::TouchStack { count: 1, span }], Instr::Prim(Rand, _)) => {
+        ([.., Node::Instr(Instr::TouchStack { count: 1, span })], Instr::Prim(Rand, _)) => {
             // By rand
             let span = *span;
-            instrs.pop();
-            instrs.push(Instr::Prim(Dup, span));
-            instrs.push(Instr::ImplPrim(ReplaceRand, span));
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::Prim(Dup, span)));
+            nodes.push(Node::Instr(Instr::ImplPrim(ReplaceRand, span)));
         }
         (
-            [.., Instr::PushTemp {
+            [.., Node::Instr(Instr::PushTemp {
                 stack: TempStack::Inline,
                 count: 1,
                 span,
-            }, Instr::Prim(Rand, _)],
+            }), Node::Instr(Instr::Prim(Rand, _))],
             Instr::PopTemp {
                 stack: TempStack::Inline,
                 count: 1,
@@ -222,149 +282,86 @@ pub(crate) fn optimize_instrs_mut(
         ) => {
             // On rand
             let span = *span;
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::copy_inline(span));
-            instrs.push(Instr::ImplPrim(ReplaceRand, span));
-            instrs.push(Instr::pop_inline(1, pop_span));
-        }
-        ([.., Instr::Prim(Windows, _), Instr::PushFunc(f)], instr @ Instr::Prim(Rows, span)) => {
-            match f.instrs(asm) {
-                // Adjacent
-                [inner @ Instr::PushFunc(reduced_f), Instr::Prim(Reduce, span)]
-                    if reduced_f.signature() == (2, 1) =>
-                {
-                    let inner = inner.clone();
-                    instrs.pop();
-                    instrs.pop();
-                    instrs.push(inner);
-                    instrs.push(Instr::ImplPrim(ImplPrimitive::Adjacent, *span));
-                }
-                // Rows Windows
-                _ if f.signature() == (1, 1) => {
-                    let f = f.clone();
-                    instrs.pop();
-                    instrs.pop();
-                    instrs.push(Instr::PushFunc(f));
-                    instrs.push(Instr::ImplPrim(ImplPrimitive::RowsWindows, span));
-                }
-                _ => {
-                    instrs.push(instr);
-                }
-            }
-        }
-        // Reduce depth
-        ([.., Instr::PushFunc(f)], instr @ Instr::Prim(Rows, _)) => {
-            if let [inner @ Instr::PushFunc(_), Instr::Prim(Reduce, span)] = f.instrs(asm) {
-                let inner = inner.clone();
-                instrs.pop();
-                instrs.push(inner);
-                instrs.push(Instr::ImplPrim(ImplPrimitive::ReduceDepth(1), *span));
-            } else if let [inner @ Instr::PushFunc(_), Instr::ImplPrim(ImplPrimitive::ReduceDepth(depth), span)] =
-                f.instrs(asm)
-            {
-                let inner = inner.clone();
-                instrs.pop();
-                instrs.push(inner);
-                instrs.push(Instr::ImplPrim(
-                    ImplPrimitive::ReduceDepth(depth + 1),
-                    *span,
-                ));
-            } else {
-                instrs.push(instr);
-            }
-        }
-        // Reduce table
-        (
-            [.., Instr::PushFunc(g), Instr::Prim(Table, _), Instr::PushFunc(f)],
-            Instr::Prim(Reduce, span),
-        ) if g.signature() == (2, 1) && f.signature() == (2, 1) => {
-            let f = instrs.pop().unwrap();
-            instrs.pop();
-            instrs.push(f);
-            instrs.push(Instr::ImplPrim(ImplPrimitive::ReduceTable, span));
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::copy_inline(span)));
+            nodes.push(Node::Instr(Instr::ImplPrim(ReplaceRand, span)));
+            nodes.push(Node::Instr(Instr::pop_inline(1, pop_span)));
         }
         // Pop constant
-        ([.., Instr::Push(_)], Instr::Prim(Pop, _)) => {
-            instrs.pop();
-        }
-        // End array repeat rand
-        (
-            [.., Instr::PushFunc(f), Instr::Prim(Repeat, span)],
-            Instr::EndArray { boxed: false, .. },
-        ) if f.as_primitive(asm) == Some(Rand) => {
-            let span = *span;
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(EndRandArray, span));
+        ([.., Node::Instr(Instr::Push(_))], Instr::Prim(Pop, _)) => {
+            nodes.pop();
         }
         // Astar first
-        ([.., Instr::Prim(Astar, span)], Instr::Prim(First, _)) => {
-            let span = *span;
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(AstarFirst, span));
+        ([.., Node::Mod(Astar, args, span)], Instr::Prim(First, _)) => {
+            let node = Node::ImplMod(AstarFirst, args.clone(), *span);
+            nodes.pop();
+            nodes.push(node);
         }
-        ([.., Instr::Prim(Astar, span)], Instr::Prim(Pop, pop_span)) => {
-            let span = *span;
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(AstarFirst, span));
-            instrs.push(Instr::Prim(Pop, pop_span));
+        ([.., Node::Mod(Astar, args, span)], Instr::Prim(Pop, pop_span)) => {
+            let node = Node::ImplMod(AstarFirst, args.clone(), *span);
+            nodes.pop();
+            nodes.push(node);
+            nodes.push(Node::Instr(Instr::Prim(Pop, pop_span)));
         }
         // TraceN
-        ([.., Instr::Prim(Trace, span)], Instr::Prim(Trace, _)) => {
+        ([.., Node::Instr(Instr::Prim(Trace, span))], Instr::Prim(Trace, _)) => {
             let span = *span;
-            instrs.pop();
-            instrs.push(Instr::ImplPrim(
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::ImplPrim(
                 TraceN {
                     n: 2,
                     inverse: false,
                     stack_sub: false,
                 },
                 span,
-            ));
+            )));
         }
         (
-            [.., Instr::ImplPrim(
+            [.., Node::Instr(Instr::ImplPrim(
                 TraceN {
                     n, inverse: false, ..
                 },
                 _,
-            )],
+            ))],
             Instr::Prim(Trace, _),
         ) => {
             *n += 1;
             if *n == 0 {
-                instrs.pop();
+                nodes.pop();
             }
         }
         // Validate type
         (
-            [.., Instr::Prim(Dup, _), Instr::Prim(Type, _), Instr::Push(val)],
+            [.., Node::Instr(Instr::Prim(Dup, _)), Node::Instr(Instr::Prim(Type, _)), Node::Instr(Instr::Push(val))],
             Instr::ImplPrim(MatchPattern, span),
         ) => {
             let val = val.clone();
-            instrs.pop();
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::Push(val));
-            instrs.push(Instr::ImplPrim(ValidateType, span));
+            nodes.pop();
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::Push(val)));
+            nodes.push(Node::Instr(Instr::ImplPrim(ValidateType, span)));
         }
-        ([.., Instr::Prim(Type, _), Instr::Push(val)], Instr::ImplPrim(MatchPattern, span)) => {
+        (
+            [.., Node::Instr(Instr::Prim(Type, _)), Node::Instr(Instr::Push(val))],
+            Instr::ImplPrim(MatchPattern, span),
+        ) => {
             let val = val.clone();
-            instrs.pop();
-            instrs.pop();
-            instrs.push(Instr::Push(val));
-            instrs.push(Instr::ImplPrim(ValidateTypeConsume, span));
+            nodes.pop();
+            nodes.pop();
+            nodes.push(Node::Instr(Instr::Push(val)));
+            nodes.push(Node::Instr(Instr::ImplPrim(ValidateTypeConsume, span)));
         }
         (
-            [.., Instr::ImplPrim(
+            [.., Node::Instr(Instr::ImplPrim(
                 TraceN {
                     n: a,
                     inverse: inv_a,
                     ..
                 },
                 _,
-            )],
+            ))],
             Instr::ImplPrim(
                 TraceN {
                     n: b,
@@ -376,26 +373,82 @@ pub(crate) fn optimize_instrs_mut(
         ) if *inv_a == inv_b => {
             *a += b;
             if *a == 0 {
-                instrs.pop();
+                nodes.pop();
             }
         }
-        (_, instr) => instrs.push(instr),
+        (_, instr) => nodes.push(Node::Instr(instr)),
+    }
+}
+
+fn optimize_mod_mut(nodes: &mut EcoVec<Node>, new: Node) {
+    use ImplPrimitive::*;
+    use Primitive::*;
+    match (nodes.make_mut(), new) {
+        ([.., Node::Instr(Instr::Prim(Windows, _))], Node::Mod(Rows, args, rows_span)) => {
+            let windowed = match args.as_slice() {
+                // Adjacent
+                [f] => match f.nodes() {
+                    [Node::Mod(Reduce, reduced, span)]
+                        if reduced.len() == 1 && reduced[0].sig == (2, 1) =>
+                    {
+                        Some(Node::ImplMod(Adjacent, reduced.clone(), *span))
+                    }
+                    // Rows Windows
+                    _ if f.sig == (1, 1) => {
+                        Some(Node::ImplMod(RowsWindows, args.clone(), rows_span))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            };
+            match windowed {
+                Some(node) => {
+                    nodes.pop();
+                    nodes.push(node);
+                }
+                None => nodes.push(Node::Mod(Rows, args, rows_span)),
+            }
+        }
+        // Reduce depth
+        (_, Node::Mod(Rows, args, rows_span)) => {
+            match args.as_slice() {
+                [f] => match f.nodes() {
+                    [Node::Mod(Reduce, reduced, span)] => {
+                        nodes.push(Node::ImplMod(ReduceDepth(1), reduced.clone(), *span))
+                    }
+                    [Node::ImplMod(ReduceDepth(depth), reduced, span)] => nodes.push(
+                        Node::ImplMod(ReduceDepth(depth + 1), reduced.clone(), *span),
+                    ),
+                    _ => nodes.push(Node::Mod(Rows, args, rows_span)),
+                },
+                _ => nodes.push(Node::Mod(Rows, args, rows_span)),
+            }
+        }
+        // Reduce table
+        ([.., Node::Mod(Table, table_args, _)], Node::Mod(Reduce, args, span))
+            if (table_args.iter().chain(&args)).all(|f| f.sig == (2, 1))
+                && table_args.len() == 1
+                && args.len() == 1 =>
+        {
+            let mut args = args;
+            args.extend(table_args.iter().cloned());
+            nodes.pop();
+            nodes.push(Node::ImplMod(ReduceTable, args, span));
+        }
+        (_, node) => nodes.push(node),
     }
 }
 
-pub(crate) fn optimize_instrs<I>(instrs: I, maximal: bool, asm: &Assembly) -> EcoVec<Instr>
+pub(crate) fn optimize_nodes<I>(nodes: I, maximal: bool, asm: &Assembly) -> EcoVec<Node>
 where
-    I: IntoIterator<Item = Instr> + fmt::Debug,
+    I: IntoIterator<Item = Node> + fmt::Debug,
     I::IntoIter: ExactSizeIterator,
 {
-    // println!("optimize {:?}", instrs);
-    let instrs = instrs.into_iter();
-    let mut new = EcoVec::with_capacity(instrs.len());
-    for instr in instrs {
-        if maximal && instr.is_compile_only() {
-            continue;
-        }
-        optimize_instrs_mut(&mut new, instr, maximal, asm);
+    // println!("optimize {:?}", nodes);
+    let nodes = nodes.into_iter();
+    let mut new = EcoVec::with_capacity(nodes.len());
+    for node in nodes {
+        optimize_nodes_mut(&mut new, node, maximal, asm);
     }
     // println!("to       {:?}", new);
     new
@@ -2737,6 +2737,8 @@ primitive!(
     /// Expects a thread id returned by [spawn] or [pool] and a value to send.
     /// The thread id `0` corresponds to the parent thread.
     /// The sent-to thread can receive the value with [recv] or [tryrecv].
+    ///
+    /// A channel handle created with [&chan] can be used in place of the thread id.
     (2(0), Send, Thread, "send", Impure),
     /// Receive a value from a thread
     ///
@@ -2745,6 +2747,8 @@ primitive!(
     /// The sending thread can send a value with [send].
     ///
     /// Unlike [tryrecv], [recv] blocks until a value is received.
+    ///
+    /// A channel handle created with [&chan] can be used in place of the thread id.
     (1, Recv, Thread, "recv", Impure),
     /// Try to receive a value from a thread
     ///
@@ -2755,6 +2759,8 @@ primitive!(
     /// Unlike [recv], [tryrecv] does not block.
     /// If no value is available, then an error is thrown.
     /// The error can be caught with [try].
+    ///
+    /// A channel handle created with [&chan] can be used in place of the thread id.
     (1, TryRecv, Thread, "tryrecv", Impure),
     /// Generate an array of random numbers with a seed
     ///
@@ -3319,6 +3325,35 @@ primitive!(
     /// While it is not useful to display the output bytes here, we can see how the result of decoding works:
     /// ex: °xlsx xlsx . ↯3_6⇡18
     (1, Xlsx, Encoding, "xlsx"),
+    /// Encode an array into NumPy's NPY format
+    ///
+    /// NPY is the binary format that NumPy uses to save arrays. It preserves an array's shape and the precision of its elements.
+    /// The output is a byte array.
+    ///
+    /// Byte arrays are encoded as `uint8`, number arrays as `float64`, complex arrays as `complex128`, and character arrays as single-character strings.
+    /// A box array of strings is encoded as a string array.
+    ///
+    /// A [map] array with names as keys is encoded as an NPZ archive, which NumPy loads as a dictionary of named arrays.
+    ///
+    /// You can use [un][npy] to decode NPY or NPZ bytes.
+    /// Integer and floating-point arrays of any size decode as numbers, and NPZ archives decode as [map]s.
+    /// ex: °npy npy ↯2_3⇡6
+    /// ex: °npy npy "Hello!"
+    /// ex: °npy npy map {"x" "y"} {[1 2 3] ℂ1 2}
+    (1, Npy, Encoding, "npy"),
+    /// Encode any value into bytes without losing information
+    ///
+    /// Unlike [json] and [repr], the encoding preserves the exact type, shape, and elements of an array, including nested boxes.
+    /// Labels and [map] keys are preserved as well.
+    /// The output is a byte array.
+    /// ex: binary [1 2 3]
+    ///
+    /// You can use [un][binary] to decode the bytes back into the original value.
+    /// ex: °binary binary {ℂ1 2 "Hi!" [1_2 3_4]}
+    /// ex: °binary binary map {"a" "b"} {$Uno 1 [2 3]}
+    ///
+    /// The encoding begins with the bytes `UAV` followed by a format version byte.
+    (1, Binary, Encoding, "binary"),
     /// Convert a value to its code representation
     ///
     /// ex: repr π
@@ -3356,18 +3391,37 @@ primitive!(
     /// A length 4 last axis is an RGB image with an alpha channel.
     ///
     /// You can decode a byte array into an image with [un][img].
+    /// [un][img] also returns the detected format, so [under][un][img] re-encodes the image in the format it was read in.
+    ///
+    /// Supported formats are `jpg`, `png`, `bmp`, `gif`, `ico`, `qoi`, `tiff`, `webp`, and `pnm`.
+    /// `webp` images are encoded losslessly.
     ///
-    /// Supported formats are `jpg`, `png`, `bmp`, `gif`, `ico`, and `qoi`.
+    /// The format can also be a list whose first item is the format and whose other items are options.
+    /// `jpg` takes a quality between 1 and 100 (default 100).
+    /// ex: ⧻img {"jpg" 30} ⊞× . ÷⟜⇡100
+    /// `png` takes a compression level of `"fast"` (default), `"default"`, or `"best"`.
+    /// ex: ⧻img {"png" "best"} ⊞× . ÷⟜⇡100
     ///
     /// See also: [&ims]
     (2, ImageEncode, Encoding, "img"),
     /// Encode a gif into a byte array
     ///
-    /// The first argument is a framerate in seconds.
+    /// The first argument is a framerate in frames per second, or a list of the delay after each frame in seconds.
     /// The second argument is the gif data and must be a rank 3 or 4 numeric array.
     /// The rows of the array are the frames of the gif, and their format must conform to that of [img].
+    /// ex: ⧻gif [0.1 0.2 0.5] ÷10⇡3_10_10
+    ///
+    /// The first argument can also be a list whose first item is the framerate or delays and whose other items are options.
+    /// The first scalar option is the number of times the gif plays (default [infinity]).
+    /// The second scalar option is an alpha threshold between 0 and 1. Pixels with a lower alpha are transparent.
+    /// `"global"` (default) makes all frames share a palette, and `"local"` gives each frame its own.
+    /// `"dither"` dithers colors that do not fit in the palette.
+    /// ex: ⧻gif {10 1 "local" "dither"} ÷10⇡3_10_10
     ///
     /// You can decode a byte array into a gif with [un][gif].
+    /// [un][gif] returns the gif's options along with its frames, so [under][un][gif] preserves the gif's timing, transparency, and palettes.
+    /// The options are always a list of the frame delays, the play count, the alpha threshold, and `"global"` or `"local"`.
+    /// ex: ⊙◌°gif gif {[0.1 0.2 0.5] 3 "local"} ÷10⇡3_10_10
     ///
     /// See also: [&gifs]
     (2, GifEncode, Encoding, "gif"),
@@ -3432,6 +3486,41 @@ primitive!(
     /// ex: # Experimental!
     ///   : ⬚[1 0 0] layout {100 0_1_0} "Green on Red!"
     (2, Layout, Encoding, "layout", Impure),
+    /// Render a chart into an image array
+    ///
+    /// The first argument is a list of options, and the second argument is the data to plot.
+    /// The result is an RGBA image array that can be shown with [&ims] or encoded with [img].
+    ///
+    /// The chart kind is set with a string option. The default is `"line"`.
+    /// ex: # Experimental!
+    ///   : plot "line" ∿÷10⇡60
+    /// Each row of a rank 2 array is plotted as a separate series.
+    /// ex: # Experimental!
+    ///   : plot "line" [⊃∿(∿×2)] ÷10⇡60
+    /// A `"scatter"` plot takes a table whose rows are x and y coordinates.
+    /// A rank 3 array is a list of such tables.
+    /// ex: # Experimental!
+    ///   : plot "scatter" ⊟:ⁿ2. ÷4 -10⇡21
+    /// A `"bar"` plot draws a bar for each value. Multiple series are grouped.
+    /// ex: # Experimental!
+    ///   : plot "bar" [3_1_4_1_5 2_7_1_8_2]
+    /// A `"hist"` plot counts how many values fall into each of a number of equal-width bins.
+    /// ex: # Experimental!
+    ///   : plot {"hist" 12 8} ⁿ2 ÷10⇡50
+    ///
+    /// The other options are similar to those of [layout].
+    /// The first scalar option is the label size (default 12).
+    /// The second scalar option is the number of histogram bins (default 10).
+    /// An array of 2 numbers is the image size (default 300_400).
+    /// Arrays of 3 or 4 numbers are the colors of the series, in order.
+    /// ex: # Experimental!
+    ///   : plot {"bar" 150_300 0.8_0.2_0.4} [5 3 6 2]
+    /// [fill] sets the background color.
+    /// ex: # Experimental!
+    ///   : ⬚0.1 plot "line" ×.⇡10
+    ///
+    /// Axis labels are rendered with the same font as [layout].
+    (2, Plot, Encoding, "plot", Impure),
 );
 
 macro_rules! impl_primitive {
@@ -3530,6 +3619,8 @@ impl_primitive!(
     (1, UnJson),
     (1, UnCsv),
     (1, UnXlsx),
+    (1, UnNpy),
+    (1, UnBinary),
     (1, UnFft),
     (1, UnDatetime),
     (2, ProgressiveIndexOf),
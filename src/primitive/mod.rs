@@ -187,6 +187,8 @@ impl fmt::Display for ImplPrimitive {
             UnJson => write!(f, "{Un}{Json}"),
             UnCsv => write!(f, "{Un}{Csv}"),
             UnXlsx => write!(f, "{Un}{Xlsx}"),
+            UnNpy => write!(f, "{Un}{Npy}"),
+            UnBinary => write!(f, "{Un}{Binary}"),
             UnFft => write!(f, "{Un}{Fft}"),
             UnDatetime => write!(f, "{Un}{DateTime}"),
             ImageDecode => write!(f, "{Un}{ImageEncode}"),
@@ -510,7 +512,7 @@ impl Primitive {
             (Anti | Off | Backward | Above | Around)
                 | (Tuples | Choose | Permute)
                 | (Struct | Obverse)
-                | (Last | Sort | Chunks | Base | Coordinate | Fft | Case | Layout)
+                | (Last | Sort | Chunks | Base | Coordinate | Fft | Case | Layout | Plot)
                 | (Astar | Triangle)
                 | Sys(Ffi | MemCopy | MemFree | TlsListen)
                 | (Stringify | Quote | Sig)
@@ -992,10 +994,17 @@ impl Primitive {
             Primitive::Xlsx => {
                 env.monadic_ref_env(|value, env| value.to_xlsx(env).map(EcoVec::from))?
             }
+            Primitive::Npy => {
+                env.monadic_ref_env(|value, env| value.to_npy(env).map(EcoVec::from))?
+            }
+            Primitive::Binary => {
+                env.monadic_ref_env(|value, env| value.to_binary(env).map(EcoVec::from))?
+            }
             Primitive::ImageEncode => encode::image_encode(env)?,
             Primitive::GifEncode => encode::gif_encode(env)?,
             Primitive::AudioEncode => encode::audio_encode(env)?,
             Primitive::Layout => env.dyadic_oo_env(encode::layout_text)?,
+            Primitive::Plot => env.dyadic_oo_env(plot::plot)?,
             Primitive::Astar => algorithm::astar(env)?,
             Primitive::Fft => algorithm::fft(env)?,
             Primitive::Stringify
@@ -1137,6 +1146,16 @@ impl ImplPrimitive {
                 let val = Value::from_xlsx(&xlsx, env)?;
                 env.push(val);
             }
+            ImplPrimitive::UnNpy => {
+                let npy = env.pop(1)?.as_bytes(env, "NPY expects bytes")?;
+                let val = Value::from_npy(&npy, env)?;
+                env.push(val);
+            }
+            ImplPrimitive::UnBinary => {
+                let bytes = env.pop(1)?.as_bytes(env, "Binary decoding expects bytes")?;
+                let val = Value::from_binary(&bytes, env)?;
+                env.push(val);
+            }
             ImplPrimitive::UnFft => algorithm::unfft(env)?,
             ImplPrimitive::UnDatetime => env.monadic_ref_env(Value::undatetime)?,
             ImplPrimitive::ProgressiveIndexOf => env.dyadic_rr_env(Value::progressive_index_of)?,
@@ -1443,6 +1462,20 @@ pub fn seed_random(seed: u64) {
     RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
 }
 
+/// Run a spawned thread with the random number generator seeded from a seed and the thread's path
+///
+/// The previous generator is restored afterwards,
+/// as pool threads may run another thread's code while one is waiting.
+pub(crate) fn with_thread_seed<T>(seed: u64, path: &[usize], f: impl FnOnce() -> T) -> T {
+    let thread_rng = (path.iter()).fold(SmallRng::seed_from_u64(seed), |mut rng, &id| {
+        SmallRng::seed_from_u64(rng.gen::<u64>() ^ id as u64)
+    });
+    let prev = RNG.with(|rng| rng.replace(thread_rng));
+    let res = f();
+    RNG.with(|rng| *rng.borrow_mut() = prev);
+    res
+}
+
 fn trace(env: &mut Uiua, inverse: bool) -> UiuaResult {
     let val = env.pop(1)?;
     let span: String = if inverse {
@@ -1929,8 +1962,8 @@ mod tests {
             for line in &prim.doc().lines {
                 if let PrimDocLine::Example(ex) = line {
                     if [
-                        "&sl", "&tcpc", "&tlsc", "&ast", "&clip", "&fo", "&fc", "&fde", "&ftr",
-                        "&fld", "&fif", "&fras", "&frab", "&fmd", "timezone",
+                        "&sl", "&tcpc", "&tlsc", "&http", "&ast", "&clip", "&fo", "&fc", "&fde",
+                        "&ftr", "&fld", "&fif", "&fras", "&frab", "&fmd", "timezone",
                     ]
                     .iter()
                     .any(|prim| ex.input.contains(prim))
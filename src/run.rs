@@ -10,24 +10,36 @@ use std::{
     panic::{catch_unwind, AssertUnwindSafe},
     path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{
+        atomic::{self, AtomicBool, AtomicU64},
+        Arc,
+    },
     time::Duration,
 };
 
 use crossbeam_channel::{Receiver, Sender, TryRecvError};
 use enum_iterator::{all, Sequence};
+use parking_lot::Mutex;
 use thread_local::ThreadLocal;
 
 use crate::{
-    algorithm::{self, invert, validate_size_impl},
+    algorithm::{
+        self, invert,
+        loops::{self, Loop},
+        validate_size_impl,
+    },
     check::instrs_temp_signatures,
     fill::Fill,
     function::*,
     instr::*,
     lex::Span,
-    Array, Assembly, BindingKind, Boxed, CodeSpan, Compiler, Ident, Inputs, IntoSysBackend,
-    LocalName, Primitive, Report, SafeSys, SysBackend, SysOp, TraceFrame, UiuaError, UiuaErrorKind,
-    UiuaResult, Value, VERSION,
+    memory::{self, format_bytes, MemoryBudget},
+    primitive::with_thread_seed,
+    seed_random,
+    step::{Budget, Execution},
+    Array, Assembly, BindingKind, Boxed, CodeSpan, Compiler, DynamicModule, Handle, HandleKind,
+    Ident, ImplPrimitive, Inputs, IntoSysBackend, LocalName, PrimClass, Primitive, Report, SafeSys,
+    SysBackend, SysOp, TraceFrame, UiuaError, UiuaErrorKind, UiuaResult, Value, VERSION,
 };
 
 /// The Uiua interpreter
@@ -51,6 +63,10 @@ pub(crate) struct Runtime {
     pub(crate) array_stack: Vec<usize>,
     /// The call stack
     pub(crate) call_stack: Vec<StackFrame>,
+    /// Loops that are calling their functions from the call stack
+    pub(crate) loops: Vec<LoopFrame>,
+    /// How far the program may run before it pauses
+    pub(crate) budget: Option<Budget>,
     /// The stack for tracking recursion points
     recur_stack: Vec<usize>,
     /// The fill stack
@@ -63,8 +79,20 @@ pub(crate) struct Runtime {
     pub(crate) execution_limit: Option<f64>,
     /// The time at which execution started
     pub(crate) execution_start: f64,
+    /// A limit on the memory used by values
+    memory_budget: Option<Arc<MemoryBudget>>,
+    /// The seed the backend gave for this run's random numbers
+    random_seed: Option<u64>,
+    /// A limit on the fuel consumed by executed instructions
+    fuel_limit: Option<u64>,
+    /// Whether to count fuel even without a limit
+    count_fuel: bool,
+    /// The fuel consumed by this thread since execution started
+    fuel_used: Arc<AtomicU64>,
     /// Whether the program was interrupted
     pub(crate) interrupted: Option<Arc<dyn Fn() -> bool + Send + Sync>>,
+    /// A hook called before each instruction is executed
+    debug_hook: Option<Arc<DebugHook>>,
     /// Whether to print the time taken to execute each instruction
     time_instrs: bool,
     /// The time at which the last instruction was executed
@@ -85,10 +113,34 @@ pub(crate) struct Runtime {
     pub(crate) test_results: Vec<UiuaResult>,
     /// Reports to print
     pub(crate) reports: Vec<Report>,
+    /// Modules loaded at runtime
+    pub(crate) dynamic_modules: HashMap<Handle, Arc<DynamicModule>>,
+    /// Channels shared between threads
+    channels: Arc<Mutex<HashMap<Handle, SharedChannel>>>,
 }
 
 type MemoMap = HashMap<FunctionId, HashMap<Vec<Value>, Vec<Value>>>;
 
+/// A hook called before each instruction is executed
+///
+/// Returning an error stops execution with that error.
+pub type DebugHook = dyn Fn(&Uiua) -> UiuaResult + Send + Sync;
+
+/// Information about a function call that is currently executing
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    /// The id of the function
+    pub id: FunctionId,
+    /// The signature of the function
+    pub sig: Signature,
+    /// The span of the code that called the function
+    pub call_span: Span,
+    /// The index of the next instruction to be executed in the function
+    pub instr: usize,
+    /// The span of the code currently being executed in the function, if it is known
+    pub span: Option<Span>,
+}
+
 impl AsRef<Assembly> for Uiua {
     fn as_ref(&self) -> &Assembly {
         &self.asm
@@ -114,6 +166,35 @@ pub(crate) struct StackFrame {
     pub(crate) pc: usize,
     /// Additional spans for error reporting
     spans: Vec<(usize, Option<Primitive>)>,
+    /// The stack height when the interpreter loop called the function
+    ///
+    /// This is used to check the function's signature when it returns.
+    start_height: Option<usize>,
+}
+
+impl StackFrame {
+    fn new(f: &Function, call_span: usize, start_height: Option<usize>) -> Self {
+        StackFrame {
+            slice: f.slice(),
+            sig: f.signature(),
+            id: f.id.clone(),
+            track_caller: f.flags.track_caller(),
+            call_span,
+            spans: Vec::new(),
+            pc: 0,
+            start_height,
+        }
+    }
+}
+
+/// A looping primitive that the interpreter runs one call at a time
+#[derive(Debug, Clone)]
+pub(crate) struct LoopFrame {
+    /// The height of the call stack when the loop started
+    pub(crate) depth: usize,
+    prim: Option<Primitive>,
+    span: usize,
+    state: Loop,
 }
 
 #[derive(Debug, Clone)]
@@ -122,11 +203,23 @@ struct Channel {
     pub recv: Receiver<Value>,
 }
 
+/// A channel created with `&chan` that any thread can use
+#[derive(Debug)]
+struct SharedChannel {
+    /// The sending end, which is removed when the channel is closed
+    send: Option<Sender<Value>>,
+    recv: Receiver<Value>,
+}
+
 #[derive(Debug, Clone)]
 struct ThisThread {
     pub parent: Option<Channel>,
     pub children: HashMap<usize, Thread>,
     pub next_child_id: usize,
+    /// The ids of this thread and its ancestors, outermost first
+    pub path: Vec<usize>,
+    /// The flags that cancel this thread, its own and those of its ancestors
+    pub cancel_flags: Vec<Arc<AtomicBool>>,
 }
 
 impl Default for ThisThread {
@@ -135,6 +228,8 @@ impl Default for ThisThread {
             parent: Default::default(),
             children: Default::default(),
             next_child_id: 1,
+            path: Vec::new(),
+            cancel_flags: Vec::new(),
         }
     }
 }
@@ -146,6 +241,8 @@ struct Thread {
     #[cfg(target_arch = "wasm32")]
     pub result: UiuaResult<Vec<Value>>,
     pub channel: Channel,
+    pub cancelled: Arc<AtomicBool>,
+    pub fuel_used: Arc<AtomicU64>,
 }
 
 impl Default for Uiua {
@@ -195,7 +292,10 @@ impl Default for Runtime {
                 call_span: 0,
                 pc: 0,
                 spans: Vec::new(),
+                start_height: None,
             }],
+            loops: Vec::new(),
+            budget: None,
             recur_stack: Vec::new(),
             fill_stack: Vec::new(),
             fill_boundary_stack: Vec::new(),
@@ -207,12 +307,20 @@ impl Default for Runtime {
             cli_file_path: PathBuf::new(),
             execution_limit: None,
             execution_start: 0.0,
+            memory_budget: None,
+            random_seed: None,
+            fuel_limit: None,
+            count_fuel: false,
+            fuel_used: Arc::new(AtomicU64::new(0)),
             interrupted: None,
+            debug_hook: None,
             thread: ThisThread::default(),
             output_comments: HashMap::new(),
             memo: Arc::new(ThreadLocal::new()),
             test_results: Vec::new(),
             reports: Vec::new(),
+            dynamic_modules: HashMap::new(),
+            channels: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
@@ -286,11 +394,99 @@ impl Uiua {
         self.rt.execution_limit = limit.map(|limit| limit.as_secs_f64());
         self
     }
+    /// Limit the memory used by values, in bytes
+    ///
+    /// Exceeding the limit produces an error that can be caught.
+    /// The limit is approximate. It applies to the arrays on the stacks and in bindings,
+    /// and is shared with any threads the program spawns.
+    pub fn with_memory_limit(mut self, bytes: usize) -> Self {
+        self.rt.memory_budget = Some(Arc::new(MemoryBudget::new(bytes)));
+        self
+    }
+    /// Limit the memory used by values, in bytes
+    pub fn maybe_with_memory_limit(mut self, bytes: Option<usize>) -> Self {
+        self.rt.memory_budget = bytes.map(|bytes| Arc::new(MemoryBudget::new(bytes)));
+        self
+    }
+    /// Limit the fuel consumed by executed instructions
+    ///
+    /// Every instruction consumes 1 fuel. Primitives also consume 1 fuel per element
+    /// of their arguments and of their outputs. The arguments of a modifier are taken to be
+    /// as many values as the most any of its functions takes.
+    /// Unlike an execution limit, running out of fuel happens at the same point on every machine.
+    ///
+    /// A spawned thread may consume the fuel that was left when it was spawned.
+    /// The fuel it consumed is added to the spawning thread's when it is waited on.
+    pub fn with_fuel_limit(mut self, fuel: u64) -> Self {
+        self.rt.fuel_limit = Some(fuel);
+        self
+    }
+    /// Limit the fuel consumed by executed instructions
+    pub fn maybe_with_fuel_limit(mut self, fuel: Option<u64>) -> Self {
+        self.rt.fuel_limit = fuel;
+        self
+    }
+    /// Count the fuel consumed by executed instructions even if there is no fuel limit
+    pub fn with_fuel_counting(mut self) -> Self {
+        self.rt.count_fuel = true;
+        self
+    }
+    /// Get the fuel consumed by the last run, including that of any threads it waited on
+    ///
+    /// Fuel is only counted if there is a fuel limit or [`Uiua::with_fuel_counting`] was used.
+    pub fn fuel_used(&self) -> u64 {
+        self.rt.fuel_used.load(atomic::Ordering::Relaxed)
+    }
+    /// Get the approximate number of bytes used by the values on the stacks and in bindings
+    pub fn memory_usage(&self) -> usize {
+        let bindings = self
+            .asm
+            .bindings
+            .iter()
+            .filter_map(|binding| match &binding.kind {
+                BindingKind::Const(Some(val)) => Some(val),
+                _ => None,
+            });
+        memory::measure(
+            (self.rt.stack.iter())
+                .chain(self.rt.temp_stacks.iter().flatten())
+                .chain(&self.rt.fill_stack)
+                .chain(&self.rt.unfill_stack)
+                .chain(bindings),
+        )
+    }
     /// Set the interrupted hook
     pub fn with_interrupt_hook(mut self, hook: impl Fn() -> bool + Send + Sync + 'static) -> Self {
         self.rt.interrupted = Some(Arc::new(hook));
         self
     }
+    /// Set a hook that is called before each instruction is executed
+    ///
+    /// This is used to implement debuggers.
+    /// Spawned threads do not call the hook.
+    pub fn with_debug_hook(
+        mut self,
+        hook: impl Fn(&Uiua) -> UiuaResult + Send + Sync + 'static,
+    ) -> Self {
+        self.rt.debug_hook = Some(Arc::new(hook));
+        self
+    }
+    /// Start running a Uiua assembly that can be advanced a few instructions at a time
+    ///
+    /// See [`Execution`] for more information.
+    pub fn start(mut self, asm: impl Into<Assembly>) -> Execution {
+        self.begin_run(asm.into());
+        Execution::new(self, true)
+    }
+    /// Start calling a function that can be advanced a few instructions at a time
+    ///
+    /// See [`Execution`] for more information.
+    pub fn start_call(mut self, f: Function) -> Execution {
+        let call_span = self.span_index();
+        let start_height = self.rt.stack.len();
+        (self.rt.call_stack).push(StackFrame::new(&f, call_span, Some(start_height)));
+        Execution::new(self, false)
+    }
     /// Set the command line arguments
     pub fn with_args(mut self, args: Vec<String>) -> Self {
         self.rt.cli_arguments = args;
@@ -354,37 +550,81 @@ impl Uiua {
     /// Run a Uiua assembly
     pub fn run_asm(&mut self, asm: impl Into<Assembly>) -> UiuaResult {
         fn run_asm(env: &mut Uiua, asm: Assembly) -> UiuaResult {
-            env.asm = asm;
-            env.rt.execution_start = env.rt.backend.now();
-            let mut res = env.run_top_slices();
-            let mut push_error = |te: UiuaError| match &mut res {
-                Ok(()) => res = Err(te),
-                Err(e) => e.multi.push(te),
-            };
-            if !env.rt.test_results.is_empty() {
-                let total = env.rt.test_results.len();
-                let mut successes = 0;
-                for res in env.rt.test_results.drain(..) {
-                    match res {
-                        Ok(()) => successes += 1,
-                        Err(e) => push_error(e),
-                    }
+            env.begin_run(asm);
+            let res = env.run_top_slices();
+            env.end_run(res)
+        }
+        run_asm(self, asm.into())
+    }
+    fn begin_run(&mut self, asm: Assembly) {
+        self.asm = asm;
+        self.rt.execution_start = self.rt.backend.now();
+        self.rt.fuel_used.store(0, atomic::Ordering::Relaxed);
+        self.rt.random_seed = self.rt.backend.random_seed();
+        if let Some(seed) = self.rt.random_seed {
+            seed_random(seed);
+        }
+    }
+    /// Report test results, and reset the runtime if the run failed
+    pub(crate) fn end_run(&mut self, mut res: UiuaResult) -> UiuaResult {
+        let mut push_error = |te: UiuaError| match &mut res {
+            Ok(()) => res = Err(te),
+            Err(e) => e.multi.push(te),
+        };
+        if !self.rt.test_results.is_empty() {
+            let total = self.rt.test_results.len();
+            let mut successes = 0;
+            for res in self.rt.test_results.drain(..) {
+                match res {
+                    Ok(()) => successes += 1,
+                    Err(e) => push_error(e),
                 }
-                (env.rt.reports).push(Report::tests(successes, total - successes));
             }
-            if res.is_err() {
-                env.rt = Runtime {
-                    backend: env.rt.backend.clone(),
-                    execution_limit: env.rt.execution_limit,
-                    time_instrs: env.rt.time_instrs,
-                    output_comments: take(&mut env.rt.output_comments),
-                    reports: take(&mut env.rt.reports),
-                    ..Runtime::default()
-                };
-            }
-            res
+            (self.rt.reports).push(Report::tests(successes, total - successes));
+        }
+        if res.is_err() {
+            self.rt = Runtime {
+                backend: self.rt.backend.clone(),
+                execution_limit: self.rt.execution_limit,
+                memory_budget: self.rt.memory_budget.clone(),
+                fuel_limit: self.rt.fuel_limit,
+                count_fuel: self.rt.count_fuel,
+                fuel_used: self.rt.fuel_used.clone(),
+                time_instrs: self.rt.time_instrs,
+                output_comments: take(&mut self.rt.output_comments),
+                reports: take(&mut self.rt.reports),
+                ..Runtime::default()
+            };
+        }
+        res
+    }
+    /// Make a runtime for code that runs on behalf of this one, such as a dynamic module
+    ///
+    /// It shares this runtime's backend, limits, cancellation, and channels,
+    /// so the code it runs counts against the same budgets.
+    pub(crate) fn nested(&self, asm: Assembly) -> Uiua {
+        Uiua {
+            asm,
+            rt: Runtime {
+                backend: self.rt.backend.clone(),
+                execution_limit: self.rt.execution_limit,
+                execution_start: self.rt.execution_start,
+                memory_budget: (self.rt.memory_budget.as_deref())
+                    .map(|budget| Arc::new(budget.split())),
+                random_seed: self.rt.random_seed,
+                fuel_limit: self.rt.fuel_limit,
+                count_fuel: self.rt.count_fuel,
+                fuel_used: self.rt.fuel_used.clone(),
+                interrupted: self.rt.interrupted.clone(),
+                channels: self.rt.channels.clone(),
+                thread: ThisThread {
+                    path: self.rt.thread.path.clone(),
+                    cancel_flags: self.rt.thread.cancel_flags.clone(),
+                    ..ThisThread::default()
+                },
+                ..Runtime::default()
+            },
         }
-        run_asm(self, asm.into())
     }
     pub(crate) fn run_top_slices(&mut self) -> UiuaResult {
         let top_slices = take(&mut self.asm.top_slices);
@@ -402,7 +642,7 @@ impl Uiua {
         self.asm.top_slices = top_slices;
         res
     }
-    fn catching_crash<T>(
+    pub(crate) fn catching_crash<T>(
         &mut self,
         input: impl fmt::Display,
         f: impl FnOnce(&mut Self) -> T,
@@ -426,12 +666,67 @@ code:
             ))),
         }
     }
-    fn exec(&mut self, frame: StackFrame) -> UiuaResult {
-        let slice = frame.slice;
+    fn exec(&mut self, mut frame: StackFrame) -> UiuaResult {
+        let base = self.rt.call_stack.len();
+        // The caller checks the signature
+        frame.start_height = None;
         self.rt.call_stack.push(frame);
+        self.run_frames(base, false).map(drop)
+    }
+    /// Run the call stack until it is back down to some height
+    ///
+    /// Functions called by instructions and by looping primitives get their own frames
+    /// instead of recursing, so a pausable run can stop before any of those instructions
+    /// once its [`Budget`] is used up. Functions called by other primitives run to completion.
+    ///
+    /// Returns whether the frames finished
+    pub(crate) fn run_frames(&mut self, base: usize, pausable: bool) -> UiuaResult<bool> {
+        let _budget = self.rt.memory_budget.as_ref().map(MemoryBudget::enter);
         let mut formatted_instr = String::new();
-        for i in slice.start..slice.end() {
-            let instr = &self.asm.instrs[i];
+        while self.rt.call_stack.len() > base {
+            let depth = self.rt.call_stack.len();
+
+            // Continue a loop started by the current frame
+            if self.rt.loops.last().is_some_and(|lp| lp.depth == depth) {
+                if pausable && self.budget_used_up() {
+                    return Ok(false);
+                }
+                let mut lp = self.rt.loops.pop().unwrap();
+                match self.with_prim_span(lp.span, lp.prim, |env| lp.state.next(env)) {
+                    Ok(Some(f)) => {
+                        let frame = StackFrame::new(&f, lp.span, Some(self.rt.stack.len()));
+                        self.rt.loops.push(lp);
+                        self.rt.call_stack.push(frame);
+                    }
+                    Ok(None) => {}
+                    Err(err) => return Err(self.unwind(base, err, true)),
+                }
+                continue;
+            }
+
+            // Return from a finished function
+            let frame = self.rt.call_stack.last().unwrap();
+            if frame.pc >= frame.slice.len {
+                let frame = self.rt.call_stack.pop().unwrap();
+                if let Some(start_height) = frame.start_height {
+                    if let Err(err) = self.check_height(start_height, frame.sig, frame.call_span) {
+                        return Err(self.unwind(base, err, true));
+                    }
+                }
+                continue;
+            }
+
+            let instr = &self.asm.instrs[frame.slice.start + frame.pc];
+            if !matches!(instr, Instr::Comment(_)) {
+                if pausable && self.budget_used_up() {
+                    return Ok(false);
+                }
+                if let Some(Budget::Instrs(n)) = &mut self.rt.budget {
+                    *n = n.saturating_sub(1);
+                }
+            }
+            let mut callee = None;
+            let mut started = None;
 
             // Uncomment to debug
             // for val in &self.rt.stack {
@@ -462,11 +757,27 @@ code:
             // }
             // println!("\n    {:?}", instr);
 
+            if let Some(hook) = self.rt.debug_hook.clone() {
+                if !matches!(instr, Instr::Comment(_)) {
+                    if let Err(err) = hook(self) {
+                        return Err(self.unwind(base, err, false));
+                    }
+                }
+            }
+
+            let mut fuel_height = None;
+            if self.rt.fuel_limit.is_some() || self.rt.count_fuel {
+                match self.consume_fuel(instr) {
+                    Ok(height) => fuel_height = height,
+                    Err(err) => return Err(self.unwind(base, err, false)),
+                }
+            }
+
             if self.rt.time_instrs {
                 formatted_instr = format!("{instr:?}");
                 self.rt.last_time = self.rt.backend.now();
             }
-            let res = match instr {
+            let mut res = match instr {
                 Instr::Comment(_) => Ok(()),
                 // Pause execution timer during &sc
                 &Instr::Prim(prim @ Primitive::Sys(SysOp::ScanLine), span) => {
@@ -477,6 +788,16 @@ code:
                         res
                     })
                 }
+                // Loops run their iterations in frames of their own
+                &Instr::Prim(prim @ (Primitive::Repeat | Primitive::Do), span) => self
+                    .with_prim_span(span, Some(prim), |env| match prim {
+                        Primitive::Repeat => loops::start_repeat(false, env),
+                        _ => loops::start_do(env).map(Some),
+                    })
+                    .map(|state| started = state.map(|state| (Some(prim), span, state))),
+                &Instr::ImplPrim(ImplPrimitive::RepeatWithInverse, span) => self
+                    .with_span(span, |env| loops::start_repeat(true, env))
+                    .map(|state| started = state.map(|state| (None, span, state))),
                 &Instr::Prim(prim, span) => {
                     self.with_prim_span(span, Some(prim), |env| prim.run(env))
                 }
@@ -495,9 +816,9 @@ code:
                             "Called unbound constant. \
                             This is a bug in the interpreter.",
                         )),
-                        BindingKind::Func(f) if call => {
-                            self.respect_recursion_limit().and_then(|_| self.call(f))
-                        }
+                        BindingKind::Func(f) if call => self
+                            .respect_recursion_limit()
+                            .map(|_| callee = Some((f, self.span_index()))),
                         BindingKind::Func(f) => self
                             .respect_recursion_limit()
                             .map(|_| self.rt.function_stack.push(f)),
@@ -545,9 +866,9 @@ code:
                 &Instr::EndArray { span, boxed } => {
                     self.with_span(span, |env| env.end_array(boxed, None))
                 }
-                &Instr::Call(span) | &Instr::CustomInverse(_, span) => self
-                    .pop_function()
-                    .and_then(|f| self.call_with_span(f, span)),
+                &Instr::Call(span) | &Instr::CustomInverse(_, span) => {
+                    self.pop_function().map(|f| callee = Some((f, span)))
+                }
                 &Instr::CallRecursive(span) => self.with_span(span, |env| {
                     let f = env.pop_function()?;
                     env.call_recursive(f)
@@ -736,22 +1057,60 @@ code:
                 );
                 self.rt.last_time = self.rt.backend.now();
             }
-            if let Err(mut err) = res {
-                // Trace errors
-                let frame = self.rt.call_stack.pop().unwrap();
+            if let (Ok(()), Some(height)) = (&res, fuel_height) {
+                res = self.consume_output_fuel(height);
+            }
+            if let Err(err) = res {
+                return Err(self.unwind(base, err, true));
+            }
+            self.rt.call_stack.last_mut().unwrap().pc += 1;
+            if let Err(err) = self.respect_execution_limit() {
+                return Err(self.unwind(base, err, false));
+            }
+            if let Some((f, call_span)) = callee {
+                let frame = StackFrame::new(&f, call_span, Some(self.rt.stack.len()));
+                self.rt.call_stack.push(frame);
+            }
+            if let Some((prim, span, state)) = started {
+                (self.rt.loops).push(LoopFrame {
+                    depth,
+                    prim,
+                    span,
+                    state,
+                });
+            }
+        }
+        Ok(true)
+    }
+    /// Pop frames down to some height after an error, tracing them in the error
+    ///
+    /// The innermost frame is only traced if `trace_top` is set.
+    fn unwind(&mut self, base: usize, mut err: UiuaError, trace_top: bool) -> UiuaError {
+        let mut trace = trace_top;
+        while self.rt.call_stack.len() > base {
+            let depth = self.rt.call_stack.len();
+            while self.rt.loops.last().is_some_and(|lp| lp.depth >= depth) {
+                self.rt.loops.pop();
+            }
+            let frame = self.rt.call_stack.pop().unwrap();
+            if trace {
                 let span = self.asm.spans[frame.call_span].clone();
                 if frame.track_caller {
                     err.track_caller(span);
                 } else {
                     err.trace.push(TraceFrame { id: frame.id, span });
                 }
-                return Err(err);
             }
-            self.rt.call_stack.last_mut().unwrap().pc += 1;
-            self.respect_execution_limit()?;
+            trace = true;
+        }
+        err
+    }
+    fn budget_used_up(&self) -> bool {
+        match self.rt.budget {
+            Some(Budget::Instrs(n)) => n == 0,
+            Some(Budget::Until(end)) => self.rt.backend.now() >= end,
+            None => false,
         }
-        self.rt.call_stack.pop();
-        Ok(())
     }
     /// Timeout if an execution limit is set and has been exceeded
     pub fn respect_execution_limit(&self) -> UiuaResult {
@@ -763,11 +1122,94 @@ code:
                 );
             }
         }
+        self.respect_memory_limit(0)?;
         if let Some(hook) = &self.rt.interrupted {
             if hook() {
                 return Err(UiuaErrorKind::Interrupted.into());
             }
         }
+        let cancel_flags = &self.rt.thread.cancel_flags;
+        if (cancel_flags.iter()).any(|flag| flag.load(atomic::Ordering::Relaxed)) {
+            return Err(self.error("Thread was cancelled"));
+        }
+        Ok(())
+    }
+    /// Consume the fuel for an instruction and its arguments
+    ///
+    /// For primitives, returns the height of the stack below their arguments,
+    /// so that the fuel for their outputs can be consumed once they have run.
+    fn consume_fuel(&self, instr: &Instr) -> UiuaResult<Option<usize>> {
+        let args = match instr {
+            Instr::Prim(prim, _) => match prim.class() {
+                PrimClass::Stack | PrimClass::Planet | PrimClass::Constant | PrimClass::Debug => {
+                    None
+                }
+                _ => Some(match prim.modifier_args() {
+                    Some(n) => self.modifier_operands(n),
+                    None => prim.args().unwrap_or(0),
+                }),
+            },
+            Instr::ImplPrim(prim, _) => match prim {
+                ImplPrimitive::TraceN { .. } => None,
+                prim => Some(match prim.modifier_args() {
+                    Some(n) => prim.args().max(self.modifier_operands(n)),
+                    None => prim.args(),
+                }),
+            },
+            _ => None,
+        };
+        let elements: usize = (self.rt.stack.iter().rev().take(args.unwrap_or(0)))
+            .map(Value::element_count)
+            .sum();
+        self.add_fuel(1 + elements as u64)?;
+        Ok(args.map(|args| self.rt.stack.len().saturating_sub(args)))
+    }
+    /// The number of values a modifier is taken to operate on, given its number of functions
+    fn modifier_operands(&self, functions: usize) -> usize {
+        let fs = &self.rt.function_stack;
+        (fs[fs.len().saturating_sub(functions)..].iter())
+            .map(|f| f.signature().args)
+            .max()
+            .unwrap_or(0)
+            .max(1)
+    }
+    /// Consume the fuel for the values a primitive left above a stack height
+    fn consume_output_fuel(&self, height: usize) -> UiuaResult {
+        let elements: usize = (self.rt.stack.get(height..).into_iter().flatten())
+            .map(Value::element_count)
+            .sum();
+        self.add_fuel(elements as u64)
+    }
+    /// Add to the fuel used, erroring if a fuel limit is set and has been exceeded
+    fn add_fuel(&self, fuel: u64) -> UiuaResult {
+        let used = self.rt.fuel_used.fetch_add(fuel, atomic::Ordering::Relaxed) + fuel;
+        if let Some(limit) = self.rt.fuel_limit {
+            if used > limit {
+                return Err(
+                    UiuaErrorKind::OutOfFuel(self.span(), self.inputs().clone().into()).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+    /// Error if a memory limit is set and allocating some more bytes would exceed it
+    ///
+    /// Memory is only measured once the bytes counted since the last measurement
+    /// might have exceeded the limit. Only this runtime's values are measured;
+    /// other threads count with their own last measurements.
+    pub(crate) fn respect_memory_limit(&self, bytes: usize) -> UiuaResult {
+        let Some(budget) = &self.rt.memory_budget else {
+            return Ok(());
+        };
+        if budget.might_exceed(bytes) {
+            budget.reset(self.memory_usage());
+            if budget.might_exceed(bytes) {
+                return Err(self.error(format!(
+                    "Memory limit of {} exceeded",
+                    format_bytes(budget.limit())
+                )));
+            }
+        }
         Ok(())
     }
     pub(crate) fn with_span<T>(
@@ -801,8 +1243,13 @@ code:
     }
     #[inline]
     fn call_slice(&mut self, slice: FuncSlice) -> UiuaResult {
+        let frame = self.slice_frame(slice);
+        self.exec(frame)
+    }
+    /// Make a frame for a top-level slice
+    pub(crate) fn slice_frame(&self, slice: FuncSlice) -> StackFrame {
         let call_span = self.span_index();
-        let frame = StackFrame {
+        StackFrame {
             slice,
             sig: Signature::new(0, 0),
             track_caller: false,
@@ -810,8 +1257,8 @@ code:
             call_span,
             spans: Vec::new(),
             pc: 0,
-        };
-        self.exec(frame)
+            start_height: None,
+        }
     }
     #[inline]
     fn call_frame(&mut self, frame: StackFrame) -> UiuaResult {
@@ -881,24 +1328,17 @@ code:
     }
     #[inline]
     fn call_with_span(&mut self, f: Function, call_span: usize) -> UiuaResult {
-        self.call_with_frame_span(
-            StackFrame {
-                slice: f.slice(),
-                sig: f.signature(),
-                id: f.id,
-                track_caller: f.flags.track_caller(),
-                call_span,
-                spans: Vec::new(),
-                pc: 0,
-            },
-            call_span,
-        )
+        self.call_with_frame_span(StackFrame::new(&f, call_span, None), call_span)
     }
     #[inline]
     fn call_with_frame_span(&mut self, frame: StackFrame, call_span: usize) -> UiuaResult {
         let start_height = self.rt.stack.len();
         let sig = frame.sig;
         self.exec(frame)?;
+        self.check_height(start_height, sig, call_span)
+    }
+    /// Check that a function changed the stack height by as much as its signature says
+    fn check_height(&self, start_height: usize, sig: Signature, call_span: usize) -> UiuaResult {
         let height_diff = self.rt.stack.len() as isize - start_height as isize;
         let sig_diff = sig.outputs as isize - sig.args as isize;
         if height_diff != sig_diff {
@@ -1094,6 +1534,30 @@ code:
     pub fn stack_mut(&mut self) -> &mut [Value] {
         &mut self.rt.stack
     }
+    /// Get a reference to a temp stack
+    pub fn temp_stack(&self, stack: TempStack) -> &[Value] {
+        &self.rt.temp_stacks[stack as usize]
+    }
+    /// Get the function calls that are currently executing, outermost first
+    pub fn call_frame_info(&self) -> Vec<CallFrame> {
+        (self.rt.call_stack.iter())
+            .map(|frame| {
+                let instr = frame.slice.start + frame.pc;
+                let span = (self.asm.instrs.get(instr))
+                    .filter(|_| frame.pc < frame.slice.len)
+                    .and_then(Instr::span)
+                    .or_else(|| frame.spans.last().map(|(span, _)| *span))
+                    .map(|span| self.asm.spans[span].clone());
+                CallFrame {
+                    id: frame.id.clone(),
+                    sig: frame.sig,
+                    call_span: self.asm.spans[frame.call_span].clone(),
+                    instr,
+                    span,
+                }
+            })
+            .collect()
+    }
     /// Pop a function from the function stack
     pub fn pop_function(&mut self) -> UiuaResult<Function> {
         self.rt.function_stack.pop().ok_or_else(|| {
@@ -1419,11 +1883,22 @@ code:
         }
         let (this_send, child_recv) = crossbeam_channel::unbounded();
         let (child_send, this_recv) = crossbeam_channel::unbounded();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut cancel_flags = self.rt.thread.cancel_flags.clone();
+        cancel_flags.push(cancelled.clone());
+        let id = self.rt.thread.next_child_id;
+        self.rt.thread.next_child_id += 1;
+        let mut path = self.rt.thread.path.clone();
+        path.push(id);
+        // Each thread counts its own fuel so that it runs out at the same point every time
+        let fuel_used = Arc::new(AtomicU64::new(0));
         let thread = ThisThread {
             parent: Some(Channel {
                 send: child_send,
                 recv: child_recv,
             }),
+            path,
+            cancel_flags,
             ..ThisThread::default()
         };
         let mut env = Uiua {
@@ -1440,6 +1915,8 @@ code:
                 unfill_stack: Vec::new(),
                 recur_stack: self.rt.recur_stack.clone(),
                 call_stack: Vec::new(),
+                loops: Vec::new(),
+                budget: None,
                 time_instrs: self.rt.time_instrs,
                 last_time: self.rt.last_time,
                 cli_arguments: self.rt.cli_arguments.clone(),
@@ -1447,31 +1924,52 @@ code:
                 backend: self.rt.backend.clone(),
                 execution_limit: self.rt.execution_limit,
                 execution_start: self.rt.execution_start,
+                memory_budget: (self.rt.memory_budget.as_deref())
+                    .map(|budget| Arc::new(budget.split())),
+                random_seed: self.rt.random_seed,
+                fuel_limit: (self.rt.fuel_limit)
+                    .map(|limit| limit.saturating_sub(self.fuel_used())),
+                count_fuel: self.rt.count_fuel,
+                fuel_used: fuel_used.clone(),
                 interrupted: self.rt.interrupted.clone(),
+                debug_hook: None,
                 output_comments: HashMap::new(),
                 memo: self.rt.memo.clone(),
                 test_results: Vec::new(),
                 reports: Vec::new(),
+                dynamic_modules: self.rt.dynamic_modules.clone(),
+                channels: self.rt.channels.clone(),
                 thread,
             },
         };
+        // System calls are recorded per thread so that they replay in order,
+        // and random numbers are seeded per thread so that they replay the same
+        let run = move || {
+            let path = env.rt.thread.path.clone();
+            let seed = env.rt.random_seed;
+            crate::sys_record::in_thread(path.clone(), || {
+                let run = || f(&mut env).map(|_| env.take_stack());
+                match seed {
+                    Some(seed) => with_thread_seed(seed, &path, run),
+                    None => run(),
+                }
+            })
+        };
         #[cfg(not(target_arch = "wasm32"))]
         let recv = {
             let (send, recv) = crossbeam_channel::unbounded();
             if _pool {
-                rayon::spawn(move || _ = send.send(f(&mut env).map(|_| env.take_stack())));
+                rayon::spawn(move || _ = send.send(run()));
             } else {
                 std::thread::Builder::new()
-                    .spawn(move || _ = send.send(f(&mut env).map(|_| env.take_stack())))
+                    .spawn(move || _ = send.send(run()))
                     .map_err(|e| self.error(format!("Error spawning thread: {e}")))?;
             }
             recv
         };
         #[cfg(target_arch = "wasm32")]
-        let result = f(&mut env).map(|_| env.take_stack());
+        let result = run();
 
-        let id = self.rt.thread.next_child_id;
-        self.rt.thread.next_child_id += 1;
         self.rt.thread.children.insert(
             id,
             Thread {
@@ -1483,56 +1981,35 @@ code:
                     send: this_send,
                     recv: this_recv,
                 },
+                cancelled,
+                fuel_used,
             },
         );
         self.push(id);
         Ok(())
     }
+    /// Wait for a child thread's stack, and consume the fuel it used
+    fn join_thread(&mut self, handle: usize) -> UiuaResult<Vec<Value>> {
+        let thread = (self.rt.thread.children.remove(&handle))
+            .ok_or_else(|| self.error("Invalid thread id"))?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let stack = thread.recv.recv().unwrap()?;
+        #[cfg(target_arch = "wasm32")]
+        let stack = thread.result?;
+        self.add_fuel(thread.fuel_used.load(atomic::Ordering::Relaxed))?;
+        Ok(stack)
+    }
     /// Wait for a thread to finish
     pub(crate) fn wait(&mut self, id: Value) -> UiuaResult {
         let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
         if ids.shape.is_empty() {
             let handle = ids.data[0];
-            #[cfg(not(target_arch = "wasm32"))]
-            let thread_stack = self
-                .rt
-                .thread
-                .children
-                .remove(&handle)
-                .ok_or_else(|| self.error("Invalid thread id"))?
-                .recv
-                .recv()
-                .unwrap()?;
-            #[cfg(target_arch = "wasm32")]
-            let thread_stack = self
-                .rt
-                .thread
-                .children
-                .remove(&handle)
-                .ok_or_else(|| self.error("Invalid thread id"))?
-                .result?;
+            let thread_stack = self.join_thread(handle)?;
             self.rt.stack.extend(thread_stack);
         } else {
             let mut rows = Vec::new();
             for handle in ids.data {
-                #[cfg(not(target_arch = "wasm32"))]
-                let thread_stack = self
-                    .rt
-                    .thread
-                    .children
-                    .remove(&handle)
-                    .ok_or_else(|| self.error("Invalid thread id"))?
-                    .recv
-                    .recv()
-                    .unwrap()?;
-                #[cfg(target_arch = "wasm32")]
-                let thread_stack = self
-                    .rt
-                    .thread
-                    .children
-                    .remove(&handle)
-                    .ok_or_else(|| self.error("Invalid thread id"))?
-                    .result?;
+                let thread_stack = self.join_thread(handle)?;
                 let row = if thread_stack.len() == 1 {
                     thread_stack.into_iter().next().unwrap()
                 } else {
@@ -1552,6 +2029,10 @@ code:
         if cfg!(target_arch = "wasm32") {
             return Err(self.error("send is not supported in this environment"));
         }
+        if let Some(handle) = self.channel_handle(&id)? {
+            return (self.channel_sender(handle)?.send(value))
+                .map_err(|_| self.error("Channel is closed"));
+        }
         let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
         for id in ids.data {
             self.channel(id)?
@@ -1565,6 +2046,13 @@ code:
         if cfg!(target_arch = "wasm32") {
             return Err(self.error("recv is not supported in this environment"));
         }
+        if let Some(handle) = self.channel_handle(&id)? {
+            let recv = self.channel_receiver(handle)?;
+            let res = recv.recv();
+            self.remove_drained_channel(handle, &recv);
+            self.push(res.map_err(|_| self.error("Channel is closed"))?);
+            return Ok(());
+        }
         let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
         let mut values = Vec::with_capacity(ids.data.len());
         for id in ids.data {
@@ -1587,6 +2075,18 @@ code:
         if cfg!(target_arch = "wasm32") {
             return Err(self.error("try_recv is not supported in this environment"));
         }
+        if let Some(handle) = self.channel_handle(&id)? {
+            let recv = self.channel_receiver(handle)?;
+            let res = recv.try_recv();
+            self.remove_drained_channel(handle, &recv);
+            let value = match res {
+                Ok(value) => value,
+                Err(TryRecvError::Empty) => return Err(self.error("No value available")),
+                Err(TryRecvError::Disconnected) => return Err(self.error("Channel is closed")),
+            };
+            self.push(value);
+            return Ok(());
+        }
         let id = id.as_nat(self, "Thread id must be a natural number")?;
         let value = match self.channel(id)?.recv.try_recv() {
             Ok(value) => value,
@@ -1602,6 +2102,98 @@ code:
         self.push(value);
         Ok(())
     }
+    /// Cancel threads and the threads they spawned
+    ///
+    /// Cancelled threads stop the next time they check the execution limit.
+    pub(crate) fn cancel(&self, id: Value) -> UiuaResult {
+        let ids = id.as_natural_array(self, "Thread id must be an array of natural numbers")?;
+        for id in ids.data {
+            (self.rt.thread.children.get(&id))
+                .ok_or_else(|| self.error("Invalid thread id"))?
+                .cancelled
+                .store(true, atomic::Ordering::Relaxed);
+        }
+        Ok(())
+    }
+    /// Limit the execution duration of the current thread
+    ///
+    /// A limit that was inherited from the parent thread can only be tightened.
+    pub(crate) fn limit_thread(&mut self, mut seconds: f64) {
+        let now = self.rt.backend.now();
+        if let Some(limit) = self.rt.execution_limit {
+            seconds = seconds.min(self.rt.execution_start + limit - now);
+        }
+        if seconds.is_finite() {
+            self.rt.execution_start = now;
+            self.rt.execution_limit = Some(seconds.max(0.0));
+        }
+    }
+    /// Create a channel that can be shared between threads
+    ///
+    /// A channel with a capacity blocks senders while it is full.
+    pub(crate) fn create_channel(&self, capacity: Option<usize>) -> Value {
+        let (send, recv) = match capacity {
+            Some(cap) => crossbeam_channel::bounded(cap),
+            None => crossbeam_channel::unbounded(),
+        };
+        let handle = Handle::unique();
+        let send = Some(send);
+        (self.rt.channels.lock()).insert(handle, SharedChannel { send, recv });
+        handle.value(HandleKind::Channel)
+    }
+    /// Close a shared channel
+    ///
+    /// Values that were already sent can still be received.
+    /// Threads blocked receiving from an empty channel wake up with an error.
+    pub(crate) fn close_channel(&self, handle: Handle) -> UiuaResult {
+        let mut channels = self.rt.channels.lock();
+        let channel = (channels.get_mut(&handle)).ok_or_else(|| self.error("Channel is closed"))?;
+        channel.send = None;
+        if channel.recv.is_empty() {
+            channels.remove(&handle);
+        }
+        Ok(())
+    }
+    /// Check if a handle belongs to a shared channel that has not been removed
+    pub(crate) fn has_channel(&self, handle: Handle) -> bool {
+        self.rt.channels.lock().contains_key(&handle)
+    }
+    /// Get the handle of a shared channel if the value is one
+    fn channel_handle(&self, id: &Value) -> UiuaResult<Option<Handle>> {
+        match crate::sys::handle_kind(id) {
+            Some(HandleKind::Channel) => id.as_handle(self, "").map(Some),
+            _ => Ok(None),
+        }
+    }
+    /// Get a copy of a shared channel's sending end
+    ///
+    /// The lock is released before the copy is used, so blocking on it
+    /// does not block other threads' access to the channels.
+    fn channel_sender(&self, handle: Handle) -> UiuaResult<Sender<Value>> {
+        (self.rt.channels.lock().get(&handle))
+            .and_then(|channel| channel.send.clone())
+            .ok_or_else(|| self.error("Channel is closed"))
+    }
+    /// Get a copy of a shared channel's receiving end
+    ///
+    /// Only the receiver is copied so that a blocked receiver
+    /// does not keep the channel open.
+    fn channel_receiver(&self, handle: Handle) -> UiuaResult<Receiver<Value>> {
+        (self.rt.channels.lock().get(&handle))
+            .map(|channel| channel.recv.clone())
+            .ok_or_else(|| self.error("Channel is closed"))
+    }
+    /// Remove a shared channel once it is closed and has no values left
+    fn remove_drained_channel(&self, handle: Handle, recv: &Receiver<Value>) {
+        let mut channels = self.rt.channels.lock();
+        if channels
+            .get(&handle)
+            .is_some_and(|channel| channel.send.is_none())
+            && recv.is_empty()
+        {
+            channels.remove(&handle);
+        }
+    }
     fn channel(&self, id: usize) -> UiuaResult<&Channel> {
         Ok(if id == 0 {
             self.rt
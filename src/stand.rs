@@ -1,41 +1,224 @@
-use std::{env, fs, io, path::Path};
+use std::{
+    env, fs,
+    io::{Read, Write},
+    path::Path,
+};
 
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
 use once_cell::sync::Lazy;
 
-use crate::{Assembly, Compiler, NativeSys, UiuaResult};
+use crate::{Assembly, AssemblyHeader, Compiler, NativeSys, UiuaError, UiuaResult};
 
 const STAND_DATA_SIGNATURE: &[u8] = b"Uiua standalone";
 
+/// The version of the layout of the data appended to a standalone executable
+const STAND_FORMAT_VERSION: u16 = 3;
+
+/// The compressed length, uncompressed length, checksum, and format version
+///
+/// The checksum covers the payload and the other footer fields.
+const FOOTER_LEN: usize = 8 + 8 + 4 + 2;
+
+fn payload_checksum(payload: &[u8], sizes: &[u8], version: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(payload);
+    hasher.update(sizes);
+    hasher.update(version);
+    hasher.finalize()
+}
+
 pub fn build_exe(root: &Path) -> UiuaResult<Vec<u8>> {
-    let asm = Compiler::with_backend(NativeSys).load_file(root)?.finish();
-    // Serialize the files
-    let asm_bytes = asm.to_uasm().into_bytes();
-    // Append the files to the current exe
-    let mut bytes = env::current_exe()
+    let asm = Compiler::with_backend(NativeSys)
+        .remove_unreachable(true)
+        .load_file(root)?
+        .finish();
+    // Append the assembly to the current exe
+    let exe = env::current_exe()
         .and_then(fs::read)
         .unwrap_or_else(|e| panic!("Unable to read current exe: {e}"));
-    bytes.extend_from_slice(&asm_bytes);
-    // Append the length of the serialized files and a signature
-    bytes.extend((asm_bytes.len() as u64).to_le_bytes());
+    append_payload(exe, &asm).map_err(|e| UiuaError::load(root.into(), std::io::Error::other(e)))
+}
+
+fn append_payload(mut bytes: Vec<u8>, asm: &Assembly) -> Result<Vec<u8>, String> {
+    // Compress the assembly
+    let asm_bytes = asm.to_binary()?;
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    // Writing to a Vec cannot fail
+    encoder.write_all(&asm_bytes).unwrap();
+    let payload = encoder.finish().unwrap();
+    bytes.extend_from_slice(&payload);
+    // Append the footer and a signature
+    let mut sizes = Vec::with_capacity(16);
+    sizes.extend((payload.len() as u64).to_le_bytes());
+    sizes.extend((asm_bytes.len() as u64).to_le_bytes());
+    let version = STAND_FORMAT_VERSION.to_le_bytes();
+    let checksum = payload_checksum(&payload, &sizes, &version);
+    bytes.extend(sizes);
+    bytes.extend(checksum.to_le_bytes());
+    bytes.extend(version);
     bytes.extend(STAND_DATA_SIGNATURE);
     Ok(bytes)
 }
 
-fn load_asm() -> io::Result<Option<Assembly>> {
-    // Read the current exe
-    let bytes = fs::read(env::current_exe()?)?;
+/// Information about the payload of a standalone executable
+#[derive(Debug, Clone)]
+pub struct StandInfo {
+    /// The version of the payload layout
+    pub format_version: u16,
+    /// The header of the embedded assembly
+    pub asm_header: AssemblyHeader,
+    /// The size of the payload as stored in the executable
+    pub compressed_size: usize,
+    /// The size of the assembly after decompression
+    pub uncompressed_size: usize,
+    /// The CRC-32 checksum of the stored payload and footer
+    pub checksum: u32,
+}
+
+/// Read the payload of a standalone executable
+///
+/// Returns `None` if the bytes are not a standalone executable.
+/// Otherwise, returns the payload info and the decompressed assembly bytes.
+fn read_payload(bytes: &[u8]) -> Result<Option<(StandInfo, Vec<u8>)>, String> {
+    let corrupted = |reason: &str| format!("Standalone executable is corrupted: {reason}");
     // Check if it is a standalone exe
     let Some(bytes) = bytes.strip_suffix(STAND_DATA_SIGNATURE) else {
         return Ok(None);
     };
-    // Get the length of the serialized files
-    let (bytes, len_bytes) = bytes.split_at(bytes.len() - 8);
-    let asm_len = u64::from_le_bytes(len_bytes.try_into().unwrap());
-    let start = bytes.len() - asm_len as usize;
-    // Deserialize the files
-    let uasm: String = std::str::from_utf8(&bytes[start..]).unwrap().to_string();
-    let asm = Assembly::from_uasm(&uasm).unwrap();
-    Ok(Some(asm))
+    if bytes.len() < FOOTER_LEN {
+        return Err(corrupted("the payload footer is missing"));
+    }
+    let (bytes, footer) = bytes.split_at(bytes.len() - FOOTER_LEN);
+    let format_version = u16::from_le_bytes([footer[20], footer[21]]);
+    if format_version != STAND_FORMAT_VERSION {
+        return Err(format!(
+            "Standalone executable uses payload format version {format_version}, \
+            but only version {STAND_FORMAT_VERSION} is supported. \
+            Rebuild it with `uiua stand`."
+        ));
+    }
+    let size = |field: &[u8], name: &str| {
+        let size = u64::from_le_bytes(field.try_into().unwrap());
+        usize::try_from(size)
+            .map_err(|_| corrupted(&format!("the {name} size {size} does not fit in memory")))
+    };
+    let compressed_size = size(&footer[0..8], "payload")?;
+    let uncompressed_size = size(&footer[8..16], "assembly")?;
+    let checksum = u32::from_le_bytes(footer[16..20].try_into().unwrap());
+    if compressed_size > bytes.len() {
+        return Err(corrupted(&format!(
+            "the payload should be {compressed_size} bytes, \
+            but only {} bytes are present",
+            bytes.len()
+        )));
+    }
+    let payload = &bytes[bytes.len() - compressed_size..];
+    let actual_checksum = payload_checksum(payload, &footer[0..16], &footer[20..22]);
+    if actual_checksum != checksum {
+        return Err(corrupted(&format!(
+            "checksum mismatch (expected {checksum:08x}, found {actual_checksum:08x})"
+        )));
+    }
+    // Decompress the assembly
+    let mut asm_bytes = Vec::new();
+    DeflateDecoder::new(payload)
+        .take((uncompressed_size as u64).saturating_add(1))
+        .read_to_end(&mut asm_bytes)
+        .map_err(|e| corrupted(&format!("decompression failed: {e}")))?;
+    if asm_bytes.len() != uncompressed_size {
+        return Err(corrupted(&format!(
+            "the assembly should be {uncompressed_size} bytes, \
+            but it decompressed to {} bytes",
+            asm_bytes.len()
+        )));
+    }
+    let asm_header = AssemblyHeader::read(&asm_bytes)?;
+    let info = StandInfo {
+        format_version,
+        asm_header,
+        compressed_size,
+        uncompressed_size,
+        checksum,
+    };
+    Ok(Some((info, asm_bytes)))
+}
+
+/// Get information about the payload of a standalone executable
+///
+/// Returns `None` if the file is not a standalone executable
+pub fn inspect_exe(path: &Path) -> Result<Option<StandInfo>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+    Ok(read_payload(&bytes)?.map(|(info, _)| info))
+}
+
+fn load_asm() -> Result<Option<Assembly>, String> {
+    // Read the current exe
+    let bytes = env::current_exe()
+        .and_then(fs::read)
+        .map_err(|e| format!("Unable to read current exe: {e}"))?;
+    let Some((_, asm_bytes)) = read_payload(&bytes)? else {
+        return Ok(None);
+    };
+    Assembly::from_binary(&asm_bytes).map(Some)
 }
 
-pub static STAND_ASM: Lazy<Option<Assembly>> = Lazy::new(|| load_asm().unwrap());
+pub static STAND_ASM: Lazy<Result<Option<Assembly>, String>> = Lazy::new(load_asm);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exe_with_payload() -> Vec<u8> {
+        let asm = Compiler::new().load_str("+ 1 2").unwrap().finish();
+        append_payload(b"not really an exe".to_vec(), &asm).unwrap()
+    }
+
+    #[test]
+    fn payload_round_trip() {
+        let bytes = exe_with_payload();
+        let (info, asm_bytes) = read_payload(&bytes).unwrap().unwrap();
+        assert_eq!(info.format_version, STAND_FORMAT_VERSION);
+        assert_eq!(info.asm_header.uiua_version, crate::VERSION);
+        assert_eq!(info.uncompressed_size, asm_bytes.len());
+        Assembly::from_binary(&asm_bytes).unwrap();
+    }
+
+    #[test]
+    fn not_standalone() {
+        assert!(read_payload(b"just an exe").unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupted_payload() {
+        let bytes = exe_with_payload();
+        let payload_end = bytes.len() - STAND_DATA_SIGNATURE.len() - FOOTER_LEN;
+
+        let mut flipped = bytes.clone();
+        flipped[payload_end - 1] ^= 0xff;
+        let err = read_payload(&flipped).unwrap_err();
+        assert!(err.contains("checksum mismatch"), "{err}");
+
+        // The checksum covers the footer
+        let mut wrong_size = bytes.clone();
+        wrong_size[payload_end + 8] ^= 0x01;
+        let err = read_payload(&wrong_size).unwrap_err();
+        assert!(err.contains("checksum mismatch"), "{err}");
+
+        let mut huge_size = bytes.clone();
+        huge_size[payload_end + 8..][..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let err = read_payload(&huge_size).unwrap_err();
+        assert!(
+            err.contains("checksum mismatch") || err.contains("fit"),
+            "{err}"
+        );
+
+        let truncated = bytes[payload_end - 4..].to_vec();
+        let err = read_payload(&truncated).unwrap_err();
+        assert!(err.contains("only 4 bytes"), "{err}");
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[payload_end + 20] = 0xff;
+        let err = read_payload(&wrong_version).unwrap_err();
+        assert!(err.contains("payload format version"), "{err}");
+    }
+}
@@ -0,0 +1,292 @@
+//! Running programs a few instructions at a time
+//!
+//! Functions called by instructions and by looping primitives run in frames on the interpreter's
+//! own call stack rather than on the Rust stack. A program can stop before any of their
+//! instructions and continue later from the same frames, so no extra thread is needed.
+
+use std::time::Duration;
+
+use crate::{Uiua, UiuaError, UiuaResult, Value};
+
+/// How far a program may run before it pauses
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Budget {
+    /// A number of instructions
+    Instrs(usize),
+    /// A time in seconds, as returned by the backend's [`now`](crate::SysBackend::now)
+    Until(f64),
+}
+
+/// A program that runs a few instructions at a time
+///
+/// Created with [`Uiua::start`] or [`Uiua::start_call`].
+/// The program does not run until it is advanced with [`Execution::step`] or [`Execution::run_for`].
+/// Between steps, its stack can be inspected with [`Execution::stack`].
+///
+/// The program can pause inside functions called by instructions and by looping primitives
+/// like [`repeat`](crate::Primitive::Repeat) and [`do`](crate::Primitive::Do).
+/// Functions called by other primitives, like [`rows`](crate::Primitive::Rows),
+/// run to completion once started, so a step may run past its budget by all of that work.
+/// Set an execution or fuel limit on the runtime to bound how far a step can run past it.
+/// Going over one of those limits is an error like any other.
+///
+/// If the program fails, the error is returned by every later step.
+///
+/// Dropping an unfinished execution stops the program.
+///
+/// ```rust
+/// use uiua::*;
+///
+/// let asm = Compiler::new().load_str("⍥(+1):0").unwrap().finish();
+/// let mut uiua = Uiua::with_safe_sys();
+/// uiua.push(100);
+/// let mut exec = uiua.start(asm);
+/// let mut steps = 0;
+/// while !exec.step(10).unwrap() {
+///     steps += 1;
+/// }
+/// assert!(steps > 1);
+/// let res = exec.finish().unwrap().pop_int().unwrap();
+/// assert_eq!(res, 100);
+/// ```
+pub struct Execution {
+    env: Uiua,
+    /// The height of the call stack below the program's frames
+    base: usize,
+    /// The index of the next top-level slice, if running an assembly
+    next_slice: Option<usize>,
+    finished: bool,
+    /// The error the program failed with
+    error: Option<UiuaError>,
+}
+
+impl Execution {
+    /// Wrap a runtime that is ready to run
+    ///
+    /// A top-level execution runs the assembly's top-level slices.
+    /// Otherwise, the function to run must already have a frame on the call stack.
+    pub(crate) fn new(env: Uiua, top_level: bool) -> Self {
+        let height = env.rt.call_stack.len();
+        let (base, next_slice) = if top_level {
+            (height, Some(0))
+        } else {
+            (height - 1, None)
+        };
+        Execution {
+            env,
+            base,
+            next_slice,
+            finished: false,
+            error: None,
+        }
+    }
+    /// Run at most some number of instructions
+    ///
+    /// Instructions run by functions that cannot pause count toward the number,
+    /// but they do not stop early, so more instructions may run.
+    ///
+    /// Returns whether the program finished
+    pub fn step(&mut self, instrs: usize) -> UiuaResult<bool> {
+        self.advance(Some(Budget::Instrs(instrs)))
+    }
+    /// Run until some time has passed
+    ///
+    /// Time is measured by the runtime's backend.
+    /// The program only pauses between instructions it can pause at,
+    /// so a long-running primitive may exceed the duration.
+    ///
+    /// Returns whether the program finished
+    pub fn run_for(&mut self, duration: Duration) -> UiuaResult<bool> {
+        let end = self.env.rt.backend.now() + duration.as_secs_f64();
+        self.advance(Some(Budget::Until(end)))
+    }
+    /// Run the program to completion and get the runtime
+    pub fn finish(mut self) -> UiuaResult<Uiua> {
+        self.advance(None)?;
+        Ok(self.env)
+    }
+    /// Check if the program has finished, whether or not it failed
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+    /// Get the error the program failed with, if it did
+    pub fn error(&self) -> Option<&UiuaError> {
+        self.error.as_ref()
+    }
+    /// Get the stack
+    ///
+    /// This is the stack as of the last pause, or the final stack if the program has finished.
+    pub fn stack(&self) -> &[Value] {
+        self.env.stack()
+    }
+    /// Get the runtime of a finished program
+    pub fn env(&self) -> Option<&Uiua> {
+        self.finished.then_some(&self.env)
+    }
+    /// Get the runtime of a finished program mutably
+    pub fn env_mut(&mut self) -> Option<&mut Uiua> {
+        self.finished.then_some(&mut self.env)
+    }
+    fn advance(&mut self, budget: Option<Budget>) -> UiuaResult<bool> {
+        if self.finished {
+            return match &self.error {
+                Some(err) => Err(err.clone()),
+                None => Ok(true),
+            };
+        }
+        self.env.rt.budget = budget;
+        let res = self.run();
+        self.env.rt.budget = None;
+        match &res {
+            Ok(false) => {}
+            Ok(true) => self.finished = true,
+            Err(err) => {
+                self.finished = true;
+                self.error = Some(err.clone());
+            }
+        }
+        res
+    }
+    fn run(&mut self) -> UiuaResult<bool> {
+        let base = self.base;
+        loop {
+            if self.env.rt.call_stack.len() == base {
+                let Some(i) = self.next_slice else {
+                    return Ok(true);
+                };
+                let Some(&slice) = self.env.asm.top_slices.get(i) else {
+                    return self.env.end_run(Ok(())).map(|_| true);
+                };
+                self.next_slice = Some(i + 1);
+                let frame = self.env.slice_frame(slice);
+                self.env.rt.call_stack.push(frame);
+            }
+            let err = match self
+                .env
+                .catching_crash("", |env| env.run_frames(base, true))
+            {
+                Ok(Ok(true)) => continue,
+                Ok(Ok(false)) => return Ok(false),
+                Ok(Err(err)) | Err(err) => err,
+            };
+            // A crash leaves its frames behind
+            self.env.rt.call_stack.truncate(base);
+            self.env.rt.loops.retain(|lp| lp.depth <= base);
+            return if self.next_slice.is_some() {
+                self.env.end_run(Err(err)).map(|_| true)
+            } else {
+                Err(err)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compiler, PreEvalMode};
+
+    use super::*;
+
+    fn start(code: &str) -> Execution {
+        let mut comp = Compiler::new();
+        comp.pre_eval_mode(PreEvalMode::Lazy);
+        let asm = comp.load_str(code).unwrap().finish();
+        Uiua::with_safe_sys().start(asm)
+    }
+
+    #[test]
+    fn steps() {
+        let mut exec = start("1 2 3");
+        assert!(exec.stack().is_empty());
+        let mut heights = Vec::new();
+        while !exec.step(1).unwrap() {
+            heights.push(exec.stack().len());
+        }
+        assert!(!heights.is_empty() && heights.iter().all(|&h| h < 3));
+        assert_eq!(exec.stack().len(), 3);
+        assert!(exec.step(1).unwrap());
+
+        let mut exec = start("⍥(+1)1e9 0");
+        assert!(!exec.run_for(Duration::from_millis(10)).unwrap());
+        assert!(!exec.is_finished());
+        assert!(!exec.stack().is_empty());
+    }
+
+    #[test]
+    fn loops() {
+        let mut exec = start("⍥(+1)10 0");
+        let mut counts = Vec::new();
+        while !exec.step(1).unwrap() {
+            if let [n] = exec.stack() {
+                counts.push(n.as_nat(&exec.env, "").unwrap());
+            }
+        }
+        assert!((1..10).all(|n| counts.contains(&n)), "{counts:?}");
+        assert_eq!(exec.finish().unwrap().pop_int().unwrap(), 10);
+
+        let mut exec = start("⍢(×2|<1000) 1");
+        let mut steps = 0;
+        while !exec.step(2).unwrap() {
+            steps += 1;
+        }
+        assert!(steps > 10);
+        assert_eq!(exec.finish().unwrap().pop_int().unwrap(), 1024);
+    }
+
+    #[test]
+    fn errors() {
+        let mut exec = start("⍤\"oops\" 0");
+        let err = loop {
+            match exec.step(1) {
+                Ok(false) => {}
+                Ok(true) => panic!("Execution succeeded"),
+                Err(e) => break e,
+            }
+        };
+        assert!(err.to_string().contains("oops"), "{err}");
+        assert!(exec.is_finished());
+        // The error is kept
+        assert!(exec.error().is_some());
+        let err = exec.step(1).unwrap_err();
+        assert!(err.to_string().contains("oops"), "{err}");
+        let Err(err) = exec.finish() else {
+            panic!("Execution succeeded");
+        };
+        assert!(err.to_string().contains("oops"), "{err}");
+
+        let mut exec = start("⍥(+1⍤\"deep\"≠5.)10 0");
+        let err = loop {
+            match exec.step(3) {
+                Ok(false) => {}
+                Ok(true) => panic!("Execution succeeded"),
+                Err(e) => break e,
+            }
+        };
+        assert!(err.to_string().contains("deep"), "{err}");
+    }
+
+    #[test]
+    fn modifiers() {
+        // Functions called by other modifiers run to completion in one step
+        let mut exec = start("∵(+1)⇡+100⌊⚂");
+        let mut steps = 0;
+        while !exec.step(1).unwrap() {
+            steps += 1;
+        }
+        assert!(steps < 20, "{steps}");
+
+        // Limits bound how far a step can run past its budget
+        let mut comp = Compiler::new();
+        comp.pre_eval_mode(PreEvalMode::Lazy);
+        let asm = comp.load_str("∵(⍢(+1|<1e9))↯2⌊⚂").unwrap().finish();
+        let mut exec = Uiua::with_safe_sys().with_fuel_limit(10_000).start(asm);
+        let err = loop {
+            match exec.step(5) {
+                Ok(false) => {}
+                Ok(true) => panic!("Execution succeeded"),
+                Err(e) => break e,
+            }
+        };
+        assert!(err.to_string().contains("fuel"), "{err}");
+    }
+}
@@ -4,10 +4,14 @@ use std::{
     mem::take,
     net::SocketAddr,
     path::{Path, PathBuf},
-    sync::{Arc, OnceLock},
+    sync::{
+        atomic::{self, AtomicU64},
+        Arc, OnceLock,
+    },
     time::Duration,
 };
 
+use ecow::EcoVec;
 use enum_iterator::{all, Sequence};
 #[cfg(feature = "image")]
 use image::DynamicImage;
@@ -17,8 +21,8 @@ use serde::*;
 use time::UtcOffset;
 
 use crate::{
-    algorithm::validate_size, cowslice::cowslice, primitive::PrimDoc, Array, Boxed, FfiType,
-    Purity, Signature, Uiua, UiuaResult, Value,
+    algorithm::validate_size, cowslice::cowslice, primitive::PrimDoc, Array, Boxed, DynamicModule,
+    FfiType, ModuleInterface, Purity, Signature, Uiua, UiuaResult, Value,
 };
 
 /// The text of Uiua's example module
@@ -148,6 +152,7 @@ pub enum SysOpClass {
     Command,
     Media,
     Tcp,
+    Udp,
     Ffi,
     Misc,
 }
@@ -277,7 +282,7 @@ sys_op! {
     (1(1), Invoke, Command, "&invk", "invoke", Mutating),
     /// Close a stream by its handle
     ///
-    /// This will close files, tcp listeners, and tcp sockets.
+    /// This will close files, tcp listeners, tcp sockets, and udp sockets.
     (1(0), Close, Stream, "&cl", "close handle", Mutating),
     /// Open a file and return a handle to it
     ///
@@ -327,6 +332,18 @@ sys_op! {
     ///
     /// ex: &fif "example.txt"
     (1, FIsFile, Filesystem, "&fif", "file - is file"),
+    /// Get metadata about a file, directory, or symlink
+    ///
+    /// Expects a path and returns a map with the following keys:
+    /// - `"size"` - The size in bytes
+    /// - `"kind"` - One of `"file"`, `"dir"`, or `"symlink"`
+    /// - `"created"`, `"modified"`, and `"accessed"` - Times in seconds since the Unix epoch, or `NaN` if the platform does not provide them
+    /// - `"permissions"` - The Unix permission bits
+    /// The values of the map are boxed.
+    ///
+    /// Symlinks are not followed.
+    /// On platforms without Unix permissions, only the read and write bits are set.
+    (1, FMetadata, Filesystem, "&fmeta", "file - metadata"),
     /// Read all the contents of a file into a string
     ///
     /// Expects a path and returns a rank-`1` character array.
@@ -463,12 +480,21 @@ sys_op! {
     /// See also: [&tcpc]
     (1, TlsConnect, Tcp, "&tlsc", "tls - connect", Mutating),
     /// Set a TCP socket to non-blocking mode
+    ///
+    /// This also works on UDP sockets.
     (1, TcpSetNonBlocking, Tcp, "&tcpsnb", "tcp - set non-blocking", Mutating),
     /// Set the read timeout of a TCP socket in seconds
+    ///
+    /// This also works on UDP sockets.
     (2(0), TcpSetReadTimeout, Tcp, "&tcpsrt", "tcp - set read timeout", Mutating),
     /// Set the write timeout of a TCP socket in seconds
+    ///
+    /// This also works on UDP sockets.
     (2(0), TcpSetWriteTimeout, Tcp, "&tcpswt", "tcp - set write timeout", Mutating),
     /// Get the connection address of a TCP socket
+    ///
+    /// This also works on UDP sockets.
+    /// For a UDP socket that is not connected, this is the address it is bound to.
     (1, TcpAddr, Tcp, "&tcpaddr", "tcp - address", Mutating),
     /// Make an HTTP(S) request
     ///
@@ -491,6 +517,96 @@ sys_op! {
     /// - The HTTP version
     /// - The `Host` header (if not defined)
     (2, HttpsWrite, Tcp, "&httpsw", "https - Make an HTTP(S) request", Mutating),
+    /// Make an HTTP request and get the response
+    ///
+    /// Expects either a URL or a map describing the request.
+    /// The request map can have the following keys:
+    /// - `"url"` - The URL, which must start with `http://` or `https://`. This key is required.
+    /// - `"method"` - The request method. Defaults to `"GET"`.
+    /// - `"headers"` - A map of header names to values
+    /// - `"body"` - The request body as a string or byte array
+    /// - `"redirects"` - The maximum number of redirects to follow. Defaults to `0`.
+    /// - `"timeout"` - The read and write timeout in seconds. Defaults to [infinity].
+    ///
+    /// Returns a map with the following keys:
+    /// - `"status"` - The status code
+    /// - `"headers"` - A map of lowercase header names to values
+    /// - `"body"` - The response body as a byte array
+    /// - `"url"` - The URL of the response, which differs from the request's if a redirect was followed
+    /// The values of the map are boxed.
+    ///
+    /// ex: °utf₈ °□get "body" &http "https://example.com"
+    /// ex: &http map {"url" "method" "body" "redirects"} {"https://example.com" "POST" "Hi!" 5}
+    ///
+    /// Chunked response bodies are decoded automatically.
+    ///
+    /// See also: [&httpsw]
+    (1, HttpRequest, Tcp, "&http", "http - request", Mutating),
+    /// Read an HTTP request from a TCP stream
+    ///
+    /// Returns a map with the following keys:
+    /// - `"method"` - The request method
+    /// - `"path"` - The path, without the query
+    /// - `"query"` - A map of the decoded query parameters
+    /// - `"headers"` - A map of lowercase header names to values
+    /// - `"body"` - The request body as a byte array
+    /// The values of the map are boxed.
+    ///
+    /// Only the bytes of a single request are read, so a connection can serve several requests.
+    /// Use [&httpw] to write a response.
+    ///
+    /// ex: &tcpa &tcpl "0.0.0.0:8080"
+    ///   : ⊸(°□get "path" &httpr)
+    ///   : &cl ⊸&httpw $"You requested _"
+    (1, HttpReadRequest, Tcp, "&httpr", "http - read request", Mutating),
+    /// Write an HTTP response to a TCP stream
+    ///
+    /// Expects a response and a stream handle.
+    /// The response can be a string or byte array body, which is sent with status `200`.
+    /// It can also be a map with the following keys:
+    /// - `"status"` - The status code. Defaults to `200`.
+    /// - `"headers"` - A map of header names to values
+    /// - `"body"` - The response body as a string or byte array
+    ///
+    /// The `Content-Length` header is added automatically.
+    /// String bodies are sent as UTF-8 with a `text/plain` `Content-Type` unless one is given.
+    ///
+    /// ex: &tcpa &tcpl "0.0.0.0:8080"
+    ///   : ⊸&httpw map {"status" "headers" "body"} {404 map {"Content-Type"} {"text/html"} "<h1>Not Found</h1>"}
+    ///   : &cl
+    ///
+    /// See also: [&httpr]
+    (2(0), HttpWriteResponse, Tcp, "&httpw", "http - write response", Mutating),
+    /// Create a UDP socket and bind it to an address
+    ///
+    /// Returns a socket handle.
+    /// Use [&udpr] to receive datagrams and [&udps] to send them.
+    /// Binding to port `0` picks an unused port. Use [&tcpaddr] to find out which one.
+    /// [under][&udpb] calls [&cl] automatically.
+    ///
+    /// See also: [&udpc]
+    (1, UdpBind, Udp, "&udpb", "udp - bind", Mutating),
+    /// Create a UDP socket and connect it to an address
+    ///
+    /// Returns a socket handle bound to an unused local port.
+    /// Datagrams can be sent to the connected address with [&w] and received from it with [&rs] or [&rb], as well as with [&udps] and [&udpr].
+    /// [under][&udpc] calls [&cl] automatically.
+    ///
+    /// See also: [&udpb]
+    (1, UdpConnect, Udp, "&udpc", "udp - connect", Mutating),
+    /// Send a datagram from a UDP socket
+    ///
+    /// Expects the data, the address to send it to, and a socket handle.
+    /// The data must be a rank-`1` byte or character array.
+    (3(0), UdpSend, Udp, "&udps", "udp - send", Mutating),
+    /// Receive a datagram on a UDP socket
+    ///
+    /// Expects a maximum number of bytes and a socket handle.
+    /// Using [infinity] as the count will receive a datagram of any size.
+    /// If the datagram is longer than the count, the extra bytes are discarded.
+    ///
+    /// Returns the received bytes and the address they were sent from.
+    (2(2), UdpReceive, Udp, "&udpr", "udp - receive", Mutating),
     /// Capture an image from a webcam
     ///
     /// Takes the index of the webcam to capture from.
@@ -601,22 +717,102 @@ sys_op! {
     /// Expects a pointer.
     /// See [&memcpy] for an example.
     (1(0), MemFree, Ffi, "&memfree", "free memory", Mutating),
+    /// Load a Uiua module at runtime
+    ///
+    /// Expects an interface and a path.
+    /// The interface is a map from the names the module must define to their `[args outputs]` signatures.
+    /// The module is compiled and run, and it is an error if it does not define every name in the interface with the given signature.
+    /// Constants have the signature `0_1`.
+    ///
+    /// Returns a handle to the loaded module.
+    /// Items of the interface can be called with [&modc].
+    /// The module can be unloaded with [&cl].
+    /// ex! Mod ← &modl map {"Add" "Neg"} [2_1 1_1] "plugin.ua"
+    ///   : &modc "Add" {1 2} Mod
+    ///
+    /// Only names in the interface can be accessed, so the module can be replaced by any other module that provides the same interface.
+    (2, ModuleLoad, Misc, "&modl", "module - load", Mutating),
+    /// Call an item of a module loaded with [&modl]
+    ///
+    /// Expects a name, a list of boxed arguments, and a module handle.
+    /// The number of arguments must match the signature in the module's interface.
+    /// If the item has exactly one output, it is returned as is.
+    /// Otherwise, a list of the boxed outputs is returned.
+    /// ex! Mod ← &modl map {"Add" "Neg"} [2_1 1_1] "plugin.ua"
+    ///   : &modc "Neg" {5} Mod
+    (3, ModuleCall, Misc, "&modc", "module - call", Mutating),
+    /// Create a channel that can be shared between threads
+    ///
+    /// Expects a capacity.
+    /// If the capacity is [infinity], the channel is unbounded.
+    /// Otherwise, [send]ing to a full channel blocks until another thread [recv]s from it.
+    ///
+    /// Returns a channel handle.
+    /// The handle can be passed to [send], [recv], and [tryrecv] in place of a thread id.
+    /// Unlike thread ids, a channel handle can be used by any thread that has it, so it can be captured by the functions passed to [spawn] or [pool].
+    /// Any number of threads may send to and receive from the same channel.
+    ///
+    /// The channel can be closed with [&cl].
+    /// Sending to a closed channel is an error.
+    /// Values that were sent before the channel was closed can still be received.
+    /// Once they are exhausted, [recv] and [tryrecv] throw an error.
+    /// ex: Ch ← &chan ∞
+    ///   : ◌≡spawn(send Ch ×2) ⇡5
+    ///   : ⊏⍏.[⍥(recv Ch)5]
+    (1, Channel, Misc, "&chan", "channel", Mutating),
+    /// Cancel a thread
+    ///
+    /// Expects a thread id returned by [spawn], [pool], or [&tspawn].
+    /// The thread, and any threads it spawned, stop with an error the next time they would check the execution time limit, which happens between instructions and during long-running loops.
+    /// [wait]ing on a cancelled thread throws that error.
+    /// ex: Th ← &tspawn(⍢(+1|≥0)) 1 0
+    ///   : &cancel Th
+    ///   : ⍣wait⋅∘ Th
+    ///
+    /// Cancellation is cooperative, so a thread that is blocked in [recv], [&sl], or a system function will not stop until it resumes running code.
+    (1(0), Cancel, Misc, "&cancel", "cancel thread", Mutating),
+    /// Spawn a thread with a time limit
+    ///
+    /// Expects a number of seconds and then the function's arguments.
+    /// This works like [spawn], but once the thread has run for longer than the limit, it stops with an error.
+    /// ex: Th ← &tspawn(⍢(+1|≥0)) 0.1 0
+    ///   : ⍣wait⋅∘ Th
+    ///
+    /// Threads inherit the time limit of the thread that spawned them, and [&tspawn] cannot extend it.
+    /// If the limit is [infinity], the inherited limit is kept.
+    (1(1)[1], TimedSpawn, Misc, "&tspawn", "thread - spawn with time limit", Mutating),
 }
 
 /// A handle to an IO stream
 ///
 /// 0 is stdin, 1 is stdout, 2 is stderr.
 ///
-/// Other handles can be used by files or sockets.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Other handles can be used by files, sockets, channels, or modules.
+/// New handles should be made with [`Handle::unique`] so that they never collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
 pub struct Handle(pub u64);
 
+/// The next handle that [`Handle::unique`] will return
+static NEXT_HANDLE: AtomicU64 = AtomicU64::new(Handle::FIRST_UNRESERVED.0);
+
 impl Handle {
     const STDIN: Self = Self(0);
     const STDOUT: Self = Self(1);
     const STDERR: Self = Self(2);
     /// The first handle that can be used by the user
     pub const FIRST_UNRESERVED: Self = Self(3);
+    /// Get a handle that has never been returned before
+    ///
+    /// System backends, channels, and dynamic modules all get their handles from here,
+    /// so a handle always refers to the same resource, even after it is closed.
+    pub fn unique() -> Self {
+        Handle(NEXT_HANDLE.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+    /// Make sure [`Handle::unique`] never returns this handle
+    pub fn reserve(self) {
+        NEXT_HANDLE.fetch_max(self.0.saturating_add(1), atomic::Ordering::Relaxed);
+    }
 }
 
 impl From<usize> for Handle {
@@ -664,9 +860,12 @@ pub enum HandleKind {
     TlsListener(SocketAddr),
     TcpSocket(SocketAddr),
     TlsSocket(SocketAddr),
+    UdpSocket(SocketAddr),
     ChildStdin(String),
     ChildStdout(String),
     ChildStderr(String),
+    Module(PathBuf),
+    Channel,
 }
 
 impl fmt::Display for HandleKind {
@@ -677,9 +876,12 @@ impl fmt::Display for HandleKind {
             Self::TlsListener(addr) => write!(f, "tls listener {}", addr),
             Self::TcpSocket(addr) => write!(f, "tcp socket {}", addr),
             Self::TlsSocket(addr) => write!(f, "tls socket {}", addr),
+            Self::UdpSocket(addr) => write!(f, "udp socket {}", addr),
             Self::ChildStdin(com) => write!(f, "stdin {com}"),
             Self::ChildStdout(com) => write!(f, "stdout {com}"),
             Self::ChildStderr(com) => write!(f, "stderr {com}"),
+            Self::Module(path) => write!(f, "module {}", path.display()),
+            Self::Channel => write!(f, "channel"),
         }
     }
 }
@@ -765,6 +967,12 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn is_file(&self, path: &str) -> Result<bool, String> {
         Err("Checking if a path is a file is not supported in this environment".into())
     }
+    /// Get metadata about a file, directory, or symlink
+    ///
+    /// Symlinks should not be followed.
+    fn file_metadata(&self, path: &str) -> Result<FileMetadata, String> {
+        Err("Getting file metadata is not supported in this environment".into())
+    }
     /// Delete a file or directory
     fn delete(&self, path: &str) -> Result<(), String> {
         Err("Deleting files is not supported in this environment".into())
@@ -865,6 +1073,12 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn now(&self) -> f64 {
         now()
     }
+    /// A seed for the random number generator
+    ///
+    /// If this returns `Some`, the generator is seeded with it at the start of each run.
+    fn random_seed(&self) -> Option<u64> {
+        None
+    }
     /// Create a TCP listener and bind it to an address
     fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
         Err("TCP listeners are not supported in this environment".into())
@@ -909,6 +1123,30 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     ) -> Result<(), String> {
         Err("TCP sockets are not supported in this environment".into())
     }
+    /// Create a UDP socket and bind it to an address
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Create a UDP socket and connect it to an address
+    fn udp_connect(&self, addr: &str) -> Result<Handle, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Send a datagram from a UDP socket to an address
+    fn udp_send(&self, handle: Handle, data: &[u8], addr: &str) -> Result<(), String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Receive a datagram of at most `max_len` bytes on a UDP socket
+    ///
+    /// Returns the datagram and the address it was sent from
+    fn udp_receive(&self, handle: Handle, max_len: usize) -> Result<(Vec<u8>, SocketAddr), String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
+    /// Get the address of a UDP socket
+    ///
+    /// This should be the peer address if the socket is connected and the local address otherwise.
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        Err("UDP sockets are not supported in this environment".into())
+    }
     /// Close a stream
     fn close(&self, handle: Handle) -> Result<(), String> {
         Ok(())
@@ -941,6 +1179,10 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
         Err("Making HTTPS requests is not supported in this environment".into())
     }
+    /// Make an HTTP or HTTPS request on a new connection
+    fn http_request(&self, request: &HttpRequest) -> Result<HttpResponse, String> {
+        Err("Making HTTP requests is not supported in this environment".into())
+    }
     /// Capture an image from the webcam
     fn webcam_capture(&self, index: usize) -> Result<WebcamImage, String> {
         Err("Capturing from webcam is not supported in this environment".into())
@@ -984,8 +1226,75 @@ pub trait SysBackend: Any + Send + Sync + 'static {
     }
 }
 
+/// Metadata about a file, directory, or symlink
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// The size in bytes
+    pub size: u64,
+    /// The kind of entry
+    pub kind: FileKind,
+    /// The creation time in seconds since the Unix epoch
+    pub created: Option<f64>,
+    /// The last modification time in seconds since the Unix epoch
+    pub modified: Option<f64>,
+    /// The last access time in seconds since the Unix epoch
+    pub accessed: Option<f64>,
+    /// The Unix permission bits
+    pub permissions: u32,
+}
+
+/// The kind of a filesystem entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[allow(missing_docs)]
+pub enum FileKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl FileKind {
+    /// Get the name used for this kind in [`SysOp::FMetadata`]'s output
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileKind::File => "file",
+            FileKind::Dir => "dir",
+            FileKind::Symlink => "symlink",
+        }
+    }
+}
+
+/// An HTTP request made with [`SysOp::HttpRequest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    /// The request method
+    pub method: String,
+    /// The URL, including the `http://` or `https://` scheme
+    pub url: String,
+    /// The header names and values
+    pub headers: Vec<(String, String)>,
+    /// The body
+    pub body: Vec<u8>,
+    /// The maximum number of redirects to follow
+    pub redirects: usize,
+    /// The timeout for connecting, and for each read and write
+    pub timeout: Option<Duration>,
+}
+
+/// A response to an [`HttpRequest`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponse {
+    /// The status code
+    pub status: u16,
+    /// The header names, in lowercase, and values
+    pub headers: Vec<(String, String)>,
+    /// The body
+    pub body: Vec<u8>,
+    /// The URL of the response, after following redirects
+    pub url: String,
+}
+
 /// A target for a git repository
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum GitTarget {
     /// The latest commit on the default branch
     #[default]
@@ -1423,6 +1732,35 @@ impl SysOp {
                 let is_file = env.rt.backend.is_file(&path).map_err(|e| env.error(e))?;
                 env.push(is_file);
             }
+            SysOp::FMetadata => {
+                let path = env.pop(1)?.as_string(env, "Path must be a string")?;
+                let meta = (env.rt.backend)
+                    .file_metadata(&path)
+                    .map_err(|e| env.error(e))?;
+                let time = |t: Option<f64>| Value::from(t.unwrap_or(f64::NAN));
+                let keys = Array::<Boxed>::from_iter([
+                    "size",
+                    "kind",
+                    "created",
+                    "modified",
+                    "accessed",
+                    "permissions",
+                ]);
+                let mut values: Value = Array::<Boxed>::from_iter(
+                    [
+                        Value::from(meta.size as f64),
+                        Value::from(meta.kind.name()),
+                        time(meta.created),
+                        time(meta.modified),
+                        time(meta.accessed),
+                        Value::from(meta.permissions as f64),
+                    ]
+                    .map(Boxed),
+                )
+                .into();
+                values.map(keys.into(), env)?;
+                env.push(values);
+            }
             SysOp::Invoke => {
                 let path = env.pop(1)?.as_string(env, "Invoke path must be a string")?;
                 env.rt.backend.invoke(&path).map_err(|e| env.error(e))?;
@@ -1585,7 +1923,9 @@ impl SysOp {
             }
             SysOp::TcpAddr => {
                 let handle = env.pop(1)?.as_handle(env, "")?;
-                let addr = env.rt.backend.tcp_addr(handle).map_err(|e| env.error(e))?;
+                let addr = (env.rt.backend.tcp_addr(handle))
+                    .or_else(|e| env.rt.backend.udp_addr(handle).map_err(|_| e))
+                    .map_err(|e| env.error(e))?;
                 env.push(addr.to_string());
             }
             SysOp::TcpSetNonBlocking => {
@@ -1628,9 +1968,110 @@ impl SysOp {
                     .map_err(|e| env.error(e))?;
                 env.push(res);
             }
+            SysOp::HttpRequest => {
+                let request = value_to_http_request(env.pop(1)?, env)?;
+                let response = (env.rt.backend)
+                    .http_request(&request)
+                    .map_err(|e| env.error(e))?;
+                let keys = Array::<Boxed>::from_iter(["status", "headers", "body", "url"]);
+                let mut values: Value = Array::<Boxed>::from_iter(
+                    [
+                        Value::from(response.status as f64),
+                        headers_to_value(&response.headers, env)?,
+                        Value::from(EcoVec::from(response.body)),
+                        Value::from(response.url),
+                    ]
+                    .map(Boxed),
+                )
+                .into();
+                values.map(keys.into(), env)?;
+                env.push(values);
+            }
+            SysOp::HttpReadRequest => {
+                #[cfg(feature = "tls")]
+                {
+                    let handle = env.pop(1)?.as_handle(env, "")?;
+                    let backend = &env.rt.backend;
+                    let request = crate::http::read_request(|len| backend.read(handle, len))
+                        .map_err(|e| env.error(e))?;
+                    let keys =
+                        Array::<Boxed>::from_iter(["method", "path", "query", "headers", "body"]);
+                    let mut values: Value = Array::<Boxed>::from_iter(
+                        [
+                            Value::from(request.method),
+                            Value::from(request.path),
+                            headers_to_value(&request.query, env)?,
+                            headers_to_value(&request.headers, env)?,
+                            Value::from(EcoVec::from(request.body)),
+                        ]
+                        .map(Boxed),
+                    )
+                    .into();
+                    values.map(keys.into(), env)?;
+                    env.push(values);
+                }
+                #[cfg(not(feature = "tls"))]
+                return Err(env.error("Reading HTTP requests is not supported in this environment"));
+            }
+            SysOp::HttpWriteResponse => {
+                let response = value_to_http_response(env.pop(1)?, env)?;
+                let handle = env.pop(2)?.as_handle(env, "")?;
+                (env.rt.backend)
+                    .write(handle, &response)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::UdpBind => {
+                let addr = env.pop(1)?.as_string(env, "Address must be a string")?;
+                let handle = (env.rt.backend).udp_bind(&addr).map_err(|e| env.error(e))?;
+                let sock_addr = env.rt.backend.udp_addr(handle).map_err(|e| env.error(e))?;
+                let handle = handle.value(HandleKind::UdpSocket(sock_addr));
+                env.push(handle);
+            }
+            SysOp::UdpConnect => {
+                let addr = env.pop(1)?.as_string(env, "Address must be a string")?;
+                let handle = (env.rt.backend)
+                    .udp_connect(&addr)
+                    .map_err(|e| env.error(e))?;
+                let sock_addr = env.rt.backend.udp_addr(handle).map_err(|e| env.error(e))?;
+                let handle = handle.value(HandleKind::UdpSocket(sock_addr));
+                env.push(handle);
+            }
+            SysOp::UdpSend => {
+                let data = env
+                    .pop(1)?
+                    .into_bytes(env, "Datagram must be a byte or character array")?;
+                let addr = env.pop(2)?.as_string(env, "Address must be a string")?;
+                let handle = env.pop(3)?.as_handle(env, "")?;
+                (env.rt.backend)
+                    .udp_send(handle, &data, &addr)
+                    .map_err(|e| env.error(e))?;
+            }
+            SysOp::UdpReceive => {
+                let count = (env.pop(1)?)
+                    .as_nat_or_inf(env, "Count must be an integer or infinity")?
+                    .unwrap_or(u16::MAX as usize);
+                validate_size::<u8>([count], env)?;
+                let handle = env.pop(2)?.as_handle(env, "")?;
+                let (bytes, addr) = (env.rt.backend)
+                    .udp_receive(handle, count)
+                    .map_err(|e| env.error(e))?;
+                env.push(addr.to_string());
+                env.push(Array::from(bytes.as_slice()));
+            }
             SysOp::Close => {
-                let handle = env.pop(1)?.as_handle(env, "")?;
-                env.rt.backend.close(handle).map_err(|e| env.error(e))?;
+                let val = env.pop(1)?;
+                let handle = val.as_handle(env, "")?;
+                // Handles are unique, so the table that has the handle owns it,
+                // even if the value has lost its handle kind
+                match handle_kind(&val) {
+                    _ if env.rt.dynamic_modules.remove(&handle).is_some() => {}
+                    _ if env.has_channel(handle) => env.close_channel(handle)?,
+                    Some(HandleKind::Module(_)) => {
+                        return Err(env.error("Module handle is not loaded"))
+                    }
+                    Some(HandleKind::Channel) => return Err(env.error("Channel is closed")),
+                    _ => env.rt.backend.close(handle).map_err(|e| env.error(e))?,
+                }
             }
             SysOp::RunInherit => {
                 let (command, args) = value_to_command(&env.pop(1)?, env)?;
@@ -1754,11 +2195,254 @@ impl SysOp {
                     .ok_or_else(|| env.error("Freed pointer must be a pointer value"))?;
                 (env.rt.backend).mem_free(ptr).map_err(|e| env.error(e))?;
             }
+            SysOp::ModuleLoad => {
+                let interface = ModuleInterface::from_value(&env.pop(1)?, env)?;
+                let path = env.pop(2)?.as_string(env, "Module path must be a string")?;
+                let path = PathBuf::from(path);
+                let module = DynamicModule::load(env, &path, &interface)?;
+                let handle = Handle::unique();
+                env.rt.dynamic_modules.insert(handle, Arc::new(module));
+                env.push(handle.value(HandleKind::Module(path)));
+            }
+            SysOp::ModuleCall => {
+                let name = env
+                    .pop(1)?
+                    .as_string(env, "Module item name must be a string")?;
+                let args = env.pop(2)?;
+                let args: Vec<Value> = args.into_rows().map(Value::unpacked).collect();
+                let val = env.pop(3)?;
+                let handle = val.as_handle(env, "Expected value to be a module handle")?;
+                if let Some(kind) = handle_kind(&val) {
+                    if !matches!(kind, HandleKind::Module(_)) {
+                        return Err(
+                            env.error(format!("Expected a module handle, but got a {kind}"))
+                        );
+                    }
+                }
+                let module = (env.rt.dynamic_modules.get(&handle))
+                    .cloned()
+                    .ok_or_else(|| env.error("Module handle is not loaded"))?;
+                let mut outputs = module.call(env, &name, args)?;
+                env.push(if outputs.len() == 1 {
+                    outputs.pop().unwrap()
+                } else {
+                    let n = outputs.len();
+                    Array::new(n, outputs.into_iter().map(Boxed).collect::<EcoVec<_>>()).into()
+                });
+            }
+            SysOp::Cancel => {
+                let id = env.pop(1)?;
+                env.cancel(id)?;
+            }
+            SysOp::TimedSpawn => {
+                let f = env.pop_function()?;
+                let seconds = (env.pop(1)?).as_num(env, "Time limit must be a number")?;
+                if seconds.is_nan() {
+                    return Err(env.error("Time limit cannot be NaN"));
+                }
+                if seconds < 0.0 {
+                    return Err(env.error("Time limit must be positive"));
+                }
+                env.spawn(f.signature().args, false, move |env| {
+                    env.limit_thread(seconds);
+                    env.call(f)
+                })?;
+            }
+            SysOp::Channel => {
+                let capacity = (env.pop(1)?)
+                    .as_nat_or_inf(env, "Channel capacity must be a natural number or infinity")?;
+                let channel = env.create_channel(capacity);
+                env.push(channel);
+            }
         }
         Ok(())
     }
 }
 
+pub(crate) fn handle_kind(val: &Value) -> Option<&HandleKind> {
+    match val {
+        Value::Box(b) => b.as_scalar()?.0.meta().handle_kind.as_ref(),
+        val => val.meta().handle_kind.as_ref(),
+    }
+}
+
+/// Get an optional, possibly boxed value from a map
+fn map_field(map: &Value, key: &str, env: &Uiua) -> UiuaResult<Option<Value>> {
+    let key = Value::from(key);
+    Ok(if map.has_key(&key, env)? {
+        Some(map.get(&key, env)?.unboxed())
+    } else {
+        None
+    })
+}
+
+/// Get a string or byte array as bytes
+fn value_to_body(value: Value, env: &Uiua) -> UiuaResult<Vec<u8>> {
+    match value {
+        Value::Char(_) => Ok(value.as_string(env, "")?.into_bytes()),
+        value => value.as_bytes(env, "HTTP body must be a string or byte array"),
+    }
+}
+
+/// Convert a map of header names to values into pairs
+/// Whether a string is an HTTP token, which methods and header names must be
+pub(crate) fn is_http_token(s: &str) -> bool {
+    !s.is_empty()
+        && (s.bytes()).all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+fn value_to_headers(value: &Value, env: &Uiua) -> UiuaResult<Vec<(String, String)>> {
+    if !value.is_map() {
+        return Err(env.error("HTTP headers must be a map"));
+    }
+    let mut headers = Vec::new();
+    for (name, value) in value.map_kv() {
+        let name = name.as_string(env, "HTTP header names must be strings")?;
+        let value = (value.unboxed()).as_string(env, "HTTP header values must be strings")?;
+        headers.push((name, value));
+    }
+    Ok(headers)
+}
+
+/// Convert name-value pairs into a map, joining repeated names with commas
+fn headers_to_value(headers: &[(String, String)], env: &Uiua) -> UiuaResult<Value> {
+    let mut joined: Vec<(&str, String)> = Vec::new();
+    for (name, value) in headers {
+        if let Some((_, existing)) = joined.iter_mut().find(|(n, _)| *n == name.as_str()) {
+            existing.push_str(", ");
+            existing.push_str(value);
+        } else {
+            joined.push((name.as_str(), value.clone()));
+        }
+    }
+    let keys = Array::<Boxed>::from_iter(joined.iter().map(|(name, _)| Boxed((*name).into())));
+    let mut values: Value =
+        Array::<Boxed>::from_iter(joined.into_iter().map(|(_, value)| Boxed(value.into()))).into();
+    values.map(keys.into(), env)?;
+    Ok(values)
+}
+
+fn value_to_http_request(value: Value, env: &Uiua) -> UiuaResult<HttpRequest> {
+    let mut request = HttpRequest {
+        method: "GET".into(),
+        url: String::new(),
+        headers: Vec::new(),
+        body: Vec::new(),
+        redirects: 0,
+        timeout: None,
+    };
+    if !value.is_map() {
+        request.url = value.as_string(env, "HTTP request must be a URL or a map")?;
+        return Ok(request);
+    }
+    request.url = map_field(&value, "url", env)?
+        .ok_or_else(|| env.error("HTTP request map must have a \"url\" key"))?
+        .as_string(env, "HTTP request URL must be a string")?;
+    if let Some(method) = map_field(&value, "method", env)? {
+        request.method =
+            (method.as_string(env, "HTTP request method must be a string")?).to_uppercase();
+        if !is_http_token(&request.method) {
+            return Err(env.error(format!("{:?} is not a valid HTTP method", request.method)));
+        }
+    }
+    if let Some(headers) = map_field(&value, "headers", env)? {
+        request.headers = value_to_headers(&headers, env)?;
+    }
+    if let Some(body) = map_field(&value, "body", env)? {
+        request.body = value_to_body(body, env)?;
+    }
+    if let Some(redirects) = map_field(&value, "redirects", env)? {
+        request.redirects = redirects
+            .as_nat_or_inf(env, "HTTP redirects must be a natural number")?
+            .unwrap_or(usize::MAX);
+    }
+    if let Some(timeout) = map_field(&value, "timeout", env)? {
+        let timeout = timeout.as_num(env, "HTTP timeout must be a number")?;
+        if timeout.is_nan() || timeout <= 0.0 {
+            return Err(env.error("HTTP timeout must be positive"));
+        }
+        request.timeout = (timeout != f64::INFINITY).then(|| Duration::from_secs_f64(timeout));
+    }
+    Ok(request)
+}
+
+/// Serialize an HTTP response from a body or a map
+fn value_to_http_response(value: Value, env: &Uiua) -> UiuaResult<Vec<u8>> {
+    let mut status = 200;
+    let mut headers = Vec::new();
+    let body = if value.is_map() {
+        if let Some(code) = map_field(&value, "status", env)? {
+            status = code.as_nat(env, "HTTP status must be a natural number")?;
+            if !(100..1000).contains(&status) {
+                return Err(env.error(format!("{status} is not a valid HTTP status")));
+            }
+        }
+        if let Some(map) = map_field(&value, "headers", env)? {
+            headers = value_to_headers(&map, env)?;
+        }
+        map_field(&value, "body", env)?
+    } else {
+        Some(value)
+    };
+    let is_text = matches!(body, Some(Value::Char(_)));
+    let body = match body {
+        Some(body) => value_to_body(body, env)?,
+        None => Vec::new(),
+    };
+    let has = |name: &str| headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name));
+    let mut head = format!("HTTP/1.1 {status} {}\r\n", http_status_reason(status));
+    if is_text && !has("content-type") {
+        head.push_str("Content-Type: text/plain; charset=utf-8\r\n");
+    }
+    let bodiless = status < 200 || status == 204 || status == 304;
+    if !bodiless && !has("content-length") && !has("transfer-encoding") {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    for (name, value) in &headers {
+        if name.contains(['\r', '\n', ':']) || value.contains(['\r', '\n']) {
+            return Err(env.error(format!("Invalid HTTP header {name:?}")));
+        }
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    let mut response = head.into_bytes();
+    response.extend(body);
+    Ok(response)
+}
+
+fn http_status_reason(status: usize) -> &'static str {
+    match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Content Too Large",
+        415 => "Unsupported Media Type",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
+
 fn value_to_command(value: &Value, env: &Uiua) -> UiuaResult<(String, Vec<String>)> {
     let mut strings = Vec::new();
     match value {
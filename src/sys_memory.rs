@@ -0,0 +1,386 @@
+//! A system backend with an in-memory filesystem
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    mem::take,
+    path::{Component, Path, PathBuf},
+};
+
+use parking_lot::Mutex;
+
+use crate::{FileKind, FileMetadata, Handle, SysBackend};
+
+/// A system backend with an in-memory filesystem
+///
+/// Files and directories can be added before running a program and inspected afterward.
+/// Standard output and error are captured like in [`SafeSys`](crate::SafeSys). There is no other IO.
+///
+/// The filesystem starts out with only a root directory, which is also the current directory.
+///
+/// ```
+/// use uiua::*;
+///
+/// let sys = MemorySys::new().with_file("data/in.txt", "Hello");
+/// let mut uiua = Uiua::with_backend(sys);
+/// uiua.run_str(r#"&fwa "out.txt" $"_, World!" &fras "data/in.txt""#)
+///     .unwrap();
+/// let sys = uiua.downcast_backend::<MemorySys>().unwrap();
+/// assert_eq!(sys.file("out.txt").unwrap(), b"Hello, World!");
+/// ```
+#[derive(Default)]
+pub struct MemorySys {
+    fs: Mutex<MemoryFs>,
+    stdout: Mutex<Vec<u8>>,
+    stderr: Mutex<Vec<u8>>,
+}
+
+struct MemoryFs {
+    /// Files and directories by absolute path
+    entries: BTreeMap<PathBuf, Entry>,
+    cwd: PathBuf,
+    streams: HashMap<Handle, MemoryStream>,
+}
+
+enum Entry {
+    File(Vec<u8>),
+    Dir,
+}
+
+/// An open file
+struct MemoryStream {
+    path: PathBuf,
+    pos: usize,
+    write: bool,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        let root = PathBuf::from("/");
+        MemoryFs {
+            entries: [(root.clone(), Entry::Dir)].into(),
+            cwd: root,
+            streams: HashMap::new(),
+        }
+    }
+}
+
+impl MemoryFs {
+    /// Get the absolute path without `.`s or `..`s
+    fn resolve(&self, path: &Path) -> PathBuf {
+        let mut resolved = self.cwd.clone();
+        for comp in path.components() {
+            match comp {
+                Component::Prefix(_) | Component::RootDir => resolved = PathBuf::from("/"),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(part) => resolved.push(part),
+            }
+        }
+        resolved
+    }
+    fn file(&self, path: &Path) -> Result<&Vec<u8>, String> {
+        match self.entries.get(&self.resolve(path)) {
+            Some(Entry::File(contents)) => Ok(contents),
+            Some(Entry::Dir) => Err(format!("{} is a directory", path.display())),
+            None => Err(format!("{} does not exist", path.display())),
+        }
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.entries.get(&self.resolve(path)), Some(Entry::Dir))
+    }
+    /// Create or overwrite a file
+    fn write_file(&mut self, path: &Path, contents: Vec<u8>) -> Result<(), String> {
+        let resolved = self.resolve(path);
+        if !resolved.parent().is_some_and(|parent| self.is_dir(parent)) {
+            return Err(format!(
+                "The directory of {} does not exist",
+                path.display()
+            ));
+        }
+        match self.entries.get_mut(&resolved) {
+            Some(Entry::Dir) => Err(format!("{} is a directory", path.display())),
+            Some(Entry::File(file)) => {
+                *file = contents;
+                Ok(())
+            }
+            None => {
+                self.entries.insert(resolved, Entry::File(contents));
+                Ok(())
+            }
+        }
+    }
+    /// Create a directory and any missing parents
+    fn make_dir(&mut self, path: &Path) -> Result<(), String> {
+        let resolved = self.resolve(path);
+        let mut dirs: Vec<&Path> = resolved.ancestors().collect();
+        dirs.reverse();
+        for dir in dirs {
+            match self.entries.get(dir) {
+                Some(Entry::Dir) => {}
+                Some(Entry::File(_)) => {
+                    return Err(format!("{} is a file", dir.display()));
+                }
+                None => {
+                    self.entries.insert(dir.into(), Entry::Dir);
+                }
+            }
+        }
+        Ok(())
+    }
+    fn open(&mut self, path: &Path, write: bool) -> Handle {
+        let handle = Handle::unique();
+        let path = self.resolve(path);
+        let stream = MemoryStream {
+            path,
+            pos: 0,
+            write,
+        };
+        self.streams.insert(handle, stream);
+        handle
+    }
+    /// Get a stream and the contents of its file
+    fn stream(&mut self, handle: Handle) -> Result<(&mut MemoryStream, &mut Vec<u8>), String> {
+        let stream = (self.streams.get_mut(&handle)).ok_or("Invalid file handle")?;
+        match self.entries.get_mut(&stream.path) {
+            Some(Entry::File(contents)) => Ok((stream, contents)),
+            _ => Err(format!("{} no longer exists", stream.path.display())),
+        }
+    }
+    /// Read as many bytes from a stream as `len` returns for the rest of its file
+    fn read(
+        &mut self,
+        handle: Handle,
+        len: impl FnOnce(&[u8]) -> usize,
+    ) -> Result<Vec<u8>, String> {
+        let (stream, contents) = self.stream(handle)?;
+        let rest = &contents[stream.pos.min(contents.len())..];
+        let bytes = rest[..len(rest).min(rest.len())].to_vec();
+        stream.pos += bytes.len();
+        Ok(bytes)
+    }
+}
+
+impl MemorySys {
+    /// Create a new in-memory backend with an empty filesystem
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a file, creating its parent directories
+    ///
+    /// # Panics
+    /// Panics if a parent of the path is a file
+    pub fn with_file(self, path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> Self {
+        let path = path.as_ref();
+        let mut fs = self.fs.lock();
+        if let Some(parent) = path.parent() {
+            fs.make_dir(parent).unwrap();
+        }
+        fs.write_file(path, contents.into()).unwrap();
+        drop(fs);
+        self
+    }
+    /// Add a directory, creating its parent directories
+    ///
+    /// # Panics
+    /// Panics if the path or one of its parents is a file
+    pub fn with_dir(self, path: impl AsRef<Path>) -> Self {
+        self.fs.lock().make_dir(path.as_ref()).unwrap();
+        self
+    }
+    /// Get the contents of a file
+    pub fn file(&self, path: impl AsRef<Path>) -> Option<Vec<u8>> {
+        self.fs.lock().file(path.as_ref()).ok().cloned()
+    }
+    /// Check if a directory exists
+    pub fn is_dir(&self, path: impl AsRef<Path>) -> bool {
+        self.fs.lock().is_dir(path.as_ref())
+    }
+    /// Get all files by their absolute paths
+    pub fn files(&self) -> BTreeMap<PathBuf, Vec<u8>> {
+        (self.fs.lock().entries.iter())
+            .filter_map(|(path, entry)| match entry {
+                Entry::File(contents) => Some((path.clone(), contents.clone())),
+                Entry::Dir => None,
+            })
+            .collect()
+    }
+    /// Take the captured stdout
+    pub fn take_stdout(&self) -> Vec<u8> {
+        take(&mut *self.stdout.lock())
+    }
+    /// Take the captured stderr
+    pub fn take_stderr(&self) -> Vec<u8> {
+        take(&mut *self.stderr.lock())
+    }
+}
+
+impl SysBackend for MemorySys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.stdout.lock().extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.stderr.lock().extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        let fs = self.fs.lock();
+        fs.entries.contains_key(&fs.resolve(path.as_ref()))
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        let fs = self.fs.lock();
+        let dir = fs.resolve(path.as_ref());
+        if !fs.is_dir(&dir) {
+            return Err(format!("{path} is not a directory"));
+        }
+        Ok((fs.entries.keys())
+            .filter(|entry| entry.parent() == Some(dir.as_path()))
+            .filter_map(|entry| entry.file_name())
+            .map(|name| Path::new(path).join(name).to_string_lossy().into())
+            .collect())
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        let fs = self.fs.lock();
+        match fs.entries.get(&fs.resolve(path.as_ref())) {
+            Some(entry) => Ok(matches!(entry, Entry::File(_))),
+            None => Err(format!("{path} does not exist")),
+        }
+    }
+    fn file_metadata(&self, path: &str) -> Result<FileMetadata, String> {
+        let fs = self.fs.lock();
+        let (size, kind, permissions) = match fs.entries.get(&fs.resolve(path.as_ref())) {
+            Some(Entry::File(contents)) => (contents.len() as u64, FileKind::File, 0o644),
+            Some(Entry::Dir) => (0, FileKind::Dir, 0o755),
+            None => return Err(format!("{path} does not exist")),
+        };
+        Ok(FileMetadata {
+            size,
+            kind,
+            created: None,
+            modified: None,
+            accessed: None,
+            permissions,
+        })
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        let mut fs = self.fs.lock();
+        let resolved = fs.resolve(path.as_ref());
+        if resolved.parent().is_none() {
+            return Err("Cannot delete the root directory".into());
+        }
+        fs.entries.retain(|entry, _| !entry.starts_with(&resolved));
+        Ok(())
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.delete(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.fs.lock().read(handle, |_| count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.fs.lock().read(handle, |rest| rest.len())
+    }
+    fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String> {
+        self.fs.lock().read(handle, |rest| {
+            if delim.is_empty() {
+                return 1;
+            }
+            (rest.windows(delim.len()))
+                .position(|window| window == delim)
+                .map_or(rest.len(), |i| i + delim.len())
+        })
+    }
+    fn write(&self, handle: Handle, conts: &[u8]) -> Result<(), String> {
+        let mut fs = self.fs.lock();
+        let (stream, contents) = fs.stream(handle)?;
+        if !stream.write {
+            return Err(format!(
+                "{} was not opened for writing",
+                stream.path.display()
+            ));
+        }
+        let end = stream.pos + conts.len();
+        if contents.len() < end {
+            contents.resize(end, 0);
+        }
+        contents[stream.pos..end].copy_from_slice(conts);
+        stream.pos = end;
+        Ok(())
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        let mut fs = self.fs.lock();
+        fs.write_file(path, Vec::new())?;
+        Ok(fs.open(path, true))
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        let mut fs = self.fs.lock();
+        fs.file(path)?;
+        Ok(fs.open(path, write))
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        self.fs.lock().make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.fs.lock().file(path).cloned()
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.fs.lock().write_file(path, contents.to_vec())
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        match self.fs.lock().streams.remove(&handle) {
+            Some(_) => Ok(()),
+            None => Err("Invalid file handle".into()),
+        }
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        let mut fs = self.fs.lock();
+        let resolved = fs.resolve(path.as_ref());
+        if !fs.is_dir(&resolved) {
+            return Err(format!("{path} is not a directory"));
+        }
+        fs.cwd = resolved;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Uiua;
+
+    use super::*;
+
+    #[test]
+    fn streams() {
+        let sys = MemorySys::new()
+            .with_file("a/lines.txt", "one\ntwo\nthree")
+            .with_dir("b");
+        let mut env = Uiua::with_backend(sys);
+        env.run_str(
+            r#"
+&fo "a/lines.txt"
+&p ⊸(&ru "\n")
+&p ⊸(&rs 3)
+&p ⊸(&rs ∞)
+&cl
+&cd "b"
+&cl ⊸(&w "hi") &fc "../a/new.txt"
+&p ≍ {"/a/lines.txt" "/a/new.txt"} &fld "/a"
+"#,
+        )
+        .unwrap();
+        let sys = env.downcast_backend::<MemorySys>().unwrap();
+        assert_eq!(sys.take_stdout(), b"one\n\ntwo\n\nthree\n1\n");
+        assert_eq!(sys.file("/a/new.txt").unwrap(), b"hi");
+        assert!(sys.is_dir("/b"));
+        assert_eq!(sys.files().len(), 2);
+    }
+}
@@ -8,14 +8,14 @@ use std::{
     process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, Stdio},
     slice,
     sync::{
-        atomic::{self, AtomicBool, AtomicU64},
+        atomic::{self, AtomicBool},
         Arc,
     },
     thread::sleep,
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use crate::{terminal_size, GitTarget, Handle, SysBackend};
+use crate::{terminal_size, FileKind, FileMetadata, GitTarget, Handle, SysBackend};
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 
@@ -25,7 +25,6 @@ pub struct NativeSys;
 
 struct GlobalNativeSys {
     output_enabled: AtomicBool,
-    next_handle: AtomicU64,
     files: DashMap<Handle, BufReader<File>>,
     child_stdins: DashMap<Handle, ChildStream<ChildStdin>>,
     child_stdouts: DashMap<Handle, ChildStream<ChildStdout>>,
@@ -34,6 +33,7 @@ struct GlobalNativeSys {
     tls_listeners: DashMap<Handle, TlsListener>,
     tcp_sockets: DashMap<Handle, TcpStream>,
     tls_sockets: DashMap<Handle, TlsSocket>,
+    udp_sockets: DashMap<Handle, UdpSocket>,
     #[cfg(feature = "webcam")]
     cam_channels: DashMap<usize, WebcamChannel>,
     hostnames: DashMap<Handle, String>,
@@ -56,6 +56,7 @@ enum SysStream<'a> {
     ChildStderr(dashmap::mapref::one::RefMut<'a, Handle, ChildStream<ChildStderr>>),
     TcpSocket(dashmap::mapref::one::Ref<'a, Handle, TcpStream>),
     TlsSocket(dashmap::mapref::one::Ref<'a, Handle, TlsSocket>),
+    UdpSocket(dashmap::mapref::one::Ref<'a, Handle, UdpSocket>),
 }
 
 struct ChildStream<T> {
@@ -200,7 +201,6 @@ impl Default for GlobalNativeSys {
     fn default() -> Self {
         Self {
             output_enabled: AtomicBool::new(true),
-            next_handle: Handle::FIRST_UNRESERVED.0.into(),
             files: DashMap::new(),
             child_stdins: DashMap::new(),
             child_stdouts: DashMap::new(),
@@ -209,6 +209,7 @@ impl Default for GlobalNativeSys {
             tls_listeners: DashMap::new(),
             tcp_sockets: DashMap::new(),
             tls_sockets: DashMap::new(),
+            udp_sockets: DashMap::new(),
             #[cfg(feature = "webcam")]
             cam_channels: DashMap::new(),
             hostnames: DashMap::new(),
@@ -227,22 +228,6 @@ impl Default for GlobalNativeSys {
 }
 
 impl GlobalNativeSys {
-    fn new_handle(&self) -> Handle {
-        for _ in 0..u64::MAX {
-            let handle = Handle(self.next_handle.fetch_add(1, atomic::Ordering::Relaxed));
-            if !self.files.contains_key(&handle)
-                && !self.child_stdins.contains_key(&handle)
-                && !self.child_stdouts.contains_key(&handle)
-                && !self.child_stderrs.contains_key(&handle)
-                && !self.tcp_listeners.contains_key(&handle)
-                && !self.tcp_sockets.contains_key(&handle)
-                && !self.tls_sockets.contains_key(&handle)
-            {
-                return handle;
-            }
-        }
-        panic!("Ran out of file handles");
-    }
     fn get_stream(&self, handle: Handle) -> Result<SysStream, String> {
         Ok(if let Some(file) = self.files.get_mut(&handle) {
             SysStream::File(file)
@@ -256,6 +241,8 @@ impl GlobalNativeSys {
             SysStream::TcpSocket(socket)
         } else if let Some(tls_socket) = self.tls_sockets.get(&handle) {
             SysStream::TlsSocket(tls_socket)
+        } else if let Some(socket) = self.udp_sockets.get(&handle) {
+            SysStream::UdpSocket(socket)
         } else {
             return Err("Invalid file handle".to_string());
         })
@@ -274,6 +261,9 @@ impl GlobalNativeSys {
             (self.tls_sockets.get(&handle)).map(|sock| f(&sock.stream))
         }
     }
+    fn get_udp_socket<T>(&self, handle: Handle, f: impl FnOnce(&UdpSocket) -> T) -> Option<T> {
+        (self.udp_sockets.get(&handle)).map(|sock| f(&sock))
+    }
 }
 
 static NATIVE_SYS: Lazy<GlobalNativeSys> = Lazy::new(Default::default);
@@ -402,6 +392,42 @@ impl SysBackend for NativeSys {
             .map(|m| m.is_file())
             .map_err(|e| e.to_string())
     }
+    fn file_metadata(&self, path: &str) -> Result<FileMetadata, String> {
+        let meta = fs::symlink_metadata(path).map_err(|e| e.to_string())?;
+        let file_type = meta.file_type();
+        let kind = if file_type.is_symlink() {
+            FileKind::Symlink
+        } else if file_type.is_dir() {
+            FileKind::Dir
+        } else {
+            FileKind::File
+        };
+        let secs = |time: std::io::Result<SystemTime>| {
+            Some(match time.ok()?.duration_since(UNIX_EPOCH) {
+                Ok(dur) => dur.as_secs_f64(),
+                Err(e) => -e.duration().as_secs_f64(),
+            })
+        };
+        #[cfg(unix)]
+        let permissions = {
+            use std::os::unix::fs::PermissionsExt;
+            meta.permissions().mode() & 0o7777
+        };
+        #[cfg(not(unix))]
+        let permissions = if meta.permissions().readonly() {
+            0o444
+        } else {
+            0o666
+        };
+        Ok(FileMetadata {
+            size: meta.len(),
+            kind,
+            created: secs(meta.created()),
+            modified: secs(meta.modified()),
+            accessed: secs(meta.accessed()),
+            permissions,
+        })
+    }
     fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
         let mut paths = Vec::new();
         for entry in fs::read_dir(path).map_err(|e| e.to_string())? {
@@ -411,7 +437,7 @@ impl SysBackend for NativeSys {
         Ok(paths)
     }
     fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
-        let handle = NATIVE_SYS.new_handle();
+        let handle = Handle::unique();
         let file = OpenOptions::new()
             .read(true)
             .write(write)
@@ -427,7 +453,7 @@ impl SysBackend for NativeSys {
         Ok(bytes)
     }
     fn create_file(&self, path: &Path) -> Result<Handle, String> {
-        let handle = NATIVE_SYS.new_handle();
+        let handle = Handle::unique();
         let file = File::create(path).map_err(|e| e.to_string())?;
         NATIVE_SYS.files.insert(handle, BufReader::new(file));
         Ok(handle)
@@ -483,6 +509,12 @@ impl SysBackend for NativeSys {
                 buf.truncate(n);
                 buf
             }
+            SysStream::UdpSocket(socket) => {
+                let mut buf = vec![0; len];
+                let n = socket.recv(&mut buf).map_err(|e| e.to_string())?;
+                buf.truncate(n);
+                buf
+            }
         })
     }
     fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
@@ -519,6 +551,13 @@ impl SysBackend for NativeSys {
                 ((&mut &*socket).read_to_end(&mut buf)).map_err(|e| e.to_string())?;
                 buf
             }
+            SysStream::UdpSocket(socket) => {
+                // A UDP socket has no end, so read a single datagram
+                let mut buf = vec![0; u16::MAX as usize];
+                let n = socket.recv(&mut buf).map_err(|e| e.to_string())?;
+                buf.truncate(n);
+                buf
+            }
         })
     }
     fn write(&self, handle: Handle, conts: &[u8]) -> Result<(), String> {
@@ -546,6 +585,7 @@ impl SysBackend for NativeSys {
             SysStream::TlsSocket(socket) => {
                 (&mut &*socket).write_all(conts).map_err(|e| e.to_string())
             }
+            SysStream::UdpSocket(socket) => socket.send(conts).map(drop).map_err(|e| e.to_string()),
         }
     }
     #[cfg(feature = "clipboard")]
@@ -715,14 +755,14 @@ impl SysBackend for NativeSys {
         }
     }
     fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
-        let handle = NATIVE_SYS.new_handle();
+        let handle = Handle::unique();
         let listener = TcpListener::bind(addr).map_err(|e| e.to_string())?;
         NATIVE_SYS.tcp_listeners.insert(handle, listener);
         Ok(handle)
     }
     #[cfg(feature = "tls")]
     fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
-        let handle = NATIVE_SYS.new_handle();
+        let handle = Handle::unique();
         let listener = TcpListener::bind(addr).map_err(|e| e.to_string())?;
         let certs = rustls_pemfile::certs(&mut BufReader::new(cert))
             .collect::<Result<Vec<_>, _>>()
@@ -743,7 +783,7 @@ impl SysBackend for NativeSys {
         if let Some(listener) = NATIVE_SYS.tcp_listeners.get_mut(&handle) {
             let (stream, _) = listener.accept().map_err(|e| e.to_string())?;
             drop(listener);
-            let handle = NATIVE_SYS.new_handle();
+            let handle = Handle::unique();
             NATIVE_SYS.tcp_sockets.insert(handle, stream);
             Ok(handle)
         } else {
@@ -753,7 +793,7 @@ impl SysBackend for NativeSys {
                     let (stream, _) = listener.listener.accept().map_err(|e| e.to_string())?;
                     let conn = rustls::ServerConnection::new(listener.config.clone())
                         .map_err(|e| e.to_string())?;
-                    let handle = NATIVE_SYS.new_handle();
+                    let handle = Handle::unique();
                     NATIVE_SYS.tls_sockets.insert(
                         handle,
                         TlsSocket {
@@ -770,7 +810,7 @@ impl SysBackend for NativeSys {
         }
     }
     fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
-        let handle = NATIVE_SYS.new_handle();
+        let handle = Handle::unique();
         let stream = TcpStream::connect(addr).map_err(|e| e.to_string())?;
         NATIVE_SYS.tcp_sockets.insert(handle, stream);
         NATIVE_SYS.hostnames.insert(
@@ -781,7 +821,7 @@ impl SysBackend for NativeSys {
     }
     #[cfg(feature = "tls")]
     fn tls_connect(&self, addr: &str) -> Result<Handle, String> {
-        let handle = NATIVE_SYS.new_handle();
+        let handle = Handle::unique();
         let root_store =
             rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
         let config = rustls::ClientConfig::builder()
@@ -818,7 +858,8 @@ impl SysBackend for NativeSys {
     fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
         NATIVE_SYS
             .get_tcp_stream(handle, |s| s.set_nonblocking(non_blocking))
-            .ok_or_else(|| "Invalid tcp socket handle".to_string())?
+            .or_else(|| NATIVE_SYS.get_udp_socket(handle, |s| s.set_nonblocking(non_blocking)))
+            .ok_or_else(|| "Invalid tcp or udp socket handle".to_string())?
             .map_err(|e| e.to_string())
     }
     fn tcp_set_read_timeout(
@@ -828,7 +869,8 @@ impl SysBackend for NativeSys {
     ) -> Result<(), String> {
         NATIVE_SYS
             .get_tcp_stream(handle, |s| s.set_read_timeout(timeout))
-            .ok_or_else(|| "Invalid tcp socket handle".to_string())?
+            .or_else(|| NATIVE_SYS.get_udp_socket(handle, |s| s.set_read_timeout(timeout)))
+            .ok_or_else(|| "Invalid tcp or udp socket handle".to_string())?
             .map_err(|e| e.to_string())
     }
     fn tcp_set_write_timeout(
@@ -838,7 +880,50 @@ impl SysBackend for NativeSys {
     ) -> Result<(), String> {
         NATIVE_SYS
             .get_tcp_stream(handle, |s| s.set_write_timeout(timeout))
-            .ok_or_else(|| "Invalid tcp socket handle".to_string())?
+            .or_else(|| NATIVE_SYS.get_udp_socket(handle, |s| s.set_write_timeout(timeout)))
+            .ok_or_else(|| "Invalid tcp or udp socket handle".to_string())?
+            .map_err(|e| e.to_string())
+    }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        let socket = UdpSocket::bind(addr).map_err(|e| e.to_string())?;
+        let handle = Handle::unique();
+        NATIVE_SYS.udp_sockets.insert(handle, socket);
+        Ok(handle)
+    }
+    fn udp_connect(&self, addr: &str) -> Result<Handle, String> {
+        let remote = (addr.to_socket_addrs().map_err(|e| e.to_string())?.next())
+            .ok_or_else(|| format!("Unable to resolve address {addr}"))?;
+        let local: SocketAddr = if remote.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(local).map_err(|e| e.to_string())?;
+        socket.connect(remote).map_err(|e| e.to_string())?;
+        let handle = Handle::unique();
+        NATIVE_SYS.udp_sockets.insert(handle, socket);
+        Ok(handle)
+    }
+    fn udp_send(&self, handle: Handle, data: &[u8], addr: &str) -> Result<(), String> {
+        NATIVE_SYS
+            .get_udp_socket(handle, |s| s.send_to(data, addr))
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?
+            .map(drop)
+            .map_err(|e| e.to_string())
+    }
+    fn udp_receive(&self, handle: Handle, max_len: usize) -> Result<(Vec<u8>, SocketAddr), String> {
+        let mut buf = vec![0; max_len];
+        let (n, addr) = NATIVE_SYS
+            .get_udp_socket(handle, |s| s.recv_from(&mut buf))
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?
+            .map_err(|e| e.to_string())?;
+        buf.truncate(n);
+        Ok((buf, addr))
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        NATIVE_SYS
+            .get_udp_socket(handle, |s| s.peer_addr().or_else(|_| s.local_addr()))
+            .ok_or_else(|| "Invalid udp socket handle".to_string())?
             .map_err(|e| e.to_string())
     }
     fn close(&self, handle: Handle) -> Result<(), String> {
@@ -860,6 +945,8 @@ impl SysBackend for NativeSys {
         {
             NATIVE_SYS.hostnames.remove(&handle);
             Ok(())
+        } else if NATIVE_SYS.udp_sockets.remove(&handle).is_some() {
+            Ok(())
         } else {
             Err("Invalid stream handle".to_string())
         }
@@ -904,7 +991,7 @@ impl SysBackend for NativeSys {
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
         let child = Arc::new(child);
-        let stdin_handle = NATIVE_SYS.new_handle();
+        let stdin_handle = Handle::unique();
         NATIVE_SYS.child_stdins.insert(
             stdin_handle,
             ChildStream {
@@ -912,7 +999,7 @@ impl SysBackend for NativeSys {
                 child: child.clone(),
             },
         );
-        let stdout_handle = NATIVE_SYS.new_handle();
+        let stdout_handle = Handle::unique();
         NATIVE_SYS.child_stdouts.insert(
             stdout_handle,
             ChildStream {
@@ -920,7 +1007,7 @@ impl SysBackend for NativeSys {
                 child: child.clone(),
             },
         );
-        let stderr_handle = NATIVE_SYS.new_handle();
+        let stderr_handle = Handle::unique();
         NATIVE_SYS.child_stderrs.insert(
             stderr_handle,
             ChildStream {
@@ -981,6 +1068,10 @@ impl SysBackend for NativeSys {
 
         Ok(s)
     }
+    #[cfg(feature = "tls")]
+    fn http_request(&self, request: &crate::HttpRequest) -> Result<crate::HttpResponse, String> {
+        crate::http::request(request, |_, _| Ok(()))
+    }
     #[cfg(feature = "webcam")]
     fn webcam_capture(&self, index: usize) -> Result<crate::WebcamImage, String> {
         let cam_channels = &NATIVE_SYS.cam_channels;
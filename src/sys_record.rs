@@ -0,0 +1,658 @@
+//! System backends that record and replay system calls
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    io::Write,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value as Json;
+
+use crate::{
+    AudioStreamFn, FfiType, FileMetadata, GitTarget, Handle, HttpRequest, HttpResponse,
+    IntoSysBackend, SysBackend, Value, WebcamImage,
+};
+
+/// A single recorded call
+///
+/// A log is made of these, one JSON object per line.
+#[derive(Serialize, Deserialize)]
+struct SysCall {
+    /// The path of the spawned thread that made the call, empty for the main thread
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    thread: Vec<usize>,
+    call: String,
+    args: Json,
+    result: Json,
+}
+
+/// Calls that return handles made by the system
+const HANDLE_CALLS: &[&str] = &[
+    "create_file",
+    "open_file",
+    "tcp_listen",
+    "tls_listen",
+    "tcp_accept",
+    "tcp_connect",
+    "tls_connect",
+    "udp_bind",
+    "udp_connect",
+    "run_command_stream",
+];
+
+thread_local! {
+    /// The path of the spawned thread running on this OS thread
+    static THREAD: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Run a spawned thread, tagging the calls it makes with its path
+///
+/// Paths are made of the ids that each thread gives its children in spawn order,
+/// so they are the same every time a program runs, no matter how its threads are scheduled.
+pub(crate) fn in_thread<T>(path: Vec<usize>, f: impl FnOnce() -> T) -> T {
+    // Pool threads may run another thread's code while this one is waiting
+    let prev = THREAD.with(|thread| thread.replace(path));
+    let res = f();
+    THREAD.with(|thread| *thread.borrow_mut() = prev);
+    res
+}
+
+fn current_thread() -> Vec<usize> {
+    THREAD.with(|thread| thread.borrow().clone())
+}
+
+fn thread_name(thread: &[usize]) -> String {
+    if thread.is_empty() {
+        return "the main thread".into();
+    }
+    let path: Vec<_> = thread.iter().map(|id| id.to_string()).collect();
+    format!("thread {}", path.join("."))
+}
+
+fn to_json(value: impl Serialize) -> Json {
+    // Only paths that are not valid UTF-8 fail to serialize
+    serde_json::to_value(value).unwrap_or(Json::Null)
+}
+
+/// Calls that get their results from the system
+///
+/// When replaying, these are served entirely from the log.
+macro_rules! input_calls {
+    ($mac:ident) => {
+        $mac! {
+            fn scan_line_stdin(&self) -> Result<Option<String>, String>;
+            fn scan_stdin(&self, count: usize) -> Result<Vec<u8>, String>;
+            fn scan_until_stdin(&self, delim: &[u8]) -> Result<Vec<u8>, String>;
+            fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String>;
+            fn get_raw_mode(&self) -> Result<bool, String>;
+            fn var(&self, name: &str) -> Option<String>;
+            fn term_size(&self) -> Result<(usize, usize), String>;
+            fn file_exists(&self, path: &str) -> bool;
+            fn list_dir(&self, path: &str) -> Result<Vec<String>, String>;
+            fn is_file(&self, path: &str) -> Result<bool, String>;
+            fn file_metadata(&self, path: &str) -> Result<FileMetadata, String>;
+            fn delete(&self, path: &str) -> Result<(), String>;
+            fn trash(&self, path: &str) -> Result<(), String>;
+            fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String>;
+            fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String>;
+            fn read_until(&self, handle: Handle, delim: &[u8]) -> Result<Vec<u8>, String>;
+            fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String>;
+            fn create_file(&self, path: &Path) -> Result<Handle, String>;
+            fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String>;
+            fn make_dir(&self, path: &Path) -> Result<(), String>;
+            fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String>;
+            fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String>;
+            fn clipboard(&self) -> Result<String, String>;
+            fn set_clipboard(&self, contents: &str) -> Result<(), String>;
+            fn sleep(&self, seconds: f64) -> Result<(), String>;
+            fn audio_sample_rate(&self) -> u32;
+            fn now(&self) -> f64;
+            fn tcp_listen(&self, addr: &str) -> Result<Handle, String>;
+            fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String>;
+            fn tcp_accept(&self, handle: Handle) -> Result<Handle, String>;
+            fn tcp_connect(&self, addr: &str) -> Result<Handle, String>;
+            fn tls_connect(&self, addr: &str) -> Result<Handle, String>;
+            fn tcp_addr(&self, handle: Handle) -> Result<SocketAddr, String>;
+            fn tcp_set_non_blocking(
+                &self, handle: Handle, non_blocking: bool
+            ) -> Result<(), String>;
+            fn tcp_set_read_timeout(
+                &self, handle: Handle, timeout: Option<Duration>
+            ) -> Result<(), String>;
+            fn tcp_set_write_timeout(
+                &self, handle: Handle, timeout: Option<Duration>
+            ) -> Result<(), String>;
+            fn udp_bind(&self, addr: &str) -> Result<Handle, String>;
+            fn udp_connect(&self, addr: &str) -> Result<Handle, String>;
+            fn udp_send(&self, handle: Handle, data: &[u8], addr: &str) -> Result<(), String>;
+            fn udp_receive(
+                &self, handle: Handle, max_len: usize
+            ) -> Result<(Vec<u8>, SocketAddr), String>;
+            fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String>;
+            fn close(&self, handle: Handle) -> Result<(), String>;
+            fn invoke(&self, path: &str) -> Result<(), String>;
+            fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String>;
+            fn run_command_capture(
+                &self, command: &str, args: &[&str]
+            ) -> Result<(i32, String, String), String>;
+            fn run_command_stream(
+                &self, command: &str, args: &[&str]
+            ) -> Result<[Handle; 3], String>;
+            fn change_directory(&self, path: &str) -> Result<(), String>;
+            fn https_get(&self, request: &str, handle: Handle) -> Result<String, String>;
+            fn http_request(&self, request: &HttpRequest) -> Result<HttpResponse, String>;
+            fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String>;
+            fn timezone(&self) -> Result<f64, String>;
+        }
+    };
+}
+
+/// Calls that show something to the user
+///
+/// When replaying, these are checked against the log, then passed on to the inner backend.
+macro_rules! output_calls {
+    ($mac:ident) => {
+        $mac! {
+            fn print_str_stdout(&self, s: &str) -> Result<(), String>;
+            fn print_str_stderr(&self, s: &str) -> Result<(), String>;
+            fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String>;
+            fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String>;
+        }
+    };
+}
+
+/// Calls that cannot be recorded and are always passed on to the inner backend
+macro_rules! unrecorded_calls {
+    () => {
+        fn any(&self) -> &dyn Any {
+            self
+        }
+        fn any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+        fn save_error_color(&self, message: String, colored: String) {
+            self.inner.save_error_color(message, colored)
+        }
+        fn print_str_trace(&self, s: &str) {
+            self.inner.print_str_trace(s)
+        }
+        #[cfg(feature = "image")]
+        fn show_image(
+            &self,
+            image: image::DynamicImage,
+            label: Option<&str>,
+        ) -> Result<(), String> {
+            self.inner.show_image(image, label)
+        }
+        fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String> {
+            self.inner.stream_audio(f)
+        }
+        fn webcam_capture(&self, index: usize) -> Result<WebcamImage, String> {
+            self.inner.webcam_capture(index)
+        }
+        fn ffi(
+            &self,
+            file: &str,
+            result_ty: FfiType,
+            name: &str,
+            arg_tys: &[FfiType],
+            args: &[Value],
+        ) -> Result<Value, String> {
+            self.inner.ffi(file, result_ty, name, arg_tys, args)
+        }
+        fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+            self.inner.mem_copy(ty, ptr, len)
+        }
+        fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+            self.inner.mem_free(ptr)
+        }
+    };
+}
+
+/// A system backend that records every call to another backend and its result
+///
+/// The log can be replayed with [`ReplaySys`] to run a program deterministically.
+/// It is written as calls are made, one JSON object per line,
+/// and contains everything the program reads and writes.
+///
+/// The random number generator is seeded with a recorded seed.
+/// Images, audio streams, the webcam, and FFI calls are not recorded.
+/// Calls made by spawned threads are tagged with the thread that made them,
+/// so each thread's calls replay in order no matter how the threads are scheduled.
+pub struct RecordSys {
+    inner: Arc<dyn SysBackend>,
+    log: Mutex<Box<dyn Write + Send>>,
+    error: Mutex<Option<String>>,
+}
+
+impl RecordSys {
+    /// Record calls to a backend, writing the log to a writer
+    pub fn new(inner: impl IntoSysBackend, log: impl Write + Send + 'static) -> Self {
+        RecordSys {
+            inner: inner.into_sys_backend(),
+            log: Mutex::new(Box::new(log)),
+            error: Mutex::new(None),
+        }
+    }
+    /// Record calls to a backend, writing the log to a file
+    pub fn create(inner: impl IntoSysBackend, path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = fs::File::create(path)
+            .map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        Ok(Self::new(inner, file))
+    }
+    /// Get the backend being recorded
+    pub fn inner(&self) -> &dyn SysBackend {
+        &*self.inner
+    }
+    /// Flush the log and check that it was written successfully
+    pub fn finish(&self) -> Result<(), String> {
+        if let Err(e) = self.log.lock().flush() {
+            self.fail(e);
+        }
+        match self.error.lock().clone() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+    fn record(&self, call: &str, args: Json, result: &impl Serialize) {
+        let call = SysCall {
+            thread: current_thread(),
+            call: call.into(),
+            args,
+            result: to_json(result),
+        };
+        let mut line = serde_json::to_vec(&call).unwrap();
+        line.push(b'\n');
+        // The whole line is written at once so that a program that exits still has a full log
+        if let Err(e) = self.log.lock().write_all(&line) {
+            self.fail(e);
+        }
+    }
+    fn fail(&self, e: std::io::Error) {
+        let mut error = self.error.lock();
+        if error.is_none() {
+            *error = Some(format!("Failed to write recording: {e}"));
+        }
+    }
+}
+
+macro_rules! record {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(fn $name(&self $(, $arg: $ty)*) -> $ret {
+            let args = to_json(($(&$arg,)*));
+            let result = self.inner.$name($($arg),*);
+            self.record(stringify!($name), args, &result);
+            result
+        })*
+    };
+}
+
+impl SysBackend for RecordSys {
+    unrecorded_calls!();
+    input_calls!(record);
+    output_calls!(record);
+    fn exit(&self, status: i32) -> Result<(), String> {
+        // Exiting usually does not return, so this is recorded first
+        self.record("exit", to_json((&status,)), &Ok::<_, String>(()));
+        self.inner.exit(status)
+    }
+    fn random_seed(&self) -> Option<u64> {
+        let seed = self.inner.random_seed().unwrap_or_else(rand::random);
+        self.record("random_seed", Json::Null, &seed);
+        Some(seed)
+    }
+}
+
+/// A system backend that replays calls recorded by a [`RecordSys`]
+///
+/// Each call must match the next one its thread made in the log, both in name and arguments.
+/// Its recorded result is returned without touching the system.
+/// Output is checked against the log, then shown by an inner backend.
+///
+/// Once the program diverges from the log, every call that can fail does so.
+/// [`ReplaySys::finish`] reports the divergence along with any calls that were never made.
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+/// use uiua::*;
+///
+/// #[derive(Clone, Default)]
+/// struct Log(Arc<Mutex<Vec<u8>>>);
+/// impl std::io::Write for Log {
+///     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+///         self.0.lock().unwrap().extend_from_slice(buf);
+///         Ok(buf.len())
+///     }
+///     fn flush(&mut self) -> std::io::Result<()> {
+///         Ok(())
+///     }
+/// }
+///
+/// let log = Log::default();
+/// let sys = MemorySys::new().with_file("in.txt", "Hello");
+/// let mut uiua = Uiua::with_backend(RecordSys::new(sys, log.clone()));
+/// uiua.run_str(r#"&p &fras "in.txt""#).unwrap();
+///
+/// // The file is not needed to replay
+/// let log = String::from_utf8(log.0.lock().unwrap().clone()).unwrap();
+/// let mut uiua = Uiua::with_backend(ReplaySys::new(MemorySys::new(), &log).unwrap());
+/// uiua.run_str(r#"&p &fras "in.txt""#).unwrap();
+/// let replay = uiua.downcast_backend::<ReplaySys>().unwrap();
+/// assert!(replay.finish().is_ok());
+/// ```
+pub struct ReplaySys {
+    inner: Arc<dyn SysBackend>,
+    /// The recorded calls of each thread
+    calls: BTreeMap<Vec<usize>, Vec<SysCall>>,
+    state: Mutex<ReplayState>,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    /// The index of the next call of each thread
+    next: BTreeMap<Vec<usize>, usize>,
+    diverged: Option<String>,
+}
+
+impl ReplaySys {
+    /// Replay a log, showing output with a backend
+    ///
+    /// Handles in the log are reserved so that channels and modules
+    /// made during the replay do not collide with them.
+    pub fn new(inner: impl IntoSysBackend, log: &str) -> Result<Self, String> {
+        let mut calls = BTreeMap::new();
+        for (i, line) in log.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let call: SysCall = serde_json::from_str(line)
+                .map_err(|e| format!("Invalid recording on line {}: {e}", i + 1))?;
+            if HANDLE_CALLS.contains(&call.call.as_str()) {
+                let handles = match call.result.get("Ok") {
+                    Some(Json::Array(handles)) => handles.iter().collect(),
+                    Some(handle) => vec![handle],
+                    None => Vec::new(),
+                };
+                for handle in handles.into_iter().filter_map(Json::as_u64) {
+                    Handle(handle).reserve();
+                }
+            }
+            (calls.entry(call.thread.clone()))
+                .or_insert_with(Vec::new)
+                .push(call);
+        }
+        Ok(ReplaySys {
+            inner: inner.into_sys_backend(),
+            calls,
+            state: Mutex::new(ReplayState::default()),
+        })
+    }
+    /// Replay a log file, showing output with a backend
+    pub fn open(inner: impl IntoSysBackend, path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let log = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        Self::new(inner, &log)
+    }
+    /// Get the backend that shows output
+    pub fn inner(&self) -> &dyn SysBackend {
+        &*self.inner
+    }
+    /// Check that the program made exactly the recorded calls
+    pub fn finish(&self) -> Result<(), String> {
+        let state = self.state.lock();
+        if let Some(diverged) = &state.diverged {
+            return Err(diverged.clone());
+        }
+        for (thread, calls) in &self.calls {
+            let next = state.next.get(thread).copied().unwrap_or(0);
+            if let Some(call) = calls.get(next) {
+                return Err(format!(
+                    "Replay diverged: {} recorded calls of {} were not made, starting with {}",
+                    calls.len() - next,
+                    thread_name(thread),
+                    call.call
+                ));
+            }
+        }
+        Ok(())
+    }
+    fn replay<T: Replayed>(&self, name: &str, args: Json) -> T {
+        let mut state = self.state.lock();
+        if let Some(diverged) = &state.diverged {
+            return T::diverged(diverged.clone());
+        }
+        let thread = current_thread();
+        let next = state.next.get(&thread).copied().unwrap_or(0);
+        let recorded = self.calls.get(&thread).and_then(|calls| calls.get(next));
+        let message = match recorded {
+            Some(call) if call.call == name && call.args == args => {
+                match serde_json::from_value(call.result.clone()) {
+                    Ok(result) => {
+                        state.next.insert(thread, next + 1);
+                        return result;
+                    }
+                    Err(e) => format!("Invalid recorded result for {name}: {e}"),
+                }
+            }
+            Some(call) if call.call == name => format!(
+                "Replay diverged at call {} of {}: {name} was called with {args} \
+                instead of {}",
+                next + 1,
+                thread_name(&thread),
+                call.args
+            ),
+            Some(call) => format!(
+                "Replay diverged at call {} of {}: {name} was called instead of {}",
+                next + 1,
+                thread_name(&thread),
+                call.call
+            ),
+            None => format!(
+                "Replay diverged: {name} was called by {} after its recording ended",
+                thread_name(&thread)
+            ),
+        };
+        state.diverged = Some(message.clone());
+        T::diverged(message)
+    }
+}
+
+/// A result that can be replayed
+trait Replayed: DeserializeOwned {
+    /// The value returned once the replay has diverged
+    ///
+    /// Calls that cannot fail return a default, but the divergence
+    /// is still reported by [`ReplaySys::finish`].
+    fn diverged(message: String) -> Self;
+}
+
+impl<T: DeserializeOwned> Replayed for Result<T, String> {
+    fn diverged(message: String) -> Self {
+        Err(message)
+    }
+}
+
+impl<T: DeserializeOwned> Replayed for Option<T> {
+    fn diverged(_: String) -> Self {
+        None
+    }
+}
+
+impl Replayed for bool {
+    fn diverged(_: String) -> Self {
+        false
+    }
+}
+
+impl Replayed for u32 {
+    fn diverged(_: String) -> Self {
+        0
+    }
+}
+
+impl Replayed for f64 {
+    fn diverged(_: String) -> Self {
+        0.0
+    }
+}
+
+macro_rules! replay {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(fn $name(&self $(, $arg: $ty)*) -> $ret {
+            self.replay(stringify!($name), to_json(($(&$arg,)*)))
+        })*
+    };
+}
+
+macro_rules! replay_output {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(fn $name(&self $(, $arg: $ty)*) -> $ret {
+            self.replay::<Result<(), String>>(stringify!($name), to_json(($(&$arg,)*)))?;
+            self.inner.$name($($arg),*)
+        })*
+    };
+}
+
+impl SysBackend for ReplaySys {
+    unrecorded_calls!();
+    input_calls!(replay);
+    output_calls!(replay_output);
+    fn exit(&self, status: i32) -> Result<(), String> {
+        self.replay::<Result<(), String>>("exit", to_json((&status,)))?;
+        self.inner.exit(status)
+    }
+    fn random_seed(&self) -> Option<u64> {
+        self.replay("random_seed", Json::Null)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MemorySys, Uiua};
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Log(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Log {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn stdout(sys: &dyn SysBackend) -> Vec<u8> {
+        let sys: &MemorySys = sys.any().downcast_ref().unwrap();
+        sys.take_stdout()
+    }
+
+    #[test]
+    fn record_replay() {
+        let code = r#"
+&p &fras "in.txt"
+&fwa "out.txt" "written"
+&p ⁅×100 ⚂
+&p ≥0 now
+"#;
+        let log = Log::default();
+        let sys = MemorySys::new().with_file("in.txt", "Hello");
+        let mut env = Uiua::with_backend(RecordSys::new(sys, log.clone()));
+        env.run_str(code).unwrap();
+        let record = env.downcast_backend::<RecordSys>().unwrap();
+        record.finish().unwrap();
+        let recorded = stdout(record.inner());
+        let log = String::from_utf8(log.0.lock().clone()).unwrap();
+
+        // Replaying gives the same output without touching the filesystem
+        let mut env = Uiua::with_backend(ReplaySys::new(MemorySys::new(), &log).unwrap());
+        env.run_str(code).unwrap();
+        let replay = env.downcast_backend::<ReplaySys>().unwrap();
+        replay.finish().unwrap();
+        assert_eq!(stdout(replay.inner()), recorded);
+        let sys: &MemorySys = replay.inner().any().downcast_ref().unwrap();
+        assert!(sys.file("out.txt").is_none());
+
+        // Changing a call diverges
+        let mut env = Uiua::with_backend(ReplaySys::new(MemorySys::new(), &log).unwrap());
+        let Err(err) = env.run_str(r#"&fras "other.txt""#) else {
+            panic!("Replay did not diverge");
+        };
+        assert!(err.to_string().contains("Replay diverged"), "{err}");
+
+        // Stopping early diverges
+        let mut env = Uiua::with_backend(ReplaySys::new(MemorySys::new(), &log).unwrap());
+        env.run_str(r#"&p &fras "in.txt""#).unwrap();
+        let replay = env.downcast_backend::<ReplaySys>().unwrap();
+        assert!(replay.finish().is_err());
+    }
+
+    #[test]
+    fn reserve_handles() {
+        let handle = 1u64 << 40;
+        let log = serde_json::json!({
+            "call": "open_file",
+            "args": ["a.txt", false],
+            "result": {"Ok": handle},
+        });
+        ReplaySys::new(MemorySys::new(), &log.to_string()).unwrap();
+        assert!(Handle::unique().0 > handle);
+    }
+
+    #[test]
+    fn threads() {
+        let code = r#"
+A ← spawn(&fras) "a.txt"
+B ← pool(&fras) "b.txt"
+C ← spawn(wait spawn(&fras) "c.txt")
+&p &fras "a.txt"
+&p wait C
+&p wait B
+&p wait A
+&p wait spawn(⚂)
+"#;
+        let log = Log::default();
+        let sys = (MemorySys::new().with_file("a.txt", "a"))
+            .with_file("b.txt", "b")
+            .with_file("c.txt", "c");
+        let mut env = Uiua::with_backend(RecordSys::new(sys, log.clone()));
+        env.run_str(code).unwrap();
+        let record = env.downcast_backend::<RecordSys>().unwrap();
+        record.finish().unwrap();
+        let recorded = stdout(record.inner());
+        let log = String::from_utf8(log.0.lock().clone()).unwrap();
+
+        // Threads may have run in any order, so the replay should not depend on it
+        let (main, spawned): (Vec<_>, Vec<_>) =
+            log.lines().partition(|line| !line.contains("\"thread\""));
+        assert_eq!(spawned.len(), 3, "{log}");
+        let reordered: Vec<_> = spawned.into_iter().rev().chain(main).collect();
+        let mut env =
+            Uiua::with_backend(ReplaySys::new(MemorySys::new(), &reordered.join("\n")).unwrap());
+        env.run_str(code).unwrap();
+        let replay = env.downcast_backend::<ReplaySys>().unwrap();
+        replay.finish().unwrap();
+        assert_eq!(stdout(replay.inner()), recorded);
+
+        // A thread's calls are not replayed for another thread
+        let swapped = code.replace("spawn(&fras) \"a.txt\"", "spawn(&fras) \"c.txt\"");
+        let mut env = Uiua::with_backend(ReplaySys::new(MemorySys::new(), &log).unwrap());
+        let Err(err) = env.run_str(&swapped) else {
+            panic!("Replay did not diverge");
+        };
+        assert!(err.to_string().contains("of thread 1"), "{err}");
+    }
+}
@@ -0,0 +1,541 @@
+//! A system backend that only allows what a policy permits
+
+use std::{
+    any::Any,
+    collections::HashSet,
+    env,
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    AudioStreamFn, FfiType, FileMetadata, GitTarget, Handle, NativeSys, SysBackend, Value,
+};
+
+/// What a [`RestrictedSys`] allows a program to access
+///
+/// By default, nothing but standard IO and time is allowed.
+///
+/// ```
+/// use uiua::*;
+///
+/// let policy = SysPolicy::default()
+///     .allow_read("assets")
+///     .allow_write("out")
+///     .allow_host("example.com:443")
+///     .allow_command("git")
+///     .allow_var("LANG");
+/// let mut uiua = Uiua::with_backend(RestrictedSys::new(policy));
+/// // The file exists, but it is outside of the allowed directories
+/// let Err(err) = uiua.run_str("&fras \"Cargo.toml\"") else {
+///     panic!("The file was read");
+/// };
+/// assert!(err.to_string().contains("Permission denied by policy"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SysPolicy {
+    /// Directories and whether they can be written to
+    dirs: Vec<(PathBuf, bool)>,
+    /// Hosts and, optionally, ports
+    hosts: Vec<(String, Option<u16>)>,
+    commands: Vec<String>,
+    vars: Vec<String>,
+    ffi: bool,
+    exit: bool,
+}
+
+impl SysPolicy {
+    /// Allow reading files in a directory and its subdirectories
+    pub fn allow_read(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push((dir.into(), false));
+        self
+    }
+    /// Allow reading and writing files in a directory and its subdirectories
+    pub fn allow_write(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dirs.push((dir.into(), true));
+        self
+    }
+    /// Allow connecting to, listening on, or sending datagrams to a host
+    ///
+    /// The host can be followed by a `:` and a port to only allow that port.
+    /// A host of `*` allows any host.
+    pub fn allow_host(mut self, host: impl Into<String>) -> Self {
+        let host = host.into();
+        let (host, port) = match split_host_port(&host) {
+            Some((host, port)) => (host, Some(port)),
+            None => (strip_brackets(&host), None),
+        };
+        self.hosts.push((host.to_ascii_lowercase(), port));
+        self
+    }
+    /// Allow running a command
+    ///
+    /// The command must match exactly how the program names it.
+    pub fn allow_command(mut self, command: impl Into<String>) -> Self {
+        self.commands.push(command.into());
+        self
+    }
+    /// Allow reading an environment variable
+    ///
+    /// A name of `*` allows any variable.
+    /// Other variables appear to be unset.
+    pub fn allow_var(mut self, name: impl Into<String>) -> Self {
+        self.vars.push(name.into());
+        self
+    }
+    /// Set whether foreign functions can be called
+    pub fn allow_ffi(mut self, allow: bool) -> Self {
+        self.ffi = allow;
+        self
+    }
+    /// Set whether the program can exit the process
+    pub fn allow_exit(mut self, allow: bool) -> Self {
+        self.exit = allow;
+        self
+    }
+}
+
+/// A system backend that delegates to [`NativeSys`], but only allows what a [`SysPolicy`] permits
+///
+/// Anything the policy does not allow fails with a "Permission denied by policy" error.
+/// Handles can only be used by the backend that opened them.
+/// The clipboard, webcam, and [`SysBackend::invoke`] are not available.
+pub struct RestrictedSys {
+    policy: SysPolicy,
+    handles: Mutex<HashSet<Handle>>,
+}
+
+impl RestrictedSys {
+    /// Create a new restricted backend
+    ///
+    /// Relative directories in the policy are resolved against the current directory.
+    pub fn new(mut policy: SysPolicy) -> Self {
+        for (dir, _) in &mut policy.dirs {
+            if let Ok(resolved) = resolve_path(dir) {
+                *dir = resolved;
+            }
+        }
+        RestrictedSys {
+            policy,
+            handles: Mutex::new(HashSet::new()),
+        }
+    }
+    /// Get the policy
+    pub fn policy(&self) -> &SysPolicy {
+        &self.policy
+    }
+    fn check_path(&self, path: &Path, write: bool) -> Result<(), String> {
+        let resolved = resolve_path(path)?;
+        if (self.policy.dirs.iter())
+            .any(|(dir, can_write)| resolved.starts_with(dir) && (*can_write || !write))
+        {
+            return Ok(());
+        }
+        let access = if write { "write to" } else { "read" };
+        Err(denied(format!("cannot {access} {}", path.display())))
+    }
+    fn check_host(&self, host: &str, port: u16) -> Result<(), String> {
+        if (self.policy.hosts.iter()).any(|(allowed, allowed_port)| {
+            (allowed == "*" || allowed.eq_ignore_ascii_case(host))
+                && allowed_port.map_or(true, |p| p == port)
+        }) {
+            return Ok(());
+        }
+        Err(denied(format!("cannot access {host}:{port}")))
+    }
+    fn check_addr(&self, addr: &str) -> Result<(), String> {
+        let (host, port) =
+            split_host_port(addr).ok_or_else(|| format!("Invalid address {addr:?}"))?;
+        self.check_host(host, port)
+    }
+    fn check_command(&self, command: &str) -> Result<(), String> {
+        if self.policy.commands.iter().any(|c| c == command) {
+            Ok(())
+        } else {
+            Err(denied(format!("cannot run `{command}`")))
+        }
+    }
+    fn check_ffi(&self) -> Result<(), String> {
+        if self.policy.ffi {
+            Ok(())
+        } else {
+            Err(denied("cannot use FFI"))
+        }
+    }
+    fn check_handle(&self, handle: Handle) -> Result<(), String> {
+        if handle < Handle::FIRST_UNRESERVED || self.handles.lock().contains(&handle) {
+            Ok(())
+        } else {
+            Err(denied(format!("handle {} was not opened here", handle.0)))
+        }
+    }
+    fn own(&self, handle: Handle) -> Handle {
+        self.handles.lock().insert(handle);
+        handle
+    }
+}
+
+fn denied(message: impl std::fmt::Display) -> String {
+    format!("Permission denied by policy: {message}")
+}
+
+fn strip_brackets(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Split an address into a host and port
+fn split_host_port(addr: &str) -> Option<(&str, u16)> {
+    let (host, port) = addr.rsplit_once(':')?;
+    // IPv6 addresses are written in brackets
+    if host.contains(':') && !host.ends_with(']') {
+        return None;
+    }
+    Some((strip_brackets(host), port.parse().ok()?))
+}
+
+/// Make a path absolute, removing `..`s and following symlinks where the path exists
+fn resolve_path(path: &Path) -> Result<PathBuf, String> {
+    let mut resolved = env::current_dir().map_err(|e| e.to_string())?;
+    resolved = resolved.canonicalize().unwrap_or(resolved);
+    let mut exists = true;
+    for comp in path.components() {
+        match comp {
+            Component::Prefix(_) | Component::RootDir => resolved.push(comp),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(part) => {
+                resolved.push(part);
+                // Symlinks must not lead out of allowed directories
+                if exists {
+                    match resolved.canonicalize() {
+                        Ok(canon) => resolved = canon,
+                        Err(_) => exists = false,
+                    }
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+macro_rules! delegate_to_native {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(fn $name(&self $(, $arg: $ty)*) -> $ret {
+            NativeSys.$name($($arg),*)
+        })*
+    };
+}
+
+impl SysBackend for RestrictedSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn save_error_color(&self, message: String, colored: String) {
+        NativeSys.save_error_color(message, colored)
+    }
+    fn print_str_trace(&self, s: &str) {
+        NativeSys.print_str_trace(s)
+    }
+    delegate_to_native! {
+        fn print_str_stdout(&self, s: &str) -> Result<(), String>;
+        fn print_str_stderr(&self, s: &str) -> Result<(), String>;
+        fn scan_line_stdin(&self) -> Result<Option<String>, String>;
+        fn scan_stdin(&self, count: usize) -> Result<Vec<u8>, String>;
+        fn scan_until_stdin(&self, delim: &[u8]) -> Result<Vec<u8>, String>;
+        fn set_raw_mode(&self, raw_mode: bool) -> Result<(), String>;
+        fn get_raw_mode(&self) -> Result<bool, String>;
+        fn term_size(&self) -> Result<(usize, usize), String>;
+        fn sleep(&self, seconds: f64) -> Result<(), String>;
+        fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String>;
+        fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String>;
+        fn audio_sample_rate(&self) -> u32;
+        fn stream_audio(&self, f: AudioStreamFn) -> Result<(), String>;
+        fn now(&self) -> f64;
+        fn timezone(&self) -> Result<f64, String>;
+    }
+    #[cfg(feature = "image")]
+    fn show_image(&self, image: image::DynamicImage, label: Option<&str>) -> Result<(), String> {
+        NativeSys.show_image(image, label)
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        (self.policy.vars.iter())
+            .any(|var| var == "*" || var == name)
+            .then(|| NativeSys.var(name))
+            .flatten()
+    }
+    fn exit(&self, status: i32) -> Result<(), String> {
+        if !self.policy.exit {
+            return Err(denied("cannot exit"));
+        }
+        NativeSys.exit(status)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        self.check_path(path.as_ref(), false).is_ok() && NativeSys.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        self.check_path(path.as_ref(), false)?;
+        NativeSys.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        self.check_path(path.as_ref(), false)?;
+        NativeSys.is_file(path)
+    }
+    fn file_metadata(&self, path: &str) -> Result<FileMetadata, String> {
+        self.check_path(path.as_ref(), false)?;
+        NativeSys.file_metadata(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        self.check_path(path.as_ref(), true)?;
+        NativeSys.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        self.check_path(path.as_ref(), true)?;
+        NativeSys.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        self.check_handle(handle)?;
+        NativeSys.read(handle, count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        self.check_handle(handle)?;
+        NativeSys.read_all(handle)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        self.check_handle(handle)?;
+        NativeSys.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        self.check_path(path, true)?;
+        NativeSys.create_file(path).map(|handle| self.own(handle))
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        self.check_path(path, write)?;
+        (NativeSys.open_file(path, write)).map(|handle| self.own(handle))
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        self.check_path(path, true)?;
+        NativeSys.make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        self.check_path(path, false)?;
+        NativeSys.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        self.check_path(path, true)?;
+        NativeSys.file_write_all(path, contents)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        self.check_path(path.as_ref(), false)?;
+        NativeSys.change_directory(path)
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        self.check_addr(addr)?;
+        NativeSys.tcp_listen(addr).map(|handle| self.own(handle))
+    }
+    fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
+        self.check_addr(addr)?;
+        (NativeSys.tls_listen(addr, cert, key)).map(|handle| self.own(handle))
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        self.check_handle(handle)?;
+        NativeSys.tcp_accept(handle).map(|handle| self.own(handle))
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.check_addr(addr)?;
+        NativeSys.tcp_connect(addr).map(|handle| self.own(handle))
+    }
+    fn tls_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.check_addr(addr)?;
+        NativeSys.tls_connect(addr).map(|handle| self.own(handle))
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.check_handle(handle)?;
+        NativeSys.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        self.check_handle(handle)?;
+        NativeSys.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.check_handle(handle)?;
+        NativeSys.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        self.check_handle(handle)?;
+        NativeSys.tcp_set_write_timeout(handle, timeout)
+    }
+    fn udp_bind(&self, addr: &str) -> Result<Handle, String> {
+        self.check_addr(addr)?;
+        NativeSys.udp_bind(addr).map(|handle| self.own(handle))
+    }
+    fn udp_connect(&self, addr: &str) -> Result<Handle, String> {
+        self.check_addr(addr)?;
+        NativeSys.udp_connect(addr).map(|handle| self.own(handle))
+    }
+    fn udp_send(&self, handle: Handle, data: &[u8], addr: &str) -> Result<(), String> {
+        self.check_handle(handle)?;
+        self.check_addr(addr)?;
+        NativeSys.udp_send(handle, data, addr)
+    }
+    fn udp_receive(&self, handle: Handle, max_len: usize) -> Result<(Vec<u8>, SocketAddr), String> {
+        self.check_handle(handle)?;
+        NativeSys.udp_receive(handle, max_len)
+    }
+    fn udp_addr(&self, handle: Handle) -> Result<SocketAddr, String> {
+        self.check_handle(handle)?;
+        NativeSys.udp_addr(handle)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        self.check_handle(handle)?;
+        NativeSys.close(handle)?;
+        self.handles.lock().remove(&handle);
+        Ok(())
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        Err(denied(format!("cannot invoke {path}")))
+    }
+    fn run_command_inherit(&self, command: &str, args: &[&str]) -> Result<i32, String> {
+        self.check_command(command)?;
+        NativeSys.run_command_inherit(command, args)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        self.check_command(command)?;
+        NativeSys.run_command_capture(command, args)
+    }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 3], String> {
+        self.check_command(command)?;
+        let handles = NativeSys.run_command_stream(command, args)?;
+        Ok(handles.map(|handle| self.own(handle)))
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        self.check_handle(handle)?;
+        NativeSys.https_get(request, handle)
+    }
+    #[cfg(feature = "tls")]
+    fn http_request(&self, request: &crate::HttpRequest) -> Result<crate::HttpResponse, String> {
+        crate::http::request(request, |host, port| self.check_host(host, port))
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        result_ty: FfiType,
+        name: &str,
+        arg_tys: &[FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        self.check_ffi()?;
+        NativeSys.ffi(file, result_ty, name, arg_tys, args)
+    }
+    fn mem_copy(&self, ty: FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        self.check_ffi()?;
+        NativeSys.mem_copy(ty, ptr, len)
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        self.check_ffi()?;
+        NativeSys.mem_free(ptr)
+    }
+    fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
+        self.check_command("git")?;
+        NativeSys.load_git_module(url, target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Uiua;
+
+    use super::*;
+
+    #[test]
+    fn policy() {
+        let dir = env::temp_dir().join("uiua_restricted_sys_policy");
+        _ = std::fs::create_dir_all(dir.join("out"));
+        let sys = RestrictedSys::new(
+            SysPolicy::default()
+                .allow_read(&dir)
+                .allow_write(dir.join("out"))
+                .allow_host("example.com:443")
+                .allow_host("[::1]"),
+        );
+        assert!(sys.check_path(&dir.join("a.txt"), false).is_ok());
+        assert!(sys.check_path(&dir.join("a.txt"), true).is_err());
+        assert!(sys.check_path(&dir.join("out/new/b.txt"), true).is_ok());
+        assert!(sys
+            .check_path(&dir.join("out/../../escape"), false)
+            .is_err());
+        assert!(sys.check_addr("example.com:443").is_ok());
+        assert!(sys.check_addr("EXAMPLE.com:443").is_ok());
+        assert!(sys.check_addr("example.com:80").is_err());
+        assert!(sys.check_addr("[::1]:8080").is_ok());
+        assert!(sys.check_command("git").is_err());
+        assert!(sys.check_handle(Handle(1000)).is_err());
+        let err = sys.file_read_all(Path::new("/")).unwrap_err();
+        assert!(err.starts_with("Permission denied by policy"), "{err}");
+    }
+
+    #[test]
+    fn programs() {
+        let dir = env::temp_dir().join("uiua_restricted_sys_programs");
+        _ = std::fs::create_dir_all(dir.join("in"));
+        _ = std::fs::create_dir_all(dir.join("out"));
+        std::fs::write(dir.join("in/a.txt"), "hi").unwrap();
+        std::fs::write(dir.join("secret.txt"), "shh").unwrap();
+        env::set_var("UIUA_RESTRICTED_SYS_VAR", "yes");
+        let run = |code: String| {
+            let policy = SysPolicy::default()
+                .allow_read(dir.join("in"))
+                .allow_write(dir.join("out"))
+                .allow_var("UIUA_RESTRICTED_SYS_VAR");
+            let mut env = Uiua::with_backend(RestrictedSys::new(policy));
+            env.run_str(&code).map(|_| env.take_stack())
+        };
+        let path = |file: &str| format!("{:?}", dir.join(file).display());
+        let denied = |code: String| {
+            let err = run(code).unwrap_err().to_string();
+            assert!(err.contains("Permission denied by policy"), "{err}");
+        };
+
+        // Files
+        let stack = run(format!("&fras {}", path("in/a.txt"))).unwrap();
+        assert_eq!(stack, [Value::from("hi")]);
+        denied(format!("&fras {}", path("secret.txt")));
+        denied(format!("&fras {}", path("in/../secret.txt")));
+        run(format!("&fwa {} \"made\"", path("out/b.txt"))).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("out/b.txt")).unwrap(),
+            "made"
+        );
+        denied(format!("&fwa {} \"changed\"", path("in/a.txt")));
+
+        // Environment variables
+        let stack = run("&var \"UIUA_RESTRICTED_SYS_VAR\"".into()).unwrap();
+        assert_eq!(stack, [Value::from("yes")]);
+        let err = run("&var \"PATH\"".into()).unwrap_err().to_string();
+        assert!(err.contains("not set"), "{err}");
+
+        // Everything else
+        denied("&exit 0".into());
+        denied("&runs {\"git\" \"status\"}".into());
+        denied("&tcpc \"example.com:80\"".into());
+    }
+}